@@ -2,12 +2,13 @@ use crate::consensus::{self, Authority};
 use crate::debug::stats;
 use crate::table::{Table, TableBuilder, TableRpc};
 use crate::view::{View, ViewBuilder, ViewRpc};
-use crate::ActivationResult;
+use crate::{ActivationResult, MigrationPlan, RecipeVersion};
 use failure::{self, ResultExt};
 use futures_util::future;
 use petgraph::graph::NodeIndex;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
@@ -31,6 +32,42 @@ pub struct ControllerDescriptor {
     pub nonce: u64,
 }
 
+/// A recipe change that Noria could not apply.
+///
+/// A recipe change that fails leaves the running data-flow graph and the recipe untouched.
+#[derive(Clone, Debug, Fail, PartialEq, Serialize, Deserialize)]
+pub struct RecipeError {
+    /// The name of the query, table or view that caused the failure, if it has one.
+    pub query: Option<String>,
+    /// The line of the recipe text at which the offending statement starts, if known.
+    pub position: Option<usize>,
+    /// Why the change could not be applied.
+    pub reason: String,
+}
+
+impl fmt::Display for RecipeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.query, self.position) {
+            (Some(ref query), Some(line)) => {
+                write!(f, "`{}` (line {}): {}", query, line, self.reason)
+            }
+            (Some(ref query), None) => write!(f, "`{}`: {}", query, self.reason),
+            (None, Some(line)) => write!(f, "line {}: {}", line, self.reason),
+            (None, None) => write!(f, "{}", self.reason),
+        }
+    }
+}
+
+impl From<String> for RecipeError {
+    fn from(reason: String) -> Self {
+        RecipeError {
+            query: None,
+            position: None,
+            reason,
+        }
+    }
+}
+
 struct Controller<A> {
     authority: Arc<A>,
     client: hyper::Client<hyper::client::HttpConnector>,
@@ -388,22 +425,117 @@ impl<A: Authority + 'static> ControllerHandle<A> {
 
     /// Extend the existing recipe with the given set of queries.
     ///
+    /// The addition may also contain `ALTER TABLE` statements that add, drop, or rename columns of
+    /// existing tables. Tables retrieved before such a change keep using the old schema, so they
+    /// should be retrieved again with `Self::table`. `DROP TABLE` and `DROP VIEW` statements
+    /// remove existing tables and views, which fails while other queries still use them.
+    ///
+    /// If the addition cannot be applied, this fails with a
+    /// [`RecipeError`](crate::error::RecipeError) that names the offending query, and the existing
+    /// recipe and graph are left as they were.
+    ///
     /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
     pub fn extend_recipe(
         &mut self,
         recipe_addition: &str,
     ) -> impl Future<Output = Result<ActivationResult, failure::Error>> {
-        self.rpc("extend_recipe", recipe_addition, "failed to extend recipe")
+        let fut = self.rpc::<_, Result<ActivationResult, RecipeError>>(
+            "extend_recipe",
+            recipe_addition,
+            "failed to extend recipe",
+        );
+
+        async move { fut.await?.map_err(failure::Error::from) }
+    }
+
+    /// Work out how extending the existing recipe with the given set of queries would change the
+    /// data-flow graph, without changing it.
+    ///
+    /// The returned plan lists the existing nodes that new queries would reuse, the domains and
+    /// state that would be created, and which state would need to be backfilled. Like
+    /// `Self::extend_recipe`, this fails with a [`RecipeError`](crate::error::RecipeError) if the
+    /// addition cannot be applied.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub fn plan_recipe(
+        &mut self,
+        recipe_addition: &str,
+    ) -> impl Future<Output = Result<MigrationPlan, failure::Error>> {
+        let fut = self.rpc::<_, Result<MigrationPlan, RecipeError>>(
+            "plan_recipe",
+            recipe_addition,
+            "failed to plan recipe",
+        );
+
+        async move { fut.await?.map_err(failure::Error::from) }
+    }
+
+    /// Remove the named view, along with any nodes that no other query uses.
+    ///
+    /// This fails if other queries still read from the view.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub fn drop_view(
+        &mut self,
+        name: &str,
+    ) -> impl Future<Output = Result<ActivationResult, failure::Error>> {
+        self.extend_recipe(&format!("DROP VIEW `{}`;", name))
     }
 
     /// Replace the existing recipe with this one.
     ///
+    /// Like `Self::extend_recipe`, this fails with a [`RecipeError`](crate::error::RecipeError) if
+    /// the recipe cannot be applied, and then leaves the existing recipe in place.
+    ///
     /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
     pub fn install_recipe(
         &mut self,
         new_recipe: &str,
     ) -> impl Future<Output = Result<ActivationResult, failure::Error>> {
-        self.rpc("install_recipe", new_recipe, "failed to install recipe")
+        let fut = self.rpc::<_, Result<ActivationResult, RecipeError>>(
+            "install_recipe",
+            new_recipe,
+            "failed to install recipe",
+        );
+
+        async move { fut.await?.map_err(failure::Error::from) }
+    }
+
+    /// Fetch the recipes that the controller applied most recently, oldest first.
+    ///
+    /// The controller only remembers a bounded number of recipes; any of them can be restored with
+    /// `Self::rollback_to`.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub fn recipe_history(
+        &mut self,
+    ) -> impl Future<Output = Result<Vec<RecipeVersion>, failure::Error>> {
+        self.rpc("recipe_history", (), "failed to fetch recipe history")
+    }
+
+    /// Replace the existing recipe with the one that had the given version.
+    ///
+    /// The version must be listed by `Self::recipe_history`. Rolling back installs the earlier
+    /// recipe as a new version, so a rollback can itself be rolled back.
+    ///
+    /// Only queries and views are rolled back. If the earlier recipe defines any table differently
+    /// than the existing one does, for example because a column was renamed or the table was
+    /// created or dropped since, the rollback fails rather than lose the data in that table. Like
+    /// `Self::install_recipe`, this fails with a [`RecipeError`](crate::error::RecipeError) if the
+    /// recipe cannot be applied, and then leaves the existing recipe in place.
+    ///
+    /// `Self::poll_ready` must have returned `Async::Ready` before you call this method.
+    pub fn rollback_to(
+        &mut self,
+        version: usize,
+    ) -> impl Future<Output = Result<ActivationResult, failure::Error>> {
+        let fut = self.rpc::<_, Result<ActivationResult, RecipeError>>(
+            "rollback_to",
+            version,
+            "failed to roll back recipe",
+        );
+
+        async move { fut.await?.map_err(failure::Error::from) }
     }
 
     /// Fetch a graphviz description of the dataflow graph.
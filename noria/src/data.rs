@@ -1,6 +1,6 @@
 use arccstr::ArcCStr;
 
-use chrono::{self, NaiveDate, NaiveDateTime};
+use chrono::{self, DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
 
 use nom_sql::Literal;
 
@@ -8,9 +8,18 @@ use std::convert::TryFrom;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::ops::{Add, Div, Mul, Sub};
+use std::sync::Arc;
+
+use uuid::Uuid;
 
 const FLOAT_PRECISION: f64 = 1_000_000_000.0;
 const TINYTEXT_WIDTH: usize = 15;
+/// The largest scale a `DataType::Decimal` may have.
+const DECIMAL_MAX_SCALE: u8 = 18;
+/// The number of fractional digits a decimal quotient has beyond those of its dividend, like
+/// MySQL's default `div_precision_increment`.
+const DECIMAL_DIV_INCREMENT: u8 = 4;
+const MICROS_PER_SECOND: i64 = 1_000_000;
 
 /// The main type used for user data throughout the codebase.
 ///
@@ -42,6 +51,24 @@ pub enum DataType {
     TinyText([u8; TINYTEXT_WIDTH]),
     /// A timestamp for date/time types.
     Timestamp(NaiveDateTime),
+    /// A boolean value, which compares equal to the integers 0 and 1.
+    Bool(bool),
+    /// A reference-counted binary string, which unlike `Text` may hold any bytes.
+    ByteArray(Arc<Vec<u8>>),
+    /// A reference-counted UUID. It's stored out of line to keep `DataType` at 16 bytes.
+    Uuid(Arc<Uuid>),
+    /// An exact fixed-point decimal value. The first field is the unscaled value, while the
+    /// second is the number of digits after the decimal point (at most 18), so `Decimal(12345, 2)`
+    /// is 123.45. Decimals compare equal to integers of the same value.
+    Decimal(i64, u8),
+    /// A calendar date. Dates compare equal to timestamps at midnight of the same day.
+    Date(NaiveDate),
+    /// A signed number of microseconds, for MySQL's `TIME` type, which holds both times of day
+    /// and intervals of up to 838 hours.
+    Time(i64),
+    /// A reference-counted timestamp with a UTC offset. Zoned timestamps compare by the instant
+    /// they refer to, regardless of their offsets.
+    TimestampTz(Arc<DateTime<FixedOffset>>),
 }
 
 impl fmt::Display for DataType {
@@ -66,6 +93,47 @@ impl fmt::Display for DataType {
                 }
             }
             DataType::Timestamp(ts) => write!(f, "{}", ts.format("%c")),
+            DataType::Bool(b) => write!(f, "{}", if b { "TRUE" } else { "FALSE" }),
+            DataType::ByteArray(ref bytes) => {
+                write!(f, "X'")?;
+                for b in bytes.iter() {
+                    write!(f, "{:02x}", b)?;
+                }
+                write!(f, "'")
+            }
+            DataType::Uuid(ref u) => write!(f, "{}", u),
+            DataType::Decimal(m, 0) => write!(f, "{}", m),
+            DataType::Decimal(m, scale) => {
+                let unit = 10u64.pow(u32::from(scale));
+                let sign = if m < 0 { "-" } else { "" };
+                let m = i128::from(m).abs() as u64;
+                write!(
+                    f,
+                    "{}{}.{:0width$}",
+                    sign,
+                    m / unit,
+                    m % unit,
+                    width = usize::from(scale)
+                )
+            }
+            DataType::Date(d) => write!(f, "{}", d.format("%Y-%m-%d")),
+            DataType::Time(t) => {
+                let sign = if t < 0 { "-" } else { "" };
+                let (secs, micros) = (t.abs() / MICROS_PER_SECOND, t.abs() % MICROS_PER_SECOND);
+                write!(
+                    f,
+                    "{}{:02}:{:02}:{:02}",
+                    sign,
+                    secs / 3600,
+                    secs / 60 % 60,
+                    secs % 60
+                )?;
+                if micros != 0 {
+                    write!(f, ".{:06}", micros)?;
+                }
+                Ok(())
+            }
+            DataType::TimestampTz(ref ts) => write!(f, "{}", ts.format("%c %:z")),
         }
     }
 }
@@ -88,6 +156,13 @@ impl fmt::Debug for DataType {
             DataType::UnsignedInt(n) => write!(f, "UnsignedInt({})", n),
             DataType::BigInt(n) => write!(f, "BigInt({})", n),
             DataType::UnsignedBigInt(n) => write!(f, "UnsignedBigInt({})", n),
+            DataType::Bool(b) => write!(f, "Bool({})", b),
+            DataType::ByteArray(ref bytes) => write!(f, "ByteArray({:?})", bytes),
+            DataType::Uuid(ref u) => write!(f, "Uuid({})", u),
+            DataType::Decimal(..) => write!(f, "Decimal({})", self),
+            DataType::Date(d) => write!(f, "Date({:?})", d),
+            DataType::Time(..) => write!(f, "Time({})", self),
+            DataType::TimestampTz(ref ts) => write!(f, "TimestampTz({:?})", ts),
         }
     }
 }
@@ -100,6 +175,9 @@ impl DataType {
     pub fn deep_clone(&self) -> Self {
         match *self {
             DataType::Text(ref cstr) => DataType::Text(ArcCStr::from(&**cstr)),
+            DataType::ByteArray(ref bytes) => DataType::ByteArray(Arc::new(Vec::clone(bytes))),
+            DataType::Uuid(ref u) => DataType::Uuid(Arc::new(**u)),
+            DataType::TimestampTz(ref ts) => DataType::TimestampTz(Arc::new(**ts)),
             ref dt => dt.clone(),
         }
     }
@@ -140,10 +218,228 @@ impl DataType {
     /// Checks if this values is of a timestamp data type.
     pub fn is_datetime(&self) -> bool {
         match *self {
-            DataType::Timestamp(_) => true,
+            DataType::Timestamp(_) | DataType::TimestampTz(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if this value is of the date data type.
+    pub fn is_date(&self) -> bool {
+        match *self {
+            DataType::Date(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if this value is of the time data type.
+    pub fn is_time(&self) -> bool {
+        match *self {
+            DataType::Time(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if this value is of any of the integer data types.
+    fn is_integral(&self) -> bool {
+        match *self {
+            DataType::Int(_)
+            | DataType::UnsignedInt(_)
+            | DataType::BigInt(_)
+            | DataType::UnsignedBigInt(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if this value is of the boolean data type.
+    pub fn is_bool(&self) -> bool {
+        match *self {
+            DataType::Bool(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if this value is of the binary string data type (i.e., can be converted into
+    /// `&[u8]`).
+    pub fn is_byte_array(&self) -> bool {
+        match *self {
+            DataType::ByteArray(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if this value is of the UUID data type.
+    pub fn is_uuid(&self) -> bool {
+        match *self {
+            DataType::Uuid(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if this value is of the exact decimal data type.
+    pub fn is_decimal(&self) -> bool {
+        match *self {
+            DataType::Decimal(..) => true,
             _ => false,
         }
     }
+
+    /// Build a decimal from an unscaled value and a scale.
+    ///
+    /// Like MySQL, fractional digits are rounded away if the value has too many digits to be
+    /// represented. `None` is returned if even its integral part is too large.
+    pub fn decimal(unscaled: i128, scale: u8) -> Option<DataType> {
+        let (mut m, mut scale) = (unscaled, scale);
+        while scale > 0
+            && (scale > DECIMAL_MAX_SCALE
+                || m > i128::from(std::i64::MAX)
+                || m < i128::from(std::i64::MIN))
+        {
+            m = rescale(m, scale, scale - 1);
+            scale -= 1;
+        }
+
+        if m > i128::from(std::i64::MAX) || m < i128::from(std::i64::MIN) {
+            return None;
+        }
+        Some(DataType::Decimal(m as i64, scale))
+    }
+
+    /// Convert this value into a `DECIMAL(precision, scale)`, rounding any extra fractional
+    /// digits.
+    ///
+    /// Integers, reals, decimals and text holding a decimal number can be converted; `None` is
+    /// returned for any other value, or if the value has too many integer digits to fit the
+    /// precision. NULL stays NULL.
+    pub fn to_decimal(&self, precision: u8, scale: u8) -> Option<DataType> {
+        let scale = std::cmp::min(scale, DECIMAL_MAX_SCALE);
+        let (m, s) = match *self {
+            DataType::None => return Some(DataType::None),
+            DataType::Real(i, f) => (i128::from(i) * 1_000_000_000 + i128::from(f), 9),
+            DataType::Text(..) | DataType::TinyText(..) => parse_decimal(self.into())?,
+            ref d => d.as_decimal()?,
+        };
+
+        let m = if scale >= s {
+            m.checked_mul(pow10(scale - s))?
+        } else {
+            rescale(m, s, scale)
+        };
+        if m.abs() >= pow10(std::cmp::min(precision, 38))
+            || m > i128::from(std::i64::MAX)
+            || m < i128::from(std::i64::MIN)
+        {
+            return None;
+        }
+        Some(DataType::Decimal(m as i64, scale))
+    }
+
+    /// The unscaled value and scale of an exact numeric value, if this is one.
+    fn as_decimal(&self) -> Option<(i128, u8)> {
+        match *self {
+            DataType::Decimal(m, scale) => Some((i128::from(m), scale)),
+            DataType::Bool(b) => Some((i128::from(b), 0)),
+            ref d if d.is_integral() => Some((d.into(), 0)),
+            _ => None,
+        }
+    }
+}
+
+fn pow10(exp: u8) -> i128 {
+    10i128.pow(u32::from(exp))
+}
+
+/// Change the scale of the unscaled decimal value `m`, rounding half away from zero.
+fn rescale(m: i128, from: u8, to: u8) -> i128 {
+    if to >= from {
+        m * pow10(to - from)
+    } else {
+        let unit = pow10(from - to);
+        if (m % unit).abs() * 2 >= unit {
+            m / unit + m.signum()
+        } else {
+            m / unit
+        }
+    }
+}
+
+/// Strip the trailing fractional zeros of a decimal, so that equal decimals have the same
+/// representation.
+pub(crate) fn normalize_decimal(mut m: i64, mut scale: u8) -> (i64, u8) {
+    while scale > 0 && m % 10 == 0 {
+        m /= 10;
+        scale -= 1;
+    }
+    (m, scale)
+}
+
+/// Parse text such as "-12.50" into an unscaled value and a scale.
+fn parse_decimal(s: &str) -> Option<(i128, u8)> {
+    let s = s.trim();
+    let (negative, digits) = match s.as_bytes().first()? {
+        b'-' => (true, &s[1..]),
+        b'+' => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let (int, frac) = match digits.find('.') {
+        Some(dot) => (&digits[..dot], &digits[dot + 1..]),
+        None => (digits, ""),
+    };
+    if int.is_empty() && frac.is_empty()
+        || !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit())
+        || int.len() + frac.len() > 38
+    {
+        return None;
+    }
+
+    let m: i128 = format!("{}{}", int, frac).parse().ok()?;
+    let m = if negative { -m } else { m };
+    Some((m, frac.len() as u8))
+}
+
+fn compare_decimals((a, sa): (i128, u8), (b, sb): (i128, u8)) -> Ordering {
+    let scale = std::cmp::max(sa, sb);
+    rescale(a, sa, scale).cmp(&rescale(b, sb, scale))
+}
+
+fn add_decimals((a, sa): (i128, u8), (b, sb): (i128, u8)) -> Option<DataType> {
+    let scale = std::cmp::max(sa, sb);
+    DataType::decimal(rescale(a, sa, scale) + rescale(b, sb, scale), scale)
+}
+
+fn sub_decimals(a: (i128, u8), (b, sb): (i128, u8)) -> Option<DataType> {
+    add_decimals(a, (-b, sb))
+}
+
+fn mul_decimals((a, sa): (i128, u8), (b, sb): (i128, u8)) -> Option<DataType> {
+    DataType::decimal(a * b, sa + sb)
+}
+
+fn div_decimals((a, sa): (i128, u8), (b, sb): (i128, u8)) -> Option<DataType> {
+    if b == 0 {
+        // like in MySQL, division by zero is NULL
+        return Some(DataType::None);
+    }
+
+    // a / b at scale s is (a * 10^(s - sa + sb)) / b, so find the largest s no bigger than the
+    // target for which that doesn't overflow
+    let mut scale = std::cmp::min(sa + DECIMAL_DIV_INCREMENT, DECIMAL_MAX_SCALE);
+    loop {
+        let shift = scale + sb - sa;
+        if let Some(n) = a.checked_mul(pow10(shift)) {
+            let (q, r) = (n / b, n % b);
+            // round half away from zero
+            let q = if r.abs() * 2 >= b.abs() {
+                q + n.signum() * b.signum()
+            } else {
+                q
+            };
+            return DataType::decimal(q, scale);
+        }
+        if scale == 0 || shift == 0 {
+            return None;
+        }
+        scale -= 1;
+    }
 }
 
 impl PartialEq for DataType {
@@ -191,6 +487,24 @@ impl PartialEq for DataType {
             }
             (&DataType::Real(ai, af), &DataType::Real(bi, bf)) => ai == bi && af == bf,
             (&DataType::Timestamp(tsa), &DataType::Timestamp(tsb)) => tsa == tsb,
+            (&DataType::Date(a), &DataType::Date(b)) => a == b,
+            (&DataType::Date(d), &DataType::Timestamp(ts))
+            | (&DataType::Timestamp(ts), &DataType::Date(d)) => d.and_hms(0, 0, 0) == ts,
+            (&DataType::Time(a), &DataType::Time(b)) => a == b,
+            (&DataType::TimestampTz(ref a), &DataType::TimestampTz(ref b)) => a == b,
+            (&DataType::Bool(a), &DataType::Bool(b)) => a == b,
+            (&DataType::Bool(a), b) | (b, &DataType::Bool(a)) if b.is_integral() => {
+                let b: i128 = b.into();
+                i128::from(a) == b
+            }
+            (&DataType::ByteArray(ref a), &DataType::ByteArray(ref b)) => a == b,
+            (&DataType::Uuid(ref a), &DataType::Uuid(ref b)) => a == b,
+            (&DataType::Decimal(..), _) | (_, &DataType::Decimal(..)) => {
+                match (self.as_decimal(), other.as_decimal()) {
+                    (Some(a), Some(b)) => compare_decimals(a, b) == Ordering::Equal,
+                    _ => false,
+                }
+            }
             (&DataType::None, &DataType::None) => true,
 
             _ => false,
@@ -240,16 +554,39 @@ impl Ord for DataType {
                 ai.cmp(bi).then_with(|| af.cmp(bf))
             }
             (&DataType::Timestamp(tsa), &DataType::Timestamp(ref tsb)) => tsa.cmp(tsb),
+            (&DataType::Date(a), &DataType::Date(ref b)) => a.cmp(b),
+            (&DataType::Date(d), &DataType::Timestamp(ref ts)) => d.and_hms(0, 0, 0).cmp(ts),
+            (&DataType::Timestamp(ts), &DataType::Date(d)) => ts.cmp(&d.and_hms(0, 0, 0)),
+            (&DataType::Time(a), &DataType::Time(ref b)) => a.cmp(b),
+            (&DataType::TimestampTz(ref a), &DataType::TimestampTz(ref b)) => a.cmp(b),
+            (&DataType::Bool(a), &DataType::Bool(ref b)) => a.cmp(b),
+            (&DataType::Bool(a), b) if b.is_integral() => i128::from(a).cmp(&b.into()),
+            (a, &DataType::Bool(b)) if a.is_integral() => i128::from(a).cmp(&i128::from(b)),
+            (&DataType::ByteArray(ref a), &DataType::ByteArray(ref b)) => a.cmp(b),
+            (&DataType::Uuid(ref a), &DataType::Uuid(ref b)) => a.cmp(b),
+            (&DataType::Decimal(..), b) if b.as_decimal().is_some() => {
+                compare_decimals(self.as_decimal().unwrap(), b.as_decimal().unwrap())
+            }
+            (a, &DataType::Decimal(..)) if a.as_decimal().is_some() => {
+                compare_decimals(a.as_decimal().unwrap(), other.as_decimal().unwrap())
+            }
             (&DataType::None, &DataType::None) => Ordering::Equal,
 
-            // order Ints, Reals, Text, Timestamps, None
+            // order Bools, Ints and Decimals, Reals, Text, Timestamps and Dates, Times, zoned
+            // Timestamps, ByteArrays, Uuids, None
+            (&DataType::Bool(..), _) => Ordering::Greater,
             (&DataType::Int(..), _)
             | (&DataType::UnsignedInt(..), _)
             | (&DataType::BigInt(..), _)
-            | (&DataType::UnsignedBigInt(..), _) => Ordering::Greater,
+            | (&DataType::UnsignedBigInt(..), _)
+            | (&DataType::Decimal(..), _) => Ordering::Greater,
             (&DataType::Real(..), _) => Ordering::Greater,
             (&DataType::Text(..), _) | (&DataType::TinyText(..), _) => Ordering::Greater,
-            (&DataType::Timestamp(..), _) => Ordering::Greater,
+            (&DataType::Timestamp(..), _) | (&DataType::Date(..), _) => Ordering::Greater,
+            (&DataType::Time(..), _) => Ordering::Greater,
+            (&DataType::TimestampTz(..), _) => Ordering::Greater,
+            (&DataType::ByteArray(..), _) => Ordering::Greater,
+            (&DataType::Uuid(..), _) => Ordering::Greater,
             (&DataType::None, _) => Ordering::Greater,
         }
     }
@@ -279,6 +616,22 @@ impl Hash for DataType {
                 t.hash(state)
             }
             DataType::Timestamp(ts) => ts.hash(state),
+            // dates hash like the timestamps they compare equal to
+            DataType::Date(d) => d.and_hms(0, 0, 0).hash(state),
+            DataType::Time(t) => t.hash(state),
+            DataType::TimestampTz(ref ts) => ts.hash(state),
+            // booleans hash like the integers they compare equal to
+            DataType::Bool(b) => i64::from(b).hash(state),
+            DataType::ByteArray(ref bytes) => bytes.hash(state),
+            DataType::Uuid(ref u) => u.hash(state),
+            DataType::Decimal(m, scale) => match normalize_decimal(m, scale) {
+                // whole decimals hash like the integers they compare equal to
+                (m, 0) => m.hash(state),
+                (m, scale) => {
+                    m.hash(state);
+                    scale.hash(state);
+                }
+            },
         }
     }
 }
@@ -361,6 +714,24 @@ impl From<f64> for DataType {
     }
 }
 
+impl From<bool> for DataType {
+    fn from(b: bool) -> Self {
+        DataType::Bool(b)
+    }
+}
+
+impl From<Vec<u8>> for DataType {
+    fn from(bytes: Vec<u8>) -> Self {
+        DataType::ByteArray(Arc::new(bytes))
+    }
+}
+
+impl From<Uuid> for DataType {
+    fn from(u: Uuid) -> Self {
+        DataType::Uuid(Arc::new(u))
+    }
+}
+
 impl<'a> From<&'a DataType> for DataType {
     fn from(dt: &'a DataType) -> Self {
         dt.clone()
@@ -377,14 +748,27 @@ impl<'a> From<&'a Literal> for DataType {
                 let ts = chrono::Local::now().naive_local();
                 DataType::Timestamp(ts)
             }
+            Literal::CurrentDate => DataType::Date(chrono::Local::today().naive_local()),
+            Literal::CurrentTime => chrono::Local::now().time().into(),
             Literal::FixedPoint(ref r) => {
                 DataType::Real(i64::from(r.integral), r.fractional as i32)
             }
+            Literal::Blob(ref b) => b.clone().into(),
             _ => unimplemented!(),
         }
     }
 }
 
+/// Whether a column default is an expression that has to be evaluated when a row is written,
+/// rather than a constant.
+#[doc(hidden)]
+pub fn is_default_expr(l: &Literal) -> bool {
+    matches!(
+        *l,
+        Literal::CurrentTime | Literal::CurrentDate | Literal::CurrentTimestamp
+    )
+}
+
 impl From<Literal> for DataType {
     fn from(l: Literal) -> Self {
         (&l).into()
@@ -397,6 +781,53 @@ impl From<NaiveDateTime> for DataType {
     }
 }
 
+impl From<NaiveDate> for DataType {
+    fn from(d: NaiveDate) -> Self {
+        DataType::Date(d)
+    }
+}
+
+impl From<NaiveTime> for DataType {
+    fn from(t: NaiveTime) -> Self {
+        let secs = i64::from(t.num_seconds_from_midnight());
+        DataType::Time(secs * MICROS_PER_SECOND + i64::from(t.nanosecond() / 1_000))
+    }
+}
+
+impl From<DateTime<FixedOffset>> for DataType {
+    fn from(ts: DateTime<FixedOffset>) -> Self {
+        DataType::TimestampTz(Arc::new(ts))
+    }
+}
+
+impl From<DateTime<Utc>> for DataType {
+    fn from(ts: DateTime<Utc>) -> Self {
+        ts.with_timezone(&FixedOffset::east(0)).into()
+    }
+}
+
+impl From<&'_ DataType> for NaiveDate {
+    fn from(data: &'_ DataType) -> Self {
+        match *data {
+            DataType::Date(d) => d,
+            DataType::Timestamp(ts) => ts.date(),
+            DataType::TimestampTz(ref ts) => ts.naive_local().date(),
+            _ => panic!("attempted to convert a {:?} to a date", data),
+        }
+    }
+}
+
+impl From<&'_ DataType> for NaiveDateTime {
+    fn from(data: &'_ DataType) -> Self {
+        match *data {
+            DataType::Timestamp(ts) => ts,
+            DataType::Date(d) => d.and_hms(0, 0, 0),
+            DataType::TimestampTz(ref ts) => ts.naive_local(),
+            _ => panic!("attempted to convert a {:?} to a timestamp", data),
+        }
+    }
+}
+
 // This conversion has many unwraps, but all of them are expected to be safe,
 // because DataType variants (i.e. `Text` and `TinyText`) constructors are all
 // generated from valid UTF-8 strings, or the constructor fails (e.g. TryFrom &[u8]).
@@ -424,6 +855,52 @@ impl<'a> From<&'a DataType> for &'a str {
     }
 }
 
+impl<'a> From<&'a DataType> for &'a [u8] {
+    fn from(data: &'a DataType) -> Self {
+        match *data {
+            DataType::ByteArray(ref bytes) => &bytes[..],
+            DataType::Text(..) | DataType::TinyText(..) => {
+                let s: &str = data.into();
+                s.as_bytes()
+            }
+            _ => panic!("attempted to convert a {:?} to a byte string", data),
+        }
+    }
+}
+
+impl From<&'_ DataType> for bool {
+    fn from(data: &'_ DataType) -> Self {
+        match *data {
+            DataType::Bool(b) => b,
+            DataType::Int(..)
+            | DataType::UnsignedInt(..)
+            | DataType::BigInt(..)
+            | DataType::UnsignedBigInt(..) => i128::from(data) != 0,
+            _ => panic!("attempted to convert a {:?} to a bool", data),
+        }
+    }
+}
+
+impl From<DataType> for bool {
+    fn from(data: DataType) -> Self {
+        (&data).into()
+    }
+}
+
+impl From<&'_ DataType> for Uuid {
+    fn from(data: &'_ DataType) -> Self {
+        match *data {
+            DataType::Uuid(ref u) => **u,
+            DataType::Text(..) | DataType::TinyText(..) => {
+                let s: &str = data.into();
+                Uuid::parse_str(s)
+                    .unwrap_or_else(|_| panic!("attempted to convert {:?} to a UUID", data))
+            }
+            _ => panic!("attempted to convert a {:?} to a UUID", data),
+        }
+    }
+}
+
 impl From<DataType> for i128 {
     fn from(data: DataType) -> Self {
         (&data).into()
@@ -517,6 +994,7 @@ impl From<&'_ DataType> for f64 {
     fn from(data: &'_ DataType) -> Self {
         match *data {
             DataType::Real(i, f) => i as f64 + f64::from(f) / FLOAT_PRECISION,
+            DataType::Decimal(m, scale) => m as f64 / 10f64.powi(i32::from(scale)),
             DataType::Int(i) => f64::from(i),
             DataType::BigInt(i) => i as f64,
             _ => panic!("attempted to convert a {:?} to an f64", data),
@@ -566,11 +1044,20 @@ impl TryFrom<mysql_common::value::Value> for DataType {
 
         match v {
             Value::NULL => Ok(DataType::None),
-            Value::Bytes(v) => DataType::try_from(&v[..]),
+            // MySQL uses the same representation for text and binary strings, so anything that
+            // can't be text becomes a byte array
+            Value::Bytes(v) => {
+                if std::str::from_utf8(&v).is_ok() && !v.contains(&0) {
+                    DataType::try_from(&v[..])
+                } else {
+                    Ok(v.into())
+                }
+            }
             Value::Int(v) => Ok(v.into()),
             Value::UInt(v) => Ok(v.into()),
             Value::Float(v) => Ok(v.into()),
             Value::Double(v) => Ok(v.into()),
+            // MySQL sends dates at midnight just like timestamps, but they compare equal anyway
             Value::Date(year, month, day, hour, minutes, seconds, micros) => {
                 Ok(DataType::Timestamp(
                     NaiveDate::from_ymd(year.into(), month.into(), day.into()).and_hms_micro(
@@ -581,30 +1068,81 @@ impl TryFrom<mysql_common::value::Value> for DataType {
                     ),
                 ))
             }
-            Value::Time(..) => Err("`mysql_common::value::Value::time` is not supported in Noria"),
+            Value::Time(negative, days, hours, minutes, seconds, micros) => {
+                let secs = ((i64::from(days) * 24 + i64::from(hours)) * 60 + i64::from(minutes))
+                    * 60
+                    + i64::from(seconds);
+                let t = secs * MICROS_PER_SECOND + i64::from(micros);
+                Ok(DataType::Time(if negative { -t } else { t }))
+            }
+        }
+    }
+}
+
+impl From<DataType> for mysql_common::value::Value {
+    fn from(data: DataType) -> Self {
+        use mysql_common::value::Value;
+
+        match data {
+            DataType::None => Value::NULL,
+            DataType::Int(n) => Value::Int(n.into()),
+            DataType::BigInt(n) => Value::Int(n),
+            DataType::UnsignedInt(n) => Value::UInt(n.into()),
+            DataType::UnsignedBigInt(n) => Value::UInt(n),
+            DataType::Bool(b) => Value::Int(b.into()),
+            DataType::Real(..) => Value::Double((&data).into()),
+            DataType::Text(..) | DataType::TinyText(..) | DataType::ByteArray(..) => {
+                let bytes: &[u8] = (&data).into();
+                Value::Bytes(bytes.to_vec())
+            }
+            // MySQL sends decimals and UUIDs as text, which `DataType::to_decimal` parses back
+            DataType::Decimal(..) | DataType::Uuid(..) => Value::Bytes(data.to_string().into()),
+            DataType::Timestamp(ts) => ts.into(),
+            DataType::Date(d) => d.and_hms(0, 0, 0).into(),
+            DataType::Time(t) => {
+                let (secs, micros) = (t.abs() / MICROS_PER_SECOND, t.abs() % MICROS_PER_SECOND);
+                Value::Time(
+                    t < 0,
+                    (secs / 86400) as u32,
+                    (secs / 3600 % 24) as u8,
+                    (secs / 60 % 60) as u8,
+                    (secs % 60) as u8,
+                    micros as u32,
+                )
+            }
+            // MySQL timestamps have no offset, so send the instant in UTC
+            DataType::TimestampTz(ref ts) => ts.naive_utc().into(),
         }
     }
 }
 
-// Performs an arithmetic operation on two numeric DataTypes,
-// returning a new DataType as the result.
+// Performs an arithmetic operation on two numeric DataTypes, returning a new DataType as the
+// result, or `None` if either isn't a number or the result can't be represented.
 macro_rules! arithmetic_operation (
-    ($op:tt, $first:ident, $second:ident) => (
+    ($op:tt, $checked:ident, $decimal_op:ident, $first:ident, $second:ident) => (
         match ($first, $second) {
-            (&DataType::None, _) | (_, &DataType::None) => DataType::None,
-            (&DataType::Int(a), &DataType::Int(b)) => (a $op b).into(),
-            (&DataType::UnsignedInt(a), &DataType::UnsignedInt(b)) => (a $op b).into(),
-            (&DataType::BigInt(a), &DataType::BigInt(b)) => (a $op b).into(),
-            (&DataType::UnsignedBigInt(a), &DataType::UnsignedBigInt(b)) => (a $op b).into(),
-
-            (&DataType::Int(a), &DataType::BigInt(b)) => (i64::from(a) $op b).into(),
-            (&DataType::BigInt(a), &DataType::Int(b)) => (a $op i64::from(b)).into(),
-            (&DataType::Int(a), &DataType::UnsignedBigInt(b)) => (i128::from(a) $op i128::from(b)).into(),
-            (&DataType::UnsignedBigInt(a), &DataType::Int(b)) => (i128::from(a) $op i128::from(b)).into(),
-            (&DataType::BigInt(a), &DataType::UnsignedBigInt(b)) => (i128::from(a) $op i128::from(b)).into(),
-            (&DataType::UnsignedBigInt(a), &DataType::BigInt(b)) => (i128::from(a) $op i128::from(b)).into(),
-            (&DataType::UnsignedBigInt(a), &DataType::UnsignedInt(b)) => (a $op u64::from(b)).into(),
-            (&DataType::UnsignedInt(a), &DataType::UnsignedBigInt(b)) => (u64::from(a) $op b).into(),
+            (&DataType::None, _) | (_, &DataType::None) => Some(DataType::None),
+            (&DataType::Int(a), &DataType::Int(b)) => a.$checked(b).map(Into::into),
+            (&DataType::UnsignedInt(a), &DataType::UnsignedInt(b)) => a.$checked(b).map(Into::into),
+            (&DataType::BigInt(a), &DataType::BigInt(b)) => a.$checked(b).map(Into::into),
+            (&DataType::UnsignedBigInt(a), &DataType::UnsignedBigInt(b)) => {
+                a.$checked(b).map(Into::into)
+            }
+
+            (&DataType::Int(a), &DataType::BigInt(b)) => i64::from(a).$checked(b).map(Into::into),
+            (&DataType::BigInt(a), &DataType::Int(b)) => a.$checked(i64::from(b)).map(Into::into),
+            (&DataType::Int(..), &DataType::UnsignedBigInt(..)) |
+            (&DataType::UnsignedBigInt(..), &DataType::Int(..)) |
+            (&DataType::BigInt(..), &DataType::UnsignedBigInt(..)) |
+            (&DataType::UnsignedBigInt(..), &DataType::BigInt(..)) => {
+                wide_integer(i128::from($first).$checked(i128::from($second)))
+            }
+            (&DataType::UnsignedBigInt(a), &DataType::UnsignedInt(b)) => {
+                a.$checked(u64::from(b)).map(Into::into)
+            }
+            (&DataType::UnsignedInt(a), &DataType::UnsignedBigInt(b)) => {
+                u64::from(a).$checked(b).map(Into::into)
+            }
 
             (first @ &DataType::Int(..), second @ &DataType::Real(..)) |
             (first @ &DataType::BigInt(..), second @ &DataType::Real(..)) |
@@ -614,28 +1152,55 @@ macro_rules! arithmetic_operation (
             (first @ &DataType::Real(..), second @ &DataType::BigInt(..)) |
             (first @ &DataType::Real(..), second @ &DataType::UnsignedInt(..)) |
             (first @ &DataType::Real(..), second @ &DataType::UnsignedBigInt(..)) |
-            (first @ &DataType::Real(..), second @ &DataType::Real(..)) => {
+            (first @ &DataType::Real(..), second @ &DataType::Real(..)) |
+            (first @ &DataType::Decimal(..), second @ &DataType::Real(..)) |
+            (first @ &DataType::Real(..), second @ &DataType::Decimal(..)) => {
                 let a: f64 = first.into();
                 let b: f64 = second.into();
-                (a $op b).into()
+                real(a $op b)
             }
-            (first, second) => panic!(
-                format!(
-                    "can't {} a {:?} and {:?}",
-                    stringify!($op),
-                    first,
-                    second,
-                )
-            ),
+
+            // decimals are exact with each other and with integers
+            (first, second) if (first.is_decimal() || second.is_decimal())
+                && first.as_decimal().is_some()
+                && second.as_decimal().is_some() => {
+                $decimal_op(first.as_decimal().unwrap(), second.as_decimal().unwrap())
+            }
+            _ => None,
         }
     );
 );
 
+/// The result of mixed signed and unsigned integer arithmetic, if it fits either a `BigInt` or an
+/// `UnsignedBigInt`.
+fn wide_integer(n: Option<i128>) -> Option<DataType> {
+    let n = n?;
+    if n >= i128::from(std::i64::MIN) && n <= i128::from(std::i64::MAX) {
+        Some(DataType::BigInt(n as i64))
+    } else if n >= 0 && n <= i128::from(std::u64::MAX) {
+        Some(DataType::UnsignedBigInt(n as u64))
+    } else {
+        None
+    }
+}
+
+/// A real result of arithmetic, if it is finite and its integral part fits a `Real`.
+fn real(f: f64) -> Option<DataType> {
+    // 2^63 is exactly representable, unlike i64::MAX
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+    if f.is_finite() && f.abs() < LIMIT {
+        Some(f.into())
+    } else {
+        None
+    }
+}
+
 impl<'a, 'b> Add<&'b DataType> for &'a DataType {
     type Output = DataType;
 
     fn add(self, other: &'b DataType) -> DataType {
-        arithmetic_operation!(+, self, other)
+        self.checked_add(other)
+            .unwrap_or_else(|| panic!("can't + a {:?} and {:?}", self, other))
     }
 }
 
@@ -643,7 +1208,8 @@ impl<'a, 'b> Sub<&'b DataType> for &'a DataType {
     type Output = DataType;
 
     fn sub(self, other: &'b DataType) -> DataType {
-        arithmetic_operation!(-, self, other)
+        self.checked_sub(other)
+            .unwrap_or_else(|| panic!("can't - a {:?} and {:?}", self, other))
     }
 }
 
@@ -651,7 +1217,8 @@ impl<'a, 'b> Mul<&'b DataType> for &'a DataType {
     type Output = DataType;
 
     fn mul(self, other: &'b DataType) -> DataType {
-        arithmetic_operation!(*, self, other)
+        self.checked_mul(other)
+            .unwrap_or_else(|| panic!("can't * a {:?} and {:?}", self, other))
     }
 }
 
@@ -659,7 +1226,98 @@ impl<'a, 'b> Div<&'b DataType> for &'a DataType {
     type Output = DataType;
 
     fn div(self, other: &'b DataType) -> DataType {
-        arithmetic_operation!(/, self, other)
+        self.checked_div(other)
+            .unwrap_or_else(|| panic!("can't / a {:?} and {:?}", self, other))
+    }
+}
+
+impl DataType {
+    /// Add `other` to this value.
+    ///
+    /// `None` is returned if either value isn't a number, or if the sum can't be represented.
+    /// NULL plus anything is NULL.
+    pub fn checked_add(&self, other: &DataType) -> Option<DataType> {
+        arithmetic_operation!(+, checked_add, add_decimals, self, other)
+    }
+
+    /// Subtract `other` from this value.
+    ///
+    /// `None` is returned if either value isn't a number, or if the difference can't be
+    /// represented. NULL minus anything is NULL.
+    pub fn checked_sub(&self, other: &DataType) -> Option<DataType> {
+        arithmetic_operation!(-, checked_sub, sub_decimals, self, other)
+    }
+
+    /// Multiply this value by `other`.
+    ///
+    /// `None` is returned if either value isn't a number, or if the product can't be represented.
+    /// NULL times anything is NULL.
+    pub fn checked_mul(&self, other: &DataType) -> Option<DataType> {
+        arithmetic_operation!(*, checked_mul, mul_decimals, self, other)
+    }
+
+    /// Divide this value by `other`. Like in MySQL, division by zero is NULL.
+    ///
+    /// `None` is returned if either value isn't a number, or if the quotient can't be
+    /// represented. NULL divided by anything is NULL.
+    pub fn checked_div(&self, other: &DataType) -> Option<DataType> {
+        if self.is_number() && other.is_zero() {
+            return Some(DataType::None);
+        }
+        arithmetic_operation!(/, checked_div, div_decimals, self, other)
+    }
+
+    fn is_number(&self) -> bool {
+        self.is_integral() || self.is_real() || self.is_decimal()
+    }
+
+    fn is_zero(&self) -> bool {
+        match *self {
+            DataType::Int(0)
+            | DataType::UnsignedInt(0)
+            | DataType::BigInt(0)
+            | DataType::UnsignedBigInt(0)
+            | DataType::Real(0, 0)
+            | DataType::Decimal(0, _) => true,
+            _ => false,
+        }
+    }
+
+    /// Negate this value. Integers whose negation doesn't fit their type are promoted to a wider
+    /// one.
+    ///
+    /// `None` is returned if this value isn't a number, or if its negation can't be represented
+    /// at all. NULL stays NULL.
+    pub fn checked_neg(&self) -> Option<DataType> {
+        match *self {
+            DataType::None => Some(DataType::None),
+            DataType::Int(a) => Some(match a.checked_neg() {
+                Some(a) => a.into(),
+                None => (-i64::from(a)).into(),
+            }),
+            DataType::BigInt(a) => Some(match a.checked_neg() {
+                Some(a) => a.into(),
+                // only i64::MIN overflows, and its negation fits in a u64
+                None => DataType::UnsignedBigInt(a.wrapping_neg() as u64),
+            }),
+            DataType::UnsignedInt(a) => Some((-i64::from(a)).into()),
+            DataType::UnsignedBigInt(a) => {
+                let a = -i128::from(a);
+                if a >= i128::from(std::i64::MIN) {
+                    Some(DataType::BigInt(a as i64))
+                } else {
+                    None
+                }
+            }
+            DataType::Real(i, frac) => Some(DataType::Real(i.checked_neg()?, -frac)),
+            DataType::Decimal(m, scale) => match m.checked_neg() {
+                Some(m) => Some(DataType::Decimal(m, scale)),
+                // rounding away a fractional digit makes room for the negation
+                None if scale > 0 => DataType::decimal(-i128::from(m), scale),
+                None => None,
+            },
+            _ => None,
+        }
     }
 }
 
@@ -717,13 +1375,16 @@ pub enum TableOperation {
         /// The key used to identify the row to update.
         key: Vec<DataType>,
     },
+    /// Insert the contained row, unless a row with the same primary or unique key already exists,
+    /// in which case the row is silently discarded (like MySQL's `INSERT IGNORE`).
+    InsertIgnore(Vec<DataType>),
 }
 
 impl TableOperation {
     #[doc(hidden)]
     pub fn row(&self) -> Option<&[DataType]> {
         match *self {
-            TableOperation::Insert(ref r) => Some(r),
+            TableOperation::Insert(ref r) | TableOperation::InsertIgnore(ref r) => Some(r),
             TableOperation::InsertOrUpdate { ref row, .. } => Some(row),
             _ => None,
         }
@@ -740,6 +1401,13 @@ impl From<Vec<DataType>> for TableOperation {
 mod tests {
     use super::*;
 
+    fn hash_of(dt: &DataType) -> u64 {
+        use std::collections::hash_map::DefaultHasher;
+        let mut s = DefaultHasher::new();
+        dt.hash(&mut s);
+        s.finish()
+    }
+
     #[test]
     fn mysql_value_to_datatype() {
         use assert_approx_eq::assert_approx_eq;
@@ -752,10 +1420,16 @@ mod tests {
         assert_eq!(a_dt.unwrap(), DataType::None);
 
         // Test Value::Bytes.
-        // Can't build a CString with interior nul-terminated chars.
+        // Can't build a CString with interior nul-terminated chars, so these are binary.
         let a = Value::Bytes(vec![0; 30]);
         let a_dt = DataType::try_from(a);
-        assert!(a_dt.is_err());
+        assert!(a_dt.is_ok());
+        assert_eq!(a_dt.unwrap(), DataType::from(vec![0; 30]));
+
+        let a = Value::Bytes(vec![0xff, 0xfe, 1]);
+        let a_dt = DataType::try_from(a);
+        assert!(a_dt.is_ok());
+        assert_eq!(a_dt.unwrap(), DataType::from(vec![0xff, 0xfe, 1]));
 
         let a = Value::Bytes(vec![1; 30]);
         let a_dt = DataType::try_from(a);
@@ -799,10 +1473,12 @@ mod tests {
         assert_eq!(a_dt.unwrap(), DataType::Timestamp(ts));
 
         // Test Value::Time.
-        // noria::DataType has no `Time` representation.
-        let a = Value::Time(true, 0, 0, 0, 0, 0);
-        let a_dt = DataType::try_from(a);
-        assert!(a_dt.is_err());
+        let a = Value::Time(true, 1, 2, 3, 4, 5);
+        let a_dt = DataType::try_from(a.clone());
+        assert!(a_dt.is_ok());
+        let a_dt = a_dt.unwrap();
+        assert_eq!(a_dt, DataType::Time(-(93_784 * 1_000_000 + 5)));
+        assert_eq!(Value::from(a_dt), a);
     }
 
     #[test]
@@ -868,6 +1544,69 @@ mod tests {
         assert_eq!(&DataType::BigInt(4) / &DataType::from(2), 2.into());
     }
 
+    #[test]
+    fn negate_data_types() {
+        let neg = |d: DataType| d.checked_neg();
+        assert_eq!(neg(DataType::from(2)), Some((-2).into()));
+        assert_eq!(neg(DataType::from(-2.5)), Some((2.5).into()));
+        assert_eq!(neg(DataType::BigInt(3)), Some((-3).into()));
+        assert_eq!(neg(DataType::UnsignedInt(3)), Some((-3).into()));
+        assert_eq!(neg(DataType::UnsignedBigInt(3)), Some((-3).into()));
+        assert_eq!(neg(DataType::None), Some(DataType::None));
+        assert_eq!(neg("3".into()), None);
+
+        // negations that overflow are promoted to a wider type
+        assert_eq!(
+            neg(DataType::Int(std::i32::MIN)),
+            Some(DataType::BigInt(-i64::from(std::i32::MIN)))
+        );
+        assert_eq!(
+            neg(DataType::BigInt(std::i64::MIN)),
+            Some(DataType::UnsignedBigInt(1 << 63))
+        );
+        assert_eq!(
+            neg(DataType::UnsignedBigInt(1 << 63)),
+            Some(DataType::BigInt(std::i64::MIN))
+        );
+        assert_eq!(neg(DataType::UnsignedBigInt(std::u64::MAX)), None);
+    }
+
+    #[test]
+    fn checked_arithmetic() {
+        let (a, b) = (DataType::Int(std::i32::MAX), DataType::Int(1));
+        assert_eq!(a.checked_add(&b), None);
+        assert_eq!(a.checked_sub(&b), Some((std::i32::MAX - 1).into()));
+        assert_eq!(DataType::Int(std::i32::MIN).checked_div(&(-1).into()), None);
+        assert_eq!(
+            DataType::BigInt(-1).checked_add(&DataType::UnsignedBigInt(std::u64::MAX)),
+            Some(DataType::UnsignedBigInt(std::u64::MAX - 1))
+        );
+        assert_eq!(
+            DataType::BigInt(-1).checked_mul(&DataType::UnsignedBigInt(std::u64::MAX)),
+            None
+        );
+        assert_eq!(DataType::from(4e18).checked_mul(&4e18.into()), None);
+
+        // division by zero is NULL
+        assert_eq!(
+            DataType::Int(5).checked_div(&0.into()),
+            Some(DataType::None)
+        );
+        assert_eq!(
+            DataType::from(2.5).checked_div(&DataType::BigInt(0)),
+            Some(DataType::None)
+        );
+
+        // only numbers can be combined
+        assert_eq!(DataType::Int(5).checked_add(&"5".into()), None);
+        assert_eq!(DataType::Bool(true).checked_mul(&DataType::Int(2)), None);
+        assert_eq!(DataType::from("hi").checked_div(&0.into()), None);
+        assert_eq!(
+            DataType::None.checked_sub(&"hi".into()),
+            Some(DataType::None)
+        );
+    }
+
     #[test]
     #[should_panic(expected = "can't + a TinyText(\"hi\") and Int(5)")]
     fn add_invalid_types() {
@@ -911,6 +1650,197 @@ mod tests {
         assert_eq!(format!("{}", big_int), "5");
     }
 
+    #[test]
+    fn bool_bytes_and_uuids() {
+        let t = DataType::from(true);
+        let f = DataType::from(false);
+        assert_eq!(t, DataType::Int(1));
+        assert_eq!(DataType::UnsignedBigInt(0), f);
+        assert_ne!(t, DataType::Int(2));
+        assert_eq!(hash_of(&t), hash_of(&DataType::BigInt(1)));
+        assert_eq!(t.cmp(&DataType::Int(2)), Ordering::Less);
+        assert_eq!(f.cmp(&t), Ordering::Less);
+        assert!(bool::from(&t));
+        assert!(!bool::from(DataType::Int(0)));
+        assert_eq!(format!("{} {:?}", t, f), "TRUE Bool(false)");
+
+        let bytes = DataType::from(vec![0, 1, 0xff]);
+        let bytes2 = DataType::from(vec![0, 2]);
+        assert_eq!(bytes, bytes.deep_clone());
+        assert_ne!(bytes, bytes2);
+        assert_ne!(hash_of(&bytes), hash_of(&bytes2));
+        assert_eq!(bytes.cmp(&bytes2), Ordering::Less);
+        assert_eq!(<&[u8]>::from(&bytes), &[0, 1, 0xff]);
+        assert_eq!(format!("{}", bytes), "X'0001ff'");
+
+        let s = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        let u = DataType::from(Uuid::parse_str(s).unwrap());
+        assert_eq!(u, u.deep_clone());
+        assert_ne!(u, DataType::from(Uuid::nil()));
+        assert_eq!(hash_of(&u), hash_of(&u.deep_clone()));
+        assert_eq!(Uuid::from(&u), Uuid::from(&DataType::from(s)));
+        assert_eq!(format!("{}", u), s);
+
+        // new variants must round-trip through serialization, as they do in persistent state
+        let row = vec![t, bytes, u];
+        let serialized = bincode::serialize(&row).unwrap();
+        assert_eq!(
+            bincode::deserialize::<Vec<DataType>>(&serialized).unwrap(),
+            row
+        );
+    }
+
+    #[test]
+    fn decimals() {
+        use mysql_common::value::Value;
+
+        let a = DataType::Decimal(12345, 2);
+        let b = DataType::Decimal(-5, 3);
+        assert_eq!(
+            format!("{} {} {:?}", a, b, a),
+            "123.45 -0.005 Decimal(123.45)"
+        );
+        assert_eq!(DataType::Decimal(-42, 0).to_string(), "-42");
+
+        // scale doesn't matter for equality, and whole decimals are integers
+        assert_eq!(DataType::Decimal(150, 2), DataType::Decimal(15, 1));
+        assert_eq!(
+            hash_of(&DataType::Decimal(150, 2)),
+            hash_of(&DataType::Decimal(15, 1))
+        );
+        assert_eq!(DataType::Decimal(500, 2), DataType::Int(5));
+        assert_eq!(
+            hash_of(&DataType::Decimal(500, 2)),
+            hash_of(&DataType::BigInt(5))
+        );
+        assert_ne!(DataType::Decimal(501, 2), DataType::Int(5));
+        assert_eq!(a.cmp(&DataType::Int(124)), Ordering::Less);
+        assert_eq!(b.cmp(&DataType::Decimal(-4, 3)), Ordering::Less);
+
+        // arithmetic is exact, unlike with reals
+        let cent = DataType::Decimal(1, 2);
+        let mut sum = DataType::Int(0);
+        for _ in 0..10 {
+            sum = &sum + &DataType::Decimal(1, 1);
+        }
+        assert_eq!(sum, DataType::Int(1));
+        assert_eq!(&a - &cent, DataType::Decimal(12344, 2));
+        assert_eq!(&a * &DataType::Int(3), DataType::Decimal(37035, 2));
+        assert_eq!(&a * &cent, DataType::Decimal(12345, 4));
+        assert_eq!(
+            &DataType::Int(1) / &DataType::Decimal(3, 0),
+            DataType::Decimal(3333, 4)
+        );
+        assert_eq!(
+            &DataType::Decimal(2, 0) / &DataType::Decimal(3, 0),
+            DataType::Decimal(6667, 4)
+        );
+        assert_eq!(&a / &DataType::Decimal(0, 1), DataType::None);
+        assert_eq!(b.checked_neg(), Some(DataType::Decimal(5, 3)));
+
+        // results too large for a decimal can't be computed, rather than panicking
+        let huge = DataType::Decimal(std::i64::MAX, 0);
+        assert_eq!(
+            DataType::decimal(i128::from(std::i64::MAX) * 10, 1),
+            Some(huge.clone())
+        );
+        assert_eq!(DataType::decimal(i128::from(std::i64::MAX) * 10, 0), None);
+        assert_eq!(huge.checked_mul(&DataType::Int(2)), None);
+        assert_eq!(huge.checked_div(&DataType::Decimal(1, 3)), None);
+        assert_eq!(
+            huge.checked_div(&DataType::Decimal(2, 0)),
+            Some(DataType::Decimal(std::i64::MAX / 2 + 1, 0))
+        );
+        assert_eq!(&a + &DataType::from(0.5), DataType::from(123.95));
+
+        // values are rounded to the column's scale, and must fit its precision
+        assert_eq!(
+            DataType::from("12.345").to_decimal(5, 2),
+            Some(DataType::Decimal(1235, 2))
+        );
+        assert_eq!(
+            DataType::from("-0.5").to_decimal(5, 0),
+            Some(DataType::Decimal(-1, 0))
+        );
+        assert_eq!(
+            DataType::from(2.5).to_decimal(5, 2),
+            Some(DataType::Decimal(250, 2))
+        );
+        assert_eq!(
+            DataType::Int(999).to_decimal(5, 2),
+            Some(DataType::Decimal(99900, 2))
+        );
+        assert_eq!(DataType::Int(1000).to_decimal(5, 2), None);
+        assert_eq!(DataType::from("1.2.3").to_decimal(5, 2), None);
+        assert_eq!(DataType::None.to_decimal(5, 2), Some(DataType::None));
+
+        // MySQL sends decimals as text
+        let v = Value::from(a.clone());
+        assert_eq!(v, Value::Bytes(b"123.45".to_vec()));
+        assert_eq!(
+            DataType::try_from(v).unwrap().to_decimal(10, 2),
+            Some(a.clone())
+        );
+
+        let serialized = bincode::serialize(&vec![a.clone(), b.clone()]).unwrap();
+        assert_eq!(
+            bincode::deserialize::<Vec<DataType>>(&serialized).unwrap(),
+            vec![a, b]
+        );
+    }
+
+    #[test]
+    fn dates_and_times() {
+        use chrono::TimeZone;
+
+        let day = NaiveDate::from_ymd(2020, 2, 29);
+        let date = DataType::from(day);
+        let midnight = DataType::from(day.and_hms(0, 0, 0));
+        let noon = DataType::from(day.and_hms(12, 0, 0));
+        assert_eq!(date, midnight);
+        assert_eq!(hash_of(&date), hash_of(&midnight));
+        assert_ne!(date, noon);
+        assert_eq!(date.cmp(&noon), Ordering::Less);
+        assert_eq!(noon.cmp(&DataType::from(day.succ())), Ordering::Less);
+        assert_eq!(NaiveDate::from(&noon), day);
+        assert_eq!(
+            format!("{} {:?}", date, date),
+            "2020-02-29 Date(2020-02-29)"
+        );
+
+        let t = DataType::from(NaiveTime::from_hms_micro(9, 5, 0, 250));
+        assert_eq!(t, DataType::Time(32_700_000_250));
+        assert_eq!(t.to_string(), "09:05:00.000250");
+        assert_eq!(
+            DataType::Time(-3_600_000_000 * 100).to_string(),
+            "-100:00:00"
+        );
+        assert_eq!(t.cmp(&DataType::Time(-1)), Ordering::Greater);
+
+        // zoned timestamps are equal if they are the same instant
+        let utc = FixedOffset::east(0).ymd(2020, 2, 29).and_hms(10, 0, 0);
+        let cet = FixedOffset::east(3600).ymd(2020, 2, 29).and_hms(11, 0, 0);
+        let later = FixedOffset::east(3600).ymd(2020, 2, 29).and_hms(10, 30, 0);
+        assert_eq!(DataType::from(utc), DataType::from(cet));
+        assert_eq!(hash_of(&DataType::from(utc)), hash_of(&DataType::from(cet)));
+        assert_eq!(
+            DataType::from(later).cmp(&DataType::from(utc)),
+            Ordering::Less
+        );
+        assert_ne!(DataType::from(utc), DataType::from(utc.naive_utc()));
+        assert_eq!(
+            DataType::from(cet).to_string(),
+            "Sat Feb 29 11:00:00 2020 +01:00"
+        );
+
+        let row = vec![date, t, DataType::from(cet)];
+        let serialized = bincode::serialize(&row).unwrap();
+        assert_eq!(
+            bincode::deserialize::<Vec<DataType>>(&serialized).unwrap(),
+            row
+        );
+    }
+
     #[test]
     #[allow(clippy::cognitive_complexity)]
     fn data_type_fungibility() {
@@ -1115,81 +2045,73 @@ mod tests {
         assert_ne!(long.cmp(&time), Ordering::Equal);
         assert_ne!(long.cmp(&shrt6), Ordering::Equal);
 
-        let hash = |dt: &DataType| {
-            use std::collections::hash_map::DefaultHasher;
-            use std::hash::{Hash, Hasher};
-            let mut s = DefaultHasher::new();
-            dt.hash(&mut s);
-            s.finish()
-        };
-
-        assert_eq!(hash(&txt1), hash(&txt1));
-        assert_eq!(hash(&txt2), hash(&txt2));
-        assert_eq!(hash(&text), hash(&text));
-        assert_eq!(hash(&shrt), hash(&shrt));
-        assert_eq!(hash(&long), hash(&long));
-        assert_eq!(hash(&real), hash(&real));
-        assert_eq!(hash(&time), hash(&time));
+        assert_eq!(hash_of(&txt1), hash_of(&txt1));
+        assert_eq!(hash_of(&txt2), hash_of(&txt2));
+        assert_eq!(hash_of(&text), hash_of(&text));
+        assert_eq!(hash_of(&shrt), hash_of(&shrt));
+        assert_eq!(hash_of(&long), hash_of(&long));
+        assert_eq!(hash_of(&real), hash_of(&real));
+        assert_eq!(hash_of(&time), hash_of(&time));
 
         // coercion
-        assert_eq!(hash(&txt1), hash(&txt2));
-        assert_eq!(hash(&txt2), hash(&txt1));
-        assert_eq!(hash(&shrt), hash(&long));
-        assert_eq!(hash(&long), hash(&shrt));
+        assert_eq!(hash_of(&txt1), hash_of(&txt2));
+        assert_eq!(hash_of(&txt2), hash_of(&txt1));
+        assert_eq!(hash_of(&shrt), hash_of(&long));
+        assert_eq!(hash_of(&long), hash_of(&shrt));
 
         // negation
-        assert_ne!(hash(&txt1), hash(&txt12));
-        assert_ne!(hash(&txt1), hash(&text));
-        assert_ne!(hash(&txt1), hash(&real));
-        assert_ne!(hash(&txt1), hash(&time));
-        assert_ne!(hash(&txt1), hash(&shrt));
-        assert_ne!(hash(&txt1), hash(&long));
-
-        assert_ne!(hash(&txt2), hash(&txt12));
-        assert_ne!(hash(&txt2), hash(&text));
-        assert_ne!(hash(&txt2), hash(&real));
-        assert_ne!(hash(&txt2), hash(&time));
-        assert_ne!(hash(&txt2), hash(&shrt));
-        assert_ne!(hash(&txt2), hash(&long));
-
-        assert_ne!(hash(&text), hash(&text2));
-        assert_ne!(hash(&text), hash(&txt1));
-        assert_ne!(hash(&text), hash(&txt2));
-        assert_ne!(hash(&text), hash(&real));
-        assert_ne!(hash(&text), hash(&time));
-        assert_ne!(hash(&text), hash(&shrt));
-        assert_ne!(hash(&text), hash(&long));
-
-        assert_ne!(hash(&real), hash(&real2));
-        assert_ne!(hash(&real), hash(&txt1));
-        assert_ne!(hash(&real), hash(&txt2));
-        assert_ne!(hash(&real), hash(&text));
-        assert_ne!(hash(&real), hash(&time));
-        assert_ne!(hash(&real), hash(&shrt));
-        assert_ne!(hash(&real), hash(&long));
-
-        assert_ne!(hash(&time), hash(&time2));
-        assert_ne!(hash(&time), hash(&txt1));
-        assert_ne!(hash(&time), hash(&txt2));
-        assert_ne!(hash(&time), hash(&text));
-        assert_ne!(hash(&time), hash(&real));
-        assert_ne!(hash(&time), hash(&shrt));
-        assert_ne!(hash(&time), hash(&long));
-
-        assert_ne!(hash(&shrt), hash(&shrt6));
-        assert_ne!(hash(&shrt), hash(&txt1));
-        assert_ne!(hash(&shrt), hash(&txt2));
-        assert_ne!(hash(&shrt), hash(&text));
-        assert_ne!(hash(&shrt), hash(&real));
-        assert_ne!(hash(&shrt), hash(&time));
-        assert_ne!(hash(&shrt), hash(&long6));
-
-        assert_ne!(hash(&long), hash(&long6));
-        assert_ne!(hash(&long), hash(&txt1));
-        assert_ne!(hash(&long), hash(&txt2));
-        assert_ne!(hash(&long), hash(&text));
-        assert_ne!(hash(&long), hash(&real));
-        assert_ne!(hash(&long), hash(&time));
-        assert_ne!(hash(&long), hash(&shrt6));
+        assert_ne!(hash_of(&txt1), hash_of(&txt12));
+        assert_ne!(hash_of(&txt1), hash_of(&text));
+        assert_ne!(hash_of(&txt1), hash_of(&real));
+        assert_ne!(hash_of(&txt1), hash_of(&time));
+        assert_ne!(hash_of(&txt1), hash_of(&shrt));
+        assert_ne!(hash_of(&txt1), hash_of(&long));
+
+        assert_ne!(hash_of(&txt2), hash_of(&txt12));
+        assert_ne!(hash_of(&txt2), hash_of(&text));
+        assert_ne!(hash_of(&txt2), hash_of(&real));
+        assert_ne!(hash_of(&txt2), hash_of(&time));
+        assert_ne!(hash_of(&txt2), hash_of(&shrt));
+        assert_ne!(hash_of(&txt2), hash_of(&long));
+
+        assert_ne!(hash_of(&text), hash_of(&text2));
+        assert_ne!(hash_of(&text), hash_of(&txt1));
+        assert_ne!(hash_of(&text), hash_of(&txt2));
+        assert_ne!(hash_of(&text), hash_of(&real));
+        assert_ne!(hash_of(&text), hash_of(&time));
+        assert_ne!(hash_of(&text), hash_of(&shrt));
+        assert_ne!(hash_of(&text), hash_of(&long));
+
+        assert_ne!(hash_of(&real), hash_of(&real2));
+        assert_ne!(hash_of(&real), hash_of(&txt1));
+        assert_ne!(hash_of(&real), hash_of(&txt2));
+        assert_ne!(hash_of(&real), hash_of(&text));
+        assert_ne!(hash_of(&real), hash_of(&time));
+        assert_ne!(hash_of(&real), hash_of(&shrt));
+        assert_ne!(hash_of(&real), hash_of(&long));
+
+        assert_ne!(hash_of(&time), hash_of(&time2));
+        assert_ne!(hash_of(&time), hash_of(&txt1));
+        assert_ne!(hash_of(&time), hash_of(&txt2));
+        assert_ne!(hash_of(&time), hash_of(&text));
+        assert_ne!(hash_of(&time), hash_of(&real));
+        assert_ne!(hash_of(&time), hash_of(&shrt));
+        assert_ne!(hash_of(&time), hash_of(&long));
+
+        assert_ne!(hash_of(&shrt), hash_of(&shrt6));
+        assert_ne!(hash_of(&shrt), hash_of(&txt1));
+        assert_ne!(hash_of(&shrt), hash_of(&txt2));
+        assert_ne!(hash_of(&shrt), hash_of(&text));
+        assert_ne!(hash_of(&shrt), hash_of(&real));
+        assert_ne!(hash_of(&shrt), hash_of(&time));
+        assert_ne!(hash_of(&shrt), hash_of(&long6));
+
+        assert_ne!(hash_of(&long), hash_of(&long6));
+        assert_ne!(hash_of(&long), hash_of(&txt1));
+        assert_ne!(hash_of(&long), hash_of(&txt2));
+        assert_ne!(hash_of(&long), hash_of(&text));
+        assert_ne!(hash_of(&long), hash_of(&real));
+        assert_ne!(hash_of(&long), hash_of(&time));
+        assert_ne!(hash_of(&long), hash_of(&shrt6));
     }
 }
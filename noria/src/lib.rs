@@ -222,6 +222,8 @@ pub mod internal;
 
 // for the row! macro
 #[doc(hidden)]
+pub use crate::data::is_default_expr;
+#[doc(hidden)]
 pub use nom_sql::ColumnConstraint;
 
 pub use crate::consensus::ZookeeperAuthority;
@@ -245,6 +247,7 @@ pub mod results {
 
 /// Noria errors.
 pub mod error {
+    pub use crate::controller::RecipeError;
     pub use crate::table::TableError;
     pub use crate::view::ViewError;
 }
@@ -299,12 +302,13 @@ pub use crate::controller::{ControllerDescriptor, ControllerHandle};
 pub use crate::data::{DataType, Modification, Operation, TableOperation};
 pub use crate::table::Table;
 pub use crate::view::View;
+pub use uuid::Uuid;
 
 #[doc(hidden)]
-pub use crate::table::Input;
+pub use crate::table::{Input, WriteError, WriteReply};
 
 #[doc(hidden)]
-pub use crate::view::{ReadQuery, ReadReply, ReadReplyBatch};
+pub use crate::view::{ReadError, ReadQuery, ReadReply, ReadReplyBatch};
 
 #[doc(hidden)]
 pub mod builders {
@@ -328,6 +332,59 @@ pub struct ActivationResult {
     pub expressions_removed: usize,
 }
 
+/// Describes the changes that a recipe change would make to the data-flow graph, as planned by
+/// `ControllerHandle::plan_recipe`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct MigrationPlan {
+    /// The queries that the change would add, by name.
+    pub queries: HashMap<String, QueryPlan>,
+    /// Leaf nodes that the change would remove.
+    pub removed_leaves: Vec<NodeIndex>,
+    /// Number of new domains that the new nodes would be placed in.
+    pub new_domains: usize,
+    /// State that the change would create, or add indices to.
+    pub materializations: Vec<PlannedState>,
+    /// Estimated memory size in bytes of the existing state that would be replayed to fill the
+    /// new state.
+    pub replay_bytes: u64,
+}
+
+/// Describes how a query would be added to the data-flow graph.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct QueryPlan {
+    /// Existing nodes that the query would reuse.
+    pub reused_nodes: Vec<NodeIndex>,
+    /// Number of nodes that the query would add.
+    pub new_nodes: usize,
+}
+
+/// Describes state that a recipe change would create, or add indices to.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PlannedState {
+    /// The name of the node that holds the state.
+    pub node: String,
+    /// The node's index, if the node already exists.
+    pub existing: Option<NodeIndex>,
+    /// The columns of the indices that the state would get.
+    pub indices: Vec<Vec<usize>>,
+    /// Whether the state would be partially materialized, and thus only filled on demand.
+    pub partial: bool,
+    /// Whether the state would be filled by replaying existing state before the change completes.
+    pub backfill: bool,
+    /// Estimated memory size in bytes of the existing state that filling this state would replay.
+    pub replay_bytes: u64,
+}
+
+/// A recipe that the controller applied, as listed by `ControllerHandle::recipe_history`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RecipeVersion {
+    /// The version of the recipe.
+    pub version: usize,
+    /// The text of the installed recipe, followed by the texts of the extensions that were applied
+    /// to it, in order.
+    pub recipes: Vec<String>,
+}
+
 #[doc(hidden)]
 #[inline]
 pub fn shard_by(dt: &DataType, shards: usize) -> usize {
@@ -336,11 +393,49 @@ pub fn shard_by(dt: &DataType, shards: usize) -> usize {
         DataType::UnsignedInt(n) => n as usize % shards,
         DataType::BigInt(n) => n as usize % shards,
         DataType::UnsignedBigInt(n) => n as usize % shards,
-        DataType::Text(..) | DataType::TinyText(..) => {
+        DataType::Bool(b) => b as usize % shards,
+        DataType::Text(..) | DataType::TinyText(..) | DataType::ByteArray(..) => {
+            use std::hash::Hasher;
+            let mut hasher = ahash::AHasher::new_with_keys(0x3306, 0x6033);
+            let bytes: &[u8] = dt.into();
+            hasher.write(bytes);
+            hasher.finish() as usize % shards
+        }
+        DataType::Uuid(ref u) => {
             use std::hash::Hasher;
             let mut hasher = ahash::AHasher::new_with_keys(0x3306, 0x6033);
-            let s: &str = dt.into();
-            hasher.write(s.as_bytes());
+            hasher.write(u.as_bytes());
+            hasher.finish() as usize % shards
+        }
+        DataType::Decimal(m, scale) => match crate::data::normalize_decimal(m, scale) {
+            // whole decimals must go to the same shard as the integers they are equal to
+            (m, 0) => m as usize % shards,
+            (m, scale) => {
+                use std::hash::Hasher;
+                let mut hasher = ahash::AHasher::new_with_keys(0x3306, 0x6033);
+                hasher.write_i64(m);
+                hasher.write_u8(scale);
+                hasher.finish() as usize % shards
+            }
+        },
+        DataType::Timestamp(..) | DataType::Date(..) => {
+            use std::hash::{Hash, Hasher};
+            let mut hasher = ahash::AHasher::new_with_keys(0x3306, 0x6033);
+            // dates must go to the same shard as the timestamps they are equal to
+            let ts: chrono::NaiveDateTime = dt.into();
+            ts.hash(&mut hasher);
+            hasher.finish() as usize % shards
+        }
+        DataType::Time(t) => {
+            use std::hash::Hasher;
+            let mut hasher = ahash::AHasher::new_with_keys(0x3306, 0x6033);
+            hasher.write_i64(t);
+            hasher.finish() as usize % shards
+        }
+        DataType::TimestampTz(ref ts) => {
+            use std::hash::{Hash, Hasher};
+            let mut hasher = ahash::AHasher::new_with_keys(0x3306, 0x6033);
+            ts.hash(&mut hasher);
             hasher.finish() as usize % shards
         }
         // a bit hacky: send all NULL values to the first shard
@@ -4,11 +4,12 @@ use crate::internal::*;
 use crate::LocalOrNot;
 use crate::{Tagged, Tagger};
 use async_bincode::{AsyncBincodeStream, AsyncDestination};
+use chrono::{NaiveDate, NaiveDateTime};
 use futures_util::{
     future, future::TryFutureExt, ready, stream::futures_unordered::FuturesUnordered,
     stream::TryStreamExt,
 };
-use nom_sql::CreateTableStatement;
+use nom_sql::{ColumnConstraint, ColumnSpecification, CreateTableStatement, Literal, SqlType};
 use petgraph::graph::NodeIndex;
 use std::collections::HashMap;
 use std::future::Future;
@@ -27,16 +28,16 @@ use vec_map::VecMap;
 
 type Transport = AsyncBincodeStream<
     tokio::net::TcpStream,
-    Tagged<()>,
+    Tagged<WriteReply>,
     Tagged<LocalOrNot<Input>>,
     AsyncDestination,
 >;
 
 /// Create a new row for insertion into a [`Table`] using column names.
 ///
-/// If the schema of the given table is known, column defaults and `NOT NULL` restrictions will
-/// also be respected. In the future, this method will also check that the provided `DataType`
-/// matches the expected data type for each column.
+/// If the schema of the given table is known, column defaults will also be respected. Values are
+/// checked against the type and `NOT NULL` restrictions of their column when the row is written
+/// to the table, which fails with [`TableError::InvalidValue`] if they do not match.
 ///
 /// Values are automatically converted to `DataType` as necessary.
 ///
@@ -49,7 +50,8 @@ type Transport = AsyncBincodeStream<
 ///     "created_at" => chrono::Local::now().naive_local(),
 ///     "logins" => 0,
 ///   );
-///   users.insert(user).await
+///   users.insert(user).await?;
+///   Ok(())
 /// }
 /// ```
 #[macro_export]
@@ -91,39 +93,27 @@ macro_rules! row {
         for (coli, col) in $tbl.columns().iter().enumerate() {
             match &**col {
                 $($k => {
-                    // TODO: check row[coli] against schema.fields[coli].sql_type ?
                     row[coli] = vals[$idx].take().expect("field name appears twice -- should be caught by match");
-                    if let Some(ref schema) = schema {
-                        if schema.fields[coli].constraints.iter().any(|c| c == &$crate::ColumnConstraint::NotNull) {
-                            assert!(!row[coli].is_none(), "Attempted to set NOT NULL column '{}' to DataType::None", col);
-                        }
-                    }
                 },)|+
-                cname if schema.is_some() => {
+                _ if schema.is_some() => {
                     let schema = schema.as_ref().unwrap();
 
                     // Maybe we have a default value?
-                    let mut allow_null = true;
+                    // NOT NULL columns without one are rejected when the row is written.
+                    // AUTO_INCREMENT values and defaults like CURRENT_TIMESTAMP are left NULL for
+                    // the table to fill in.
                     let spec = &schema.fields[coli];
                     for c in &spec.constraints {
                         use $crate::ColumnConstraint;
                         match c {
-                            ColumnConstraint::NotNull => {
-                                allow_null = false;
-                            }
-                            ColumnConstraint::DefaultValue(ref literal) => {
+                            ColumnConstraint::DefaultValue(ref literal)
+                                if !$crate::is_default_expr(literal) =>
+                            {
                                 row[coli] = Into::<$crate::DataType>::into(literal);
                             }
-                            ColumnConstraint::AutoIncrement => {
-                                // TODO
-                            }
                             _ => {}
                         }
                     }
-
-                    if !allow_null && row[coli].is_none() {
-                        panic!("Column {} is declared NOT NULL, has no default, and was not provided", cname);
-                    }
                 }
                 _ => { /* leave column value as None */ }
             }
@@ -146,13 +136,15 @@ async fn add_user(users: &mut Table) -> Result<(), TableError> {
       "not an ident" => s,
       "logins" => 0,
     );
-    users.insert(user).await
+    users.insert(user).await?;
+    Ok(())
 }
 
 /// Create an update for a given [`Table`] using column names.
 ///
-/// In the future, this method will also check that the provided `DataType`
-/// matches the expected data type for each column if the schema is known.
+/// If the schema of the table is known, the new values are checked against the type and `NOT
+/// NULL` restrictions of their column when the update is applied, which fails with
+/// [`TableError::InvalidValue`] if they do not match.
 ///
 /// Values are automatically converted to `DataType` as necessary.
 ///
@@ -181,7 +173,6 @@ macro_rules! update {
         for (coli, col) in $tbl.columns().iter().enumerate() {
             match &**col {
                 $($k => {
-                    // TODO: check set[$idx].1 against schema.fields[coli].sql_type ?
                     set[$idx].0 = coli;
                 },)|+
                 _ => { /* column value not updated */ }
@@ -289,6 +280,30 @@ pub enum TableError {
     )]
     WrongKeyColumnCount(usize, usize),
 
+    /// A value did not match the type or constraints of the column it was written to.
+    #[fail(
+        display = "invalid value {} for column `{}`: {}",
+        value, column, reason
+    )]
+    InvalidValue {
+        /// The name of the offending column.
+        column: String,
+        /// The value that was rejected.
+        value: DataType,
+        /// Why the value was rejected.
+        reason: String,
+    },
+
+    /// An insert was rejected because it would duplicate the primary or a unique key of an
+    /// existing row.
+    #[fail(display = "duplicate entry {:?} for key {:?}", key, columns)]
+    DuplicateKey {
+        /// The columns of the violated key.
+        columns: Vec<String>,
+        /// The duplicated key.
+        key: Vec<DataType>,
+    },
+
     /// The underlying connection to Noria produced an error.
     #[fail(display = "{}", _0)]
     TransportError(#[cause] failure::Error),
@@ -300,6 +315,32 @@ impl From<Box<dyn std::error::Error + Send + Sync>> for TableError {
     }
 }
 
+impl From<WriteError> for TableError {
+    fn from(e: WriteError) -> Self {
+        match e {
+            WriteError::DuplicateKey { columns, key } => TableError::DuplicateKey { columns, key },
+        }
+    }
+}
+
+/// Why a base table refused to apply a write.
+#[doc(hidden)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WriteError {
+    /// An insert would have duplicated the primary or a unique key of an existing row.
+    DuplicateKey {
+        /// The columns of the violated key.
+        columns: Vec<String>,
+        /// The duplicated key.
+        key: Vec<DataType>,
+    },
+}
+
+/// The reply a base table sends once it has processed a batch of writes. On success, it holds
+/// the values that were generated for the auto-increment column of inserted rows, in order.
+#[doc(hidden)]
+pub type WriteReply = Result<Vec<DataType>, WriteError>;
+
 #[doc(hidden)]
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Input {
@@ -436,7 +477,7 @@ impl Table {
     fn input(
         &mut self,
         mut i: Input,
-    ) -> impl Future<Output = Result<Tagged<()>, TableError>> + Send {
+    ) -> impl Future<Output = Result<Tagged<Vec<DataType>>, TableError>> + Send {
         let span = if crate::trace_next_op() {
             Some(tracing::trace_span!(
                 "table-request",
@@ -451,7 +492,7 @@ impl Table {
             let ncols = self.columns.len() + self.dropped.len();
             for op in &i.data {
                 match op {
-                    TableOperation::Insert(ref row) => {
+                    TableOperation::Insert(ref row) | TableOperation::InsertIgnore(ref row) => {
                         if row.len() != ncols {
                             return Err(TableError::WrongColumnCount(ncols, row.len()));
                         }
@@ -507,7 +548,12 @@ impl Table {
             let _guard = span.as_ref().map(tracing::Span::enter);
             tracing::trace!("submit request");
             future::Either::Right(future::Either::Left(
-                self.shards[0].call(request).map_err(TableError::from),
+                self.shards[0]
+                    .call(request)
+                    .map_err(TableError::from)
+                    .and_then(|Tagged { tag, v }| {
+                        future::ready(v.map(|v| Tagged { tag, v }).map_err(TableError::from))
+                    }),
             ))
         } else {
             if self.key.is_empty() {
@@ -525,7 +571,9 @@ impl Table {
             for r in i.data.drain(..) {
                 let shard = {
                     let key = match r {
-                        TableOperation::Insert(ref r) => &r[key_col],
+                        TableOperation::Insert(ref r) | TableOperation::InsertIgnore(ref r) => {
+                            &r[key_col]
+                        }
                         TableOperation::Delete { ref key } => &key[0],
                         TableOperation::Update { ref key, .. } => &key[0],
                         TableOperation::InsertOrUpdate { ref row, .. } => &row[key_col],
@@ -573,8 +621,16 @@ impl Table {
 
             future::Either::Right(future::Either::Right(
                 wait_for
-                    .try_for_each(|_| async { Ok(()) })
                     .map_err(TableError::from)
+                    .try_fold(Vec::new(), |mut generated, r| {
+                        future::ready(
+                            r.v.map(|v| {
+                                generated.extend(v);
+                                generated
+                            })
+                            .map_err(TableError::from),
+                        )
+                    })
                     .map_ok(Tagged::from),
             ))
         }
@@ -583,12 +639,12 @@ impl Table {
 
 impl Service<Vec<TableOperation>> for Table {
     type Error = TableError;
-    type Response = <TableRpc as Service<Tagged<LocalOrNot<Input>>>>::Response;
+    type Response = Tagged<Vec<DataType>>;
 
     #[cfg(not(doc))]
-    type Future = impl Future<Output = Result<Tagged<()>, TableError>> + Send;
+    type Future = impl Future<Output = Result<Tagged<Vec<DataType>>, TableError>> + Send;
     #[cfg(doc)]
-    type Future = crate::doc_mock::Future<Result<Tagged<()>, TableError>>;
+    type Future = crate::doc_mock::Future<Result<Tagged<Vec<DataType>>, TableError>>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         for s in &mut self.shards {
@@ -598,8 +654,10 @@ impl Service<Vec<TableOperation>> for Table {
     }
 
     fn call(&mut self, ops: Vec<TableOperation>) -> Self::Future {
-        let i = self.prep_records(ops);
-        self.input(i)
+        match self.prep_records(ops) {
+            Ok(i) => future::Either::Left(self.input(i)),
+            Err(e) => future::Either::Right(async move { Err(e) }),
+        }
     }
 }
 
@@ -640,6 +698,7 @@ impl Table {
             // get a handle to the underlying data vector
             let r = match *r {
                 TableOperation::Insert(ref mut row)
+                | TableOperation::InsertIgnore(ref mut row)
                 | TableOperation::InsertOrUpdate { ref mut row, .. } => row,
                 _ => unimplemented!("we need to shift the update/delete cols!"),
             };
@@ -716,15 +775,79 @@ impl Table {
         }
     }
 
-    fn prep_records(&self, mut ops: Vec<TableOperation>) -> Input {
+    /// Check the values of a write against the types and constraints of the columns they are
+    /// written to, converting them to the column type where MySQL would do so implicitly.
+    fn coerce_to_schema(&self, r: &mut TableOperation) -> Result<(), TableError> {
+        let schema = match self.schema {
+            Some(ref schema) => schema,
+            None => return Ok(()),
+        };
+        // look columns up by name, since the schema isn't updated if columns are added or dropped
+        let spec = |col: usize| {
+            let name = self.columns.get(col)?;
+            schema.fields.iter().find(|f| f.column.name == *name)
+        };
+        let coerce = |col: usize, v: &mut DataType| {
+            let spec = match spec(col) {
+                Some(spec) => spec,
+                None => return Ok(()),
+            };
+            coerce_to_column(spec, v).map_err(|reason| TableError::InvalidValue {
+                column: spec.column.name.clone(),
+                value: v.clone(),
+                reason,
+            })
+        };
+        let coerce_all = |mods: &mut [Modification]| -> Result<(), TableError> {
+            for (col, m) in mods.iter_mut().enumerate() {
+                match *m {
+                    Modification::Set(ref mut v) => coerce(col, v)?,
+                    Modification::Apply(_, ref mut v) => {
+                        // only the result of the operation has to fit the column, which we can't
+                        // check here, but deltas for decimals must still have the column's scale.
+                        if let Some(SqlType::Decimal(p, s)) = spec(col).map(|spec| &spec.sql_type) {
+                            if let Some(d) = v.to_decimal(*p, *s) {
+                                *v = d;
+                            }
+                        }
+                    }
+                    Modification::None => {}
+                }
+            }
+            Ok(())
+        };
+
+        match *r {
+            TableOperation::Insert(ref mut row) | TableOperation::InsertIgnore(ref mut row) => {
+                for (col, v) in row.iter_mut().enumerate() {
+                    coerce(col, v)?;
+                }
+            }
+            TableOperation::InsertOrUpdate {
+                ref mut row,
+                ref mut update,
+            } => {
+                for (col, v) in row.iter_mut().enumerate() {
+                    coerce(col, v)?;
+                }
+                coerce_all(update)?;
+            }
+            TableOperation::Update { ref mut set, .. } => coerce_all(set)?,
+            TableOperation::Delete { .. } => {}
+        }
+        Ok(())
+    }
+
+    fn prep_records(&self, mut ops: Vec<TableOperation>) -> Result<Input, TableError> {
         for r in &mut ops {
+            self.coerce_to_schema(r)?;
             self.inject_dropped_cols(r);
         }
 
-        Input {
+        Ok(Input {
             dst: self.node,
             data: ops,
-        }
+        })
     }
 
     async fn quick_n_dirty<Request, R>(
@@ -740,12 +863,37 @@ impl Table {
     }
 
     /// Insert a single row of data into this base table.
-    pub async fn insert<V>(&mut self, u: V) -> Result<(), TableError>
+    ///
+    /// If the table has an `AUTO_INCREMENT` column and the row leaves it `NULL`, the table
+    /// generates a value for it, which is returned. Columns whose `DEFAULT` is an expression like
+    /// `CURRENT_TIMESTAMP` are similarly filled in by the table if the row leaves them `NULL`.
+    ///
+    /// Fails with [`TableError::DuplicateKey`] if a row with the same primary or unique key
+    /// already exists.
+    pub async fn insert<V>(&mut self, u: V) -> Result<Option<DataType>, TableError>
+    where
+        V: Into<Vec<DataType>>,
+    {
+        let mut generated = self
+            .quick_n_dirty(vec![TableOperation::Insert(u.into())])
+            .await?;
+        Ok(generated.pop())
+    }
+
+    /// Insert a single row of data into this base table, unless a row with the same primary or
+    /// unique key already exists.
+    ///
+    /// Unlike [`Table::insert`], which fails with [`TableError::DuplicateKey`] in that case, the
+    /// row is then silently discarded, like with MySQL's `INSERT IGNORE`, and no generated value
+    /// is returned.
+    pub async fn insert_ignore<V>(&mut self, u: V) -> Result<Option<DataType>, TableError>
     where
         V: Into<Vec<DataType>>,
     {
-        self.quick_n_dirty(vec![TableOperation::Insert(u.into())])
-            .await
+        let mut generated = self
+            .quick_n_dirty(vec![TableOperation::InsertIgnore(u.into())])
+            .await?;
+        Ok(generated.pop())
     }
 
     /// Perform multiple operation on this base table.
@@ -755,7 +903,8 @@ impl Table {
         V: Into<TableOperation>,
     {
         self.quick_n_dirty(i.into_iter().map(Into::into).collect::<Vec<_>>())
-            .await
+            .await?;
+        Ok(())
     }
 
     /// Delete the row with the given key from this base table.
@@ -764,7 +913,8 @@ impl Table {
         I: Into<Vec<DataType>>,
     {
         self.quick_n_dirty(vec![TableOperation::Delete { key: key.into() }])
-            .await
+            .await?;
+        Ok(())
     }
 
     /// Update the row with the given key in this base table.
@@ -789,7 +939,8 @@ impl Table {
         }
 
         self.quick_n_dirty(vec![TableOperation::Update { key, set }])
-            .await
+            .await?;
+        Ok(())
     }
 
     /// Perform a insert-or-update on this base table.
@@ -821,6 +972,288 @@ impl Table {
             row: insert,
             update: set,
         }])
-        .await
+        .await?;
+        Ok(())
+    }
+}
+
+/// Check a value against the type and constraints of the column it is written to, converting it
+/// to the column's type where MySQL would do so implicitly.
+///
+/// Returns why the value can't be stored in the column otherwise.
+fn coerce_to_column(spec: &ColumnSpecification, v: &mut DataType) -> Result<(), String> {
+    if v.is_none() {
+        let generated = spec.constraints.iter().any(|c| match *c {
+            ColumnConstraint::AutoIncrement => true,
+            ColumnConstraint::DefaultValue(ref l) => is_default_expr(l),
+            _ => false,
+        });
+        if spec.constraints.contains(&ColumnConstraint::NotNull) && !generated {
+            return Err(String::from("column is declared NOT NULL"));
+        }
+        return Ok(());
+    }
+
+    let ty = &spec.sql_type;
+    let expected = || format!("expected a value of type {}", ty);
+    let coerced = match *ty {
+        SqlType::Bool | SqlType::Tinyint(_) => coerce_integer(v, ty, -128, 127)?,
+        SqlType::UnsignedTinyint(_) => coerce_integer(v, ty, 0, 255)?,
+        SqlType::Int(_) => coerce_integer(v, ty, i32::min_value().into(), i32::max_value().into())?,
+        SqlType::UnsignedInt(_) => coerce_integer(v, ty, 0, u32::max_value().into())?,
+        SqlType::Bigint(_) => {
+            coerce_integer(v, ty, i64::min_value().into(), i64::max_value().into())?
+        }
+        SqlType::UnsignedBigint(_) => coerce_integer(v, ty, 0, u64::max_value().into())?,
+        SqlType::Double | SqlType::Float | SqlType::Real => match *v {
+            DataType::Int(..)
+            | DataType::UnsignedInt(..)
+            | DataType::BigInt(..)
+            | DataType::UnsignedBigInt(..)
+            | DataType::Real(..) => None,
+            DataType::Bool(b) => Some(DataType::from(i32::from(b))),
+            DataType::Decimal(..) => Some(DataType::from(f64::from(&*v))),
+            DataType::Text(..) | DataType::TinyText(..) => {
+                let s: &str = (&*v).into();
+                match s.trim().parse::<f64>() {
+                    Ok(f) if f.is_finite() => Some(DataType::from(f)),
+                    _ => return Err(expected()),
+                }
+            }
+            _ => return Err(expected()),
+        },
+        SqlType::Decimal(p, s) => match v.to_decimal(p, s) {
+            Some(d) => Some(d),
+            None if v.is_decimal() || v.is_integer() || v.is_real() => {
+                return Err(format!("out of range for {}", ty));
+            }
+            None => return Err(expected()),
+        },
+        SqlType::Char(n) | SqlType::Varchar(n) => coerce_text(v, ty, usize::from(n), true)?,
+        SqlType::Tinytext => coerce_text(v, ty, 255, false)?,
+        SqlType::Text => coerce_text(v, ty, 65_535, false)?,
+        SqlType::Mediumtext => coerce_text(v, ty, 16_777_215, false)?,
+        SqlType::Longtext => coerce_text(v, ty, u32::max_value() as usize, false)?,
+        SqlType::Binary(n) | SqlType::Varbinary(n) => coerce_binary(v, ty, usize::from(n))?,
+        SqlType::Tinyblob => coerce_binary(v, ty, 255)?,
+        SqlType::Blob => coerce_binary(v, ty, 65_535)?,
+        SqlType::Mediumblob => coerce_binary(v, ty, 16_777_215)?,
+        SqlType::Longblob => coerce_binary(v, ty, u32::max_value() as usize)?,
+        SqlType::Enum(ref variants) => {
+            if !v.is_string() {
+                return Err(expected());
+            }
+            let s: &str = (&*v).into();
+            let known = variants.iter().any(|variant| match *variant {
+                Literal::String(ref variant) => variant == s,
+                _ => false,
+            });
+            if !known {
+                return Err(format!("'{}' is not one of the values of the ENUM", s));
+            }
+            None
+        }
+        SqlType::Date => match *v {
+            DataType::Date(_) => None,
+            DataType::Timestamp(_) | DataType::TimestampTz(_) => {
+                Some(DataType::Date(NaiveDate::from(&*v)))
+            }
+            DataType::Text(..) | DataType::TinyText(..) => {
+                let ts = parse_datetime((&*v).into()).ok_or_else(expected)?;
+                Some(DataType::Date(ts.date()))
+            }
+            _ => return Err(expected()),
+        },
+        SqlType::DateTime(_) | SqlType::Timestamp => match *v {
+            DataType::Timestamp(_) | DataType::TimestampTz(_) => None,
+            DataType::Date(d) => Some(DataType::Timestamp(d.and_hms(0, 0, 0))),
+            DataType::Text(..) | DataType::TinyText(..) => {
+                let ts = parse_datetime((&*v).into()).ok_or_else(expected)?;
+                Some(DataType::Timestamp(ts))
+            }
+            _ => return Err(expected()),
+        },
+    };
+
+    if let Some(coerced) = coerced {
+        *v = coerced;
+    }
+    Ok(())
+}
+
+/// Check that `v` is an integer in `min..=max`, rounding reals, decimals and numeric strings.
+fn coerce_integer(
+    v: &DataType,
+    ty: &SqlType,
+    min: i128,
+    max: i128,
+) -> Result<Option<DataType>, String> {
+    let (n, coerced) = match *v {
+        DataType::Bool(_) if *ty == SqlType::Bool => return Ok(None),
+        DataType::Int(..)
+        | DataType::UnsignedInt(..)
+        | DataType::BigInt(..)
+        | DataType::UnsignedBigInt(..) => (i128::from(v), None),
+        _ => match v.to_decimal(38, 0) {
+            Some(DataType::Decimal(n, _)) => (i128::from(n), Some(DataType::from(n))),
+            _ => return Err(format!("expected a value of type {}", ty)),
+        },
+    };
+
+    if n < min || n > max {
+        return Err(format!("out of range for {}", ty));
+    }
+    Ok(coerced)
+}
+
+/// Check that `v` is a string of at most `max` characters (or bytes), turning numbers into text.
+fn coerce_text(
+    v: &DataType,
+    ty: &SqlType,
+    max: usize,
+    in_chars: bool,
+) -> Result<Option<DataType>, String> {
+    let coerced = match *v {
+        DataType::Text(..) | DataType::TinyText(..) => None,
+        DataType::Int(..)
+        | DataType::UnsignedInt(..)
+        | DataType::BigInt(..)
+        | DataType::UnsignedBigInt(..)
+        | DataType::Decimal(..)
+        | DataType::Uuid(..) => Some(DataType::from(v.to_string())),
+        DataType::Real(..) => Some(DataType::from(f64::from(v).to_string())),
+        _ => return Err(format!("expected a value of type {}", ty)),
+    };
+
+    let s: &str = coerced.as_ref().unwrap_or(v).into();
+    let (len, unit) = if in_chars {
+        (s.chars().count(), "characters")
+    } else {
+        (s.len(), "bytes")
+    };
+    if len > max {
+        return Err(format!(
+            "longer than the {} {} allowed by {}",
+            max, unit, ty
+        ));
+    }
+    Ok(coerced)
+}
+
+/// Check that `v` is a binary or text string of at most `max` bytes.
+fn coerce_binary(v: &DataType, ty: &SqlType, max: usize) -> Result<Option<DataType>, String> {
+    if !v.is_string() && !v.is_byte_array() {
+        return Err(format!("expected a value of type {}", ty));
+    }
+    let bytes: &[u8] = v.into();
+    if bytes.len() > max {
+        return Err(format!("longer than the {} bytes allowed by {}", max, ty));
+    }
+    Ok(None)
+}
+
+/// Parse a MySQL `DATETIME` or `DATE` literal.
+fn parse_datetime(s: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f")
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .map(|d| d.and_hms(0, 0, 0))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coerce(sql_type: SqlType, v: DataType) -> Result<DataType, String> {
+        let spec = ColumnSpecification::new("t.c".into(), sql_type);
+        let mut v = v;
+        coerce_to_column(&spec, &mut v).map(|_| v)
+    }
+
+    #[test]
+    fn it_coerces_integers() {
+        assert_eq!(coerce(SqlType::Int(32), 42.into()), Ok(42.into()));
+        assert_eq!(coerce(SqlType::Int(32), "42".into()), Ok(42.into()));
+        assert_eq!(coerce(SqlType::Int(32), 2.5.into()), Ok(3.into()));
+        assert_eq!(
+            coerce(SqlType::Bigint(64), DataType::None),
+            Ok(DataType::None)
+        );
+        assert!(coerce(SqlType::Int(32), "forty-two".into()).is_err());
+        assert!(coerce(SqlType::Int(32), (1i64 << 40).into()).is_err());
+        assert!(coerce(SqlType::UnsignedInt(32), (-1).into()).is_err());
+        assert!(coerce(SqlType::Tinyint(8), 128.into()).is_err());
+        assert_eq!(coerce(SqlType::Bool, true.into()), Ok(true.into()));
+    }
+
+    #[test]
+    fn it_coerces_text() {
+        assert_eq!(
+            coerce(SqlType::Varchar(5), "hello".into()),
+            Ok("hello".into())
+        );
+        assert_eq!(coerce(SqlType::Text, 42.into()), Ok("42".into()));
+        assert_eq!(
+            coerce(SqlType::Varchar(3), "too long".into()),
+            Err(String::from(
+                "longer than the 3 characters allowed by VARCHAR(3)"
+            ))
+        );
+        // lengths of CHAR and VARCHAR are in characters, not bytes
+        assert!(coerce(SqlType::Char(2), "ñó".into()).is_ok());
+        assert!(coerce(SqlType::Tinyblob, DataType::from(vec![0; 256])).is_err());
+        assert!(coerce(SqlType::Text, chrono::Local::now().naive_local().into()).is_err());
+
+        let e = SqlType::Enum(vec![Literal::String("a".into())]);
+        assert!(coerce(e.clone(), "a".into()).is_ok());
+        assert!(coerce(e, "b".into()).is_err());
+    }
+
+    #[test]
+    fn it_coerces_dates() {
+        let d = NaiveDate::from_ymd(2020, 2, 29);
+        assert_eq!(coerce(SqlType::Date, "2020-02-29".into()), Ok(d.into()));
+        assert_eq!(
+            coerce(SqlType::Date, d.and_hms(12, 0, 0).into()),
+            Ok(d.into())
+        );
+        assert_eq!(
+            coerce(SqlType::DateTime(0), "2020-02-29 12:00:00".into()),
+            Ok(d.and_hms(12, 0, 0).into())
+        );
+        assert!(coerce(SqlType::Date, "2020-02-30".into()).is_err());
+        assert!(coerce(SqlType::Timestamp, 42.into()).is_err());
+    }
+
+    #[test]
+    fn it_coerces_decimals() {
+        assert_eq!(
+            coerce(SqlType::Decimal(4, 2), "1.005".into()),
+            Ok(DataType::Decimal(101, 2))
+        );
+        assert_eq!(
+            coerce(SqlType::Decimal(4, 2), 100.into()),
+            Err(String::from("out of range for DECIMAL(4, 2)"))
+        );
+        assert!(coerce(SqlType::Decimal(4, 2), "cheap".into()).is_err());
+    }
+
+    #[test]
+    fn it_rejects_null_in_not_null_columns() {
+        let spec = ColumnSpecification::with_constraints(
+            "t.c".into(),
+            SqlType::Int(32),
+            vec![ColumnConstraint::NotNull],
+        );
+        let mut v = DataType::None;
+        assert_eq!(
+            coerce_to_column(&spec, &mut v),
+            Err(String::from("column is declared NOT NULL"))
+        );
+        let mut v = 1.into();
+        assert_eq!(coerce_to_column(&spec, &mut v), Ok(()));
     }
 }
@@ -12,6 +12,7 @@ use std::fmt;
 use std::future::Future;
 use std::io;
 use std::net::SocketAddr;
+use std::ops::{Bound, RangeBounds};
 use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
 use tokio_tower::multiplex;
@@ -101,6 +102,20 @@ pub enum ViewError {
     /// The given view is not yet available.
     #[fail(display = "the view is not yet available")]
     NotYetAvailable,
+    /// The view cannot answer a lookup for the given range of keys.
+    #[fail(display = "the view does not support lookups over this range")]
+    UnsupportedRange,
+    /// A lookup did not give one value for each of the view's parameters.
+    #[fail(
+        display = "the view takes {} values per lookup, but {} were given",
+        expected, got
+    )]
+    WrongArity {
+        /// The number of values the view takes.
+        expected: usize,
+        /// The number of values the lookup gave.
+        got: usize,
+    },
     /// A lower-level error occurred while communicating with Soup.
     #[fail(display = "{}", _0)]
     TransportError(#[cause] failure::Error),
@@ -123,6 +138,17 @@ pub enum ReadQuery {
         keys: Vec<Vec<DataType>>,
         /// Whether to block if a partial replay is triggered
         block: bool,
+        /// Number of rows to skip for each key, after the view's ordering is applied
+        offset: usize,
+        /// Maximum number of rows to return for each key
+        limit: Option<usize>,
+    },
+    /// Read all keys within a range from a leaf view
+    Range {
+        /// Where to read from
+        target: (NodeIndex, usize),
+        /// Range of keys to read
+        range: (Bound<Vec<DataType>>, Bound<Vec<DataType>>),
     },
     /// Read the size of a leaf view
     Size {
@@ -131,11 +157,40 @@ pub enum ReadQuery {
     },
 }
 
+/// Why a reader could not answer a read.
+#[doc(hidden)]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum ReadError {
+    /// The view isn't ready yet.
+    NotReady,
+    /// A lookup did not give the number of values that the view takes.
+    WrongArity {
+        /// The number of values the view takes.
+        expected: usize,
+        /// The number of values the lookup gave.
+        got: usize,
+    },
+}
+
+impl From<ReadError> for ViewError {
+    fn from(e: ReadError) -> Self {
+        match e {
+            ReadError::NotReady => ViewError::NotYetAvailable,
+            ReadError::WrongArity { expected, got } => ViewError::WrongArity { expected, got },
+        }
+    }
+}
+
 #[doc(hidden)]
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ReadReply<D = ReadReplyBatch> {
-    /// Errors if view isn't ready yet.
-    Normal(Result<Vec<D>, ()>),
+    /// Errors if view isn't ready yet, or if the lookups don't fit the view.
+    Normal(Result<Vec<D>, ReadError>),
+    /// Rows of a range read, in key order.
+    ///
+    /// `None` if the view is partially materialized but not ordered, and so cannot answer range
+    /// reads.
+    Range(Result<Option<D>, ()>),
     /// Read size of view
     Size(usize),
 }
@@ -145,6 +200,9 @@ pub enum ReadReply<D = ReadReplyBatch> {
 pub struct ViewBuilder {
     pub node: NodeIndex,
     pub columns: Vec<String>,
+    pub key: Vec<usize>,
+    /// The position in each lookup of the value for the view's first key column.
+    pub key_position: usize,
     pub schema: Option<Vec<ColumnSpecification>>,
     pub shards: Vec<SocketAddr>,
 }
@@ -158,6 +216,8 @@ impl ViewBuilder {
     ) -> Result<View, io::Error> {
         let node = self.node;
         let columns = self.columns.clone();
+        let key = self.key.clone();
+        let key_position = self.key_position;
         let shards = self.shards.clone();
         let schema = self.schema.clone();
 
@@ -201,6 +261,8 @@ impl ViewBuilder {
             node,
             schema,
             columns,
+            key,
+            key_position,
             shard_addrs: addrs,
             shards: conns,
             tracer,
@@ -216,6 +278,8 @@ impl ViewBuilder {
 pub struct View {
     node: NodeIndex,
     columns: Vec<String>,
+    key: Vec<usize>,
+    key_position: usize,
     schema: Option<Vec<ColumnSpecification>>,
 
     shards: Vec<ViewRpc>,
@@ -254,6 +318,20 @@ impl Service<(Vec<Vec<DataType>>, bool)> for View {
     }
 
     fn call(&mut self, (keys, block): (Vec<Vec<DataType>>, bool)) -> Self::Future {
+        self.read(keys, block, 0, None)
+    }
+}
+
+#[allow(clippy::len_without_is_empty)]
+impl View {
+    /// Issue a read for `keys` to the shards that hold them. The view must have been polled ready.
+    fn read(
+        &mut self,
+        keys: Vec<Vec<DataType>>,
+        block: bool,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> impl Future<Output = Result<Vec<Results>, ViewError>> + Send {
         let span = if crate::trace_next_op() {
             Some(tracing::trace_span!(
                 "view-request",
@@ -270,6 +348,8 @@ impl Service<(Vec<Vec<DataType>>, bool)> for View {
                 target: (self.node, 0),
                 keys,
                 block,
+                offset,
+                limit,
             });
 
             let _guard = span.as_ref().map(tracing::Span::enter);
@@ -285,7 +365,7 @@ impl Service<(Vec<Vec<DataType>>, bool)> for View {
                                 .into_iter()
                                 .map(|rows| Results::new(rows.into(), Arc::clone(&columns)))
                                 .collect()),
-                            ReadReply::Normal(Err(())) => Err(ViewError::NotYetAvailable),
+                            ReadReply::Normal(Err(e)) => Err(ViewError::from(e)),
                             _ => unreachable!(),
                         }
                     }),
@@ -295,10 +375,15 @@ impl Service<(Vec<Vec<DataType>>, bool)> for View {
         if let Some(ref span) = span {
             span.in_scope(|| tracing::trace!("shard request"));
         }
-        assert!(keys.iter().all(|k| k.len() == 1));
+        // sharded readers are keyed on a single column. the other values of a lookup are
+        // parameters for the reader's post-lookup filters. lookups that are too short to contain
+        // the key are rejected by whichever shard they are sent to.
         let mut shard_queries = vec![Vec::new(); self.shards.len()];
         for key in keys {
-            let shard = crate::shard_by(&key[0], self.shards.len());
+            let shard = key
+                .get(self.key_position)
+                .map(|k| crate::shard_by(k, self.shards.len()))
+                .unwrap_or(0);
             shard_queries[shard].push(key);
         }
 
@@ -324,6 +409,8 @@ impl Service<(Vec<Vec<DataType>>, bool)> for View {
                         target: (node, shardi),
                         keys: shard_queries,
                         block,
+                        offset,
+                        limit,
                     });
 
                     let _guard = span.as_ref().map(tracing::Span::enter);
@@ -342,7 +429,7 @@ impl Service<(Vec<Vec<DataType>>, bool)> for View {
                         .and_then(|reply| async move {
                             match reply.v {
                                 ReadReply::Normal(Ok(rows)) => Ok(rows),
-                                ReadReply::Normal(Err(())) => Err(ViewError::NotYetAvailable),
+                                ReadReply::Normal(Err(e)) => Err(ViewError::from(e)),
                                 _ => unreachable!(),
                             }
                         })
@@ -356,10 +443,7 @@ impl Service<(Vec<Vec<DataType>>, bool)> for View {
                 }),
         )
     }
-}
 
-#[allow(clippy::len_without_is_empty)]
-impl View {
     /// Get the list of columns in this view.
     pub fn columns(&self) -> &[String] {
         &*self.columns
@@ -423,6 +507,25 @@ impl View {
         Ok(rs.into_iter().next().unwrap())
     }
 
+    /// Retrieve a page of the query results for the given parameter value.
+    ///
+    /// The results are sorted according to the view's `ORDER BY` clause, if it has one. The first
+    /// `offset` of them are skipped, and at most `limit` are returned. Otherwise, this behaves
+    /// like `lookup`.
+    pub async fn lookup_page(
+        &mut self,
+        key: &[DataType],
+        offset: usize,
+        limit: usize,
+        block: bool,
+    ) -> Result<Results, ViewError> {
+        future::poll_fn(|cx| self.poll_ready(cx)).await?;
+        let rs = self
+            .read(vec![Vec::from(key)], block, offset, Some(limit))
+            .await?;
+        Ok(rs.into_iter().next().unwrap())
+    }
+
     /// Retrieve the first query result for the given parameter value.
     ///
     /// The method will block if the results are not yet available only when `block` is `true`.
@@ -435,6 +538,68 @@ impl View {
         let rs = self.multi_lookup(vec![Vec::from(key)], block).await?;
         Ok(rs.into_iter().next().unwrap().into_iter().next())
     }
+
+    /// Retrieve the query results for all keys that fall within the given range, ordered by key.
+    ///
+    /// Keys are compared lexicographically. Ordered views (see `Migration::maintain_ordered`)
+    /// answer range lookups in time proportional to the size of the range. If an ordered view is
+    /// partially materialized, the parts of the range it is missing are replayed before the
+    /// lookup completes. Other partially materialized views cannot tell which of the keys in a
+    /// range are missing, so range lookups against them yield `ViewError::UnsupportedRange`.
+    pub async fn lookup_range<R>(&mut self, range: R) -> Result<Results, ViewError>
+    where
+        R: RangeBounds<Vec<DataType>>,
+    {
+        let range = (
+            owned_bound(range.start_bound()),
+            owned_bound(range.end_bound()),
+        );
+        future::poll_fn(|cx| self.poll_ready(cx)).await?;
+
+        let node = self.node;
+        let mut rsps = self
+            .shards
+            .iter_mut()
+            .enumerate()
+            .map(|(shardi, shard)| {
+                shard.call(Tagged::from(ReadQuery::Range {
+                    target: (node, shardi),
+                    range: range.clone(),
+                }))
+            })
+            .collect::<FuturesUnordered<_>>();
+
+        let mut rows = Vec::new();
+        while let Some(reply) = rsps.next().await.transpose()? {
+            match reply.v {
+                ReadReply::Range(Ok(Some(rs))) => rows.extend(rs),
+                ReadReply::Range(Ok(None)) => return Err(ViewError::UnsupportedRange),
+                ReadReply::Range(Err(())) => return Err(ViewError::NotYetAvailable),
+                _ => unreachable!(),
+            }
+        }
+
+        if self.shards.len() > 1 {
+            // every shard returns its rows in order, but they still need to be merged
+            let key = &self.key;
+            rows.sort_by(|a, b| {
+                key.iter()
+                    .map(|&k| a[k].cmp(&b[k]))
+                    .find(|o| *o != std::cmp::Ordering::Equal)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+        }
+
+        Ok(Results::new(rows, Arc::from(&self.columns[..])))
+    }
+}
+
+fn owned_bound(b: Bound<&Vec<DataType>>) -> Bound<Vec<DataType>> {
+    match b {
+        Bound::Included(k) => Bound::Included(k.clone()),
+        Bound::Excluded(k) => Bound::Excluded(k.clone()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
 }
 
 #[derive(Debug, Default)]
@@ -22,6 +22,9 @@ impl SizeOf for DataType {
 
         let inner = match *self {
             DataType::Text(ref t) => size_of_val(t) as u64 + t.to_bytes().len() as u64,
+            DataType::ByteArray(ref b) => size_of_val(b) as u64 + (**b).len() as u64,
+            DataType::Uuid(ref u) => size_of_val(u) as u64 + size_of_val(&**u) as u64,
+            DataType::TimestampTz(ref ts) => size_of_val(ts) as u64 + size_of_val(&**ts) as u64,
             _ => 0u64,
         };
 
@@ -65,7 +68,7 @@ mod tests {
     #[test]
     fn data_type_mem_size() {
         use arccstr::ArcCStr;
-        use chrono::NaiveDateTime;
+        use chrono::{DateTime, NaiveDateTime, Utc};
         use std::convert::TryFrom;
         use std::mem::{size_of, size_of_val};
 
@@ -73,6 +76,14 @@ mod tests {
         let shrt = DataType::Int(5);
         let long = DataType::BigInt(5);
         let time = DataType::Timestamp(NaiveDateTime::from_timestamp(0, 42_000_000));
+        let boolean = DataType::Bool(true);
+        let bytes = DataType::from(vec![0u8; 20]);
+        let uuid = DataType::from(noria::Uuid::nil());
+        let date = DataType::from(NaiveDateTime::from_timestamp(0, 0).date());
+        let zoned = DataType::from(DateTime::<Utc>::from_utc(
+            NaiveDateTime::from_timestamp(0, 0),
+            Utc,
+        ));
 
         let rec = vec![DataType::Int(5), "asdfasdfasdfasdf".into(), "asdf".into()];
 
@@ -86,6 +97,16 @@ mod tests {
         assert_eq!(size_of_val(&time), 16);
         assert_eq!(size_of_val(&time) as u64, time.size_of());
         assert_eq!(time.deep_size_of(), 16); // DataType + inline NaiveDateTime
+        assert_eq!(size_of_val(&boolean), 16);
+        assert_eq!(boolean.deep_size_of(), 16);
+        assert_eq!(size_of_val(&bytes), 16);
+        assert_eq!(bytes.deep_size_of(), 16 + 8 + 20); // DataType + Arc's ptr + 20 bytes
+        assert_eq!(size_of_val(&uuid), 16);
+        assert_eq!(uuid.deep_size_of(), 16 + 8 + 16); // DataType + Arc's ptr + 16 byte UUID
+        assert_eq!(size_of_val(&date), 16);
+        assert_eq!(date.deep_size_of(), 16); // DataType + inline NaiveDate
+        assert_eq!(size_of_val(&zoned), 16);
+        assert_eq!(zoned.deep_size_of(), 16 + 8 + 16); // DataType + Arc's ptr + zoned timestamp
 
         assert_eq!(size_of_val(&rec), 24);
         assert_eq!(rec.size_of(), 24 + 3 * 16);
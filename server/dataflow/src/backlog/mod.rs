@@ -1,13 +1,45 @@
+use crate::node::special::PostLookupFilter;
+use crate::ops::filter::Operator;
+use crate::ops::topk::Order;
 use crate::prelude::*;
 use ahash::RandomState;
 use common::SizeOf;
+use nom_sql::OrderType;
 use rand::prelude::*;
 use std::borrow::Cow;
-use std::sync::Arc;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashSet};
+use std::ops::Bound;
+use std::sync::{Arc, RwLock};
+
+/// A range of reader keys. Keys are compared lexicographically.
+pub type KeyRange = (Bound<Vec<DataType>>, Bound<Vec<DataType>>);
+
+type RangeTrigger = Arc<dyn Fn(Vec<KeyRange>) -> bool + Send + Sync>;
+
+/// The keys of an ordered reader.
+#[derive(Default)]
+struct OrderedKeys {
+    /// The keys that have rows, in key order.
+    keys: BTreeSet<Vec<DataType>>,
+    /// For partially materialized readers, the ranges that range replays have filled. Every key
+    /// in them that has rows is in `keys`.
+    filled: Vec<KeyRange>,
+}
+
+type KeyIndex = Arc<RwLock<OrderedKeys>>;
 
 /// Allocate a new end-user facing result table.
 pub(crate) fn new(cols: usize, key: &[usize]) -> (SingleReadHandle, WriteHandle) {
-    new_inner(cols, key, None)
+    new_inner(cols, key, None, None, false)
+}
+
+/// Allocate a new end-user facing result table that also keeps its keys in sorted order.
+///
+/// This makes range lookups proportional to the size of the range, rather than to the size of the
+/// table.
+pub(crate) fn new_ordered(cols: usize, key: &[usize]) -> (SingleReadHandle, WriteHandle) {
+    new_inner(cols, key, None, None, true)
 }
 
 /// Allocate a new partially materialized end-user facing result table.
@@ -21,13 +53,39 @@ pub(crate) fn new_partial<F>(
 where
     F: Fn(&mut dyn Iterator<Item = &[DataType]>) -> bool + 'static + Send + Sync,
 {
-    new_inner(cols, key, Some(Arc::new(trigger)))
+    new_inner(cols, key, Some(Arc::new(trigger)), None, false)
+}
+
+/// Allocate a new partially materialized end-user facing result table that also keeps its keys in
+/// sorted order.
+///
+/// Misses on single keys call `trigger`, as in `new_partial`. Range lookups that overlap ranges
+/// the table has not been filled for call `range_trigger` with the missing ranges.
+pub(crate) fn new_partial_ordered<F, R>(
+    cols: usize,
+    key: &[usize],
+    trigger: F,
+    range_trigger: R,
+) -> (SingleReadHandle, WriteHandle)
+where
+    F: Fn(&mut dyn Iterator<Item = &[DataType]>) -> bool + 'static + Send + Sync,
+    R: Fn(Vec<KeyRange>) -> bool + 'static + Send + Sync,
+{
+    new_inner(
+        cols,
+        key,
+        Some(Arc::new(trigger)),
+        Some(Arc::new(range_trigger)),
+        true,
+    )
 }
 
 fn new_inner(
     cols: usize,
     key: &[usize],
     trigger: Option<Arc<dyn Fn(&mut dyn Iterator<Item = &[DataType]>) -> bool + Send + Sync>>,
+    range_trigger: Option<RangeTrigger>,
+    ordered: bool,
 ) -> (SingleReadHandle, WriteHandle) {
     let contiguous = {
         let mut contiguous = true;
@@ -63,6 +121,12 @@ fn new_inner(
         _ => make!(Many),
     };
 
+    let index = if ordered {
+        Some(KeyIndex::default())
+    } else {
+        None
+    };
+
     let w = WriteHandle {
         partial: trigger.is_some(),
         handle: w,
@@ -70,11 +134,21 @@ fn new_inner(
         cols,
         contiguous,
         mem_size: 0,
+        index: index.clone(),
+        touched: HashSet::new(),
+        reindex: false,
+        ranges: Vec::new(),
     };
     let r = SingleReadHandle {
         handle: r,
         trigger,
+        range_trigger,
         key: Vec::from(key),
+        index,
+        ranged: false,
+        post_lookup: Arc::new(Vec::new()),
+        lookup_order: Arc::new(Vec::new()),
+        order: Arc::new(Order::default()),
     };
 
     (r, w)
@@ -111,6 +185,15 @@ pub(crate) struct WriteHandle {
     key: Vec<usize>,
     contiguous: bool,
     mem_size: usize,
+
+    // keys whose presence in `index` must be re-checked on the next swap
+    index: Option<KeyIndex>,
+    touched: HashSet<Vec<DataType>>,
+    reindex: bool,
+
+    // the ranges that range replays have filled, each with the keys in it that have yet to be
+    // replayed before lookups of the range can be answered
+    ranges: Vec<(KeyRange, HashSet<Vec<DataType>>)>,
 }
 
 type Key<'a> = Cow<'a, [DataType]>;
@@ -125,6 +208,9 @@ pub(crate) struct WriteHandleEntry<'a> {
 
 impl<'a> MutWriteHandleEntry<'a> {
     pub(crate) fn mark_filled(self) {
+        if self.handle.index.is_some() {
+            self.handle.touched.insert(self.key.to_vec());
+        }
         if let Some((None, _)) = self
             .handle
             .handle
@@ -146,6 +232,17 @@ impl<'a> MutWriteHandleEntry<'a> {
             .map(|r| r.0.unwrap_or(0))
             .unwrap_or(0);
         self.handle.mem_size = self.handle.mem_size.checked_sub(size as usize).unwrap();
+        if self.handle.index.is_some() {
+            self.handle.touched.insert(self.key.to_vec());
+        }
+        if !self.handle.ranges.is_empty() {
+            // the key's rows are gone, so the ranges around it no longer hold all of their keys,
+            // unless the key is still to be replayed into them anyway
+            let key = &*self.key;
+            self.handle
+                .ranges
+                .retain(|(range, pending)| pending.contains(key) || !in_range(range, key));
+        }
         self.handle.handle.empty(self.key)
     }
 }
@@ -229,7 +326,107 @@ impl WriteHandle {
     }
 
     pub(crate) fn swap(&mut self) {
+        if let (true, Some(index)) = (self.partial, self.index.as_ref()) {
+            // ranges that have lost keys must no longer be read as filled by the time the holes
+            // for those keys become visible.
+            let ranges = &self.ranges;
+            index
+                .write()
+                .unwrap()
+                .filled
+                .retain(|filled| ranges.iter().any(|(range, _)| range == filled));
+        }
+
         self.handle.refresh();
+
+        if let Some(ref index) = self.index {
+            // the key index is updated only once the writes are visible in the map, so readers
+            // never find a key in the index that they cannot then look up.
+            let handle = &self.handle;
+            let present = |key: &[DataType]| match handle
+                .meta_get_and(Cow::Borrowed(key), |rs| !rs.is_empty())
+            {
+                Some((Some(true), _)) => true,
+                _ => false,
+            };
+
+            let mut index = index.write().unwrap();
+            if self.reindex {
+                // evictions do not tell us which keys they removed
+                let kept: BTreeSet<_> = index
+                    .keys
+                    .iter()
+                    .filter(|k| present(&k[..]))
+                    .cloned()
+                    .collect();
+                index.keys = kept;
+                self.reindex = false;
+            }
+            for key in self.touched.drain() {
+                if present(&key[..]) {
+                    index.keys.insert(key);
+                } else {
+                    index.keys.remove(&key);
+                }
+            }
+            if self.partial {
+                index.filled = self
+                    .ranges
+                    .iter()
+                    .filter(|(_, pending)| pending.is_empty())
+                    .map(|(range, _)| range.clone())
+                    .collect();
+            }
+        }
+    }
+
+    /// Record that a range replay has filled `range`, and that lookups of the range can be
+    /// answered once the keys in `pending` have been replayed as well.
+    ///
+    /// This is made visible to readers after the next call to `swap()`.
+    pub(crate) fn mark_range_filled(&mut self, range: KeyRange, pending: HashSet<Vec<DataType>>) {
+        self.ranges.push((range, pending));
+    }
+
+    /// Record that `keys` have been replayed, so that the filled ranges no longer wait for them.
+    pub(crate) fn mark_range_keys_filled<'a, I>(&mut self, keys: I)
+    where
+        I: IntoIterator<Item = &'a Vec<DataType>>,
+    {
+        if self.ranges.is_empty() {
+            return;
+        }
+        for key in keys {
+            for (_, pending) in &mut self.ranges {
+                pending.remove(key);
+            }
+        }
+    }
+
+    /// Returns true if `key` lies in a range that a range replay has filled, and is not waiting to
+    /// be replayed, so that all of its rows arrive through regular writes.
+    pub(crate) fn covers(&self, key: &[DataType]) -> bool {
+        self.ranges
+            .iter()
+            .any(|(range, pending)| in_range(range, key) && !pending.contains(key))
+    }
+
+    /// The parts of `range` that no range replay has filled, or is filling.
+    pub(crate) fn unfilled(&self, range: &KeyRange) -> Vec<KeyRange> {
+        let filled: Vec<_> = self.ranges.iter().map(|(range, _)| range.clone()).collect();
+        missing_ranges(range, &filled)
+    }
+
+    /// Fill the hole for the key of `record` if the key lies in a filled range (see `covers`),
+    /// since `record` is then the key's first row. Returns true if the key was filled.
+    pub(crate) fn fill_if_covered(&mut self, record: &[DataType]) -> bool {
+        let key = key_from_record(&self.key[..], self.contiguous, record).into_owned();
+        if self.covers(&key[..]) {
+            self.mut_with_key(key).mark_filled();
+            true
+        } else {
+            false
+        }
     }
 
     /// Add a new set of records to the backlog.
@@ -239,7 +436,16 @@ impl WriteHandle {
     where
         I: IntoIterator<Item = Record>,
     {
-        let mem_delta = self.handle.add(&self.key[..], self.cols, rs);
+        let mem_delta = if self.index.is_some() {
+            let rs: Vec<_> = rs.into_iter().collect();
+            for r in &rs {
+                let key = key_from_record(&self.key[..], self.contiguous, &r[..]);
+                self.touched.insert(key.into_owned());
+            }
+            self.handle.add(&self.key[..], self.cols, rs)
+        } else {
+            self.handle.add(&self.key[..], self.cols, rs)
+        };
         if mem_delta > 0 {
             self.mem_size += mem_delta as usize;
         } else if mem_delta < 0 {
@@ -268,6 +474,9 @@ impl WriteHandle {
                 bytes_to_be_freed += size;
                 n -= 1;
             });
+            self.reindex = self.index.is_some();
+            // we do not know which ranges the evicted keys were in
+            self.ranges.clear();
         }
 
         self.mem_size = self
@@ -299,7 +508,13 @@ impl SizeOf for WriteHandle {
 pub struct SingleReadHandle {
     handle: multir::Handle,
     trigger: Option<Arc<dyn Fn(&mut dyn Iterator<Item = &[DataType]>) -> bool + Send + Sync>>,
+    range_trigger: Option<RangeTrigger>,
     key: Vec<usize>,
+    index: Option<KeyIndex>,
+    ranged: bool,
+    post_lookup: Arc<Vec<PostLookupFilter>>,
+    lookup_order: Arc<Vec<usize>>,
+    order: Arc<Order>,
 }
 
 impl std::fmt::Debug for SingleReadHandle {
@@ -307,7 +522,13 @@ impl std::fmt::Debug for SingleReadHandle {
         f.debug_struct("SingleReadHandle")
             .field("handle", &self.handle)
             .field("has_trigger", &self.trigger.is_some())
+            .field("has_range_trigger", &self.range_trigger.is_some())
             .field("key", &self.key)
+            .field("ordered", &self.index.is_some())
+            .field("ranged", &self.ranged)
+            .field("post_lookup", &self.post_lookup)
+            .field("lookup_order", &self.lookup_order)
+            .field("order", &self.order)
             .finish()
     }
 }
@@ -329,6 +550,39 @@ impl SingleReadHandle {
         (*self.trigger.as_ref().unwrap())(&mut it)
     }
 
+    /// Trigger range replays of the parts of `range` that a partially materialized ordered view
+    /// has not been filled for.
+    pub fn trigger_range(&self, range: &KeyRange) -> bool {
+        let trigger = self
+            .range_trigger
+            .as_ref()
+            .expect("tried to trigger a range replay for a view that cannot replay ranges");
+        let missing = {
+            let index = self.index.as_ref().unwrap().read().unwrap();
+            missing_ranges(range, &index.filled)
+        };
+        missing.is_empty() || trigger(missing)
+    }
+
+    /// Returns true if range lookups that miss in this view can be filled with `trigger_range`.
+    pub fn can_trigger_range(&self) -> bool {
+        self.range_trigger.is_some()
+    }
+
+    /// Returns true if `key` lies in a range that a range replay has filled, so that a hole for it
+    /// means that it has no rows.
+    fn in_filled_range(&self, key: &[DataType]) -> bool {
+        match (&self.range_trigger, &self.index) {
+            (Some(_), Some(index)) => index
+                .read()
+                .unwrap()
+                .filled
+                .iter()
+                .any(|range| in_range(range, key)),
+            _ => false,
+        }
+    }
+
     /// Find all entries that matched the given conditions.
     ///
     /// Returned records are passed to `then` before being returned.
@@ -345,13 +599,268 @@ impl SingleReadHandle {
             .meta_get_and(key, &mut then)
             .ok_or(())
             .map(|(mut records, meta)| {
-                if records.is_none() && self.trigger.is_none() {
+                if records.is_none() && (self.trigger.is_none() || self.in_filled_range(key)) {
                     records = Some(then(&evmap::Values::default()));
                 }
                 (records, meta)
             })
     }
 
+    /// Find all entries whose key falls within `range`, in key order.
+    ///
+    /// `then` is called once for each matching key. A partially materialized ordered view returns
+    /// `Ok((None, _))` unless range replays have filled all of `range` (see `trigger_range`). Other
+    /// partially materialized views cannot tell which of the keys in a range are holes, so range
+    /// lookups against them always return `Ok((None, _))`.
+    pub fn try_find_range_and<F, T>(
+        &self,
+        range: &KeyRange,
+        mut then: F,
+    ) -> Result<(Option<Vec<T>>, i64), ()>
+    where
+        F: FnMut(&evmap::Values<Vec<DataType>, RandomState>) -> T,
+    {
+        let miss = || {
+            self.handle
+                .meta_get_many_and(&[], |_| ())
+                .map(|(_, meta)| (None, meta))
+                .ok_or(())
+        };
+
+        let found = if range_is_empty(range) {
+            self.handle.meta_get_many_and(&[], &mut then)
+        } else if let Some(ref index) = self.index {
+            let keys: Vec<_> = {
+                let index = index.read().unwrap();
+                if self.trigger.is_some() && !missing_ranges(range, &index.filled).is_empty() {
+                    return miss();
+                }
+                index
+                    .keys
+                    .range::<Vec<DataType>, _>(range.clone())
+                    .cloned()
+                    .collect()
+            };
+            match self.handle.meta_get_many_and(&keys[..], &mut then) {
+                // a key was evicted after we read the index
+                Some((ref rs, _)) if self.trigger.is_some() && rs.len() != keys.len() => {
+                    return miss();
+                }
+                found => found,
+            }
+        } else if self.trigger.is_some() {
+            return miss();
+        } else {
+            self.handle.meta_range_and(range, &mut then)
+        };
+
+        found.map(|(rs, meta)| (Some(rs), meta)).ok_or(())
+    }
+
+    /// Find the entries for a lookup against a ranged reader (see `Reader::set_ranged`), in key
+    /// order: those whose key starts with the key values that the lookup gives, and whose value
+    /// for the last key column is within the bounds that the lookup's parameter values set.
+    ///
+    /// `then` is called once for each matching key.
+    pub fn try_find_ranged_and<F, T>(
+        &self,
+        lookup: &[DataType],
+        mut then: F,
+    ) -> Result<(Option<Vec<T>>, i64), ()>
+    where
+        F: FnMut(&evmap::Values<Vec<DataType>, RandomState>) -> T,
+    {
+        let (prefix, args) = self.split_lookup(lookup);
+        let (lo, hi) = self.range_bounds(args);
+        let with_prefix = |v: &DataType| {
+            let mut key = prefix.to_vec();
+            key.push(v.clone());
+            key
+        };
+        let range = (
+            match lo {
+                Bound::Included(v) => Bound::Included(with_prefix(v)),
+                Bound::Excluded(v) => Bound::Excluded(with_prefix(v)),
+                // the prefix on its own sorts before every key that starts with it
+                Bound::Unbounded => Bound::Included(prefix.to_vec()),
+            },
+            match hi {
+                Bound::Included(v) => Bound::Included(with_prefix(v)),
+                Bound::Excluded(v) => Bound::Excluded(with_prefix(v)),
+                Bound::Unbounded => Bound::Unbounded,
+            },
+        );
+
+        let found = if range_is_empty(&range) {
+            self.handle.meta_get_many_and(&[], &mut then)
+        } else {
+            let index = self.index.as_ref().expect("ranged readers are ordered");
+            let keys: Vec<_> = index
+                .read()
+                .unwrap()
+                .keys
+                .range::<Vec<DataType>, _>(range)
+                .take_while(|k| k.starts_with(prefix))
+                .cloned()
+                .collect();
+            self.handle.meta_get_many_and(&keys[..], &mut then)
+        };
+
+        found.map(|(rs, meta)| (Some(rs), meta)).ok_or(())
+    }
+
+    /// The tightest bounds that the post-lookup filters on the last key column set for its values,
+    /// given the filters' parameter values.
+    fn range_bounds<'a>(
+        &self,
+        mut args: &'a [DataType],
+    ) -> (Bound<&'a DataType>, Bound<&'a DataType>) {
+        let column = *self.key.last().unwrap();
+        let mut lo = Bound::Unbounded;
+        let mut hi = Bound::Unbounded;
+        for f in self.post_lookup.iter() {
+            if args.len() < f.arity {
+                break;
+            }
+            let (these, rest) = args.split_at(f.arity);
+            args = rest;
+            if f.column != column {
+                continue;
+            }
+            match f.operator {
+                Operator::Greater => {
+                    lo = tighter(lo, Bound::Excluded(&these[0]), Ordering::Greater)
+                }
+                Operator::GreaterOrEqual => {
+                    lo = tighter(lo, Bound::Included(&these[0]), Ordering::Greater)
+                }
+                Operator::Less => hi = tighter(hi, Bound::Excluded(&these[0]), Ordering::Less),
+                Operator::LessOrEqual => {
+                    hi = tighter(hi, Bound::Included(&these[0]), Ordering::Less)
+                }
+                _ => {}
+            }
+        }
+        (lo, hi)
+    }
+
+    pub(crate) fn set_ranged(&mut self) {
+        assert!(self.index.is_some(), "ranged readers must be ordered");
+        self.ranged = true;
+    }
+
+    /// Returns true if lookups against this handle give a range of values for the last key column
+    /// rather than a single value (see `Reader::set_ranged`).
+    pub fn is_ranged(&self) -> bool {
+        self.ranged
+    }
+
+    /// The number of key values that each lookup gives.
+    fn lookup_key_len(&self) -> usize {
+        if self.ranged {
+            self.key.len() - 1
+        } else {
+            self.key.len()
+        }
+    }
+
+    pub(crate) fn set_post_lookup_filters(&mut self, filters: &[PostLookupFilter]) {
+        self.post_lookup = Arc::new(Vec::from(filters));
+    }
+
+    pub(crate) fn set_lookup_order(&mut self, lookup_order: &[usize]) {
+        self.lookup_order = Arc::new(Vec::from(lookup_order));
+    }
+
+    /// The number of values that each lookup must give: one for each key column (but the last, for
+    /// ranged readers), and then those that the post-lookup filters take.
+    pub fn lookup_arity(&self) -> usize {
+        self.lookup_key_len() + self.post_lookup.iter().map(|f| f.arity).sum::<usize>()
+    }
+
+    /// Arrange the values given for each lookup, which follow the order of the query's
+    /// parameters, into the key to look up followed by the parameter values for this reader's
+    /// post-lookup filters.
+    ///
+    /// Fails with the number of values given if a lookup does not give `lookup_arity` values.
+    pub fn arrange_lookups(&self, lookups: &mut [Vec<DataType>]) -> Result<(), usize> {
+        let arity = self.lookup_arity();
+        if let Some(lookup) = lookups.iter().find(|l| l.len() != arity) {
+            return Err(lookup.len());
+        }
+        if !self.lookup_order.is_empty() {
+            for lookup in lookups {
+                *lookup = self
+                    .lookup_order
+                    .iter()
+                    .map(|&i| lookup[i].clone())
+                    .collect();
+            }
+        }
+        Ok(())
+    }
+
+    /// Split the values of a lookup arranged by `arrange_lookups` into the key to look up, and the
+    /// parameter values for this reader's post-lookup filters.
+    pub fn split_lookup<'a>(&self, lookup: &'a [DataType]) -> (&'a [DataType], &'a [DataType]) {
+        lookup.split_at(std::cmp::min(self.lookup_key_len(), lookup.len()))
+    }
+
+    pub(crate) fn set_order(&mut self, order: &[(usize, OrderType)]) {
+        self.order = Arc::new(Vec::from(order).into());
+    }
+
+    /// Returns true if the rows found for a key must pass through `post_lookup` before they are
+    /// returned.
+    pub fn has_post_lookup(&self) -> bool {
+        !self.post_lookup.is_empty() || !self.order.is_empty()
+    }
+
+    /// Process the rows found for a key before they are returned: drop those that don't pass this
+    /// reader's post-lookup filters, sort the rest by the reader's order, and then skip `offset`
+    /// rows and return at most `limit` of the remainder.
+    ///
+    /// `args` are the filters' parameter values, as returned by `split_lookup`.
+    pub fn post_lookup<'a, I>(
+        &self,
+        rows: I,
+        args: &[DataType],
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Vec<&'a Vec<DataType>>
+    where
+        I: IntoIterator<Item = &'a Vec<DataType>>,
+    {
+        let mut rows: Vec<_> = rows
+            .into_iter()
+            .filter(|r| self.post_lookup_matches(r, args))
+            .collect();
+        if !self.order.is_empty() {
+            rows.sort_by(|a, b| self.order.cmp(a, b));
+        }
+        rows.into_iter()
+            .skip(offset)
+            .take(limit.unwrap_or(usize::max_value()))
+            .collect()
+    }
+
+    fn post_lookup_matches(&self, row: &[DataType], mut args: &[DataType]) -> bool {
+        self.post_lookup.iter().all(|f| {
+            if args.len() < f.arity {
+                // `arrange_lookups` rejects lookups without enough values
+                return false;
+            }
+            let (these, rest) = args.split_at(f.arity);
+            args = rest;
+            f.matches(row, these)
+        })
+    }
+
+    /// Returns true if this handle keeps its keys in sorted order.
+    pub fn is_ordered(&self) -> bool {
+        self.index.is_some()
+    }
+
     pub fn len(&self) -> usize {
         self.handle.len()
     }
@@ -361,6 +870,134 @@ impl SingleReadHandle {
     }
 }
 
+/// Pick the bound that admits fewer values: the greater of two lower bounds if `towards` is
+/// `Greater`, or the lesser of two upper bounds if it is `Less`.
+fn tighter<'a>(
+    a: Bound<&'a DataType>,
+    b: Bound<&'a DataType>,
+    towards: Ordering,
+) -> Bound<&'a DataType> {
+    let value = |b: &Bound<&'a DataType>| match *b {
+        Bound::Included(v) | Bound::Excluded(v) => Some(v),
+        Bound::Unbounded => None,
+    };
+    match (value(&a), value(&b)) {
+        (None, _) => b,
+        (_, None) => a,
+        (Some(x), Some(y)) if x.cmp(y) == towards => a,
+        (Some(x), Some(y)) if x == y => match a {
+            Bound::Excluded(_) => a,
+            _ => b,
+        },
+        _ => b,
+    }
+}
+
+/// Returns true if `key` falls within `range`.
+fn in_range(range: &KeyRange, key: &[DataType]) -> bool {
+    let above = match range.0 {
+        Bound::Included(ref start) => &start[..] <= key,
+        Bound::Excluded(ref start) => &start[..] < key,
+        Bound::Unbounded => true,
+    };
+    let below = match range.1 {
+        Bound::Included(ref end) => key <= &end[..],
+        Bound::Excluded(ref end) => key < &end[..],
+        Bound::Unbounded => true,
+    };
+    above && below
+}
+
+/// Order two lower bounds by the first keys that they admit.
+fn cmp_lower(a: &Bound<Vec<DataType>>, b: &Bound<Vec<DataType>>) -> Ordering {
+    match (a, b) {
+        (Bound::Unbounded, Bound::Unbounded) => Ordering::Equal,
+        (Bound::Unbounded, _) => Ordering::Less,
+        (_, Bound::Unbounded) => Ordering::Greater,
+        (Bound::Included(a), Bound::Included(b)) | (Bound::Excluded(a), Bound::Excluded(b)) => {
+            a.cmp(b)
+        }
+        (Bound::Included(a), Bound::Excluded(b)) => a.cmp(b).then(Ordering::Less),
+        (Bound::Excluded(a), Bound::Included(b)) => a.cmp(b).then(Ordering::Greater),
+    }
+}
+
+/// Order two upper bounds by the last keys that they admit.
+fn cmp_upper(a: &Bound<Vec<DataType>>, b: &Bound<Vec<DataType>>) -> Ordering {
+    match (a, b) {
+        (Bound::Unbounded, Bound::Unbounded) => Ordering::Equal,
+        (Bound::Unbounded, _) => Ordering::Greater,
+        (_, Bound::Unbounded) => Ordering::Less,
+        (Bound::Included(a), Bound::Included(b)) | (Bound::Excluded(a), Bound::Excluded(b)) => {
+            a.cmp(b)
+        }
+        (Bound::Included(a), Bound::Excluded(b)) => a.cmp(b).then(Ordering::Greater),
+        (Bound::Excluded(a), Bound::Included(b)) => a.cmp(b).then(Ordering::Less),
+    }
+}
+
+/// The bound that admits exactly the keys that `bound` does not, on the other side of it.
+fn flip(bound: &Bound<Vec<DataType>>) -> Bound<Vec<DataType>> {
+    match *bound {
+        Bound::Included(ref k) => Bound::Excluded(k.clone()),
+        Bound::Excluded(ref k) => Bound::Included(k.clone()),
+        Bound::Unbounded => Bound::Unbounded,
+    }
+}
+
+/// The parts of `range` that none of the ranges in `filled` contain, in key order.
+pub(crate) fn missing_ranges(range: &KeyRange, filled: &[KeyRange]) -> Vec<KeyRange> {
+    let mut filled: Vec<_> = filled.iter().collect();
+    filled.sort_by(|a, b| cmp_lower(&a.0, &b.0));
+
+    let mut missing = Vec::new();
+    let mut start = range.0.clone();
+    for f in filled {
+        if cmp_lower(&f.0, &start) == Ordering::Greater {
+            let end = flip(&f.0);
+            let end = if cmp_upper(&end, &range.1) == Ordering::Less {
+                end
+            } else {
+                range.1.clone()
+            };
+            let gap = (start.clone(), end);
+            if !range_is_empty(&gap) {
+                missing.push(gap);
+            }
+        }
+
+        match flip(&f.1) {
+            // the rest of the range is filled
+            Bound::Unbounded => return missing,
+            next => {
+                if cmp_lower(&next, &start) == Ordering::Greater {
+                    start = next;
+                }
+            }
+        }
+        if range_is_empty(&(start.clone(), range.1.clone())) {
+            return missing;
+        }
+    }
+
+    let rest = (start, range.1.clone());
+    if !range_is_empty(&rest) {
+        missing.push(rest);
+    }
+    missing
+}
+
+/// `BTreeSet::range` panics on ranges that end before they start, so we filter those out early.
+fn range_is_empty(range: &KeyRange) -> bool {
+    match *range {
+        (Bound::Included(ref s), Bound::Included(ref e)) => s > e,
+        (Bound::Included(ref s), Bound::Excluded(ref e))
+        | (Bound::Excluded(ref s), Bound::Included(ref e))
+        | (Bound::Excluded(ref s), Bound::Excluded(ref e)) => s >= e,
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -564,4 +1201,298 @@ mod tests {
             .0
             .unwrap());
     }
+
+    fn range_of(rs: &[Vec<Vec<DataType>>]) -> Vec<DataType> {
+        rs.iter()
+            .flat_map(|rs| rs.iter().map(|r| r[1].clone()))
+            .collect()
+    }
+
+    #[test]
+    fn range_lookup() {
+        use std::ops::Bound::*;
+
+        let (r, mut w) = new(2, &[0]);
+        w.add((0..10).map(|i| Record::Positive(vec![i.into(), (i * 10).into()])));
+        w.swap();
+
+        let range = (Included(vec![3.into()]), Excluded(vec![6.into()]));
+        let (rs, _) = r
+            .try_find_range_and(&range, |rs| rs.iter().cloned().collect::<Vec<_>>())
+            .unwrap();
+        assert_eq!(
+            range_of(&rs.unwrap()),
+            vec![30.into(), 40.into(), 50.into()]
+        );
+
+        let range = (Excluded(vec![6.into()]), Excluded(vec![3.into()]));
+        let (rs, _) = r.try_find_range_and(&range, |rs| rs.len()).unwrap();
+        assert_eq!(rs, Some(vec![]));
+    }
+
+    #[test]
+    fn ordered_range_lookup() {
+        use std::ops::Bound::*;
+
+        let (r, mut w) = new_ordered(2, &[0]);
+        let range = (Excluded(vec![7.into()]), Unbounded);
+        assert_eq!(r.try_find_range_and(&range, |rs| rs.len()), Err(()));
+
+        w.add(
+            (0..10)
+                .rev()
+                .map(|i| Record::Positive(vec![i.into(), i.into()])),
+        );
+        w.swap();
+
+        let (rs, _) = r
+            .try_find_range_and(&range, |rs| rs.iter().cloned().collect::<Vec<_>>())
+            .unwrap();
+        assert_eq!(range_of(&rs.unwrap()), vec![8.into(), 9.into()]);
+
+        // removing the last row for a key also removes it from the index
+        w.add(vec![Record::Negative(vec![8.into(), 8.into()])]);
+        w.swap();
+
+        let (rs, _) = r
+            .try_find_range_and(&range, |rs| rs.iter().cloned().collect::<Vec<_>>())
+            .unwrap();
+        assert_eq!(range_of(&rs.unwrap()), vec![9.into()]);
+    }
+
+    #[test]
+    fn partial_range_lookup() {
+        use std::ops::Bound::*;
+
+        let (r, mut w) = new_partial(1, &[0], |_: &mut dyn Iterator<Item = &[DataType]>| true);
+        w.swap();
+
+        let range = (Unbounded, Unbounded);
+        assert_eq!(r.try_find_range_and(&range, |rs| rs.len()).unwrap().0, None);
+    }
+
+    #[test]
+    fn partial_ordered_range_lookup() {
+        use std::ops::Bound::*;
+        use std::sync::Mutex;
+
+        let triggered = Arc::new(Mutex::new(Vec::new()));
+        let t = triggered.clone();
+        let (r, mut w) = new_partial_ordered(
+            2,
+            &[0],
+            |_: &mut dyn Iterator<Item = &[DataType]>| true,
+            move |ranges: Vec<KeyRange>| {
+                t.lock().unwrap().extend(ranges);
+                true
+            },
+        );
+        w.swap();
+
+        let key = |i: i32| vec![DataType::from(i)];
+        let range = (Included(key(2)), Excluded(key(6)));
+        assert_eq!(r.try_find_range_and(&range, |rs| rs.len()).unwrap().0, None);
+        assert!(r.trigger_range(&range));
+        assert_eq!(*triggered.lock().unwrap(), vec![range.clone()]);
+
+        // a range replay finds keys 3 and 4, but only 3 has been replayed so far
+        let mut pending = HashSet::new();
+        pending.insert(key(4));
+        w.mark_range_filled(range.clone(), pending);
+        w.mut_with_key(key(3)).mark_filled();
+        w.add(vec![Record::Positive(vec![3.into(), 30.into()])]);
+        w.swap();
+        assert_eq!(r.try_find_range_and(&range, |rs| rs.len()).unwrap().0, None);
+
+        w.mut_with_key(key(4)).mark_filled();
+        w.add(vec![Record::Positive(vec![4.into(), 40.into()])]);
+        w.mark_range_keys_filled(&[key(4)]);
+        w.swap();
+        let (rs, _) = r
+            .try_find_range_and(&range, |rs| rs.iter().cloned().collect::<Vec<_>>())
+            .unwrap();
+        assert_eq!(range_of(&rs.unwrap()), vec![30.into(), 40.into()]);
+
+        // keys without rows in a filled range are empty rather than holes, and new keys in it are
+        // filled by regular writes
+        assert_eq!(r.try_find_and(&key(2), |rs| rs.len()).unwrap().0, Some(0));
+        assert!(w.fill_if_covered(&[5.into(), 50.into()]));
+        w.add(vec![Record::Positive(vec![5.into(), 50.into()])]);
+        assert!(!w.fill_if_covered(&[7.into(), 70.into()]));
+        w.swap();
+        let (rs, _) = r
+            .try_find_range_and(&range, |rs| rs.iter().cloned().collect::<Vec<_>>())
+            .unwrap();
+        assert_eq!(
+            range_of(&rs.unwrap()),
+            vec![30.into(), 40.into(), 50.into()]
+        );
+
+        // only the part of a range that has not been filled is replayed
+        triggered.lock().unwrap().clear();
+        assert!(r.trigger_range(&(Included(key(3)), Included(key(8)))));
+        assert_eq!(
+            *triggered.lock().unwrap(),
+            vec![(Included(key(6)), Included(key(8)))]
+        );
+
+        // once a key in the range is evicted, the range must be replayed again
+        w.mut_with_key(key(4)).mark_hole();
+        w.swap();
+        assert_eq!(r.try_find_range_and(&range, |rs| rs.len()).unwrap().0, None);
+        assert_eq!(r.try_find_and(&key(4), |rs| rs.len()).unwrap().0, None);
+    }
+
+    #[test]
+    fn find_missing_ranges() {
+        use std::ops::Bound::*;
+
+        let key = |i: i32| vec![DataType::from(i)];
+        let filled = vec![
+            (Excluded(key(3)), Included(key(6))),
+            (Included(key(2)), Excluded(key(4))),
+            (Included(key(8)), Unbounded),
+        ];
+        assert_eq!(
+            missing_ranges(&(Included(key(0)), Included(key(10))), &filled),
+            vec![
+                (Included(key(0)), Excluded(key(2))),
+                (Excluded(key(6)), Excluded(key(8))),
+            ]
+        );
+        assert_eq!(
+            missing_ranges(&(Included(key(3)), Excluded(key(5))), &filled),
+            Vec::<KeyRange>::new()
+        );
+        assert_eq!(
+            missing_ranges(&(Unbounded, Unbounded), &[]),
+            vec![(Unbounded, Unbounded)]
+        );
+    }
+
+    #[test]
+    fn post_lookup() {
+        let (mut r, mut w) = new(3, &[0]);
+        w.add(vec![
+            Record::Positive(vec![1.into(), "a".into(), 10.into()]),
+            Record::Positive(vec![1.into(), "b".into(), 30.into()]),
+            Record::Positive(vec![1.into(), "c".into(), 20.into()]),
+            Record::Positive(vec![2.into(), "d".into(), 40.into()]),
+        ]);
+        w.swap();
+
+        r.set_post_lookup_filters(&[PostLookupFilter {
+            column: 2,
+            operator: Operator::Greater,
+            arity: 1,
+        }]);
+        r.set_order(&[(2, OrderType::OrderDescending)]);
+        assert!(r.has_post_lookup());
+
+        let lookup: Vec<DataType> = vec![1.into(), 15.into()];
+        let (key, args) = r.split_lookup(&lookup[..]);
+        assert_eq!(key, &lookup[..1]);
+        let names = |offset: usize, limit: Option<usize>| -> Vec<DataType> {
+            r.try_find_and(key, |rs| {
+                r.post_lookup(rs, args, offset, limit)
+                    .into_iter()
+                    .map(|row| row[1].clone())
+                    .collect()
+            })
+            .unwrap()
+            .0
+            .unwrap()
+        };
+        assert_eq!(names(0, None), vec!["b".into(), "c".into()]);
+        assert_eq!(names(0, Some(1)), vec!["b".into()]);
+        assert_eq!(names(1, Some(5)), vec!["c".into()]);
+        assert_eq!(names(2, None), Vec::<DataType>::new());
+    }
+
+    #[test]
+    fn arrange_lookups() {
+        let (mut r, _w) = new(3, &[1]);
+        r.set_post_lookup_filters(&[PostLookupFilter {
+            column: 2,
+            operator: Operator::Greater,
+            arity: 1,
+        }]);
+        assert_eq!(r.lookup_arity(), 2);
+
+        // the filter's parameter comes first in the query
+        r.set_lookup_order(&[1, 0]);
+        let mut lookups = vec![vec![15.into(), "a".into()]];
+        assert_eq!(r.arrange_lookups(&mut lookups[..]), Ok(()));
+        assert_eq!(lookups, vec![vec!["a".into(), 15.into()]]);
+        let (key, args) = r.split_lookup(&lookups[0]);
+        assert_eq!(key, &["a".into()]);
+        assert_eq!(args, &[15.into()]);
+
+        let mut lookups = vec![vec![15.into(), "a".into()], vec!["a".into()]];
+        assert_eq!(r.arrange_lookups(&mut lookups[..]), Err(1));
+    }
+
+    #[test]
+    fn ranged_lookups() {
+        let (mut r, mut w) = new_ordered(2, &[0, 1]);
+        w.add(vec![
+            Record::Positive(vec![0.into(), 1.into()]),
+            Record::Positive(vec![0.into(), 2.into()]),
+            Record::Positive(vec![0.into(), 3.into()]),
+            Record::Positive(vec![0.into(), 4.into()]),
+            Record::Positive(vec![1.into(), 2.into()]),
+        ]);
+        w.swap();
+
+        r.set_post_lookup_filters(&[
+            PostLookupFilter {
+                column: 1,
+                operator: Operator::Greater,
+                arity: 1,
+            },
+            PostLookupFilter {
+                column: 1,
+                operator: Operator::GreaterOrEqual,
+                arity: 1,
+            },
+            PostLookupFilter {
+                column: 1,
+                operator: Operator::LessOrEqual,
+                arity: 1,
+            },
+        ]);
+        r.set_ranged();
+        assert_eq!(r.lookup_arity(), 4);
+
+        let found = |lookup: Vec<DataType>| -> Vec<DataType> {
+            let (_, args) = r.split_lookup(&lookup[..]);
+            let rows = r
+                .try_find_ranged_and(&lookup[..], |rs| rs.iter().cloned().collect::<Vec<_>>())
+                .unwrap()
+                .0
+                .unwrap();
+            let rows: Vec<_> = rows.into_iter().flatten().collect();
+            r.post_lookup(&rows, args, 0, None)
+                .into_iter()
+                .map(|row| row[1].clone())
+                .collect()
+        };
+        // the tighter of the two lower bounds applies, and keys with another prefix are skipped
+        assert_eq!(
+            found(vec![0.into(), 1.into(), 2.into(), 4.into()]),
+            vec![2.into(), 3.into(), 4.into()]
+        );
+        assert_eq!(
+            found(vec![0.into(), 2.into(), 1.into(), 3.into()]),
+            vec![3.into()]
+        );
+        assert_eq!(
+            found(vec![1.into(), 0.into(), 0.into(), 9.into()]),
+            vec![2.into()]
+        );
+        assert_eq!(
+            found(vec![0.into(), 3.into(), 3.into(), 1.into()]),
+            Vec::<DataType>::new()
+        );
+    }
 }
@@ -1,6 +1,8 @@
+use super::KeyRange;
 use ahash::RandomState;
 use common::DataType;
 use evmap;
+use std::ops::RangeBounds;
 
 #[derive(Clone, Debug)]
 pub(super) enum Handle {
@@ -68,4 +70,82 @@ impl Handle {
             }
         }
     }
+
+    /// Look up each of the given keys under a single read guard.
+    ///
+    /// Keys that are not present are skipped.
+    pub(super) fn meta_get_many_and<F, T>(
+        &self,
+        keys: &[Vec<DataType>],
+        mut then: F,
+    ) -> Option<(Vec<T>, i64)>
+    where
+        F: FnMut(&evmap::Values<Vec<DataType>, RandomState>) -> T,
+    {
+        match *self {
+            Handle::Single(ref h) => {
+                let map = h.read()?;
+                let vs = keys
+                    .iter()
+                    .filter_map(|k| map.get(&k[0]).map(&mut then))
+                    .collect();
+                Some((vs, *map.meta()))
+            }
+            Handle::Double(ref h) => {
+                let map = h.read()?;
+                let vs = keys
+                    .iter()
+                    .filter_map(|k| map.get(&(k[0].clone(), k[1].clone())).map(&mut then))
+                    .collect();
+                Some((vs, *map.meta()))
+            }
+            Handle::Many(ref h) => {
+                let map = h.read()?;
+                let vs = keys
+                    .iter()
+                    .filter_map(|k| map.get(&k[..]).map(&mut then))
+                    .collect();
+                Some((vs, *map.meta()))
+            }
+        }
+    }
+
+    /// Find all keys that fall within `range` by scanning the whole map, and return their values
+    /// in key order.
+    pub(super) fn meta_range_and<F, T>(
+        &self,
+        range: &KeyRange,
+        mut then: F,
+    ) -> Option<(Vec<T>, i64)>
+    where
+        F: FnMut(&evmap::Values<Vec<DataType>, RandomState>) -> T,
+    {
+        macro_rules! scan {
+            ($h:expr, $key:expr) => {{
+                let map = $h.read()?;
+                let mut hits: Vec<_> = map
+                    .iter()
+                    .filter_map(|(k, vs)| {
+                        let k: Vec<DataType> = $key(k);
+                        if range.contains(&k) {
+                            Some((k, vs))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+                hits.sort_by(|a, b| a.0.cmp(&b.0));
+                let vs = hits.into_iter().map(|(_, vs)| then(vs)).collect();
+                Some((vs, *map.meta()))
+            }};
+        }
+
+        match *self {
+            Handle::Single(ref h) => scan!(h, |k: &DataType| vec![k.clone()]),
+            Handle::Double(ref h) => {
+                scan!(h, |k: &(DataType, DataType)| vec![k.0.clone(), k.1.clone()])
+            }
+            Handle::Many(ref h) => scan!(h, |k: &Vec<DataType>| k.clone()),
+        }
+    }
 }
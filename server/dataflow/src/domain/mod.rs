@@ -5,12 +5,14 @@ use std::cmp;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::mem;
 use std::net::SocketAddr;
+use std::ops::RangeBounds;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time;
 
+use crate::backlog::KeyRange;
 use crate::group_commit::GroupCommitQueueSet;
-use crate::payload::{ControlReplyPacket, ReplayPieceContext, SourceSelection};
+use crate::payload::{ControlReplyPacket, ReplayPieceContext, ReplayRange, SourceSelection};
 use crate::prelude::*;
 use ahash::RandomState;
 use futures_util::{future::FutureExt, stream::StreamExt};
@@ -172,6 +174,7 @@ impl DomainBuilder {
             mode: DomainMode::Forwarding,
             waiting: Default::default(),
             reader_triggered: Default::default(),
+            reader_range_triggered: Default::default(),
             replay_paths: Default::default(),
             replay_paths_by_dst: Default::default(),
 
@@ -233,6 +236,7 @@ pub struct Domain {
     waiting: Map<Waiting>,
     replay_paths: HashMap<Tag, ReplayPath>,
     reader_triggered: Map<HashSet<Vec<DataType>, RandomState>>,
+    reader_range_triggered: Map<Vec<KeyRange>>,
     timed_purges: VecDeque<TimedPurge>,
 
     replay_paths_by_dst: Map<HashMap<Vec<usize>, Vec<Tag>>>,
@@ -329,6 +333,73 @@ impl Domain {
         }
     }
 
+    fn find_tag_and_replay_ranges(
+        &mut self,
+        ranges: Vec<KeyRange>,
+        miss_columns: &[usize],
+        miss_in: LocalNodeIndex,
+    ) {
+        let tag = match self
+            .replay_paths_by_dst
+            .get(miss_in)
+            .and_then(|candidates| candidates.get(miss_columns))
+        {
+            Some(tags) if tags.len() == 1 => tags[0],
+            tags => unreachable!(format!(
+                "range replays of {:?} in {}.{:?} need exactly one tag, found {:?}",
+                ranges, miss_in, miss_columns, tags
+            )),
+        };
+
+        let requesting_shard = self.shard.unwrap_or(0);
+        match self.replay_paths.get_mut(&tag).unwrap().trigger {
+            TriggerEndpoint::Local(..) => {
+                // see find_tags_and_replay for why we don't seed the replay right away
+                self.delayed_for_self
+                    .push_back(Box::new(Packet::RequestRangeReplay {
+                        tag,
+                        ranges,
+                        unishard: true, // local replays are necessarily single-shard
+                        requesting_shard,
+                    }));
+            }
+            TriggerEndpoint::End {
+                source,
+                ref mut options,
+            } => {
+                // ordered readers are only partial if there is no shuffle between them and the
+                // source of their replays, so each shard of the reader holds the keys of the same
+                // shard of the source.
+                let shard = match source {
+                    _ if options.len() == 1 => 0,
+                    SourceSelection::KeyShard { .. } | SourceSelection::SameShard => {
+                        requesting_shard
+                    }
+                    SourceSelection::AllShards(_) => {
+                        unreachable!("range replays can't ask all shards")
+                    }
+                };
+
+                // range replays don't count against max_concurrent_replays, since we don't know
+                // how many keys they will fill until they arrive.
+                if options[shard]
+                    .send(Box::new(Packet::RequestRangeReplay {
+                        tag,
+                        ranges,
+                        unishard: true, // only one shard, so only one path
+                        requesting_shard,
+                    }))
+                    .is_err()
+                {
+                    // we're shutting down -- it's fine.
+                }
+            }
+            TriggerEndpoint::Start(..) | TriggerEndpoint::None => {
+                unreachable!("asked to replay along non-existing path")
+            }
+        }
+    }
+
     fn on_replay_miss(
         &mut self,
         miss_in: LocalNodeIndex,
@@ -834,6 +905,18 @@ impl Domain {
                             .send(ControlReplyPacket::ack())
                             .unwrap();
                     }
+                    Packet::RenameBaseColumn {
+                        node,
+                        column,
+                        field,
+                    } => {
+                        let mut n = self.nodes[node].borrow_mut();
+                        assert!(n.is_base(), "told to rename column of non-base node");
+                        n.rename_column(column, &field);
+                        self.control_reply_tx
+                            .send(ControlReplyPacket::ack())
+                            .unwrap();
+                    }
                     Packet::UpdateEgress {
                         node,
                         new_tx,
@@ -857,7 +940,8 @@ impl Domain {
                     }
                     Packet::StateSizeProbe { node } => {
                         let row_count = self.state.get(node).map(|r| r.rows()).unwrap_or(0);
-                        let mem_size = self.state.get(node).map(|s| s.deep_size_of()).unwrap_or(0);
+                        let mem_size = self.state.get(node).map(|s| s.deep_size_of()).unwrap_or(0)
+                            + self.nodes[node].borrow().auxiliary_state_size();
                         self.control_reply_tx
                             .send(ControlReplyPacket::StateSize(row_count, mem_size))
                             .unwrap();
@@ -898,7 +982,6 @@ impl Domain {
                                 let k = key.clone(); // ugh
                                 let txs = (0..shards)
                                     .map(|shard| {
-                                        let key = key.clone();
                                         let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
                                         let sender = self
                                             .channel_coordinator
@@ -910,13 +993,6 @@ impl Domain {
                                         tokio::spawn(
                                             self.shutdown_valve
                                                 .wrap(rx)
-                                                .map(move |misses| {
-                                                    Box::new(Packet::RequestReaderReplay {
-                                                        keys: misses,
-                                                        cols: key.clone(),
-                                                        node,
-                                                    })
-                                                })
                                                 .map(Ok)
                                                 .forward(sender)
                                                 .map(|r| {
@@ -932,9 +1008,16 @@ impl Domain {
                                         tx
                                     })
                                     .collect::<Vec<_>>();
-                                let (r_part, w_part) = backlog::new_partial(
-                                    cols,
-                                    &k[..],
+                                let range_tx = txs[self.shard.unwrap_or(0)].clone();
+                                let range_cols = key.clone();
+                                let request = move |keys| {
+                                    Box::new(Packet::RequestReaderReplay {
+                                        keys,
+                                        cols: key.clone(),
+                                        node,
+                                    })
+                                };
+                                let trigger =
                                     move |misses: &mut dyn Iterator<Item = &[DataType]>| {
                                         let n = txs.len();
                                         if n == 1 {
@@ -943,7 +1026,7 @@ impl Domain {
                                             if misses.is_empty() {
                                                 return true;
                                             }
-                                            txs[0].send(misses).is_ok()
+                                            txs[0].send(request(misses)).is_ok()
                                         } else {
                                             // TODO: compound reader
                                             let mut per_shard = HashMap::new();
@@ -958,16 +1041,39 @@ impl Domain {
                                             if per_shard.is_empty() {
                                                 return true;
                                             }
-                                            per_shard
-                                                .into_iter()
-                                                .all(|(shard, keys)| txs[shard].send(keys).is_ok())
+                                            per_shard.into_iter().all(|(shard, keys)| {
+                                                txs[shard].send(request(keys)).is_ok()
+                                            })
                                         }
-                                    },
-                                );
+                                    };
 
                                 let mut n = self.nodes[node].borrow_mut();
+                                let ordered = n.with_reader(|r| r.is_ordered()).unwrap();
+                                let (mut r_part, w_part) = if ordered {
+                                    // the reader's shard only replays ranges from the matching
+                                    // shard of the replay source
+                                    backlog::new_partial_ordered(
+                                        cols,
+                                        &k[..],
+                                        trigger,
+                                        move |ranges: Vec<KeyRange>| {
+                                            range_tx
+                                                .send(Box::new(Packet::RequestReaderRangeReplay {
+                                                    ranges,
+                                                    cols: range_cols.clone(),
+                                                    node,
+                                                }))
+                                                .is_ok()
+                                        },
+                                    )
+                                } else {
+                                    backlog::new_partial(cols, &k[..], trigger)
+                                };
                                 tokio::task::block_in_place(|| {
                                     n.with_reader_mut(|r| {
+                                        r_part.set_post_lookup_filters(r.post_lookup_filters());
+                                        r_part.set_lookup_order(r.lookup_order());
+                                        r_part.set_order(r.order());
                                         assert!(self
                                             .readers
                                             .lock()
@@ -986,11 +1092,22 @@ impl Domain {
                             }
                             InitialState::Global { gid, cols, key } => {
                                 use crate::backlog;
-                                let (r_part, w_part) = backlog::new(cols, &key[..]);
-
                                 let mut n = self.nodes[node].borrow_mut();
+                                let ordered = n.with_reader(|r| r.is_ordered()).unwrap();
+                                let (mut r_part, w_part) = if ordered {
+                                    backlog::new_ordered(cols, &key[..])
+                                } else {
+                                    backlog::new(cols, &key[..])
+                                };
+
                                 tokio::task::block_in_place(|| {
                                     n.with_reader_mut(|r| {
+                                        r_part.set_post_lookup_filters(r.post_lookup_filters());
+                                        r_part.set_lookup_order(r.lookup_order());
+                                        r_part.set_order(r.order());
+                                        if r.is_ranged() {
+                                            r_part.set_ranged();
+                                        }
                                         assert!(self
                                             .readers
                                             .lock()
@@ -1118,13 +1235,25 @@ impl Domain {
                                     .writer_mut()
                                     .expect("reader replay requested for non-materialized reader");
 
+                                let mut filled = false;
                                 keys.retain(|key| {
-                                    w.with_key(&*key)
+                                    let hole = w
+                                        .with_key(&*key)
                                         .try_find_and(|_| ())
                                         .expect("reader replay requested for non-ready reader")
                                         .0
-                                        .is_none()
+                                        .is_none();
+                                    if hole && w.covers(&key[..]) {
+                                        // a range replay has shown that the key has no rows
+                                        w.mut_with_key(&key[..]).mark_filled();
+                                        filled = true;
+                                        return false;
+                                    }
+                                    hole
                                 });
+                                if filled {
+                                    w.swap();
+                                }
                             })
                             .unwrap();
 
@@ -1140,6 +1269,57 @@ impl Domain {
                         }
                         self.total_replay_time.stop();
                     }
+                    Packet::RequestReaderRangeReplay { ranges, cols, node } => {
+                        use crate::backlog;
+                        self.total_replay_time.start();
+                        // don't request the parts of the ranges that have been filled since the
+                        // request was sent, or that we have already requested
+                        let in_flight = self.reader_range_triggered.entry(node).or_default();
+                        let mut missing = Vec::new();
+                        self.nodes[node]
+                            .borrow_mut()
+                            .with_reader_mut(|r| {
+                                let w = r
+                                    .writer_mut()
+                                    .expect("reader replay requested for non-materialized reader");
+                                // ensure that all writes have been applied
+                                w.swap();
+
+                                for range in &ranges {
+                                    for range in w.unfilled(range) {
+                                        missing.extend(backlog::missing_ranges(
+                                            &range,
+                                            &in_flight[..],
+                                        ));
+                                    }
+                                }
+                            })
+                            .expect("reader replay requested for non-reader node");
+
+                        if !missing.is_empty() {
+                            in_flight.extend(missing.iter().cloned());
+                            self.find_tag_and_replay_ranges(missing, &cols[..], node);
+                        }
+                        self.total_replay_time.stop();
+                    }
+                    Packet::RequestRangeReplay {
+                        tag,
+                        ranges,
+                        unishard,
+                        requesting_shard,
+                    } => {
+                        trace!(
+                            self.log,
+                           "got range replay request";
+                           "tag" => tag,
+                           "ranges" => format!("{:?}", ranges)
+                        );
+                        self.total_replay_time.start();
+                        for range in ranges {
+                            self.seed_range(tag, range, unishard, requesting_shard, executor);
+                        }
+                        self.total_replay_time.stop();
+                    }
                     Packet::RequestPartialReplay {
                         tag,
                         keys,
@@ -1391,6 +1571,7 @@ impl Domain {
                                         .get(local_index)
                                         .map(|s| s.deep_size_of())
                                         .unwrap_or(0)
+                                        + n.auxiliary_state_size()
                                 };
 
                                 let mat_state = if !n.is_reader() {
@@ -1607,6 +1788,7 @@ impl Domain {
                         tag,
                         context: ReplayPieceContext::Partial {
                             for_keys: keys,
+                            for_range: None,
                             unishard: single_shard, // if we are the only source, only one path
                             ignore: false,
                             requesting_shard,
@@ -1735,6 +1917,7 @@ impl Domain {
                         tag,
                         context: ReplayPieceContext::Partial {
                             for_keys: k,
+                            for_range: None,
                             unishard: single_shard, // if we are the only source, only one path
                             ignore: false,
                             requesting_shard,
@@ -1779,6 +1962,76 @@ impl Domain {
         }
     }
 
+    fn seed_range(
+        &mut self,
+        tag: Tag,
+        range: KeyRange,
+        single_shard: bool,
+        requesting_shard: usize,
+        ex: &mut dyn Executor,
+    ) {
+        let m = match self.replay_paths[&tag] {
+            ReplayPath {
+                source: Some(source),
+                trigger: TriggerEndpoint::Start(ref cols),
+                ref path,
+                ..
+            }
+            | ReplayPath {
+                source: Some(source),
+                trigger: TriggerEndpoint::Local(ref cols),
+                ref path,
+                ..
+            } => {
+                let state = self
+                    .state
+                    .get(source)
+                    .expect("migration replay path started with non-materialized node");
+                // partial state can't tell us which keys fall within a range
+                assert!(!state.is_partial(), "range replay from partial state");
+
+                let mut keys = HashSet::new();
+                let data: Records = state
+                    .cloned_records()
+                    .into_iter()
+                    .filter_map(|r| {
+                        let r = self.seed_row(source, Cow::Owned(r));
+                        let key: Vec<_> = cols.iter().map(|&c| r[c].clone()).collect();
+                        if range.contains(&key) {
+                            keys.insert(key);
+                            Some(r)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+
+                trace!(self.log,
+                       "satisfied range replay request";
+                       "tag" => tag,
+                       "range" => ?range,
+                       "keys" => keys.len(),
+                );
+
+                Box::new(Packet::ReplayPiece {
+                    link: Link::new(source, path[0].node),
+                    tag,
+                    context: ReplayPieceContext::Partial {
+                        for_keys: keys.clone(),
+                        for_range: Some(ReplayRange { range, keys }),
+                        unishard: single_shard, // if we are the only source, only one path
+                        ignore: false,
+                        requesting_shard,
+                    },
+                    data,
+                })
+            }
+            _ => unreachable!(),
+        };
+
+        self.handle_replay(m, ex);
+    }
+
     #[allow(clippy::cognitive_complexity)]
     fn handle_replay(&mut self, m: Box<Packet>, ex: &mut dyn Executor) {
         let tag = m.tag().unwrap();
@@ -1856,13 +2109,78 @@ impl Domain {
                         .with_reader(|r| r.is_materialized())
                         .unwrap_or(false);
                     let dst_is_target = !self.nodes[dst].borrow().is_sender();
+                    let for_range = matches!(
+                        context,
+                        ReplayPieceContext::Partial {
+                            for_range: Some(_),
+                            ..
+                        }
+                    );
 
                     if dst_is_target {
-                        // prune keys and data for keys we're not waiting for
                         if let ReplayPieceContext::Partial {
+                            ref mut for_keys,
+                            for_range: Some(ref range),
+                            ..
+                        } = context
+                        {
+                            // a range replay fills the holes for all the keys in its range. the
+                            // range is filled once the keys that other replays are filling, and
+                            // those that miss on the way, have been replayed as well.
+                            let had = for_keys.len();
+                            if let Some(in_flight) = self.reader_range_triggered.get_mut(dst) {
+                                in_flight.retain(|r| r != &range.range);
+                            }
+                            let triggered = self.reader_triggered.entry(dst).or_default();
+                            self.nodes[dst]
+                                .borrow_mut()
+                                .with_reader_mut(|r| {
+                                    let w = r
+                                        .writer_mut()
+                                        .expect("range replay to non-materialized reader");
+                                    // make sure that keys filled by earlier replays aren't holes
+                                    w.swap();
+
+                                    let is_hole = |key: &Vec<DataType>| {
+                                        w.with_key(&key[..])
+                                            .try_find_and(|_| ())
+                                            .expect("range replay to non-ready reader")
+                                            .0
+                                            .is_none()
+                                    };
+                                    let mut pending: HashSet<Vec<DataType>> = triggered
+                                        .iter()
+                                        .filter(|&key| range.range.contains(key) && is_hole(key))
+                                        .cloned()
+                                        .collect();
+                                    // the keys that other replays are filling are left to them
+                                    for_keys.retain(|key| is_hole(key) && !pending.contains(key));
+                                    pending.extend(
+                                        range.keys.iter().filter(|&key| is_hole(key)).cloned(),
+                                    );
+                                    triggered.extend(pending.iter().cloned());
+
+                                    w.mark_range_filled(range.range.clone(), pending);
+                                    if for_keys.is_empty() {
+                                        w.swap();
+                                    }
+                                })
+                                .expect("range replay to non-reader node");
+
+                            if for_keys.len() != had {
+                                let partial_keys =
+                                    path.first().unwrap().partial_key.as_ref().unwrap();
+                                data.retain(|r| {
+                                    for_keys.iter().any(|k| {
+                                        partial_keys.iter().enumerate().all(|(i, c)| r[*c] == k[i])
+                                    })
+                                });
+                            }
+                        } else if let ReplayPieceContext::Partial {
                             ref mut for_keys, ..
                         } = context
                         {
+                            // prune keys and data for keys we're not waiting for
                             let had = for_keys.len();
                             let partial_keys = path.last().unwrap().partial_key.as_ref().unwrap();
                             if let Some(w) = self.waiting.get(dst) {
@@ -2022,6 +2340,29 @@ impl Domain {
                             HashSet::new()
                         };
 
+                        if target && is_reader {
+                            // the keys that didn't miss are filled, so range replays no longer
+                            // wait for them, and we no longer wait for a replay of them.
+                            let filled: Vec<_> = backfill_keys
+                                .as_ref()
+                                .unwrap()
+                                .iter()
+                                .filter(|&k| !missed_on.contains(k))
+                                .collect();
+                            n.with_reader_mut(|r| {
+                                if let Some(wh) = r.writer_mut() {
+                                    wh.mark_range_keys_filled(filled.iter().cloned());
+                                }
+                            })
+                            .unwrap();
+                            if let Some(ref mut prev) = self.reader_triggered.get_mut(segment.node)
+                            {
+                                for key in filled {
+                                    prev.remove(&key[..]);
+                                }
+                            }
+                        }
+
                         if target {
                             if !misses.is_empty() {
                                 // we missed while processing
@@ -2048,14 +2389,6 @@ impl Domain {
                                     }
                                 })
                                 .unwrap();
-                                // and also unmark the replay request
-                                if let Some(ref mut prev) =
-                                    self.reader_triggered.get_mut(segment.node)
-                                {
-                                    for key in backfill_keys.as_ref().unwrap().iter() {
-                                        prev.remove(&key[..]);
-                                    }
-                                }
                             }
                         }
 
@@ -2104,7 +2437,9 @@ impl Domain {
                         //     replay count! note that it's *not* sufficient to check if the
                         //     *current* node is a target/reader, because we could miss during a
                         //     join along the path.
+                        //  4. range replays don't count towards the concurrent replays.
                         if backfill_keys.is_some()
+                            && !for_range
                             && finished_partial == 0
                             && (dst_is_reader || dst_is_target)
                         {
@@ -2185,11 +2520,13 @@ impl Domain {
                             });
                         }
 
-                        // no more keys to replay, so we might as well terminate early
-                        if backfill_keys
-                            .as_ref()
-                            .map(|b| b.is_empty())
-                            .unwrap_or(false)
+                        // no more keys to replay, so we might as well terminate early. range replays
+                        // must still reach the reader to fill the rest of their range.
+                        if !for_range
+                            && backfill_keys
+                                .as_ref()
+                                .map(|b| b.is_empty())
+                                .unwrap_or(false)
                         {
                             break 'outer;
                         }
@@ -2398,6 +2735,7 @@ impl Domain {
                         }
                         ReplayPieceContext::Partial {
                             for_keys,
+                            for_range: _,
                             ignore,
                             unishard: _,
                             requesting_shard: _,
@@ -2414,7 +2752,9 @@ impl Domain {
                                         tag,
                                     });
                                 }
-                                assert_ne!(finished_partial, 0);
+                                if !for_range {
+                                    assert_ne!(finished_partial, 0);
+                                }
                             } else if dst_is_target {
                                 trace!(self.log, "partial replay completed"; "local" => dst.id());
                                 if finished_partial == 0 {
@@ -2753,7 +3093,10 @@ impl Domain {
                                 self.state
                                     .get(local_index)
                                     .filter(|state| state.is_partial())
-                                    .map(|state| (local_index, state.deep_size_of()))
+                                    .map(|state| {
+                                        let size = state.deep_size_of() + n.auxiliary_state_size();
+                                        (local_index, size)
+                                    })
                             }
                         })
                         .filter(|&(_, s)| s > 0)
@@ -2936,7 +3279,7 @@ impl Domain {
                     self.state
                         .get(local_index)
                         .filter(|state| state.is_partial())
-                        .map(|s| s.deep_size_of())
+                        .map(|s| s.deep_size_of() + n.auxiliary_state_size())
                         .unwrap_or(0)
                 }
             })
@@ -104,11 +104,11 @@ impl GroupCommitQueueSet {
 
                     assert_eq!(senders.len(), 0);
                     assert_eq!(merged_dst, dst);
-                    acc.extend(data);
 
                     if let Some(src) = src {
-                        all_senders.push(src);
+                        all_senders.push((src, data.len()));
                     }
+                    acc.extend(data);
                 }
                 _ => unreachable!(),
             }
@@ -140,6 +140,20 @@ impl Node {
         Ingredient::can_query_through(&**self)
     }
 
+    /// The number of bytes of state the node's operator keeps besides its materialization.
+    pub fn auxiliary_state_size(&self) -> u64 {
+        if self.is_internal() {
+            Ingredient::auxiliary_state_size(&**self)
+        } else {
+            0
+        }
+    }
+
+    /// Returns false if partial replays through this operator could miss rows.
+    pub fn can_replay_partially(&self) -> bool {
+        Ingredient::can_replay_partially(&**self)
+    }
+
     pub fn is_join(&self) -> bool {
         Ingredient::is_join(&**self)
     }
@@ -340,6 +354,10 @@ impl Node {
         self.fields.len() - 1
     }
 
+    pub fn rename_column(&mut self, column: usize, field: &str) {
+        self.fields[column] = field.to_string();
+    }
+
     pub fn has_domain(&self) -> bool {
         self.domain.is_some()
     }
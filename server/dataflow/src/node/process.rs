@@ -36,7 +36,12 @@ impl Node {
                         inner, mut senders, ..
                     }) => {
                         let Input { dst, data } = unsafe { inner.take() };
-                        let mut rs = b.process(addr, data, &*state);
+                        let shard = on_shard.map(|shard| {
+                            let shards =
+                                self.sharded_by.shards().expect("on a shard, but unsharded");
+                            (shard, shards)
+                        });
+                        let (mut rs, rejected, generated) = b.process(addr, data, &*state, shard);
 
                         // When a replay originates at a base node, we replay the data *through* that
                         // same base node because its column set may have changed. However, this replay
@@ -50,8 +55,32 @@ impl Node {
                         }
 
                         // Send write-ACKs to all the clients with updates that made
-                        // it into this merged packet:
-                        senders.drain(..).for_each(|src| ex.ack(src));
+                        // it into this merged packet, telling them about the values generated
+                        // for their writes, or the first of their writes that was rejected:
+                        let mut rejected = rejected.into_iter().peekable();
+                        let mut generated = generated.into_iter().peekable();
+                        let mut ops_before = 0;
+                        for (src, nops) in senders.drain(..) {
+                            ops_before += nops;
+                            let mut values = Vec::new();
+                            while let Some((_, v)) = generated.next_if(|&(op, _)| op < ops_before) {
+                                values.push(v);
+                            }
+                            let mut reply = Ok(values);
+                            while let Some(r) = rejected.next_if(|r| r.op < ops_before) {
+                                if reply.is_ok() {
+                                    reply = Err(WriteError::DuplicateKey {
+                                        columns: r
+                                            .columns
+                                            .iter()
+                                            .map(|&c| self.fields[c].clone())
+                                            .collect(),
+                                        key: r.key,
+                                    });
+                                }
+                            }
+                            ex.ack(src, reply);
+                        }
 
                         *m = Some(Box::new(Packet::Message {
                             link: Link::new(dst, dst),
@@ -101,6 +130,7 @@ impl Node {
                                     requesting_shard,
                                     unishard,
                                     ignore,
+                                    ..
                                 },
                             ..
                         } => {
@@ -1,8 +1,9 @@
 use crate::prelude::*;
+use nom_sql::Literal;
 use noria::{Modification, Operation, TableOperation};
 use std::borrow::Cow;
-use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
 use vec_map::VecMap;
 
 /// Base is used to represent the root nodes of the Noria data flow graph.
@@ -13,6 +14,11 @@ use vec_map::VecMap;
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Base {
     primary_key: Option<Vec<usize>>,
+    unique_keys: Vec<Vec<usize>>,
+    auto_increment: Option<usize>,
+    // the largest value seen in the auto-increment column, once we've looked
+    last_auto_increment: Option<i64>,
+    default_exprs: Vec<(usize, Literal)>,
 
     defaults: Vec<DataType>,
     dropped: Vec<usize>,
@@ -33,10 +39,35 @@ impl Base {
         self
     }
 
+    /// Builder with keys whose values must be unique across all rows (like `UNIQUE` in MySQL).
+    pub fn with_unique_keys(mut self, unique_keys: Vec<Vec<usize>>) -> Base {
+        self.unique_keys = unique_keys;
+        self
+    }
+
+    /// Builder with a column whose value is generated for inserted rows that leave it `NULL`
+    /// (like `AUTO_INCREMENT` in MySQL).
+    pub fn with_auto_increment(mut self, column: usize) -> Base {
+        self.auto_increment = Some(column);
+        self
+    }
+
+    /// Builder with default values that are computed when a row is inserted, such as
+    /// `CURRENT_TIMESTAMP`. They fill in columns that inserted rows leave `NULL`.
+    pub fn with_default_exprs(mut self, default_exprs: Vec<(usize, Literal)>) -> Base {
+        self.default_exprs = default_exprs;
+        self
+    }
+
     pub fn key(&self) -> Option<&[usize]> {
         self.primary_key.as_ref().map(|cols| &cols[..])
     }
 
+    /// The keys other than the primary key whose values must be unique across all rows.
+    pub fn unique_keys(&self) -> &[Vec<usize>] {
+        &self.unique_keys[..]
+    }
+
     /// Add a new column to this base node.
     pub fn add_column(&mut self, default: DataType) -> usize {
         assert!(
@@ -91,6 +122,10 @@ impl Clone for Base {
     fn clone(&self) -> Base {
         Base {
             primary_key: self.primary_key.clone(),
+            unique_keys: self.unique_keys.clone(),
+            auto_increment: self.auto_increment,
+            last_auto_increment: self.last_auto_increment,
+            default_exprs: self.default_exprs.clone(),
 
             defaults: self.defaults.clone(),
             dropped: self.dropped.clone(),
@@ -103,6 +138,10 @@ impl Default for Base {
     fn default() -> Self {
         Base {
             primary_key: None,
+            unique_keys: Vec::new(),
+            auto_increment: None,
+            last_auto_increment: None,
+            default_exprs: Vec::new(),
 
             defaults: Vec::new(),
             dropped: Vec::new(),
@@ -113,7 +152,7 @@ impl Default for Base {
 
 fn key_val(i: usize, col: usize, r: &TableOperation) -> &DataType {
     match *r {
-        TableOperation::Insert(ref row) => &row[col],
+        TableOperation::Insert(ref row) | TableOperation::InsertIgnore(ref row) => &row[col],
         TableOperation::Delete { ref key } => &key[i],
         TableOperation::Update { ref key, .. } => &key[i],
         TableOperation::InsertOrUpdate { ref row, .. } => &row[col],
@@ -127,38 +166,233 @@ fn key_of<'a>(key_cols: &'a [usize], r: &'a TableOperation) -> impl Iterator<Ite
         .map(move |(i, col)| key_val(i, *col, r))
 }
 
+/// A write that a base node refused to apply.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Rejection {
+    /// The position of the rejected operation in the batch given to `Base::process`.
+    pub(crate) op: usize,
+    /// The columns of the key the operation would have duplicated.
+    pub(crate) columns: Vec<usize>,
+    /// The duplicated key.
+    pub(crate) key: Vec<DataType>,
+}
+
+/// Checks rows against the unique keys of a base, including the changes made by the batch that
+/// is being processed, which are not yet in the base's materialization.
+struct UniqueKeys<'a> {
+    keys: &'a [Vec<usize>],
+    db: Option<&'a dyn State>,
+    // how many rows the batch so far has added (or removed) for each value of each unique key
+    pending: Vec<HashMap<Vec<DataType>, isize>>,
+}
+
+impl<'a> UniqueKeys<'a> {
+    /// Find a unique key that `row` would duplicate if it replaced the materialized row
+    /// `replaces`, and return the key's columns and the duplicated value.
+    fn violated_by(
+        &self,
+        row: &[DataType],
+        replaces: Option<&[DataType]>,
+    ) -> Option<(&'a [usize], Vec<DataType>)> {
+        for (cols, pending) in self.keys.iter().zip(&self.pending) {
+            let key: Vec<DataType> = cols.iter().map(|&c| row[c].clone()).collect();
+            if key.iter().any(DataType::is_none) {
+                // like in MySQL, NULLs are never duplicates of each other
+                continue;
+            }
+
+            let db = self.db.expect("base with unique keys must be materialized");
+            let mut n = match db.lookup(cols, &KeyType::from(&key[..])) {
+                LookupResult::Some(rows) => rows.len() as isize,
+                LookupResult::Missing => unreachable!("bases are fully materialized"),
+            };
+            n += pending.get(&key).cloned().unwrap_or(0);
+            if let Some(replaces) = replaces {
+                if cols.iter().all(|&c| replaces[c] == row[c]) {
+                    n -= 1;
+                }
+            }
+
+            if n > 0 {
+                return Some((&cols[..], key));
+            }
+        }
+        None
+    }
+
+    /// Remember that the batch added (`delta = 1`) or removed (`delta = -1`) `row`.
+    fn record(&mut self, row: &[DataType], delta: isize) {
+        for (cols, pending) in self.keys.iter().zip(&mut self.pending) {
+            let key = cols.iter().map(|&c| row[c].clone()).collect();
+            *pending.entry(key).or_insert(0) += delta;
+        }
+    }
+}
+
+/// The value of an auto-increment column, if it is one that moves the counter along.
+fn auto_increment_value(v: &DataType) -> Option<i64> {
+    match *v {
+        DataType::Int(_) | DataType::BigInt(_) | DataType::UnsignedInt(_) => Some(v.into()),
+        DataType::UnsignedBigInt(n) => Some(i64::try_from(n).unwrap_or_else(|_| i64::max_value())),
+        _ => None,
+    }
+}
+
+/// The auto-increment value to hand out after `last` on the given `(shard, shards)`.
+///
+/// Integers are sharded by their value modulo the number of shards, so each shard only hands out
+/// values that it would also be sent. This also keeps the shards from handing out the same values.
+fn next_auto_increment(last: i64, shard: Option<(usize, usize)>) -> i64 {
+    let next = last.max(0) + 1;
+    match shard {
+        None => next,
+        Some((shard, shards)) => {
+            let (shard, shards) = (shard as i64, shards as i64);
+            next + (shard - next % shards).rem_euclid(shards)
+        }
+    }
+}
+
+/// Apply the modifications of an update to `row`.
+fn modify(mut row: Vec<DataType>, update: Vec<Modification>) -> Vec<DataType> {
+    for (col, op) in update.into_iter().enumerate() {
+        // XXX: make sure user doesn't update primary key?
+        match op {
+            Modification::Set(v) => row[col] = v,
+            Modification::Apply(ref op, ref v) if row[col].is_decimal() || v.is_decimal() => {
+                // decimal arithmetic is exact, so no need to widen. results that can't be
+                // represented, or values that aren't numbers, make the column NULL.
+                row[col] = match *op {
+                    Operation::Add => row[col].checked_add(v),
+                    Operation::Sub => row[col].checked_sub(v),
+                }
+                .unwrap_or(DataType::None);
+            }
+            Modification::Apply(op, v) => {
+                let old: i128 = row[col].clone().into();
+                let delta: i128 = v.into();
+                row[col] = match op {
+                    Operation::Add => (old + delta).into(),
+                    Operation::Sub => (old - delta).into(),
+                };
+            }
+            Modification::None => {}
+        }
+    }
+    row
+}
+
 impl Base {
     pub(in crate::node) fn take(&mut self) -> Self {
         Clone::clone(self)
     }
 
+    /// Fill in the values of inserted rows that are generated by the base, and return the
+    /// auto-increment values that were generated, along with the positions of their operations.
+    fn generate(
+        &mut self,
+        ops: &mut [TableOperation],
+        shard: Option<(usize, usize)>,
+        db: Option<&dyn State>,
+    ) -> Vec<(usize, DataType)> {
+        let mut generated = Vec::new();
+        if self.auto_increment.is_none() && self.default_exprs.is_empty() {
+            return generated;
+        }
+
+        for (i, op) in ops.iter_mut().enumerate() {
+            let row = match *op {
+                TableOperation::Insert(ref mut row)
+                | TableOperation::InsertIgnore(ref mut row)
+                | TableOperation::InsertOrUpdate { ref mut row, .. } => row,
+                TableOperation::Delete { .. } | TableOperation::Update { .. } => continue,
+            };
+
+            for &(col, ref expr) in &self.default_exprs {
+                if row[col].is_none() {
+                    row[col] = expr.into();
+                }
+            }
+
+            if let Some(col) = self.auto_increment {
+                let last = self.last_auto_increment.get_or_insert_with(|| {
+                    // we may be recovering, or the column may have been made auto-increment
+                    // after rows were written, so start after what's already there
+                    db.map(|db| db.cloned_records())
+                        .unwrap_or_default()
+                        .iter()
+                        .filter_map(|r| auto_increment_value(&r[col]))
+                        .max()
+                        .unwrap_or(0)
+                });
+                if row[col].is_none() {
+                    *last = next_auto_increment(*last, shard);
+                    row[col] = (*last).into();
+                    generated.push((i, row[col].clone()));
+                } else if let Some(v) = auto_increment_value(&row[col]) {
+                    // like MySQL, continue after explicitly given values
+                    *last = (*last).max(v);
+                }
+            }
+        }
+        generated
+    }
+
+    /// Apply a batch of operations to this base, and produce the resulting records.
+    ///
+    /// `shard` is the index of the shard this base is on, and the number of shards, if the base
+    /// is sharded. Values generated for auto-increment columns are returned along with the
+    /// positions of the operations whose rows were given them.
+    ///
+    /// Inserts that would duplicate the primary key or a unique key of an existing row are not
+    /// applied, and are returned as `Rejection`s unless they asked to be ignored. The same goes
+    /// for updates that would duplicate a unique key. Operations are checked in the order they
+    /// were given, against the rows as they are after the operations before them.
     pub(in crate::node) fn process(
         &mut self,
         us: LocalNodeIndex,
         mut ops: Vec<TableOperation>,
         state: &StateMap,
-    ) -> Records {
+        shard: Option<(usize, usize)>,
+    ) -> (Records, Vec<Rejection>, Vec<(usize, DataType)>) {
+        let mut generated = self.generate(&mut ops, shard, state.get(us).map(|db| &**db));
+        // operations that were not applied, and so did not use the values generated for them
+        let mut dropped = HashSet::new();
+        let mut rejected = Vec::new();
+        let mut unique = UniqueKeys {
+            keys: &self.unique_keys[..],
+            db: state.get(us).map(|db| &**db),
+            pending: vec![HashMap::new(); self.unique_keys.len()],
+        };
+
         if self.primary_key.is_none() || ops.is_empty() {
-            return ops
-                .into_iter()
-                .map(|r| {
-                    if let TableOperation::Insert(mut r) = r {
-                        self.fix(&mut r);
-                        Record::Positive(r)
-                    } else {
-                        unreachable!("unkeyed base got non-insert operation {:?}", r);
+            let mut results = Vec::with_capacity(ops.len());
+            for (i, op) in ops.into_iter().enumerate() {
+                let (mut r, ignore) = match op {
+                    TableOperation::Insert(r) => (r, false),
+                    TableOperation::InsertIgnore(r) => (r, true),
+                    r => unreachable!("unkeyed base got non-insert operation {:?}", r),
+                };
+                if let Some((columns, key)) = unique.violated_by(&r, None) {
+                    dropped.insert(i);
+                    if !ignore {
+                        rejected.push(Rejection {
+                            op: i,
+                            columns: columns.to_vec(),
+                            key,
+                        });
                     }
-                })
-                .collect();
+                    continue;
+                }
+                unique.record(&r, 1);
+                self.fix(&mut r);
+                results.push(Record::Positive(r));
+            }
+            generated.retain(|&(op, _)| !dropped.contains(&op));
+            return (results.into(), rejected, generated);
         }
 
         let key_cols = &self.primary_key.as_ref().unwrap()[..];
-        ops.sort_by(|a, b| key_of(key_cols, a).cmp(key_of(key_cols, b)));
-
-        // starting key
-        let mut this_key: Vec<_> = key_of(key_cols, &ops[0]).cloned().collect();
-
-        // starting record state
         let db = state
             .get(us)
             .expect("base with primary key must be materialized");
@@ -179,87 +413,83 @@ impl Base {
                 LookupResult::Missing => unreachable!(),
             }
         };
-        let mut current = get_current(&this_key);
-        let mut was = current.clone();
-
-        let mut results = Vec::with_capacity(ops.len());
-        for op in ops {
-            if this_key.iter().cmp(key_of(key_cols, &op)) != Ordering::Equal {
-                if current != was {
-                    if let Some(was) = was {
-                        results.push(Record::Negative(was.into_owned()));
-                    }
-                    if let Some(current) = current {
-                        results.push(Record::Positive(current.into_owned()));
-                    }
-                }
-
-                this_key = key_of(key_cols, &op).cloned().collect();
-                current = get_current(&this_key);
-                was = current.clone();
-            }
 
-            let update = match op {
-                TableOperation::Insert(row) => {
-                    if let Some(ref was) = was {
-                        eprintln!("base ignoring {:?} since it already has {:?}", row, was);
-                    } else {
-                        //assert!(was.is_none());
-                        current = Some(Cow::Owned(row));
+        // the row each key had before the batch, and the row it has now. kept in key order, so
+        // that the records for a key come out together.
+        let mut rows = BTreeMap::new();
+        for (i, op) in ops.into_iter().enumerate() {
+            let this_key: Vec<_> = key_of(key_cols, &op).cloned().collect();
+            let (_, current) = rows.entry(this_key.clone()).or_insert_with(|| {
+                let current = get_current(&this_key);
+                (current.clone(), current)
+            });
+
+            let ignore = matches!(op, TableOperation::InsertIgnore(_));
+            let future = match op {
+                TableOperation::Insert(_) | TableOperation::InsertIgnore(_)
+                    if current.is_some() =>
+                {
+                    dropped.insert(i);
+                    if !ignore {
+                        rejected.push(Rejection {
+                            op: i,
+                            columns: key_cols.to_vec(),
+                            key: this_key,
+                        });
                     }
                     continue;
                 }
+                TableOperation::Insert(row) | TableOperation::InsertIgnore(row) => row,
                 TableOperation::Delete { .. } => {
-                    if current.is_some() {
-                        current = None;
+                    if let Some(current) = current.take() {
+                        unique.record(&current, -1);
                     } else {
                         // supposed to delete a non-existing row?
                         // TODO: warn?
                     }
                     continue;
                 }
-                TableOperation::Update { set, .. } => set,
-                TableOperation::InsertOrUpdate { row, update } => {
-                    if current.is_none() {
-                        current = Some(Cow::Owned(row));
+                TableOperation::Update { set, .. } => match *current {
+                    Some(ref current) => modify(current.to_vec(), set),
+                    None => {
+                        // supposed to update a non-existing row?
+                        // TODO: also warn here?
                         continue;
                     }
-                    update
-                }
+                },
+                TableOperation::InsertOrUpdate { row, update } => match *current {
+                    Some(ref current) => modify(current.to_vec(), update),
+                    None => row,
+                },
             };
 
-            if current.is_none() {
-                // supposed to update a non-existing row?
-                // TODO: also warn here?
+            if let Some((columns, key)) = unique.violated_by(&future, current.as_deref()) {
+                dropped.insert(i);
+                if !ignore {
+                    rejected.push(Rejection {
+                        op: i,
+                        columns: columns.to_vec(),
+                        key,
+                    });
+                }
                 continue;
             }
-
-            let mut future = current.unwrap().into_owned();
-            for (col, op) in update.into_iter().enumerate() {
-                // XXX: make sure user doesn't update primary key?
-                match op {
-                    Modification::Set(v) => future[col] = v,
-                    Modification::Apply(op, v) => {
-                        let old: i128 = future[col].clone().into();
-                        let delta: i128 = v.into();
-                        future[col] = match op {
-                            Operation::Add => (old + delta).into(),
-                            Operation::Sub => (old - delta).into(),
-                        };
-                    }
-                    Modification::None => {}
-                }
+            if let Some(current) = current.take() {
+                unique.record(&current, -1);
             }
-            current = Some(Cow::Owned(future));
+            unique.record(&future, 1);
+            *current = Some(Cow::Owned(future));
         }
 
-        // we may have changed things in the last iteration of the loop above
-        if current != was {
-            if let Some(was) = was {
-                results.push(Record::Negative(was.into_owned()));
-            }
-            if let Some(current) = current {
-                results.push(Record::Positive(current.into_owned()));
+        let mut results = Vec::with_capacity(rows.len());
+        for (_, (was, current)) in rows {
+            if current != was {
+                if let Some(was) = was {
+                    results.push(Record::Negative(was.into_owned()));
+                }
+                if let Some(current) = current {
+                    results.push(Record::Positive(current.into_owned()));
+                }
             }
         }
 
@@ -267,7 +497,8 @@ impl Base {
             self.fix(r);
         }
 
-        results.into()
+        generated.retain(|&(op, _)| !dropped.contains(&op));
+        (results.into(), rejected, generated)
     }
 
     pub(in crate::node) fn suggest_indexes(&self, n: NodeIndex) -> HashMap<NodeIndex, Vec<usize>> {
@@ -345,7 +576,7 @@ mod tests {
         let mut n = n.finalize(&graph);
 
         let mut one = move |u: Vec<TableOperation>| {
-            let mut m = n.get_base_mut().unwrap().process(local, u, &states);
+            let (mut m, _, _) = n.get_base_mut().unwrap().process(local, u, &states, None);
             node::materialize(&mut m, None, states.get_mut(local));
             m
         };
@@ -417,4 +648,225 @@ mod tests {
 
         test_lots_of_changes_in_same_batch(Box::new(state));
     }
+
+    #[test]
+    fn it_generates_values() {
+        let mut b = Base::new(vec![])
+            .with_auto_increment(0)
+            .with_default_exprs(vec![(2, Literal::CurrentDate)]);
+        let local = unsafe { LocalNodeIndex::make(0 as u32) };
+        let states = StateMap::new();
+
+        let (rs, _, generated) = b.process(
+            local,
+            vec![
+                TableOperation::Insert(vec![DataType::None, "a".into(), DataType::None]),
+                TableOperation::Insert(vec![7.into(), "b".into(), DataType::None]),
+                TableOperation::Insert(vec![DataType::None, "c".into(), DataType::None]),
+            ],
+            &states,
+            Some((1, 3)),
+        );
+
+        // shard 1 of 3 only hands out values that are sent to shard 1, and never reuses values
+        // that were given explicitly
+        assert_eq!(generated, vec![(0, 1.into()), (2, 10.into())]);
+        let ids: Vec<_> = rs.iter().map(|r| r[0].clone()).collect();
+        assert_eq!(ids, vec![1.into(), 7.into(), 10.into()]);
+        for r in rs.iter() {
+            match r[2] {
+                DataType::Date(_) => {}
+                ref d => panic!("expected the current date, got {:?}", d),
+            }
+        }
+    }
+
+    #[test]
+    fn it_rejects_duplicate_keys() {
+        use crate::node;
+        use crate::prelude::*;
+
+        let mut graph = Graph::new();
+        let source = graph.add_node(Node::new(
+            "source",
+            &["because-type-inference"],
+            node::NodeType::Source,
+        ));
+
+        let b = Base::new(vec![])
+            .with_key(vec![0])
+            .with_unique_keys(vec![vec![1]]);
+        let global = graph.add_node(Node::new("b", &["id", "email"], b));
+        graph.add_edge(source, global, ());
+        let local = unsafe { LocalNodeIndex::make(0 as u32) };
+        let mut ip: IndexPair = global.into();
+        ip.set_local(local);
+        graph
+            .node_weight_mut(global)
+            .unwrap()
+            .set_finalized_addr(ip);
+
+        let mut remap = HashMap::new();
+        remap.insert(global, ip);
+        graph.node_weight_mut(global).unwrap().on_commit(&remap);
+        graph.node_weight_mut(global).unwrap().add_to(0.into());
+
+        let mut state = MemoryState::default();
+        state.add_key(&[0], None);
+        state.add_key(&[1], None);
+        let mut states = StateMap::new();
+        states.insert(local, Box::new(state) as Box<dyn State>);
+        let n = graph[global].take();
+        let mut n = n.finalize(&graph);
+
+        let mut one = move |u: Vec<TableOperation>| {
+            let (mut m, rejected, _) = n.get_base_mut().unwrap().process(local, u, &states, None);
+            node::materialize(&mut m, None, states.get_mut(local));
+            (m, rejected)
+        };
+
+        let (m, rejected) = one(vec![
+            TableOperation::Insert(vec![1.into(), "a".into()]),
+            TableOperation::Insert(vec![1.into(), "b".into()]),
+            TableOperation::InsertIgnore(vec![1.into(), "c".into()]),
+            TableOperation::Insert(vec![2.into(), "a".into()]),
+            TableOperation::Insert(vec![3.into(), DataType::None]),
+            TableOperation::Insert(vec![4.into(), DataType::None]),
+        ]);
+        assert_eq!(
+            m,
+            vec![
+                vec![1.into(), "a".into()],
+                vec![3.into(), DataType::None],
+                vec![4.into(), DataType::None],
+            ]
+            .into()
+        );
+        assert_eq!(
+            rejected,
+            vec![
+                Rejection {
+                    op: 1,
+                    columns: vec![0],
+                    key: vec![1.into()],
+                },
+                Rejection {
+                    op: 3,
+                    columns: vec![1],
+                    key: vec!["a".into()],
+                },
+            ]
+        );
+
+        // operations are checked in the order they were given, so the delete of 1 makes room
+        // for 5, but not for 6, and not for 0 either, which came before it
+        let (m, rejected) = one(vec![
+            TableOperation::Insert(vec![0.into(), "a".into()]),
+            TableOperation::Delete {
+                key: vec![1.into()],
+            },
+            TableOperation::InsertIgnore(vec![5.into(), "a".into()]),
+            TableOperation::Insert(vec![6.into(), "a".into()]),
+        ]);
+        assert_eq!(
+            m,
+            vec![
+                (vec![1.into(), "a".into()], false),
+                (vec![5.into(), "a".into()], true),
+            ]
+            .into()
+        );
+        assert_eq!(
+            rejected,
+            vec![
+                Rejection {
+                    op: 0,
+                    columns: vec![1],
+                    key: vec!["a".into()],
+                },
+                Rejection {
+                    op: 3,
+                    columns: vec![1],
+                    key: vec!["a".into()],
+                },
+            ]
+        );
+
+        // and writes are checked against what is already in the base
+        let (m, rejected) = one(vec![
+            TableOperation::Insert(vec![5.into(), "b".into()]),
+            TableOperation::Insert(vec![7.into(), "a".into()]),
+        ]);
+        assert!(m.is_empty());
+        assert_eq!(
+            rejected,
+            vec![
+                Rejection {
+                    op: 0,
+                    columns: vec![0],
+                    key: vec![5.into()],
+                },
+                Rejection {
+                    op: 1,
+                    columns: vec![1],
+                    key: vec!["a".into()],
+                },
+            ]
+        );
+
+        // updates are checked too, and may take a value once an earlier update has freed it
+        let set_email = |email: &str| vec![Modification::None, Modification::Set(email.into())];
+        let (m, rejected) = one(vec![
+            TableOperation::Insert(vec![8.into(), "c".into()]),
+            TableOperation::Update {
+                key: vec![8.into()],
+                set: set_email("a"),
+            },
+            TableOperation::InsertOrUpdate {
+                row: vec![5.into(), "x".into()],
+                update: set_email("c"),
+            },
+            TableOperation::InsertOrUpdate {
+                row: vec![9.into(), "c".into()],
+                update: set_email("never"),
+            },
+            TableOperation::Update {
+                key: vec![5.into()],
+                set: set_email("d"),
+            },
+            TableOperation::Update {
+                key: vec![8.into()],
+                set: set_email("a"),
+            },
+        ]);
+        assert_eq!(
+            m,
+            vec![
+                (vec![5.into(), "a".into()], false),
+                (vec![5.into(), "d".into()], true),
+                (vec![8.into(), "a".into()], true),
+            ]
+            .into()
+        );
+        assert_eq!(
+            rejected,
+            vec![
+                Rejection {
+                    op: 1,
+                    columns: vec![1],
+                    key: vec!["a".into()],
+                },
+                Rejection {
+                    op: 2,
+                    columns: vec![1],
+                    key: vec!["c".into()],
+                },
+                Rejection {
+                    op: 3,
+                    columns: vec![1],
+                    key: vec!["c".into()],
+                },
+            ]
+        );
+    }
 }
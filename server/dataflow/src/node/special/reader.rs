@@ -1,5 +1,48 @@
 use crate::backlog;
+use crate::ops::filter::{Operator, Pattern, PatternOperator};
 use crate::prelude::*;
+use nom_sql::OrderType;
+
+/// A comparison between a column and one or more query parameters that a reader applies to the
+/// rows it finds for a key, rather than using the parameters as part of the key.
+///
+/// The parameter values are passed along with each lookup, following the key itself.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PostLookupFilter {
+    /// The column to compare.
+    pub column: usize,
+    /// How to compare the column to the parameter value(s).
+    pub operator: Operator,
+    /// The number of parameter values this filter takes. Only `IN` takes more than one.
+    pub arity: usize,
+}
+
+impl PostLookupFilter {
+    /// Check if `row` satisfies this filter given the parameter values in `args`.
+    pub fn matches(&self, row: &[DataType], args: &[DataType]) -> bool {
+        let d = &row[self.column];
+        match self.operator {
+            Operator::In => args.contains(d),
+            Operator::Equal => d == &args[0],
+            Operator::NotEqual => d != &args[0],
+            Operator::Greater => d > &args[0],
+            Operator::GreaterOrEqual => d >= &args[0],
+            Operator::Less => d < &args[0],
+            Operator::LessOrEqual => d <= &args[0],
+            Operator::Like | Operator::NotLike => {
+                if !d.is_string() || !args[0].is_string() {
+                    // NULL and non-text values match neither LIKE nor NOT LIKE
+                    return false;
+                }
+                let negated = self.operator == Operator::NotLike;
+                Pattern::new(PatternOperator::Like, negated, (&args[0]).into())
+                    .map(|p| p.matches(d))
+                    .unwrap_or(false)
+            }
+            ref op => unreachable!("{} is not a valid post-lookup comparison", op),
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct Reader {
@@ -8,6 +51,11 @@ pub struct Reader {
 
     for_node: NodeIndex,
     state: Option<Vec<usize>>,
+    ordered: bool,
+    ranged: bool,
+    post_lookup: Vec<PostLookupFilter>,
+    lookup_order: Vec<usize>,
+    order: Vec<(usize, OrderType)>,
 }
 
 impl Clone for Reader {
@@ -17,6 +65,11 @@ impl Clone for Reader {
             writer: None,
             state: self.state.clone(),
             for_node: self.for_node,
+            ordered: self.ordered,
+            ranged: self.ranged,
+            post_lookup: self.post_lookup.clone(),
+            lookup_order: self.lookup_order.clone(),
+            order: self.order.clone(),
         }
     }
 }
@@ -27,6 +80,11 @@ impl Reader {
             writer: None,
             state: None,
             for_node,
+            ordered: false,
+            ranged: false,
+            post_lookup: Vec::new(),
+            lookup_order: Vec::new(),
+            order: Vec::new(),
         }
     }
 
@@ -50,6 +108,11 @@ impl Reader {
             writer: self.writer.take(),
             state: self.state.clone(),
             for_node: self.for_node,
+            ordered: self.ordered,
+            ranged: self.ranged,
+            post_lookup: self.post_lookup.clone(),
+            lookup_order: self.lookup_order.clone(),
+            order: self.order.clone(),
         }
     }
 
@@ -81,6 +144,60 @@ impl Reader {
         }
     }
 
+    /// Keep this reader's keys in sorted order so that it can efficiently serve range lookups.
+    ///
+    /// Partially materialized ordered readers fill the key ranges that lookups miss on with range
+    /// replays.
+    pub fn set_ordered(&mut self) {
+        self.ordered = true;
+    }
+
+    pub fn is_ordered(&self) -> bool {
+        self.ordered
+    }
+
+    /// Look up ranges of values of the last key column rather than single values: each lookup
+    /// gives only the other key columns, and the post-lookup filters that compare the last key
+    /// column bound the range of its values to return rows for.
+    ///
+    /// This makes the reader ordered.
+    pub fn set_ranged(&mut self) {
+        self.ordered = true;
+        self.ranged = true;
+    }
+
+    pub fn is_ranged(&self) -> bool {
+        self.ranged
+    }
+
+    /// Filter the rows found for each key according to `filters` before returning them.
+    pub fn set_post_lookup_filters(&mut self, filters: Vec<PostLookupFilter>) {
+        self.post_lookup = filters;
+    }
+
+    pub fn post_lookup_filters(&self) -> &[PostLookupFilter] {
+        &self.post_lookup[..]
+    }
+
+    /// Take the values of each lookup in a different order: the key values, followed by the
+    /// parameter values for the post-lookup filters, are taken from the given positions.
+    pub fn set_lookup_order(&mut self, lookup_order: Vec<usize>) {
+        self.lookup_order = lookup_order;
+    }
+
+    pub fn lookup_order(&self) -> &[usize] {
+        &self.lookup_order[..]
+    }
+
+    /// Sort the rows returned for each key by the given columns.
+    pub fn set_order(&mut self, order: Vec<(usize, OrderType)>) {
+        self.order = order;
+    }
+
+    pub fn order(&self) -> &[(usize, OrderType)] {
+        &self.order[..]
+    }
+
     pub(crate) fn is_empty(&self) -> bool {
         self.writer.as_ref().map(|w| w.is_empty()).unwrap_or(true)
     }
@@ -123,8 +240,9 @@ impl Reader {
                         match state.entry_from_record(&row[..]).try_find_and(|_| ()) {
                             Ok((None, _)) => {
                                 // row would miss in partial state.
-                                // leave it blank so later lookup triggers replay.
-                                false
+                                // leave it blank so later lookup triggers replay, unless a range
+                                // replay has already told us that the key has no other rows.
+                                state.fill_if_covered(&row[..])
                             }
                             Err(_) => unreachable!(),
                             _ => {
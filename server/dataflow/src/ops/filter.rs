@@ -1,3 +1,5 @@
+use regex::{Regex, RegexBuilder};
+use serde::{de, Deserializer, Serializer};
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt::{self, Display};
@@ -10,7 +12,7 @@ pub use nom_sql::Operator;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Filter {
     src: IndexPair,
-    filter: sync::Arc<Vec<(usize, FilterCondition)>>,
+    filter: sync::Arc<Predicate>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -38,16 +40,328 @@ impl Display for Value {
 pub enum FilterCondition {
     Comparison(Operator, Value),
     In(Vec<DataType>),
+    Pattern(Pattern),
+}
+
+/// The ways in which a `Pattern` can match text.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum PatternOperator {
+    /// SQL `LIKE`, which must match the whole text
+    Like,
+    /// Case-insensitive `LIKE`
+    ILike,
+    /// `REGEXP`, which matches if the regular expression matches anywhere in the text
+    Regexp,
+}
+
+/// A pattern that a text column is matched against, such as the `'a%'` in `col LIKE 'a%'`.
+///
+/// The pattern is compiled into a regular expression once, when the condition is constructed.
+/// `NULL` and non-text values match neither a pattern nor its negation.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    operator: PatternOperator,
+    negated: bool,
+    pattern: String,
+    regex: Regex,
+}
+
+impl Pattern {
+    /// Construct a new pattern condition. It fails if `pattern` is not a valid regular expression
+    /// for `PatternOperator::Regexp`.
+    pub fn new(
+        operator: PatternOperator,
+        negated: bool,
+        pattern: &str,
+    ) -> Result<Pattern, regex::Error> {
+        let regex = match operator {
+            PatternOperator::Like | PatternOperator::ILike => {
+                RegexBuilder::new(&like_to_regex(pattern))
+                    .case_insensitive(operator == PatternOperator::ILike)
+                    .build()?
+            }
+            PatternOperator::Regexp => Regex::new(pattern)?,
+        };
+        Ok(Pattern {
+            operator,
+            negated,
+            pattern: pattern.to_owned(),
+            regex,
+        })
+    }
+
+    pub fn operator(&self) -> PatternOperator {
+        self.operator
+    }
+
+    pub fn is_negated(&self) -> bool {
+        self.negated
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Check if `d` matches this condition.
+    pub fn matches(&self, d: &DataType) -> bool {
+        if !d.is_string() {
+            return false;
+        }
+        let text: &str = d.into();
+        self.regex.is_match(text) != self.negated
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Pattern) -> bool {
+        self.operator == other.operator
+            && self.negated == other.negated
+            && self.pattern == other.pattern
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match self.operator {
+            PatternOperator::Like => "LIKE",
+            PatternOperator::ILike => "ILIKE",
+            PatternOperator::Regexp => "REGEXP",
+        };
+        if self.negated {
+            write!(f, "NOT ")?;
+        }
+        write!(f, "{} {:?}", op, self.pattern)
+    }
+}
+
+// the compiled regex can't be serialized, so we send the pattern and compile it again on arrival
+impl serde::Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&(self.operator, self.negated, &self.pattern), serializer)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (operator, negated, pattern) =
+            <(PatternOperator, bool, String) as serde::Deserialize>::deserialize(deserializer)?;
+        Pattern::new(operator, negated, &pattern).map_err(de::Error::custom)
+    }
+}
+
+/// Translate an SQL `LIKE` pattern into an equivalent regular expression that matches the whole
+/// text.
+///
+/// `%` matches any sequence of characters, and `_` matches any single character. A backslash
+/// escapes the character that follows it.
+fn like_to_regex(pattern: &str) -> String {
+    let mut re = String::from("^(?s:");
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '%' => re.push_str(".*"),
+            '_' => re.push('.'),
+            '\\' => match chars.next() {
+                Some(c) => re.push_str(&regex::escape(&c.to_string())),
+                None => re.push_str(&regex::escape("\\")),
+            },
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push_str(")$");
+    re
+}
+
+/// A boolean combination of filter conditions.
+///
+/// Predicates are evaluated with SQL's three-valued logic: a condition on a `NULL` value is
+/// unknown rather than false, so that `NOT (x > 5)` rejects rows where `x` is `NULL` just like
+/// `x > 5` does. Comparisons against a `NULL` constant are the exception, and check whether the
+/// column is (`=`) or isn't (`!=`) `NULL`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Predicate {
+    /// A condition on the given column
+    Condition(usize, FilterCondition),
+    /// Holds if all of the predicates hold; an empty conjunction always holds
+    And(Vec<Predicate>),
+    /// Holds if any of the predicates holds; an empty disjunction never holds
+    Or(Vec<Predicate>),
+    /// Holds if the predicate does not
+    Not(Box<Predicate>),
+}
+
+impl Predicate {
+    /// The conjunction of the given conditions.
+    pub fn all(conditions: &[(usize, FilterCondition)]) -> Predicate {
+        Predicate::And(
+            conditions
+                .iter()
+                .map(|(i, cond)| Predicate::Condition(*i, cond.clone()))
+                .collect(),
+        )
+    }
+
+    /// The conditions of this predicate, if it is a plain conjunction of conditions.
+    pub fn conditions(&self) -> Option<Vec<(usize, FilterCondition)>> {
+        match *self {
+            Predicate::Condition(i, ref cond) => Some(vec![(i, cond.clone())]),
+            Predicate::And(ref ps) => ps.iter().try_fold(Vec::new(), |mut conds, p| {
+                conds.extend(p.conditions()?);
+                Some(conds)
+            }),
+            Predicate::Or(..) | Predicate::Not(..) => None,
+        }
+    }
+
+    /// All columns that the predicate reads, including those compared against.
+    pub fn columns(&self) -> Vec<usize> {
+        match *self {
+            Predicate::Condition(i, FilterCondition::Comparison(_, Value::Column(c))) => {
+                vec![i, c]
+            }
+            Predicate::Condition(i, _) => vec![i],
+            Predicate::And(ref ps) | Predicate::Or(ref ps) => {
+                ps.iter().flat_map(Predicate::columns).collect()
+            }
+            Predicate::Not(ref p) => p.columns(),
+        }
+    }
+
+    /// Evaluate the predicate over a record; `None` if its truth is unknown because of `NULL`s.
+    pub fn eval(&self, r: &[DataType]) -> Option<bool> {
+        match *self {
+            Predicate::Condition(i, ref cond) => {
+                let d = &r[i];
+                match *cond {
+                    FilterCondition::Comparison(ref op, Value::Constant(DataType::None)) => {
+                        match *op {
+                            Operator::Equal | Operator::Is => Some(d.is_none()),
+                            Operator::NotEqual => Some(!d.is_none()),
+                            _ => None,
+                        }
+                    }
+                    FilterCondition::Comparison(ref op, ref f) => {
+                        let v = match *f {
+                            Value::Constant(ref dt) => dt,
+                            Value::Column(c) => &r[c],
+                        };
+                        if *op == Operator::Is {
+                            // `IS` compares NULLs like any other value
+                            return Some(d == v);
+                        }
+                        if d.is_none() || v.is_none() {
+                            return None;
+                        }
+                        match *op {
+                            Operator::Equal | Operator::In => Some(d == v),
+                            Operator::NotEqual => Some(d != v),
+                            Operator::Greater => Some(d > v),
+                            Operator::GreaterOrEqual => Some(d >= v),
+                            Operator::Less => Some(d < v),
+                            Operator::LessOrEqual => Some(d <= v),
+                            Operator::Like | Operator::NotLike
+                                if d.is_string() && v.is_string() =>
+                            {
+                                // the pattern may differ for every record
+                                let negated = *op == Operator::NotLike;
+                                Pattern::new(PatternOperator::Like, negated, v.into())
+                                    .ok()
+                                    .map(|p| p.matches(d))
+                            }
+                            Operator::Like | Operator::NotLike => None,
+                            Operator::Is => unreachable!(),
+                            Operator::And | Operator::Or | Operator::Not => {
+                                unreachable!("{} is not a comparison", op)
+                            }
+                        }
+                    }
+                    FilterCondition::In(ref fs) if !d.is_none() => Some(fs.contains(d)),
+                    FilterCondition::Pattern(ref p) if d.is_string() => Some(p.matches(d)),
+                    FilterCondition::In(..) | FilterCondition::Pattern(..) => None,
+                }
+            }
+            Predicate::And(ref ps) => {
+                let mut result = Some(true);
+                for p in ps {
+                    match p.eval(r) {
+                        Some(false) => return Some(false),
+                        Some(true) => {}
+                        None => result = None,
+                    }
+                }
+                result
+            }
+            Predicate::Or(ref ps) => {
+                let mut result = Some(false);
+                for p in ps {
+                    match p.eval(r) {
+                        Some(true) => return Some(true),
+                        Some(false) => {}
+                        None => result = None,
+                    }
+                }
+                result
+            }
+            Predicate::Not(ref p) => p.eval(r).map(|b| !b),
+        }
+    }
+
+    /// Check if a record satisfies the predicate.
+    pub fn matches(&self, r: &[DataType]) -> bool {
+        self.eval(r) == Some(true)
+    }
+}
+
+impl Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // compound operands are parenthesized, so the tree's structure is never ambiguous
+        let operand = |p: &Predicate| match *p {
+            Predicate::Condition(..) => format!("{}", p),
+            _ => format!("({})", p),
+        };
+        match *self {
+            Predicate::Condition(i, FilterCondition::Comparison(ref op, ref x)) => {
+                write!(f, "f{} {} {}", i, op, x)
+            }
+            Predicate::Condition(i, FilterCondition::In(ref xs)) => write!(
+                f,
+                "f{} IN ({})",
+                i,
+                xs.iter()
+                    .map(|d| format!("{}", d))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Predicate::Condition(i, FilterCondition::Pattern(ref p)) => write!(f, "f{} {}", i, p),
+            Predicate::And(ref ps) if ps.is_empty() => write!(f, "TRUE"),
+            Predicate::Or(ref ps) if ps.is_empty() => write!(f, "FALSE"),
+            Predicate::And(ref ps) => write!(
+                f,
+                "{}",
+                ps.iter().map(operand).collect::<Vec<_>>().join(" AND ")
+            ),
+            Predicate::Or(ref ps) => write!(
+                f,
+                "{}",
+                ps.iter().map(operand).collect::<Vec<_>>().join(" OR ")
+            ),
+            Predicate::Not(ref p) => write!(f, "NOT {}", operand(p)),
+        }
+    }
 }
 
 impl Filter {
-    /// Construct a new filter operator. The `filter` vector must have as many elements as the
-    /// `src` node has columns. Each column that is set to `None` matches any value, while columns
-    /// in the filter that have values set will check for equality on that column.
+    /// Construct a new filter operator that only forwards records which match all of the given
+    /// conditions.
     pub fn new(src: NodeIndex, filter: &[(usize, FilterCondition)]) -> Filter {
+        Filter::with_predicate(src, Predicate::all(filter))
+    }
+
+    /// Construct a new filter operator that only forwards records which satisfy `predicate`.
+    pub fn with_predicate(src: NodeIndex, predicate: Predicate) -> Filter {
         Filter {
             src: src.into(),
-            filter: sync::Arc::new(Vec::from(filter)),
+            filter: sync::Arc::new(predicate),
         }
     }
 }
@@ -63,9 +377,13 @@ impl Ingredient for Filter {
 
     fn on_connected(&mut self, g: &Graph) {
         let srcn = &g[self.src.as_global()];
-        // N.B.: <= because the adjacent node might be a base with a suffix of removed columns.
-        // It's okay to just ignore those.
-        assert!(self.filter.len() <= srcn.fields().len());
+        // N.B.: the adjacent node might be a base with a suffix of removed columns, which we
+        // simply never read.
+        assert!(self
+            .filter
+            .columns()
+            .into_iter()
+            .all(|c| c < srcn.fields().len()));
     }
 
     fn on_commit(&mut self, _: NodeIndex, remap: &HashMap<NodeIndex, IndexPair>) {
@@ -81,31 +399,7 @@ impl Ingredient for Filter {
         _: &DomainNodes,
         _: &StateMap,
     ) -> ProcessingResult {
-        rs.retain(|r| {
-            self.filter.iter().all(|(i, cond)| {
-                // check if this filter matches
-                let d = &r[*i];
-                match cond {
-                    FilterCondition::Comparison(ref op, ref f) => {
-                        let v = match *f {
-                            Value::Constant(ref dt) => dt,
-                            Value::Column(c) => &r[c],
-                        };
-                        match *op {
-                            Operator::Equal => d == v,
-                            Operator::NotEqual => d != v,
-                            Operator::Greater => d > v,
-                            Operator::GreaterOrEqual => d >= v,
-                            Operator::Less => d < v,
-                            Operator::LessOrEqual => d <= v,
-                            Operator::In => unreachable!(),
-                            _ => unimplemented!(),
-                        }
-                    }
-                    FilterCondition::In(ref fs) => fs.contains(d),
-                }
-            })
-        });
+        rs.retain(|r| self.filter.matches(r));
 
         ProcessingResult {
             results: rs,
@@ -134,27 +428,19 @@ impl Ingredient for Filter {
                 .replace_all(s, "\\$1")
                 .to_string()
         };
-        format!(
-            "σ[{}]",
-            self.filter
+        // a conjunction at the top is shown as a list of conditions
+        let conditions = match *self.filter {
+            Predicate::And(ref ps) => ps
                 .iter()
-                .filter_map(|(i, ref cond)| match *cond {
-                    FilterCondition::Comparison(ref op, ref x) => {
-                        Some(format!("f{} {} {}", i, escape(&format!("{}", op)), x))
-                    }
-                    FilterCondition::In(ref xs) => Some(format!(
-                        "f{} IN ({})",
-                        i,
-                        xs.iter()
-                            .map(|d| format!("{}", d))
-                            .collect::<Vec<_>>()
-                            .join(", ")
-                    )),
+                .map(|p| match *p {
+                    Predicate::Condition(..) => format!("{}", p),
+                    _ => format!("({})", p),
                 })
                 .collect::<Vec<_>>()
-                .as_slice()
-                .join(", ")
-        )
+                .join(", "),
+            ref p => format!("{}", p),
+        };
+        format!("σ[{}]", escape(&conditions))
     }
 
     fn can_query_through(&self) -> bool {
@@ -172,30 +458,7 @@ impl Ingredient for Filter {
         self.lookup(*self.src, columns, key, nodes, states)
             .and_then(|result| {
                 let f = self.filter.clone();
-                let filter = move |r: &[DataType]| {
-                    f.iter().all(|(i, ref cond)| {
-                        // check if this filter matches
-                        let d = &r[*i];
-                        match *cond {
-                            FilterCondition::Comparison(ref op, ref f) => {
-                                let v = match *f {
-                                    Value::Constant(ref dt) => dt,
-                                    Value::Column(c) => &r[c],
-                                };
-                                match *op {
-                                    Operator::Equal => d == v,
-                                    Operator::NotEqual => d != v,
-                                    Operator::Greater => d > v,
-                                    Operator::GreaterOrEqual => d >= v,
-                                    Operator::Less => d < v,
-                                    Operator::LessOrEqual => d <= v,
-                                    _ => unimplemented!(),
-                                }
-                            }
-                            FilterCondition::In(ref fs) => fs.contains(d),
-                        }
-                    })
-                };
+                let filter = move |r: &[DataType]| f.matches(r);
 
                 match result {
                     Some(rs) => {
@@ -420,4 +683,215 @@ mod tests {
         left = vec![42.into(), "b".into()];
         assert_eq!(g.narrow_one_row(left.clone(), false), vec![left].into());
     }
+
+    #[test]
+    fn like_patterns() {
+        let like = |text: &str, pattern: &str| {
+            Pattern::new(PatternOperator::Like, false, pattern)
+                .unwrap()
+                .matches(&text.into())
+        };
+        assert!(like("hello", "hello"));
+        assert!(like("hello", "h%"));
+        assert!(like("hello", "%llo"));
+        assert!(like("hello", "h_l%o"));
+        assert!(like("hello", "%%"));
+        assert!(like("", "%"));
+        assert!(like("50%", "50\\%"));
+        assert!(!like("500", "50\\%"));
+        assert!(!like("hello", "h_llo_"));
+        assert!(!like("hello", "Hello"));
+        assert!(like("abcabd", "%abd"));
+    }
+
+    #[test]
+    fn it_works_with_patterns() {
+        let pattern =
+            |op, negated, p| FilterCondition::Pattern(Pattern::new(op, negated, p).unwrap());
+        let mut g = setup(
+            false,
+            Some(&[
+                (1, pattern(PatternOperator::ILike, false, "n_ria%")),
+                (1, pattern(PatternOperator::Regexp, true, "[0-9]")),
+            ]),
+        );
+
+        let mut left: Vec<DataType>;
+
+        // both conditions match
+        left = vec![1.into(), "Noria rocks".into()];
+        assert_eq!(g.narrow_one_row(left.clone(), false), vec![left].into());
+
+        // first condition fails
+        left = vec![1.into(), "Soup".into()];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+
+        // second condition fails
+        left = vec![1.into(), "noria 2".into()];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+
+        // NULL matches neither the pattern nor its negation
+        left = vec![1.into(), DataType::None];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+    }
+
+    fn setup_predicate(predicate: Predicate) -> ops::test::MockGraph {
+        let mut g = ops::test::MockGraph::new();
+        let s = g.add_base("source", &["x", "y"]);
+        g.set_op(
+            "filter",
+            &["x", "y"],
+            Filter::with_predicate(s.as_global(), predicate),
+            false,
+        );
+        g
+    }
+
+    fn compare(i: usize, op: Operator, v: DataType) -> Predicate {
+        Predicate::Condition(i, FilterCondition::Comparison(op, Value::Constant(v)))
+    }
+
+    #[test]
+    fn it_works_with_predicate_trees() {
+        // x = 1 OR (y != "a" AND NOT x > 5)
+        let mut g = setup_predicate(Predicate::Or(vec![
+            compare(0, Operator::Equal, 1.into()),
+            Predicate::And(vec![
+                compare(1, Operator::NotEqual, "a".into()),
+                Predicate::Not(Box::new(compare(0, Operator::Greater, 5.into()))),
+            ]),
+        ]));
+
+        let mut left: Vec<DataType>;
+
+        // first disjunct matches
+        left = vec![1.into(), "a".into()];
+        assert_eq!(g.narrow_one_row(left.clone(), false), vec![left].into());
+
+        // second disjunct matches
+        left = vec![3.into(), "b".into()];
+        assert_eq!(g.narrow_one_row(left.clone(), false), vec![left].into());
+
+        // neither matches
+        left = vec![6.into(), "b".into()];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+        left = vec![3.into(), "a".into()];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+
+        // NOT x > 5 is unknown if x is NULL, just like x > 5
+        left = vec![DataType::None, "b".into()];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+
+        // ... but an unknown disjunct doesn't matter if another one holds
+        let mut g = setup_predicate(Predicate::Or(vec![
+            compare(0, Operator::Less, 5.into()),
+            compare(1, Operator::Equal, "a".into()),
+        ]));
+        left = vec![DataType::None, "a".into()];
+        assert_eq!(g.narrow_one_row(left.clone(), false), vec![left].into());
+    }
+
+    #[test]
+    fn it_compares_against_null() {
+        let mut g = setup_predicate(Predicate::Not(Box::new(compare(
+            1,
+            Operator::Equal,
+            DataType::None,
+        ))));
+
+        let mut left: Vec<DataType>;
+        left = vec![1.into(), "a".into()];
+        assert_eq!(g.narrow_one_row(left.clone(), false), vec![left].into());
+        left = vec![1.into(), DataType::None];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+    }
+
+    #[test]
+    fn it_compares_columns_with_is_and_like() {
+        let column =
+            |op| Predicate::Condition(0, FilterCondition::Comparison(op, Value::Column(1)));
+
+        // x IS y holds for two NULLs
+        let mut g = setup_predicate(column(Operator::Is));
+        let mut left: Vec<DataType>;
+        left = vec![DataType::None, DataType::None];
+        assert_eq!(g.narrow_one_row(left.clone(), false), vec![left].into());
+        left = vec![1.into(), DataType::None];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+
+        // x LIKE y uses y as the pattern
+        let mut g = setup_predicate(column(Operator::Like));
+        left = vec!["abc".into(), "a%".into()];
+        assert_eq!(g.narrow_one_row(left.clone(), false), vec![left].into());
+        left = vec!["abc".into(), "b%".into()];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+        left = vec![1.into(), "%".into()];
+        assert!(g.narrow_one_row(left.clone(), false).is_empty());
+    }
+
+    #[test]
+    fn it_describes_predicate_trees() {
+        let g = setup_predicate(Predicate::And(vec![
+            compare(0, Operator::Less, 1.into()),
+            Predicate::Or(vec![
+                compare(1, Operator::Equal, "a".into()),
+                Predicate::Not(Box::new(Predicate::Condition(
+                    0,
+                    FilterCondition::In(vec![2.into(), 3.into()]),
+                ))),
+            ]),
+        ]));
+        assert_eq!(
+            g.node().description(true),
+            "σ[f0 \\< 1, (f1 = \"a\" OR NOT f0 IN (2, 3))]"
+        );
+    }
+
+    #[test]
+    fn it_flattens_conjunctions() {
+        let p = Predicate::And(vec![
+            compare(0, Operator::Equal, 1.into()),
+            Predicate::And(vec![compare(1, Operator::Equal, "a".into())]),
+        ]);
+        assert_eq!(
+            p.conditions(),
+            Some(vec![
+                (
+                    0,
+                    FilterCondition::Comparison(Operator::Equal, Value::Constant(1.into()))
+                ),
+                (
+                    1,
+                    FilterCondition::Comparison(Operator::Equal, Value::Constant("a".into()))
+                ),
+            ])
+        );
+        assert_eq!(Predicate::Or(vec![p]).conditions(), None);
+    }
+
+    #[test]
+    fn patterns_agree_with_like() {
+        let texts = ["hello", "", "50%", "500", "h\\llo", "abcabd", "a.b", "a\nb"];
+        let patterns = ["h%", "h_l%o", "%%", "50\\%", "%abd", "a.b", "a_b", "%\\"];
+        for pattern in &patterns {
+            let p = Pattern::new(PatternOperator::Like, false, pattern).unwrap();
+            let not_p = Pattern::new(PatternOperator::Like, true, pattern).unwrap();
+            for text in &texts {
+                let d = DataType::from(*text);
+                assert_eq!(
+                    p.matches(&d),
+                    like(text, pattern),
+                    "{} LIKE {}",
+                    text,
+                    pattern
+                );
+                assert_eq!(not_p.matches(&d), !like(text, pattern));
+            }
+        }
+
+        let p = Pattern::new(PatternOperator::ILike, false, "HeLLo").unwrap();
+        assert!(p.matches(&"hello".into()));
+        assert!(!p.matches(&5.into()));
+        assert!(Pattern::new(PatternOperator::Regexp, false, "(").is_err());
+    }
 }
@@ -0,0 +1,424 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone};
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::prelude::*;
+
+/// The unit of the interval that `DATE_ADD` adds to a timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum IntervalUnit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl IntervalUnit {
+    /// Whether intervals of this unit are whole days, so adding them to a date gives a date.
+    fn is_whole_days(self) -> bool {
+        match self {
+            IntervalUnit::Second | IntervalUnit::Minute | IntervalUnit::Hour => false,
+            IntervalUnit::Day | IntervalUnit::Week | IntervalUnit::Month | IntervalUnit::Year => {
+                true
+            }
+        }
+    }
+}
+
+/// A built-in SQL scalar function.
+///
+/// All functions return `NULL` if one of their arguments is `NULL`, except for `Coalesce`, which
+/// returns its first non-`NULL` argument. Functions on dates also return `NULL` for arguments
+/// that are not valid dates, as MySQL does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BuiltinFunction {
+    /// `COALESCE(a, b, ...)`, and `IFNULL(a, b)`
+    Coalesce,
+    /// `CONCAT(a, b, ...)`
+    Concat,
+    /// `LOWER(s)`
+    Lower,
+    /// `UPPER(s)`
+    Upper,
+    /// `SUBSTRING(s, pos)` and `SUBSTRING(s, pos, len)`, with 1-based character positions
+    Substring,
+    /// `LENGTH(s)`, the length of the string in bytes
+    Length,
+    /// `DATE(ts)`, the date part of the timestamp
+    Date,
+    /// `UNIX_TIMESTAMP(ts)`, the number of seconds since the epoch
+    UnixTimestamp,
+    /// `DATE_ADD(ts, INTERVAL n unit)`
+    DateAdd(IntervalUnit),
+}
+
+impl BuiltinFunction {
+    /// Whether the function can be called with `n` arguments.
+    pub fn accepts(&self, n: usize) -> bool {
+        match *self {
+            BuiltinFunction::Coalesce | BuiltinFunction::Concat => n >= 1,
+            BuiltinFunction::Lower
+            | BuiltinFunction::Upper
+            | BuiltinFunction::Length
+            | BuiltinFunction::Date
+            | BuiltinFunction::UnixTimestamp => n == 1,
+            BuiltinFunction::Substring => n == 2 || n == 3,
+            BuiltinFunction::DateAdd(_) => n == 2,
+        }
+    }
+
+    /// Computes the function over the given argument values.
+    pub fn eval(&self, args: &[DataType]) -> DataType {
+        debug_assert!(self.accepts(args.len()));
+
+        if let BuiltinFunction::Coalesce = *self {
+            return args
+                .iter()
+                .find(|a| !a.is_none())
+                .cloned()
+                .unwrap_or(DataType::None);
+        }
+
+        if args.iter().any(DataType::is_none) {
+            return DataType::None;
+        }
+
+        match *self {
+            BuiltinFunction::Coalesce => unreachable!(),
+            BuiltinFunction::Concat => args.iter().map(text).collect::<Vec<_>>().concat().into(),
+            BuiltinFunction::Lower => text(&args[0]).to_lowercase().into(),
+            BuiltinFunction::Upper => text(&args[0]).to_uppercase().into(),
+            BuiltinFunction::Substring => {
+                let s = text(&args[0]);
+                let len = s.chars().count() as i64;
+                let pos = match integer(&args[1]) {
+                    Some(pos) => pos,
+                    None => return DataType::None,
+                };
+                // positions count from the end if negative, and position 0 is before the string
+                let start = match pos {
+                    0 => len,
+                    p if p > 0 => p - 1,
+                    p => len + p,
+                };
+                if start < 0 || start >= len {
+                    return "".into();
+                }
+                let take = match args.get(2).map(integer) {
+                    Some(Some(take)) => take.max(0),
+                    Some(None) => return DataType::None,
+                    None => len,
+                };
+                s.chars()
+                    .skip(start as usize)
+                    .take(take as usize)
+                    .collect::<String>()
+                    .into()
+            }
+            BuiltinFunction::Length => (text(&args[0]).len() as i64).into(),
+            BuiltinFunction::Date => timestamp(&args[0]).map(|ts| ts.date()).into(),
+            BuiltinFunction::UnixTimestamp => match args[0] {
+                // zoned timestamps know which instant they refer to
+                DataType::TimestampTz(ref ts) => ts.timestamp().into(),
+                ref d => timestamp(d).map(|ts| ts.timestamp()).into(),
+            },
+            BuiltinFunction::DateAdd(unit) => {
+                let n = match integer(&args[1]) {
+                    Some(n) => n,
+                    None => return DataType::None,
+                };
+                match args[0] {
+                    // like in MySQL, adding days or more to a date gives a date
+                    DataType::Date(d) if unit.is_whole_days() => {
+                        date_add(d.and_hms(0, 0, 0), n, unit)
+                            .map(|ts| ts.date())
+                            .into()
+                    }
+                    // zoned timestamps keep their offset
+                    DataType::TimestampTz(ref ts) => date_add(ts.naive_local(), n, unit)
+                        .and_then(|local| ts.offset().from_local_datetime(&local).single())
+                        .into(),
+                    ref d => timestamp(d).and_then(|ts| date_add(ts, n, unit)).into(),
+                }
+            }
+        }
+    }
+}
+
+/// The textual representation of a value, as used by string functions.
+fn text(d: &DataType) -> Cow<str> {
+    match *d {
+        DataType::Text(..) | DataType::TinyText(..) => Cow::Borrowed(d.into()),
+        DataType::Real(..) => Cow::Owned(f64::from(d).to_string()),
+        DataType::Timestamp(ts) => Cow::Owned(ts.format("%Y-%m-%d %H:%M:%S").to_string()),
+        DataType::TimestampTz(ref ts) => Cow::Owned(ts.format("%Y-%m-%d %H:%M:%S%:z").to_string()),
+        _ => Cow::Owned(d.to_string()),
+    }
+}
+
+/// The integer value of an argument, rounding reals and decimals.
+///
+/// Booleans count as 0 and 1, and strings are parsed as numbers. Arguments that have no integer
+/// value, like dates or strings that are not numbers, give `None`, and the function `NULL`.
+fn integer(d: &DataType) -> Option<i64> {
+    match *d {
+        DataType::Int(..)
+        | DataType::UnsignedInt(..)
+        | DataType::BigInt(..)
+        | DataType::UnsignedBigInt(..) => {
+            let n = i128::from(d);
+            Some(n.max(i128::from(i64::MIN)).min(i128::from(i64::MAX)) as i64)
+        }
+        DataType::Real(..) | DataType::Decimal(..) => Some(f64::from(d).round() as i64),
+        DataType::Bool(b) => Some(i64::from(b)),
+        DataType::Text(..) | DataType::TinyText(..) => {
+            let s: &str = d.into();
+            let s = s.trim();
+            s.parse::<i64>().ok().or_else(|| {
+                s.parse::<f64>()
+                    .ok()
+                    .filter(|f| f.is_finite())
+                    .map(|f| f.round() as i64)
+            })
+        }
+        _ => None,
+    }
+}
+
+/// The timestamp value of a timestamp or date argument, or of a string in MySQL's date formats.
+///
+/// Zoned timestamps are taken in their own offset.
+fn timestamp(d: &DataType) -> Option<NaiveDateTime> {
+    match *d {
+        DataType::Timestamp(..) | DataType::TimestampTz(..) | DataType::Date(..) => Some(d.into()),
+        DataType::Text(..) | DataType::TinyText(..) => {
+            let s: &str = d.into();
+            NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .or_else(|| {
+                    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                        .ok()
+                        .map(|d| d.and_hms(0, 0, 0))
+                })
+        }
+        _ => None,
+    }
+}
+
+fn date_add(ts: NaiveDateTime, n: i64, unit: IntervalUnit) -> Option<NaiveDateTime> {
+    let months = match unit {
+        IntervalUnit::Second => return ts.checked_add_signed(Duration::seconds(n)),
+        IntervalUnit::Minute => return ts.checked_add_signed(Duration::minutes(n)),
+        IntervalUnit::Hour => return ts.checked_add_signed(Duration::hours(n)),
+        IntervalUnit::Day => return ts.checked_add_signed(Duration::days(n)),
+        IntervalUnit::Week => return ts.checked_add_signed(Duration::weeks(n)),
+        IntervalUnit::Month => n,
+        IntervalUnit::Year => n.checked_mul(12)?,
+    };
+
+    // like MySQL, clamp the day to the last day of the resulting month
+    let month0 = i64::from(ts.year()) * 12 + i64::from(ts.month0()) + months;
+    let year = month0.div_euclid(12) as i32;
+    let month = month0.rem_euclid(12) as u32 + 1;
+    (1..=ts.day())
+        .rev()
+        .filter_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+        .next()
+        .map(|date| date.and_time(ts.time()))
+}
+
+impl fmt::Display for IntervalUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unit = match *self {
+            IntervalUnit::Second => "second",
+            IntervalUnit::Minute => "minute",
+            IntervalUnit::Hour => "hour",
+            IntervalUnit::Day => "day",
+            IntervalUnit::Week => "week",
+            IntervalUnit::Month => "month",
+            IntervalUnit::Year => "year",
+        };
+        write!(f, "{}", unit)
+    }
+}
+
+impl fmt::Display for BuiltinFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BuiltinFunction::Coalesce => write!(f, "coalesce"),
+            BuiltinFunction::Concat => write!(f, "concat"),
+            BuiltinFunction::Lower => write!(f, "lower"),
+            BuiltinFunction::Upper => write!(f, "upper"),
+            BuiltinFunction::Substring => write!(f, "substring"),
+            BuiltinFunction::Length => write!(f, "length"),
+            BuiltinFunction::Date => write!(f, "date"),
+            BuiltinFunction::UnixTimestamp => write!(f, "unix_timestamp"),
+            BuiltinFunction::DateAdd(unit) => write!(f, "date_add[{}]", unit),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(s: &str) -> DataType {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn it_coalesces() {
+        let f = BuiltinFunction::Coalesce;
+        assert_eq!(f.eval(&[DataType::None, 2.into(), 3.into()]), 2.into());
+        assert_eq!(f.eval(&[DataType::None, DataType::None]), DataType::None);
+    }
+
+    #[test]
+    fn it_computes_string_functions() {
+        let concat = BuiltinFunction::Concat;
+        assert_eq!(
+            concat.eval(&["id-".into(), 42.into(), "!".into()]),
+            "id-42!".into()
+        );
+        assert_eq!(concat.eval(&["a".into(), DataType::None]), DataType::None);
+
+        assert_eq!(
+            BuiltinFunction::Lower.eval(&["NoRia".into()]),
+            "noria".into()
+        );
+        assert_eq!(
+            BuiltinFunction::Upper.eval(&["NoRia".into()]),
+            "NORIA".into()
+        );
+        assert_eq!(BuiltinFunction::Length.eval(&["héllo".into()]), 6.into());
+
+        let substr = BuiltinFunction::Substring;
+        assert_eq!(substr.eval(&["dataflow".into(), 5.into()]), "flow".into());
+        assert_eq!(
+            substr.eval(&["dataflow".into(), 1.into(), 4.into()]),
+            "data".into()
+        );
+        assert_eq!(
+            substr.eval(&["dataflow".into(), (-4).into()]),
+            "flow".into()
+        );
+        assert_eq!(substr.eval(&["dataflow".into(), 0.into()]), "".into());
+        assert_eq!(substr.eval(&["dataflow".into(), 20.into()]), "".into());
+    }
+
+    #[test]
+    fn it_computes_date_functions() {
+        assert_eq!(
+            BuiltinFunction::Date.eval(&[ts("2020-02-29 13:14:15")]),
+            ts("2020-02-29 00:00:00")
+        );
+        assert_eq!(
+            BuiltinFunction::Date.eval(&["2020-02-29 13:14:15".into()]),
+            ts("2020-02-29 00:00:00")
+        );
+        assert_eq!(
+            BuiltinFunction::Date.eval(&["not a date".into()]),
+            DataType::None
+        );
+        assert_eq!(
+            BuiltinFunction::UnixTimestamp.eval(&[ts("1970-01-02 00:00:00")]),
+            86400.into()
+        );
+
+        let add = |unit, t, n: i32| BuiltinFunction::DateAdd(unit).eval(&[t, n.into()]);
+        assert_eq!(
+            add(IntervalUnit::Hour, ts("2020-02-29 23:00:00"), 2),
+            ts("2020-03-01 01:00:00")
+        );
+        assert_eq!(
+            add(IntervalUnit::Month, ts("2020-01-31 10:00:00"), 1),
+            ts("2020-02-29 10:00:00")
+        );
+        assert_eq!(
+            add(IntervalUnit::Year, ts("2020-02-29 10:00:00"), -1),
+            ts("2019-02-28 10:00:00")
+        );
+        assert_eq!(
+            add(IntervalUnit::Month, ts("2020-11-15 10:00:00"), 14),
+            ts("2022-01-15 10:00:00")
+        );
+    }
+
+    #[test]
+    fn it_computes_date_functions_on_dates_and_zones() {
+        let day = NaiveDate::from_ymd(2020, 2, 29);
+        let date = BuiltinFunction::Date.eval(&[ts("2020-02-29 13:14:15")]);
+        assert!(date.is_date());
+        assert_eq!(date, day.into());
+
+        let add = |unit, t, n: i32| BuiltinFunction::DateAdd(unit).eval(&[t, n.into()]);
+        let next = add(IntervalUnit::Day, day.into(), 1);
+        assert!(next.is_date());
+        assert_eq!(next, NaiveDate::from_ymd(2020, 3, 1).into());
+        assert_eq!(
+            add(IntervalUnit::Hour, day.into(), 1),
+            ts("2020-02-29 01:00:00")
+        );
+
+        // zoned timestamps are bucketed by their local date, but keep their instant
+        let offset = chrono::FixedOffset::east(-5 * 3600);
+        let zoned: DataType = offset.ymd(2020, 2, 29).and_hms(22, 0, 0).into();
+        assert_eq!(BuiltinFunction::Date.eval(&[zoned.clone()]), day.into());
+        assert_eq!(
+            BuiltinFunction::UnixTimestamp.eval(&[zoned.clone()]),
+            1_583_031_600.into()
+        );
+        assert_eq!(
+            add(IntervalUnit::Hour, zoned, 3),
+            offset.ymd(2020, 3, 1).and_hms(1, 0, 0).into()
+        );
+    }
+
+    #[test]
+    fn it_propagates_nulls() {
+        for f in &[
+            BuiltinFunction::Lower,
+            BuiltinFunction::Length,
+            BuiltinFunction::Date,
+            BuiltinFunction::UnixTimestamp,
+        ] {
+            assert_eq!(f.eval(&[DataType::None]), DataType::None);
+        }
+        assert_eq!(
+            BuiltinFunction::DateAdd(IntervalUnit::Day)
+                .eval(&[ts("2020-01-01 00:00:00"), DataType::None]),
+            DataType::None
+        );
+    }
+
+    #[test]
+    fn it_handles_non_integer_arguments() {
+        let substr = BuiltinFunction::Substring;
+        assert_eq!(substr.eval(&["dataflow".into(), "5".into()]), "flow".into());
+        assert_eq!(
+            substr.eval(&["dataflow".into(), DataType::Bool(true), 4.4.into()]),
+            "data".into()
+        );
+        assert_eq!(
+            substr.eval(&["dataflow".into(), "five".into()]),
+            DataType::None
+        );
+        assert_eq!(
+            substr.eval(&["dataflow".into(), 1.into(), ts("2020-01-01 00:00:00")]),
+            DataType::None
+        );
+
+        let day = NaiveDate::from_ymd(2020, 2, 29);
+        let add = BuiltinFunction::DateAdd(IntervalUnit::Day);
+        assert_eq!(
+            add.eval(&[day.into(), "1".into()]),
+            NaiveDate::from_ymd(2020, 3, 1).into()
+        );
+        assert_eq!(add.eval(&[day.into(), day.into()]), DataType::None);
+    }
+}
@@ -1,3 +1,9 @@
+use std::cmp;
+use std::collections::HashMap;
+use std::mem;
+
+use common::SizeOf;
+
 use crate::ops::grouped::GroupedOperation;
 use crate::ops::grouped::GroupedOperator;
 
@@ -5,11 +11,23 @@ use crate::prelude::*;
 
 /// Supported aggregation operators.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[allow(non_camel_case_types)]
 pub enum Aggregation {
     /// Count the number of records for each group. The value for the `over` column is ignored.
     COUNT,
     /// Sum the value of the `over` column for all records of each group.
     SUM,
+    /// Average the non-NULL values of the `over` column for each group.
+    AVG,
+    /// The population variance of the non-NULL values of the `over` column for each group.
+    VARIANCE,
+    /// The population standard deviation of the non-NULL values of the `over` column for each
+    /// group.
+    STDDEV,
+    /// 1 if the `over` column is non-zero for all non-NULL records of each group, 0 otherwise.
+    BOOL_AND,
+    /// 1 if the `over` column is non-zero for any non-NULL record of each group, 0 otherwise.
+    BOOL_OR,
 }
 
 impl Aggregation {
@@ -34,11 +52,141 @@ impl Aggregation {
                 op: self,
                 over,
                 group: group_by.into(),
+                totals: HashMap::new(),
+                totals_size: 0,
             },
         )
     }
 }
 
+/// Bring two unscaled decimal values to the larger of their scales, so they can be added exactly.
+fn align((a, sa): (i128, u8), (b, sb): (i128, u8)) -> (i128, i128, u8) {
+    let scale = cmp::max(sa, sb);
+    (
+        a * 10i128.pow(u32::from(scale - sa)),
+        b * 10i128.pow(u32::from(scale - sb)),
+        scale,
+    )
+}
+
+/// The unscaled value `v` with `to` instead of `from` decimal digits, if it fits.
+fn rescale(v: i128, from: u8, to: u8) -> Option<i128> {
+    10i128.checked_pow(u32::from(to - from))?.checked_mul(v)
+}
+
+/// The number of decimal digits with which reals are aggregated, which is the precision with which
+/// `DataType::Real` stores their fractional part.
+const REAL_SCALE: u8 = 9;
+
+/// The unscaled value of the real `i + frac / 10^9` with `REAL_SCALE` decimal digits.
+fn real_to_unscaled(i: i64, frac: i32) -> i128 {
+    i128::from(i) * 10i128.pow(u32::from(REAL_SCALE)) + i128::from(frac)
+}
+
+/// The real value of the unscaled `v`, which has `REAL_SCALE` decimal digits.
+fn unscaled_to_real(v: i128) -> DataType {
+    let unit = 10i128.pow(u32::from(REAL_SCALE));
+    DataType::Real((v / unit) as i64, (v % unit) as i32)
+}
+
+/// Running totals over the non-NULL values of a group, from which the aggregations that cannot be
+/// updated using only their previous value are computed.
+///
+/// Averages and boolean aggregations use the exact sum of the values, unscaled by `scale` decimal
+/// digits. Variances and standard deviations instead use the mean of the values and the sum of
+/// their squared differences from it, which are updated as in Welford's algorithm.
+#[derive(Debug, Clone, Default)]
+struct Totals {
+    count: i128,
+    sum: i128,
+    scale: u8,
+    /// Whether `sum` no longer fits into an `i128`.
+    overflowed: bool,
+    mean: f64,
+    m2: f64,
+}
+
+impl Totals {
+    fn add(&mut self, v: (i128, u8), pos: bool, op: &Aggregation) {
+        self.count += if pos { 1 } else { -1 };
+        match *op {
+            Aggregation::VARIANCE | Aggregation::STDDEV => {
+                let x = v.0 as f64 / 10f64.powi(i32::from(v.1));
+                self.add_to_moments(x, pos);
+            }
+            _ => self.add_to_sum(v, pos),
+        }
+    }
+
+    fn add_to_sum(&mut self, (v, scale): (i128, u8), pos: bool) {
+        if scale > self.scale {
+            match rescale(self.sum, self.scale, scale) {
+                Some(sum) => self.sum = sum,
+                None => self.overflowed = true,
+            }
+            self.scale = scale;
+        }
+        let sum = rescale(v, scale, self.scale).and_then(|v| {
+            if pos {
+                self.sum.checked_add(v)
+            } else {
+                self.sum.checked_sub(v)
+            }
+        });
+        match sum {
+            Some(sum) => self.sum = sum,
+            None => self.overflowed = true,
+        }
+    }
+
+    fn add_to_moments(&mut self, x: f64, pos: bool) {
+        if self.count == 0 {
+            self.mean = 0.0;
+            self.m2 = 0.0;
+            return;
+        }
+        // the count has already been updated, and removing a value undoes adding it
+        let d = x - self.mean;
+        if pos {
+            self.mean += d / self.count as f64;
+        } else {
+            self.mean -= d / self.count as f64;
+        }
+        let m2 = d * (x - self.mean);
+        if pos {
+            self.m2 += m2;
+        } else {
+            self.m2 -= m2;
+        }
+    }
+
+    fn value(&self, op: &Aggregation) -> DataType {
+        if self.count == 0 || self.overflowed {
+            // like in SQL, these aggregations are NULL over no values, and we can't say what they
+            // are once the sum no longer fits
+            return DataType::None;
+        }
+
+        match *op {
+            Aggregation::AVG => (self.sum as f64 / self.count as f64 / self.unit()).into(),
+            Aggregation::VARIANCE => self.variance().into(),
+            Aggregation::STDDEV => self.variance().sqrt().into(),
+            Aggregation::BOOL_AND => i32::from(self.sum == self.count).into(),
+            Aggregation::BOOL_OR => i32::from(self.sum > 0).into(),
+            Aggregation::COUNT | Aggregation::SUM => unreachable!(),
+        }
+    }
+
+    fn variance(&self) -> f64 {
+        // rounding can leave a tiny negative remainder where the values are all the same
+        f64::max(self.m2 / self.count as f64, 0.0)
+    }
+
+    fn unit(&self) -> f64 {
+        10f64.powi(i32::from(self.scale))
+    }
+}
+
 /// Aggregator implementas a Soup node that performans common aggregation operations such as counts
 /// and sums.
 ///
@@ -52,15 +200,46 @@ impl Aggregation {
 /// identifying the group, and appending the aggregated value. For example, for a sum with
 /// `self.over == 1`, a previous sum of `3`, and an incoming record with `[a, 1, x]`, the output
 /// would be `[a, x, 4]`.
+///
+/// Averages, variances and boolean aggregations cannot be updated from their previous value alone,
+/// so for those the aggregator also keeps running totals of each group's values.
+/// Only groups with non-NULL values have totals, and their size counts towards the node's memory
+/// use.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Aggregator {
     op: Aggregation,
     over: usize,
     group: Vec<usize>,
+
+    #[serde(skip)]
+    totals: HashMap<Vec<DataType>, Totals>,
+    #[serde(skip)]
+    totals_size: u64,
+}
+
+impl Aggregator {
+    /// The aggregation this operator performs.
+    pub fn kind(&self) -> &Aggregation {
+        &self.op
+    }
+
+    fn totals_entry_size(group: &[DataType]) -> u64 {
+        let entry = mem::size_of::<(Vec<DataType>, Totals)>() as u64;
+        entry + group.iter().map(SizeOf::deep_size_of).sum::<u64>()
+    }
+
+    fn remove_totals(&mut self, group: &[DataType]) {
+        if self.totals.remove(group).is_some() {
+            self.totals_size -= Self::totals_entry_size(group);
+        }
+    }
 }
 
 impl GroupedOperation for Aggregator {
-    type Diff = i128;
+    /// The record's value in the `over` column as an unscaled value and its number of decimal
+    /// digits (`None` if it is NULL), whether that value is a real, and whether the record was
+    /// added or removed.
+    type Diff = (Option<(i128, u8)>, bool, bool);
 
     fn setup(&mut self, parent: &Node) {
         assert!(
@@ -74,41 +253,95 @@ impl GroupedOperation for Aggregator {
     }
 
     fn to_diff(&self, r: &[DataType], pos: bool) -> Self::Diff {
+        if self.op == Aggregation::COUNT {
+            // the value is ignored when counting
+            return (Some((1, 0)), false, pos);
+        }
+
+        let v = match r[self.over] {
+            DataType::Int(n) => Some((i128::from(n), 0)),
+            DataType::UnsignedInt(n) => Some((i128::from(n), 0)),
+            DataType::BigInt(n) => Some((i128::from(n), 0)),
+            DataType::UnsignedBigInt(n) => Some((i128::from(n), 0)),
+            DataType::Decimal(n, scale) => Some((i128::from(n), scale)),
+            // reals are aggregated exactly, as decimals with all of their fractional digits
+            DataType::Real(i, frac) => Some((real_to_unscaled(i, frac), REAL_SCALE)),
+            DataType::Bool(b) => Some((i128::from(b), 0)),
+            // recipes refuse to aggregate over columns of other types, so these can only come from
+            // untyped inputs, whose values that aren't numbers are ignored like NULLs
+            _ => None,
+        };
+        let real = r[self.over].is_real();
         match self.op {
-            Aggregation::COUNT if pos => 1,
-            Aggregation::COUNT => -1,
-            Aggregation::SUM => {
-                let v = match r[self.over] {
-                    DataType::Int(n) => i128::from(n),
-                    DataType::UnsignedInt(n) => i128::from(n),
-                    DataType::BigInt(n) => i128::from(n),
-                    DataType::UnsignedBigInt(n) => i128::from(n),
-                    DataType::None => 0,
-                    ref x => unreachable!("tried to aggregate over {:?} on {:?}", x, r),
-                };
-                if pos {
-                    v
-                } else {
-                    0i128 - v
-                }
+            Aggregation::BOOL_AND | Aggregation::BOOL_OR => {
+                (v.map(|(v, _)| (i128::from(v != 0), 0)), false, pos)
             }
+            _ => (v, real, pos),
         }
     }
 
     fn apply(
-        &self,
+        &mut self,
         current: Option<&DataType>,
+        group: &[DataType],
         diffs: &mut dyn Iterator<Item = Self::Diff>,
     ) -> DataType {
-        let n = match current {
-            Some(&DataType::Int(n)) => i128::from(n),
-            Some(&DataType::UnsignedInt(n)) => i128::from(n),
-            Some(&DataType::BigInt(n)) => i128::from(n),
-            Some(&DataType::UnsignedBigInt(n)) => i128::from(n),
-            None => 0,
-            _ => unreachable!(),
-        };
-        diffs.fold(n, |n, d| n + d).into()
+        if self.op == Aggregation::COUNT || self.op == Aggregation::SUM {
+            let mut real = false;
+            let n = match current {
+                Some(&DataType::Int(n)) => (i128::from(n), 0),
+                Some(&DataType::UnsignedInt(n)) => (i128::from(n), 0),
+                Some(&DataType::BigInt(n)) => (i128::from(n), 0),
+                Some(&DataType::UnsignedBigInt(n)) => (i128::from(n), 0),
+                Some(&DataType::Decimal(n, scale)) => (i128::from(n), scale),
+                Some(&DataType::Real(i, frac)) => {
+                    real = true;
+                    (real_to_unscaled(i, frac), REAL_SCALE)
+                }
+                None => (0, 0),
+                _ => unreachable!(),
+            };
+            let (n, scale) = diffs.fold(n, |n, (v, r, pos)| {
+                real |= r;
+                let (n, v, scale) = align(n, v.unwrap_or((0, 0)));
+                if pos {
+                    (n + v, scale)
+                } else {
+                    (n - v, scale)
+                }
+            });
+            // sums of decimals are exact decimals, and sums of reals are reals
+            return if real {
+                let (n, _, _) = align((n, scale), (0, REAL_SCALE));
+                unscaled_to_real(n)
+            } else if scale == 0 {
+                n.into()
+            } else {
+                // sums too large for a decimal are NULL, like other overflowing aggregations
+                DataType::decimal(n, scale).unwrap_or(DataType::None)
+            };
+        }
+
+        if current.is_none() {
+            // the group has no value yet, so we are about to see all of its records. any totals we
+            // still have for it are from before it was evicted.
+            self.remove_totals(group);
+        }
+        if !self.totals.contains_key(group) {
+            self.totals_size += Self::totals_entry_size(group);
+        }
+        let totals = self.totals.entry(group.to_vec()).or_default();
+        for (v, _, pos) in diffs {
+            if let Some(v) = v {
+                totals.add(v, pos, &self.op);
+            }
+        }
+        let value = totals.value(&self.op);
+        if totals.count == 0 {
+            // the group has no non-NULL values left, which is what having no totals means too
+            self.remove_totals(group);
+        }
+        value
     }
 
     fn description(&self, detailed: bool) -> String {
@@ -116,12 +349,22 @@ impl GroupedOperation for Aggregator {
             return String::from(match self.op {
                 Aggregation::COUNT => "+",
                 Aggregation::SUM => "𝛴",
+                Aggregation::AVG => "AVG",
+                Aggregation::VARIANCE => "VAR",
+                Aggregation::STDDEV => "STDDEV",
+                Aggregation::BOOL_AND => "AND",
+                Aggregation::BOOL_OR => "OR",
             });
         }
 
         let op_string = match self.op {
             Aggregation::COUNT => "|*|".into(),
             Aggregation::SUM => format!("𝛴({})", self.over),
+            Aggregation::AVG => format!("avg({})", self.over),
+            Aggregation::VARIANCE => format!("var({})", self.over),
+            Aggregation::STDDEV => format!("stddev({})", self.over),
+            Aggregation::BOOL_AND => format!("bool_and({})", self.over),
+            Aggregation::BOOL_OR => format!("bool_or({})", self.over),
         };
         let group_cols = self
             .group
@@ -135,6 +378,10 @@ impl GroupedOperation for Aggregator {
     fn over_columns(&self) -> Vec<usize> {
         vec![self.over]
     }
+
+    fn auxiliary_state_size(&self) -> u64 {
+        self.totals_size
+    }
 }
 
 #[cfg(test)]
@@ -174,6 +421,9 @@ mod tests {
         let c = Aggregation::COUNT.over(s, 1, &[0, 2]);
         assert_eq!(c.description(true), "|*| γ[0, 2]");
 
+        let a = Aggregation::AVG.over(s, 1, &[2, 0]);
+        assert_eq!(a.description(true), "avg(1) γ[2, 0]");
+
         let s = Aggregation::SUM.over(s, 1, &[2, 0]);
         assert_eq!(s.description(true), "𝛴(1) γ[2, 0]");
     }
@@ -389,6 +639,318 @@ mod tests {
 
     // TODO: also test SUM
 
+    fn setup_op(op: Aggregation) -> ops::test::MockGraph {
+        let mut g = ops::test::MockGraph::new();
+        let s = g.add_base("source", &["x", "y"]);
+        g.set_op("agg", &["x", "ys"], op.over(s.as_global(), 1, &[0]), true);
+        g
+    }
+
+    #[test]
+    fn it_averages() {
+        let mut c = setup_op(Aggregation::AVG);
+
+        let rs = c.narrow_one_row(vec![1.into(), 1.into()], true);
+        assert_eq!(rs, vec![(vec![1.into(), 1.0.into()], true)].into());
+        assert!(c.node().auxiliary_state_size() > 0);
+
+        // NULLs are ignored
+        let rs = c.narrow_one_row(vec![1.into(), DataType::None], true);
+        assert!(rs.is_empty());
+
+        let rs = c.narrow_one_row(vec![1.into(), 2.into()], true);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), 1.0.into()], false),
+                (vec![1.into(), 1.5.into()], true)
+            ]
+            .into()
+        );
+
+        let rs = c.narrow_one_row((vec![1.into(), 1.into()], false), true);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), 1.5.into()], false),
+                (vec![1.into(), 2.0.into()], true)
+            ]
+            .into()
+        );
+
+        // a group without values averages to NULL
+        let rs = c.narrow_one_row((vec![1.into(), 2.into()], false), true);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), 2.0.into()], false),
+                (vec![1.into(), DataType::None], true)
+            ]
+            .into()
+        );
+        // and needs no totals
+        assert_eq!(c.node().auxiliary_state_size(), 0);
+    }
+
+    #[test]
+    fn it_computes_variance() {
+        let mut c = setup_op(Aggregation::VARIANCE);
+        let mut d = setup_op(Aggregation::STDDEV);
+
+        for v in &[2, 4, 4, 4, 5, 5, 7, 9] {
+            c.narrow_one_row(vec![1.into(), (*v).into()], true);
+            d.narrow_one_row(vec![1.into(), (*v).into()], true);
+        }
+
+        let rs = c.narrow_one_row(vec![1.into(), 5.into()], true);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), 4.0.into()], false),
+                (vec![1.into(), (32.0 / 9.0).into()], true)
+            ]
+            .into()
+        );
+        let rs = d.narrow_one_row((vec![1.into(), 9.into()], false), true);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), 2.0.into()], false),
+                (vec![1.into(), (96.0f64 / 49.0).sqrt().into()], true)
+            ]
+            .into()
+        );
+    }
+
+    #[test]
+    fn it_aggregates_large_reals() {
+        let mut c = setup_op(Aggregation::VARIANCE);
+        let mut d = setup_op(Aggregation::STDDEV);
+        let mut a = setup_op(Aggregation::AVG);
+
+        // squaring these as unscaled integers would overflow
+        for v in &[2e10 + 0.5, 2e10 + 1.5] {
+            c.narrow_one_row(vec![1.into(), (*v).into()], true);
+            d.narrow_one_row(vec![1.into(), (*v).into()], true);
+        }
+
+        let rs = c.narrow_one_row((vec![1.into(), (2e10 + 1.5).into()], false), true);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), 0.25.into()], false),
+                (vec![1.into(), 0.0.into()], true)
+            ]
+            .into()
+        );
+        let rs = d.narrow_one_row(vec![1.into(), (2e10 + 2.5).into()], true);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), 0.5.into()], false),
+                (vec![1.into(), (2.0f64 / 3.0).sqrt().into()], true)
+            ]
+            .into()
+        );
+
+        // an average whose sum no longer fits is NULL
+        a.narrow_one_row(vec![1.into(), DataType::Decimal(i64::MAX, 0)], true);
+        let rs = a.narrow_one_row(vec![1.into(), DataType::Decimal(1, 30)], true);
+        assert_eq!(
+            rs.into_iter().last(),
+            Some((vec![1.into(), DataType::None], true).into())
+        );
+    }
+
+    #[test]
+    fn it_aggregates_bools() {
+        let mut c = setup_op(Aggregation::BOOL_AND);
+        let mut d = setup_op(Aggregation::BOOL_OR);
+
+        let rs = c.narrow_one_row(vec![1.into(), true.into()], true);
+        assert_eq!(rs, vec![(vec![1.into(), 1.into()], true)].into());
+        let rs = c.narrow_one_row(vec![1.into(), false.into()], true);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), 1.into()], false),
+                (vec![1.into(), 0.into()], true)
+            ]
+            .into()
+        );
+
+        let rs = d.narrow_one_row(vec![1.into(), false.into()], true);
+        assert_eq!(rs, vec![(vec![1.into(), 0.into()], true)].into());
+        let rs = d.narrow_one_row(vec![1.into(), true.into()], true);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), 0.into()], false),
+                (vec![1.into(), 1.into()], true)
+            ]
+            .into()
+        );
+    }
+
+    #[test]
+    fn it_groups_by_dates() {
+        use chrono::NaiveDate;
+
+        let mut c = setup(true);
+        let day = NaiveDate::from_ymd(2020, 2, 29);
+
+        let rs = c.narrow_one_row(vec![day.into(), 1.into()], true);
+        assert_eq!(rs, vec![(vec![day.into(), 1.into()], true)].into());
+
+        // a timestamp at midnight is the same day, but a timestamp later that day is not
+        let rs = c.narrow_one_row(vec![day.and_hms(0, 0, 0).into(), 2.into()], true);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![day.into(), 1.into()], false),
+                (vec![day.into(), 2.into()], true)
+            ]
+            .into()
+        );
+        let rs = c.narrow_one_row(vec![day.and_hms(12, 0, 0).into(), 3.into()], true);
+        assert_eq!(
+            rs,
+            vec![(vec![day.and_hms(12, 0, 0).into(), 1.into()], true)].into()
+        );
+    }
+
+    #[test]
+    fn it_sums_decimals() {
+        let mut c = setup_op(Aggregation::SUM);
+
+        let rs = c.narrow_one_row(vec![1.into(), DataType::Decimal(10, 2)], true);
+        assert_eq!(
+            rs,
+            vec![(vec![1.into(), DataType::Decimal(10, 2)], true)].into()
+        );
+
+        // integers and decimals of different scales add up exactly
+        let rs = c.narrow_one(
+            vec![
+                (vec![1.into(), DataType::Decimal(2, 1)], true),
+                (vec![1.into(), 1.into()], true),
+            ],
+            true,
+        );
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), DataType::Decimal(10, 2)], false),
+                (vec![1.into(), DataType::Decimal(130, 2)], true)
+            ]
+            .into()
+        );
+
+        let rs = c.narrow_one_row((vec![1.into(), DataType::Decimal(10, 2)], false), true);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), DataType::Decimal(130, 2)], false),
+                (vec![1.into(), DataType::Decimal(120, 2)], true)
+            ]
+            .into()
+        );
+
+        let mut a = setup_op(Aggregation::AVG);
+        a.narrow_one_row(vec![1.into(), DataType::Decimal(10, 2)], true);
+        let rs = a.narrow_one_row(vec![1.into(), DataType::Decimal(2, 1)], true);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), 0.1.into()], false),
+                (vec![1.into(), 0.15.into()], true)
+            ]
+            .into()
+        );
+    }
+
+    #[test]
+    fn it_aggregates_reals() {
+        let mut c = setup_op(Aggregation::SUM);
+
+        let rs = c.narrow_one_row(vec![1.into(), 0.1.into()], true);
+        assert_eq!(rs, vec![(vec![1.into(), 0.1.into()], true)].into());
+
+        // reals add up exactly to their stored precision
+        let rs = c.narrow_one_row(vec![1.into(), 0.2.into()], true);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), 0.1.into()], false),
+                (vec![1.into(), DataType::Real(0, 300_000_000)], true)
+            ]
+            .into()
+        );
+
+        let rs = c.narrow_one_row((vec![1.into(), 1.5.into()], true), true);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), DataType::Real(0, 300_000_000)], false),
+                (vec![1.into(), DataType::Real(1, 800_000_000)], true)
+            ]
+            .into()
+        );
+
+        let mut a = setup_op(Aggregation::AVG);
+        a.narrow_one_row(vec![1.into(), (-0.5).into()], true);
+        let rs = a.narrow_one_row(vec![1.into(), 1.5.into()], true);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), (-0.5).into()], false),
+                (vec![1.into(), 0.5.into()], true)
+            ]
+            .into()
+        );
+    }
+
+    #[test]
+    fn it_aggregates_booleans() {
+        let mut and = setup_op(Aggregation::BOOL_AND);
+        let mut or = setup_op(Aggregation::BOOL_OR);
+
+        let rs = and.narrow_one_row(vec![1.into(), 1.into()], true);
+        assert_eq!(rs, vec![(vec![1.into(), 1.into()], true)].into());
+        let rs = or.narrow_one_row(vec![1.into(), 0.into()], true);
+        assert_eq!(rs, vec![(vec![1.into(), 0.into()], true)].into());
+
+        let rs = and.narrow_one_row(vec![1.into(), 0.into()], true);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), 1.into()], false),
+                (vec![1.into(), 0.into()], true)
+            ]
+            .into()
+        );
+        let rs = or.narrow_one_row(vec![1.into(), 7.into()], true);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), 0.into()], false),
+                (vec![1.into(), 1.into()], true)
+            ]
+            .into()
+        );
+
+        // removing the only false value makes the conjunction true again
+        let rs = and.narrow_one_row((vec![1.into(), 0.into()], false), true);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), 0.into()], false),
+                (vec![1.into(), 1.into()], true)
+            ]
+            .into()
+        );
+    }
+
     #[test]
     fn it_suggests_indices() {
         let me = 1.into();
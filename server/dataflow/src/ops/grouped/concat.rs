@@ -97,8 +97,14 @@ impl GroupConcat {
                     DataType::UnsignedInt(ref n) => s.push_str(&n.to_string()),
                     DataType::BigInt(ref n) => s.push_str(&n.to_string()),
                     DataType::UnsignedBigInt(ref n) => s.push_str(&n.to_string()),
-                    DataType::Real(..) => s.push_str(&rec[*i].to_string()),
+                    DataType::Real(..) | DataType::Decimal(..) => s.push_str(&rec[*i].to_string()),
                     DataType::Timestamp(ref ts) => s.push_str(&ts.format("%+").to_string()),
+                    DataType::TimestampTz(ref ts) => s.push_str(&ts.to_rfc3339()),
+                    DataType::Date(..) | DataType::Time(..) => s.push_str(&rec[*i].to_string()),
+                    DataType::Bool(b) => s.push_str(if b { "1" } else { "0" }),
+                    DataType::ByteArray(..) | DataType::Uuid(..) => {
+                        s.push_str(&rec[*i].to_string())
+                    }
                     DataType::None => unreachable!(),
                 },
             }
@@ -151,8 +157,9 @@ impl GroupedOperation for GroupConcat {
     }
 
     fn apply(
-        &self,
+        &mut self,
         current: Option<&DataType>,
+        _: &[DataType],
         diffs: &mut dyn Iterator<Item = Self::Diff>,
     ) -> DataType {
         use std::collections::BTreeSet;
@@ -105,8 +105,9 @@ impl GroupedOperation for ExtremumOperator {
     }
 
     fn apply(
-        &self,
+        &mut self,
         current: Option<&DataType>,
+        _: &[DataType],
         diffs: &mut dyn Iterator<Item = Self::Diff>,
     ) -> DataType {
         // Extreme values are those that are at least as extreme as the current min/max (if any).
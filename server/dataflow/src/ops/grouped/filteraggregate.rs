@@ -107,6 +107,7 @@ impl GroupedOperation for FilterAggregator {
                     }
                 }
                 FilterCondition::In(ref fs) => fs.contains(d),
+                FilterCondition::Pattern(ref p) => p.matches(d),
             }
         });
         let v = if passes_filter {
@@ -144,8 +145,9 @@ impl GroupedOperation for FilterAggregator {
     }
 
     fn apply(
-        &self,
+        &mut self,
         current: Option<&DataType>,
+        _: &[DataType],
         diffs: &mut dyn Iterator<Item = Self::Diff>,
     ) -> DataType {
         let n = match current {
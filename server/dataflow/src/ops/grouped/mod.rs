@@ -53,14 +53,23 @@ pub trait GroupedOperation: fmt::Debug + Clone {
 
     /// Given the given `current` value, and a number of changes for a group (`diffs`), compute the
     /// updated group value.
+    ///
+    /// `group` holds the values of the group's `group_by` columns. `current` is `None` if the group
+    /// has no value yet, in which case `diffs` covers all of the group's records.
     fn apply(
-        &self,
+        &mut self,
         current: Option<&DataType>,
+        group: &[DataType],
         diffs: &mut dyn Iterator<Item = Self::Diff>,
     ) -> DataType;
 
     fn description(&self, detailed: bool) -> String;
     fn over_columns(&self) -> Vec<usize>;
+
+    /// The number of bytes of state the operation keeps besides the node's materialization.
+    fn auxiliary_state_size(&self) -> u64 {
+        0
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -95,6 +104,11 @@ impl<T: GroupedOperation> GroupedOperator<T> {
     pub fn over_columns(&self) -> Vec<usize> {
         self.inner.over_columns()
     }
+
+    /// The grouped operation this operator performs.
+    pub fn operation(&self) -> &T {
+        &self.inner
+    }
 }
 
 /// Extract a copy of all values in the record being targeted by the group
@@ -245,7 +259,11 @@ where
                     });
 
                     // new is the result of applying all diffs for the group to the current value
-                    let new = inner.apply(current.as_ref().map(|v| &**v), &mut diffs as &mut _);
+                    let new = inner.apply(
+                        current.as_ref().map(|v| &**v),
+                        &group[..],
+                        &mut diffs as &mut _,
+                    );
                     match current {
                         Some(ref current) if new == **current => {
                             // no change
@@ -312,4 +330,8 @@ where
     fn is_selective(&self) -> bool {
         true
     }
+
+    fn auxiliary_state_size(&self) -> u64 {
+        self.inner.auxiliary_state_size()
+    }
 }
@@ -1,3 +1,4 @@
+use slog::Logger;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::mem;
@@ -11,6 +12,8 @@ pub enum JoinType {
     Left,
     /// Inner join between two views
     Inner,
+    /// Full outer join between two views
+    Full,
 }
 
 /// Where to source a join column
@@ -30,8 +33,8 @@ pub struct Join {
     left: IndexPair,
     right: IndexPair,
 
-    // Key column in the left and right parents respectively
-    on: (usize, usize),
+    // Key columns in the left and right parents respectively
+    on: Vec<(usize, usize)>,
 
     // Which columns to emit. True means the column is from the left parent, false means from the
     // right
@@ -54,10 +57,10 @@ enum Preprocessed {
 impl Join {
     /// Create a new instance of Join
     ///
-    /// `left` and `right` are the left and right parents respectively. `on` is a tuple specifying
-    /// the join columns: (left_parent_column, right_parent_column) and `emit` dictates for each
-    /// output colunm, which source and column should be used (true means left parent, and false
-    /// means right parent).
+    /// `left` and `right` are the left and right parents respectively. `emit` dictates for each
+    /// output column which source and column should be used. Join columns are given as
+    /// `JoinSource::B(left_parent_column, right_parent_column)`; rows are joined when they agree on
+    /// all of them.
     pub fn new(left: NodeIndex, right: NodeIndex, kind: JoinType, emit: Vec<JoinSource>) -> Self {
         let mut join_columns = Vec::new();
         let emit: Vec<_> = emit
@@ -72,8 +75,11 @@ impl Join {
             })
             .collect();
 
-        assert_eq!(join_columns.len(), 1, "only supports single column joins");
-        let on = *join_columns.iter().next().unwrap();
+        assert!(
+            !join_columns.is_empty(),
+            "join must have at least one join column"
+        );
+        let on = join_columns;
 
         let (in_place_left_emit, in_place_right_emit) = {
             let compute_in_place_emit = |left| {
@@ -124,6 +130,14 @@ impl Join {
         }
     }
 
+    fn on_left(&self) -> Vec<usize> {
+        self.on.iter().map(|&(l, _)| l).collect()
+    }
+
+    fn on_right(&self) -> Vec<usize> {
+        self.on.iter().map(|&(_, r)| r).collect()
+    }
+
     fn generate_row(
         &self,
         left: &[DataType],
@@ -180,18 +194,58 @@ impl Join {
     }
 
     // TODO: make non-allocating
-    fn generate_null(&self, left: &[DataType]) -> Vec<DataType> {
+    fn generate_null(&self, row: &[DataType], row_is_left: bool) -> Vec<DataType> {
         self.emit
             .iter()
             .map(|&(from_left, col)| {
-                if from_left {
-                    left[col].clone()
+                if from_left == row_is_left {
+                    row[col].clone()
+                } else if !row_is_left {
+                    // join columns are emitted from the left, but a right row has them too
+                    self.on
+                        .iter()
+                        .find(|&&(l, _)| l == col)
+                        .map(|&(_, r)| row[r].clone())
+                        .unwrap_or(DataType::None)
                 } else {
                     DataType::None
                 }
             })
             .collect()
     }
+
+    /// Whether rows from `side` are emitted even when they have no match on the other side.
+    fn preserves(&self, side: LocalNodeIndex) -> bool {
+        match self.kind {
+            JoinType::Inner => false,
+            JoinType::Left => side == *self.left,
+            JoinType::Full => true,
+        }
+    }
+
+    /// Find the rows in the right parent that have no match in the left parent, padded with
+    /// NULLs.
+    fn unmatched_right(&self, nodes: &DomainNodes, states: &StateMap) -> Vec<Record> {
+        let right = states
+            .get(*self.right)
+            .expect("full join must be able to read all of its right parent's rows");
+        let (on_left, on_right) = (self.on_left(), self.on_right());
+        right
+            .cloned_records()
+            .into_iter()
+            .filter(|r| {
+                let key: Vec<_> = on_right.iter().map(|&c| &r[c]).collect();
+                let lefts = self
+                    .lookup(*self.left, &on_left, &KeyType::from(key), nodes, states)
+                    .expect("full join must be able to look up its left parent");
+                match lefts {
+                    Some(mut lefts) => lefts.next().is_none(),
+                    None => unreachable!("full join replayed left, but left has holes"),
+                }
+            })
+            .map(|r| (self.generate_null(&r, false), true).into())
+            .collect()
+    }
 }
 
 impl Ingredient for Join {
@@ -210,6 +264,9 @@ impl Ingredient for Join {
     fn must_replay_among(&self) -> Option<HashSet<NodeIndex>> {
         match self.kind {
             JoinType::Left => Some(Some(self.left.as_global()).into_iter().collect()),
+            // the right rows that have no match on the left are added once the left has been
+            // fully replayed; see `on_input_raw`.
+            JoinType::Full => Some(Some(self.left.as_global()).into_iter().collect()),
             JoinType::Inner => Some(
                 vec![self.left.as_global(), self.right.as_global()]
                     .into_iter()
@@ -225,6 +282,32 @@ impl Ingredient for Join {
         self.right.remap(remap);
     }
 
+    fn can_replay_partially(&self) -> bool {
+        // a partial replay of a key from the left would miss right rows without a left match
+        self.kind != JoinType::Full
+    }
+
+    fn on_input_raw(
+        &mut self,
+        executor: &mut dyn Executor,
+        from: LocalNodeIndex,
+        rs: Records,
+        replay: ReplayContext,
+        nodes: &DomainNodes,
+        state: &StateMap,
+        _: &Logger,
+    ) -> RawProcessingResult {
+        let mut result = self.on_input(executor, from, rs, replay.key(), nodes, state);
+        if let ReplayContext::Full { last: true } = replay {
+            if self.kind == JoinType::Full {
+                // a full replay only ever comes from the left, so we also need to produce the
+                // right rows that the replayed left rows did not match
+                result.results.extend(self.unmatched_right(nodes, state));
+            }
+        }
+        RawProcessingResult::Regular(result)
+    }
+
     #[allow(clippy::cognitive_complexity)]
     fn on_input(
         &mut self,
@@ -246,10 +329,11 @@ impl Ingredient for Join {
         }
 
         let (other, from_key, other_key) = if from == *self.left {
-            (*self.right, self.on.0, self.on.1)
+            (*self.right, self.on_left(), self.on_right())
         } else {
-            (*self.left, self.on.1, self.on.0)
+            (*self.left, self.on_right(), self.on_left())
         };
+        let from_left = from == *self.left;
 
         let replay_key_cols = replay_key_cols.map(|cols| {
             cols.iter()
@@ -257,14 +341,14 @@ impl Ingredient for Join {
                     match self.emit[col] {
                         (true, l) if from == *self.left => l,
                         (false, r) if from == *self.right => r,
-                        (true, l) if l == self.on.0 => {
+                        (true, l) if self.on.iter().any(|&(ol, _)| ol == l) => {
                             // since we didn't hit the case above, we know that the message
                             // *isn't* from left.
-                            self.on.1
+                            self.on.iter().find(|&&(ol, _)| ol == l).unwrap().1
                         }
-                        (false, r) if r == self.on.1 => {
+                        (false, r) if self.on.iter().any(|&(_, or)| or == r) => {
                             // same
-                            self.on.0
+                            self.on.iter().find(|&&(_, or)| or == r).unwrap().0
                         }
                         _ => {
                             // we're getting a partial replay, but the replay key doesn't exist
@@ -276,35 +360,46 @@ impl Ingredient for Join {
                 .collect()
         });
 
+        let join_key =
+            |r: &[DataType]| -> Vec<DataType> { from_key.iter().map(|&c| r[c].clone()).collect() };
+        let has_join_key =
+            |r: &[DataType], key: &[DataType]| from_key.iter().zip(key).all(|(&c, k)| r[c] == *k);
+
         // First, we want to be smart about multiple added/removed rows with the same join key
         // value. For example, if we get a -, then a +, for the same key, we don't want to execute
         // two queries. We'll do this by sorting the batch by our join key.
         let mut rs: Vec<_> = rs.into();
         {
-            let cmp = |a: &Record, b: &Record| a[from_key].cmp(&b[from_key]);
+            let cmp = |a: &Record, b: &Record| {
+                from_key
+                    .iter()
+                    .map(|&c| a[c].cmp(&b[c]))
+                    .find(|o| *o != std::cmp::Ordering::Equal)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            };
             rs.sort_by(cmp);
         }
 
         let mut ret: Vec<Record> = Vec::with_capacity(rs.len());
         let mut at = 0;
         while at != rs.len() {
-            let mut old_right_count = None;
-            let mut new_right_count = None;
-            let prev_join_key = rs[at][from_key].clone();
+            let mut old_from_count = None;
+            let mut new_from_count = None;
+            let prev_join_key = join_key(&rs[at]);
 
-            if from == *self.right && self.kind == JoinType::Left {
+            if self.preserves(other) {
                 let rc = self
                     .lookup(
-                        *self.right,
-                        &[self.on.1],
-                        &KeyType::Single(&prev_join_key),
+                        from,
+                        &from_key,
+                        &KeyType::from(&prev_join_key[..]),
                         nodes,
                         state,
                     )
                     .unwrap();
 
                 if rc.is_none() {
-                    // we got something from right, but that row's key is not in right??
+                    // we got something from a side, but that row's key is not in that side??
                     //
                     // this *can* happen! imagine if you have two partial indices on right,
                     // one on column a and one on column b. imagine that a is the join key.
@@ -315,22 +410,22 @@ impl Ingredient for Join {
                     // (possibly several times over for each a).
                     at = rs[at..]
                         .iter()
-                        .position(|r| r[from_key] != prev_join_key)
+                        .position(|r| !has_join_key(r, &prev_join_key))
                         .map(|p| at + p)
                         .unwrap_or_else(|| rs.len());
                     continue;
                 } else {
                     if replay_key_cols.is_some() {
                         lookups.push(Lookup {
-                            on: *self.right,
-                            cols: vec![self.on.1],
-                            key: vec![prev_join_key.clone()],
+                            on: from,
+                            cols: from_key.clone(),
+                            key: prev_join_key.clone(),
                         });
                     }
 
                     let rc = rc.unwrap().count();
-                    old_right_count = Some(rc);
-                    new_right_count = Some(rc);
+                    old_from_count = Some(rc);
+                    new_from_count = Some(rc);
                 }
             }
 
@@ -338,8 +433,8 @@ impl Ingredient for Join {
             let mut other_rows = self
                 .lookup(
                     other,
-                    &[other_key],
-                    &KeyType::Single(&prev_join_key),
+                    &other_key,
+                    &KeyType::from(&prev_join_key[..]),
                     nodes,
                     state,
                 )
@@ -350,13 +445,13 @@ impl Ingredient for Join {
                 let from = at;
                 at = rs[at..]
                     .iter()
-                    .position(|r| r[from_key] != prev_join_key)
+                    .position(|r| !has_join_key(r, &prev_join_key))
                     .map(|p| at + p)
                     .unwrap_or_else(|| rs.len());
                 misses.extend((from..at).map(|i| Miss {
                     on: other,
-                    lookup_idx: vec![other_key],
-                    lookup_cols: vec![from_key],
+                    lookup_idx: other_key.clone(),
+                    lookup_cols: from_key.clone(),
                     replay_cols: replay_key_cols.clone(),
                     // NOTE: we're stealing data here!
                     record: mem::replace(&mut *rs[i], Vec::new()),
@@ -367,19 +462,19 @@ impl Ingredient for Join {
             if replay_key_cols.is_some() {
                 lookups.push(Lookup {
                     on: other,
-                    cols: vec![other_key],
-                    key: vec![prev_join_key.clone()],
+                    cols: other_key.clone(),
+                    key: prev_join_key.clone(),
                 });
             }
 
             let start = at;
             let mut make_null = None;
-            if self.kind == JoinType::Left && from == *self.right {
-                // If records are being received from the right, we need to find the number of
-                // records that existed *before* this batch of records was processed so we know
-                // whether or not to generate +/- NULL rows.
-                if let Some(mut old_rc) = old_right_count {
-                    while at != rs.len() && rs[at][from_key] == prev_join_key {
+            if self.preserves(other) {
+                // If the other side's rows are padded with NULLs when they have no match on this
+                // side, we need to find the number of records that existed *before* this batch of
+                // records was processed so we know whether or not to generate +/- NULL rows.
+                if let Some(mut old_rc) = old_from_count {
+                    while at != rs.len() && has_join_key(&rs[at], &prev_join_key) {
                         if rs[at].is_positive() {
                             old_rc -= 1
                         } else {
@@ -388,27 +483,27 @@ impl Ingredient for Join {
                         at += 1;
                     }
 
-                    // emit null rows if necessary for left join
-                    let new_rc = new_right_count.unwrap();
+                    // emit null rows if necessary for outer joins
+                    let new_rc = new_from_count.unwrap();
                     if new_rc == 0 && old_rc != 0 {
-                        // all lefts for this key must emit + NULLs
+                        // all others for this key must emit + NULLs
                         make_null = Some(true);
                     } else if new_rc != 0 && old_rc == 0 {
-                        // all lefts for this key must emit - NULLs
+                        // all others for this key must emit - NULLs
                         make_null = Some(false);
                     }
                 } else {
-                    // we got a right, but missed in right; clearly, a replay is needed
+                    // we got a row, but missed in our own side; clearly, a replay is needed
                     let start = at;
                     at = rs[at..]
                         .iter()
-                        .position(|r| r[from_key] != prev_join_key)
+                        .position(|r| !has_join_key(r, &prev_join_key))
                         .map(|p| at + p)
                         .unwrap_or_else(|| rs.len());
                     misses.extend((start..at).map(|i| Miss {
                         on: from,
-                        lookup_idx: vec![self.on.1],
-                        lookup_cols: vec![from_key],
+                        lookup_idx: from_key.clone(),
+                        lookup_cols: from_key.clone(),
                         replay_cols: replay_key_cols.clone(),
                         // NOTE: we're stealing data here!
                         record: mem::replace(&mut *rs[i], Vec::new()),
@@ -421,12 +516,13 @@ impl Ingredient for Join {
                 // we didn't find the end above, so find it now
                 at = rs[at..]
                     .iter()
-                    .position(|r| r[from_key] != prev_join_key)
+                    .position(|r| !has_join_key(r, &prev_join_key))
                     .map(|p| at + p)
                     .unwrap_or_else(|| rs.len());
             }
 
-            let mut other_rows_count = 0;
+            // where in `ret` the rows joined with each of the other side's rows are
+            let mut other_rows_at = Vec::new();
             for r in &mut rs[start..at] {
                 // put something bogus in rs (which will be discarded anyway) so we can take r.
                 let r = mem::replace(r, Record::Positive(Vec::new()));
@@ -436,9 +532,9 @@ impl Ingredient for Join {
                     // we have yet to iterate through other_rows
                     let mut other_rows = other_rows.peekable();
                     if other_rows.peek().is_none() {
-                        if self.kind == JoinType::Left && from == *self.left {
-                            // left join, got a thing from left, no rows in right == NULL
-                            ret.push((self.generate_null(&row), positive).into());
+                        if self.preserves(from) {
+                            // outer join, got a thing from a preserved side, no match == NULL
+                            ret.push((self.generate_null(&row, from_left), positive).into());
                         }
                         continue;
                     }
@@ -446,13 +542,13 @@ impl Ingredient for Join {
                     // we're going to pull a little trick here so that the *last* time we use
                     // `row`, we re-use its memory instead of allocating a new Vec. we do this by
                     // (ab)using .peek() to terminate the loop one iteration early.
-                    other_rows_count += 1;
                     let mut other = other_rows.next().unwrap();
                     while other_rows.peek().is_some() {
                         if let Some(false) = make_null {
-                            // we need to generate a -NULL for all these lefts
-                            ret.push((self.generate_null(&other), false).into());
+                            // we need to generate a -NULL for all these others
+                            ret.push((self.generate_null(&other, !from_left), false).into());
                         }
+                        other_rows_at.push(ret.len());
                         if from == *self.left {
                             ret.push(
                                 (
@@ -471,17 +567,17 @@ impl Ingredient for Join {
                             );
                         }
                         if let Some(true) = make_null {
-                            // we need to generate a +NULL for all these lefts
-                            ret.push((self.generate_null(&other), true).into());
+                            // we need to generate a +NULL for all these others
+                            ret.push((self.generate_null(&other, !from_left), true).into());
                         }
                         other = other_rows.next().unwrap();
-                        other_rows_count += 1;
                     }
 
                     if let Some(false) = make_null {
-                        // we need to generate a -NULL for the last left too
-                        ret.push((self.generate_null(&other), false).into());
+                        // we need to generate a -NULL for the last other too
+                        ret.push((self.generate_null(&other, !from_left), false).into());
                     }
+                    other_rows_at.push(ret.len());
                     ret.push(
                         (
                             self.regenerate_row(row, &other, from == *self.left, false),
@@ -490,21 +586,21 @@ impl Ingredient for Join {
                             .into(),
                     );
                     if let Some(true) = make_null {
-                        // we need to generate a +NULL for the last left too
-                        ret.push((self.generate_null(&other), true).into());
+                        // we need to generate a +NULL for the last other too
+                        ret.push((self.generate_null(&other, !from_left), true).into());
                     }
-                } else if other_rows_count == 0 {
-                    if self.kind == JoinType::Left && from == *self.left {
-                        // left join, got a thing from left, no rows in right == NULL
-                        ret.push((self.generate_null(&row), positive).into());
+                } else if other_rows_at.is_empty() {
+                    if self.preserves(from) {
+                        // outer join, got a thing from a preserved side, no match == NULL
+                        ret.push((self.generate_null(&row, from_left), positive).into());
                     }
                 } else {
                     // we no longer have access to `other_rows`
-                    // *but* the values are all in ret[-other_rows_count:]!
-                    let start = ret.len() - other_rows_count;
-                    let end = ret.len();
+                    // *but* the values are all in ret at other_rows_at! (they aren't necessarily
+                    // contiguous, since NULL rows may have been emitted in between)
+                    let (&last, rest) = other_rows_at.split_last().unwrap();
                     // we again use the trick above where the last row we produce reuses `row`
-                    for i in start..(end - 1) {
+                    for &i in rest {
                         if from == *self.left {
                             let r = (
                                 self.generate_row(&row, &ret[i], Preprocessed::Right),
@@ -522,7 +618,7 @@ impl Ingredient for Join {
                         }
                     }
                     let r = (
-                        self.regenerate_row(row, &ret[end - 1], from == *self.left, true),
+                        self.regenerate_row(row, &ret[last], from == *self.left, true),
                         positive,
                     )
                         .into();
@@ -540,8 +636,8 @@ impl Ingredient for Join {
 
     fn suggest_indexes(&self, _this: NodeIndex) -> HashMap<NodeIndex, Vec<usize>> {
         vec![
-            (self.left.as_global(), vec![self.on.0]),
-            (self.right.as_global(), vec![self.on.1]),
+            (self.left.as_global(), self.on_left()),
+            (self.right.as_global(), self.on_right()),
         ]
         .into_iter()
         .collect()
@@ -577,26 +673,38 @@ impl Ingredient for Join {
         let op = match self.kind {
             JoinType::Left => "⋉",
             JoinType::Inner => "⋈",
+            JoinType::Full => "⟗",
+        };
+
+        let cols = |cols: Vec<usize>| {
+            cols.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
         };
 
         format!(
             "[{}] {}:{} {} {}:{}",
             emit,
             self.left.as_global().index(),
-            self.on.0,
+            cols(self.on_left()),
             op,
             self.right.as_global().index(),
-            self.on.1
+            cols(self.on_right())
         )
     }
 
     fn parent_columns(&self, col: usize) -> Vec<(NodeIndex, Option<usize>)> {
         let pcol = self.emit[col];
-        if (pcol.0 && pcol.1 == self.on.0) || (!pcol.0 && pcol.1 == self.on.1) {
+        let on = self
+            .on
+            .iter()
+            .find(|&&(l, r)| (pcol.0 && pcol.1 == l) || (!pcol.0 && pcol.1 == r));
+        if let Some(&(l, r)) = on {
             // Join column comes from both parents
             vec![
-                (self.left.as_global(), Some(self.on.0)),
-                (self.right.as_global(), Some(self.on.1)),
+                (self.left.as_global(), Some(l)),
+                (self.right.as_global(), Some(r)),
             ]
         } else {
             vec![(
@@ -737,6 +845,79 @@ mod tests {
         assert_eq!(rs.len(), 0);
     }
 
+    #[test]
+    fn it_works_full() {
+        let mut j = ops::test::MockGraph::new();
+        let l = j.add_base("left", &["l0", "l1"]);
+        let r = j.add_base("right", &["r0", "r1"]);
+
+        use self::JoinSource::*;
+        let join = Join::new(
+            l.as_global(),
+            r.as_global(),
+            JoinType::Full,
+            vec![B(0, 0), L(1), R(1)],
+        );
+        j.set_op("join", &["j0", "j1", "j2"], join, false);
+        assert_eq!(
+            j.node().description(true),
+            format!("[{}:0, {}:1, {}:1] {}:0 ⟗ {}:0", l, l, r, l, r)
+        );
+
+        // a left row without a match is padded with NULLs on the right
+        let l_a1 = vec![1.into(), "a".into()];
+        j.seed(l, l_a1.clone());
+        let rs = j.one_row(l, l_a1.clone(), false);
+        assert_eq!(
+            rs,
+            vec![(vec![1.into(), "a".into(), DataType::None], true)].into()
+        );
+
+        // and so is a right row, which still carries the join column
+        let r_x2 = vec![2.into(), "x".into()];
+        j.seed(r, r_x2.clone());
+        let rs = j.one_row(r, r_x2.clone(), false);
+        assert_eq!(
+            rs,
+            vec![(vec![2.into(), DataType::None, "x".into()], true)].into()
+        );
+
+        // the first match from the right revokes the left's NULL row
+        let r_y1 = vec![1.into(), "y".into()];
+        j.seed(r, r_y1.clone());
+        let rs = j.one_row(r, r_y1.clone(), false);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![1.into(), "a".into(), DataType::None], false),
+                (vec![1.into(), "a".into(), "y".into()], true),
+            ]
+            .into()
+        );
+
+        // and the first match from the left revokes the right's NULL row
+        let l_b2 = vec![2.into(), "b".into()];
+        j.seed(l, l_b2.clone());
+        let rs = j.one_row(l, l_b2.clone(), false);
+        assert_eq!(
+            rs,
+            vec![
+                (vec![2.into(), DataType::None, "x".into()], false),
+                (vec![2.into(), "b".into(), "x".into()], true),
+            ]
+            .into()
+        );
+
+        // further matches don't touch the NULL rows
+        let l_c2 = vec![2.into(), "c".into()];
+        j.seed(l, l_c2.clone());
+        let rs = j.one_row(l, l_c2.clone(), false);
+        assert_eq!(
+            rs,
+            vec![(vec![2.into(), "c".into(), "x".into()], true)].into()
+        );
+    }
+
     #[test]
     fn it_suggests_indices() {
         use std::collections::HashMap;
@@ -751,6 +932,64 @@ mod tests {
         assert_eq!(g.node().suggest_indexes(me), hm);
     }
 
+    #[test]
+    fn it_works_with_multiple_columns() {
+        let mut g = ops::test::MockGraph::new();
+        let l = g.add_base("left", &["l0", "l1", "l2"]);
+        let r = g.add_base("right", &["r0", "r1", "r2"]);
+
+        use self::JoinSource::*;
+        let j = Join::new(
+            l.as_global(),
+            r.as_global(),
+            JoinType::Inner,
+            vec![B(0, 0), B(1, 1), L(2), R(2)],
+        );
+        g.set_op("join", &["j0", "j1", "j2", "j3"], j, false);
+
+        let me = 2.into();
+        let hm: HashMap<_, _> = vec![(l.as_global(), vec![0, 1]), (r.as_global(), vec![0, 1])]
+            .into_iter()
+            .collect();
+        assert_eq!(g.node().suggest_indexes(me), hm);
+        assert_eq!(g.node().resolve(1), Some(vec![(l.as_global(), 1)]));
+
+        let r_1a = vec![1.into(), "a".into(), "x".into()];
+        let r_1b = vec![1.into(), "b".into(), "y".into()];
+        g.seed(r, r_1a.clone());
+        g.seed(r, r_1b.clone());
+        g.one_row(r, r_1a.clone(), false);
+        g.one_row(r, r_1b.clone(), false);
+
+        // only the right row that matches on *both* columns should join
+        let l_1a = vec![1.into(), "a".into(), "u".into()];
+        g.seed(l, l_1a.clone());
+        let rs = g.one_row(l, l_1a.clone(), false);
+        assert_eq!(
+            rs,
+            vec![(vec![1.into(), "a".into(), "u".into(), "x".into()], true)].into()
+        );
+
+        // a left row matching on just one of the columns shouldn't join at all
+        let l_2a = vec![2.into(), "a".into(), "v".into()];
+        g.seed(l, l_2a.clone());
+        let rs = g.one_row(l, l_2a.clone(), false);
+        assert_eq!(rs.len(), 0);
+
+        // rows in one batch with different keys are joined separately
+        let r_batch: Vec<Record> = vec![
+            (vec![1.into(), "b".into(), "z".into()], true).into(),
+            (vec![1.into(), "a".into(), "w".into()], true).into(),
+        ];
+        g.seed(r, vec![1.into(), "b".into(), "z".into()]);
+        g.seed(r, vec![1.into(), "a".into(), "w".into()]);
+        let rs = g.one(r, r_batch, false);
+        assert_eq!(
+            rs,
+            vec![(vec![1.into(), "a".into(), "u".into(), "w".into()], true)].into()
+        );
+    }
+
     #[test]
     fn it_resolves() {
         let (g, l, r) = setup();
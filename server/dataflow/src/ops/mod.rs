@@ -6,12 +6,15 @@ use crate::prelude::*;
 
 pub mod distinct;
 pub mod filter;
+pub mod function;
 pub mod grouped;
 pub mod identity;
 pub mod join;
 pub mod latest;
 pub mod project;
 pub mod rewrite;
+pub mod semi_join;
+pub mod theta_join;
 pub mod topk;
 pub mod trigger;
 pub mod union;
@@ -24,6 +27,8 @@ pub enum NodeOperator {
     Concat(grouped::GroupedOperator<grouped::concat::GroupConcat>),
     FilterSum(grouped::GroupedOperator<grouped::filteraggregate::FilterAggregator>),
     Join(join::Join),
+    ThetaJoin(theta_join::ThetaJoin),
+    SemiJoin(semi_join::SemiJoin),
     Latest(latest::Latest),
     Project(project::Project),
     Union(union::Union),
@@ -62,6 +67,8 @@ nodeop_from_impl!(
     grouped::GroupedOperator<grouped::filteraggregate::FilterAggregator>
 );
 nodeop_from_impl!(NodeOperator::Join, join::Join);
+nodeop_from_impl!(NodeOperator::ThetaJoin, theta_join::ThetaJoin);
+nodeop_from_impl!(NodeOperator::SemiJoin, semi_join::SemiJoin);
 nodeop_from_impl!(NodeOperator::Latest, latest::Latest);
 nodeop_from_impl!(NodeOperator::Project, project::Project);
 nodeop_from_impl!(NodeOperator::Union, union::Union);
@@ -80,6 +87,8 @@ macro_rules! impl_ingredient_fn_mut {
             NodeOperator::Concat(ref mut i) => i.$fn($($arg),*),
             NodeOperator::FilterSum(ref mut i) => i.$fn($($arg),*),
             NodeOperator::Join(ref mut i) => i.$fn($($arg),*),
+            NodeOperator::ThetaJoin(ref mut i) => i.$fn($($arg),*),
+            NodeOperator::SemiJoin(ref mut i) => i.$fn($($arg),*),
             NodeOperator::Latest(ref mut i) => i.$fn($($arg),*),
             NodeOperator::Project(ref mut i) => i.$fn($($arg),*),
             NodeOperator::Union(ref mut i) => i.$fn($($arg),*),
@@ -101,6 +110,8 @@ macro_rules! impl_ingredient_fn_ref {
             NodeOperator::Concat(ref i) => i.$fn($($arg),*),
             NodeOperator::FilterSum(ref i) => i.$fn($($arg),*),
             NodeOperator::Join(ref i) => i.$fn($($arg),*),
+            NodeOperator::ThetaJoin(ref i) => i.$fn($($arg),*),
+            NodeOperator::SemiJoin(ref i) => i.$fn($($arg),*),
             NodeOperator::Latest(ref i) => i.$fn($($arg),*),
             NodeOperator::Project(ref i) => i.$fn($($arg),*),
             NodeOperator::Union(ref i) => i.$fn($($arg),*),
@@ -223,6 +234,12 @@ impl Ingredient for NodeOperator {
     fn requires_full_materialization(&self) -> bool {
         impl_ingredient_fn_ref!(self, requires_full_materialization,)
     }
+    fn can_replay_partially(&self) -> bool {
+        impl_ingredient_fn_ref!(self, can_replay_partially,)
+    }
+    fn auxiliary_state_size(&self) -> u64 {
+        impl_ingredient_fn_ref!(self, auxiliary_state_size,)
+    }
 }
 
 #[cfg(test)]
@@ -419,7 +436,7 @@ pub mod test {
             struct Ex;
 
             impl Executor for Ex {
-                fn ack(&mut self, _: SourceChannelIdentifier) {}
+                fn ack(&mut self, _: SourceChannelIdentifier, _: WriteReply) {}
                 fn create_universe(&mut self, _: HashMap<String, DataType>) {}
                 fn send(&mut self, _: ReplicaAddr, _: Box<Packet>) {}
             }
@@ -1,53 +1,267 @@
-use nom_sql::ArithmeticOperator;
+use nom_sql::{ArithmeticOperator, Operator};
 
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt;
 
+use crate::ops::filter::{Pattern, PatternOperator};
+use crate::ops::function::BuiltinFunction;
 use crate::prelude::*;
 
+/// An expression computed over the columns of a `Project`'s source node.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ProjectExpressionBase {
+pub enum ProjectExpression {
+    /// The value of the given column of the source node
     Column(usize),
+    /// A constant value
     Literal(DataType),
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ProjectExpression {
-    op: ArithmeticOperator,
-    left: ProjectExpressionBase,
-    right: ProjectExpressionBase,
+    /// An arithmetic operation on the values of two expressions
+    Op {
+        op: ArithmeticOperator,
+        left: Box<ProjectExpression>,
+        right: Box<ProjectExpression>,
+    },
+    /// The negated value of an expression
+    Negate(Box<ProjectExpression>),
+    /// A comparison (or `AND`/`OR`) of two expressions, which is 1 if true, 0 if false and
+    /// `NULL` if either side is `NULL`
+    Condition {
+        op: Operator,
+        left: Box<ProjectExpression>,
+        right: Box<ProjectExpression>,
+    },
+    /// A call to a built-in scalar function
+    Call {
+        function: BuiltinFunction,
+        arguments: Vec<ProjectExpression>,
+    },
+    /// `CASE WHEN`: the value of the first branch whose condition is true, or else the value of
+    /// `otherwise`
+    Case {
+        branches: Vec<(ProjectExpression, ProjectExpression)>,
+        otherwise: Box<ProjectExpression>,
+    },
 }
 
 impl ProjectExpression {
     pub fn new(
         op: ArithmeticOperator,
-        left: ProjectExpressionBase,
-        right: ProjectExpressionBase,
+        left: ProjectExpression,
+        right: ProjectExpression,
     ) -> ProjectExpression {
-        ProjectExpression { op, left, right }
+        ProjectExpression::Op {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
     }
-}
 
-impl fmt::Display for ProjectExpressionBase {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    pub fn negate(e: ProjectExpression) -> ProjectExpression {
+        ProjectExpression::Negate(Box::new(e))
+    }
+
+    pub fn condition(
+        op: Operator,
+        left: ProjectExpression,
+        right: ProjectExpression,
+    ) -> ProjectExpression {
+        assert!(op != Operator::Not, "NOT can't compare two expressions");
+        ProjectExpression::Condition {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    pub fn call(function: BuiltinFunction, arguments: Vec<ProjectExpression>) -> ProjectExpression {
+        assert!(
+            function.accepts(arguments.len()),
+            "{} can't take {} arguments",
+            function,
+            arguments.len()
+        );
+        ProjectExpression::Call {
+            function,
+            arguments,
+        }
+    }
+
+    pub fn case(
+        branches: Vec<(ProjectExpression, ProjectExpression)>,
+        otherwise: ProjectExpression,
+    ) -> ProjectExpression {
+        ProjectExpression::Case {
+            branches,
+            otherwise: Box::new(otherwise),
+        }
+    }
+
+    fn eval(&self, record: &[DataType]) -> DataType {
         match *self {
-            ProjectExpressionBase::Column(u) => write!(f, "{}", u),
-            ProjectExpressionBase::Literal(ref l) => write!(f, "(lit: {})", l),
+            ProjectExpression::Column(i) => record[i].clone(),
+            ProjectExpression::Literal(ref data) => data.clone(),
+            ProjectExpression::Op {
+                ref op,
+                ref left,
+                ref right,
+            } => {
+                let left = left.eval(record);
+                let right = right.eval(record);
+                // values that can't be combined, or whose result can't be represented, are NULL
+                match *op {
+                    ArithmeticOperator::Add => left.checked_add(&right),
+                    ArithmeticOperator::Subtract => left.checked_sub(&right),
+                    ArithmeticOperator::Multiply => left.checked_mul(&right),
+                    ArithmeticOperator::Divide => left.checked_div(&right),
+                }
+                .unwrap_or(DataType::None)
+            }
+            // values that can't be negated, or whose negation can't be represented, are NULL
+            ProjectExpression::Negate(ref e) => {
+                e.eval(record).checked_neg().unwrap_or(DataType::None)
+            }
+            ProjectExpression::Condition {
+                ref op,
+                ref left,
+                ref right,
+            } => {
+                let left = left.eval(record);
+                let right = right.eval(record);
+                let result = match *op {
+                    Operator::And => match (truth(&left), truth(&right)) {
+                        (Some(false), _) | (_, Some(false)) => Some(false),
+                        (Some(true), Some(true)) => Some(true),
+                        _ => None,
+                    },
+                    Operator::Or => match (truth(&left), truth(&right)) {
+                        (Some(true), _) | (_, Some(true)) => Some(true),
+                        (Some(false), Some(false)) => Some(false),
+                        _ => None,
+                    },
+                    // `IS` compares NULLs like any other value
+                    Operator::Is => Some(left == right),
+                    _ if left.is_none() || right.is_none() => None,
+                    Operator::Equal | Operator::In => Some(left == right),
+                    Operator::NotEqual => Some(left != right),
+                    Operator::Greater => Some(left > right),
+                    Operator::GreaterOrEqual => Some(left >= right),
+                    Operator::Less => Some(left < right),
+                    Operator::LessOrEqual => Some(left <= right),
+                    Operator::Like | Operator::NotLike if left.is_string() && right.is_string() => {
+                        // the pattern may differ for every record
+                        let negated = *op == Operator::NotLike;
+                        Pattern::new(PatternOperator::Like, negated, (&right).into())
+                            .ok()
+                            .map(|p| p.matches(&left))
+                    }
+                    Operator::Like | Operator::NotLike => None,
+                    Operator::Not => unreachable!("NOT can't compare two expressions"),
+                };
+                result.map(|b| b as i32).into()
+            }
+            ProjectExpression::Call {
+                ref function,
+                ref arguments,
+            } => {
+                let args: Vec<_> = arguments.iter().map(|a| a.eval(record)).collect();
+                function.eval(&args)
+            }
+            ProjectExpression::Case {
+                ref branches,
+                ref otherwise,
+            } => branches
+                .iter()
+                .find(|(cond, _)| truth(&cond.eval(record)) == Some(true))
+                .map(|(_, e)| e)
+                .unwrap_or(&**otherwise)
+                .eval(record),
+        }
+    }
+
+    /// Formats the expression as an operand of another one, which needs parentheses unless it's
+    /// a single value.
+    fn fmt_operand(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProjectExpression::Op { .. } | ProjectExpression::Condition { .. } => {
+                write!(f, "({})", self)
+            }
+            _ => write!(f, "{}", self),
         }
     }
 }
 
 impl fmt::Display for ProjectExpression {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let op = match self.op {
-            ArithmeticOperator::Add => "+",
-            ArithmeticOperator::Subtract => "-",
-            ArithmeticOperator::Divide => "/",
-            ArithmeticOperator::Multiply => "*",
-        };
+        match *self {
+            ProjectExpression::Column(u) => write!(f, "{}", u),
+            ProjectExpression::Literal(ref l) => write!(f, "(lit: {})", l),
+            ProjectExpression::Op {
+                ref op,
+                ref left,
+                ref right,
+            } => {
+                let op = match *op {
+                    ArithmeticOperator::Add => "+",
+                    ArithmeticOperator::Subtract => "-",
+                    ArithmeticOperator::Divide => "/",
+                    ArithmeticOperator::Multiply => "*",
+                };
+                left.fmt_operand(f)?;
+                write!(f, " {} ", op)?;
+                right.fmt_operand(f)
+            }
+            ProjectExpression::Negate(ref e) => {
+                write!(f, "-")?;
+                e.fmt_operand(f)
+            }
+            ProjectExpression::Condition {
+                ref op,
+                ref left,
+                ref right,
+            } => {
+                left.fmt_operand(f)?;
+                write!(f, " {} ", op)?;
+                right.fmt_operand(f)
+            }
+            ProjectExpression::Call {
+                ref function,
+                ref arguments,
+            } => write!(
+                f,
+                "{}({})",
+                function,
+                arguments
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            ProjectExpression::Case {
+                ref branches,
+                ref otherwise,
+            } => {
+                write!(f, "CASE")?;
+                for &(ref cond, ref e) in branches {
+                    write!(f, " WHEN {} THEN {}", cond, e)?;
+                }
+                write!(f, " ELSE {} END", otherwise)
+            }
+        }
+    }
+}
 
-        write!(f, "{} {} {}", self.left, op, self.right)
+/// The truth value of a condition, which is unknown for `NULL`.
+fn truth(d: &DataType) -> Option<bool> {
+    match *d {
+        DataType::None => None,
+        DataType::Bool(b) => Some(b),
+        DataType::Real(..) => Some(f64::from(d) != 0.0),
+        DataType::Decimal(m, _) => Some(m != 0),
+        DataType::Int(..)
+        | DataType::UnsignedInt(..)
+        | DataType::BigInt(..)
+        | DataType::UnsignedBigInt(..) => Some(i128::from(d) != 0),
+        _ => Some(true),
     }
 }
 
@@ -100,25 +314,6 @@ impl Project {
     }
 }
 
-fn eval_expression(expression: &ProjectExpression, record: &[DataType]) -> DataType {
-    let left = match expression.left {
-        ProjectExpressionBase::Column(i) => &record[i],
-        ProjectExpressionBase::Literal(ref data) => data,
-    };
-
-    let right = match expression.right {
-        ProjectExpressionBase::Column(i) => &record[i],
-        ProjectExpressionBase::Literal(ref data) => data,
-    };
-
-    match expression.op {
-        ArithmeticOperator::Add => left + right,
-        ArithmeticOperator::Subtract => left - right,
-        ArithmeticOperator::Multiply => left * right,
-        ArithmeticOperator::Divide => left / right,
-    }
-}
-
 impl Ingredient for Project {
     fn take(&mut self) -> NodeOperator {
         Clone::clone(self).into()
@@ -168,7 +363,7 @@ impl Ingredient for Project {
                         Some(emit) => Box::new(rs.map(move |r| {
                             let mut new_r = Vec::with_capacity(r.len());
                             let mut expr: Vec<DataType> = if let Some(ref e) = expressions {
-                                e.iter().map(|i| eval_expression(i, &r[..])).collect()
+                                e.iter().map(|i| i.eval(&r[..])).collect()
                             } else {
                                 vec![]
                             };
@@ -239,7 +434,7 @@ impl Ingredient for Project {
                 }
 
                 if let Some(ref e) = self.expressions {
-                    new_r.extend(e.iter().map(|i| eval_expression(i, &r[..])));
+                    new_r.extend(e.iter().map(|i| i.eval(&r[..])));
                 }
 
                 if let Some(ref a) = self.additional {
@@ -351,11 +546,11 @@ mod tests {
     }
 
     fn setup_column_arithmetic(op: ArithmeticOperator) -> ops::test::MockGraph {
-        let expression = ProjectExpression {
-            left: ProjectExpressionBase::Column(0),
-            right: ProjectExpressionBase::Column(1),
+        let expression = ProjectExpression::new(
             op,
-        };
+            ProjectExpression::Column(0),
+            ProjectExpression::Column(1),
+        );
 
         setup_arithmetic(expression)
     }
@@ -473,11 +668,11 @@ mod tests {
     #[test]
     fn it_forwards_arithmetic_w_literals() {
         let number: DataType = 40.into();
-        let expression = ProjectExpression {
-            left: ProjectExpressionBase::Column(0),
-            right: ProjectExpressionBase::Literal(number),
-            op: ArithmeticOperator::Multiply,
-        };
+        let expression = ProjectExpression::new(
+            ArithmeticOperator::Multiply,
+            ProjectExpression::Column(0),
+            ProjectExpression::Literal(number),
+        );
 
         let mut p = setup_arithmetic(expression);
         let rec = vec![10.into(), 0.into()];
@@ -491,11 +686,11 @@ mod tests {
     fn it_forwards_arithmetic_w_only_literals() {
         let a: DataType = 80.into();
         let b: DataType = 40.into();
-        let expression = ProjectExpression {
-            left: ProjectExpressionBase::Literal(a),
-            right: ProjectExpressionBase::Literal(b),
-            op: ArithmeticOperator::Divide,
-        };
+        let expression = ProjectExpression::new(
+            ArithmeticOperator::Divide,
+            ProjectExpression::Literal(a),
+            ProjectExpression::Literal(b),
+        );
 
         let mut p = setup_arithmetic(expression);
         let rec = vec![0.into(), 0.into()];
@@ -505,6 +700,189 @@ mod tests {
         );
     }
 
+    fn nested_expression() -> ProjectExpression {
+        // -((x - 2) * y) + 1
+        ProjectExpression::new(
+            ArithmeticOperator::Add,
+            ProjectExpression::negate(ProjectExpression::new(
+                ArithmeticOperator::Multiply,
+                ProjectExpression::new(
+                    ArithmeticOperator::Subtract,
+                    ProjectExpression::Column(0),
+                    ProjectExpression::Literal(2.into()),
+                ),
+                ProjectExpression::Column(1),
+            )),
+            ProjectExpression::Literal(1.into()),
+        )
+    }
+
+    #[test]
+    fn it_describes_nested_arithmetic() {
+        let p = setup_arithmetic(nested_expression());
+        assert_eq!(
+            p.node().description(true),
+            "π[0, 1, -((0 - (lit: 2)) * 1) + (lit: 1)]"
+        );
+    }
+
+    #[test]
+    fn it_forwards_nested_arithmetic() {
+        let mut p = setup_arithmetic(nested_expression());
+        let rec = vec![10.into(), 3.into()];
+        assert_eq!(
+            p.narrow_one_row(rec, false),
+            vec![vec![10.into(), 3.into(), (-23).into()]].into()
+        );
+
+        // NULL propagates through the whole expression
+        let rec = vec![DataType::None, 3.into()];
+        assert_eq!(
+            p.narrow_one_row(rec, false),
+            vec![vec![DataType::None, 3.into(), DataType::None]].into()
+        );
+    }
+
+    #[test]
+    fn it_forwards_invalid_arithmetic_as_null() {
+        let mut p = setup_arithmetic(ProjectExpression::new(
+            ArithmeticOperator::Divide,
+            ProjectExpression::Column(0),
+            ProjectExpression::Column(1),
+        ));
+        for (x, y) in vec![
+            (DataType::Int(1), DataType::Int(0)),
+            (DataType::Int(std::i32::MIN), DataType::Int(-1)),
+            ("hi".into(), DataType::Int(2)),
+            (DataType::Int(2), DataType::Bool(true)),
+        ] {
+            assert_eq!(
+                p.narrow_one_row(vec![x.clone(), y.clone()], false),
+                vec![vec![x, y, DataType::None]].into()
+            );
+        }
+
+        let mut p = setup_arithmetic(ProjectExpression::new(
+            ArithmeticOperator::Multiply,
+            ProjectExpression::Column(0),
+            ProjectExpression::Column(1),
+        ));
+        let rec = vec![DataType::BigInt(std::i64::MAX), DataType::Int(2)];
+        assert_eq!(
+            p.narrow_one_row(rec.clone(), false),
+            vec![vec![rec[0].clone(), rec[1].clone(), DataType::None]].into()
+        );
+    }
+
+    fn case_expression() -> ProjectExpression {
+        // CASE WHEN x > 10 AND coalesce(y, '') != '' THEN upper(y) ELSE coalesce(y, 'none') END
+        ProjectExpression::case(
+            vec![(
+                ProjectExpression::condition(
+                    Operator::And,
+                    ProjectExpression::condition(
+                        Operator::Greater,
+                        ProjectExpression::Column(0),
+                        ProjectExpression::Literal(10.into()),
+                    ),
+                    ProjectExpression::condition(
+                        Operator::NotEqual,
+                        ProjectExpression::call(
+                            BuiltinFunction::Coalesce,
+                            vec![
+                                ProjectExpression::Column(1),
+                                ProjectExpression::Literal("".into()),
+                            ],
+                        ),
+                        ProjectExpression::Literal("".into()),
+                    ),
+                ),
+                ProjectExpression::call(BuiltinFunction::Upper, vec![ProjectExpression::Column(1)]),
+            )],
+            ProjectExpression::call(
+                BuiltinFunction::Coalesce,
+                vec![
+                    ProjectExpression::Column(1),
+                    ProjectExpression::Literal("none".into()),
+                ],
+            ),
+        )
+    }
+
+    #[test]
+    fn it_computes_conditions() {
+        let cond = |op, right: DataType| {
+            ProjectExpression::condition(
+                op,
+                ProjectExpression::Column(1),
+                ProjectExpression::Literal(right),
+            )
+        };
+        let mut p = setup_arithmetic(ProjectExpression::case(
+            vec![
+                (cond(Operator::Is, DataType::None), "null".into()),
+                (cond(Operator::Like, "a%".into()), "a".into()),
+                (cond(Operator::NotLike, "%c".into()), "not c".into()),
+            ],
+            ProjectExpression::Literal("c".into()),
+        ));
+
+        for (y, expected) in vec![
+            (DataType::None, "null"),
+            ("ab".into(), "a"),
+            ("bb".into(), "not c"),
+            ("bc".into(), "c"),
+        ] {
+            let rec = vec![1.into(), y.clone()];
+            assert_eq!(
+                p.narrow_one_row(rec, false),
+                vec![vec![1.into(), y, expected.into()]].into()
+            );
+        }
+    }
+
+    #[test]
+    fn it_describes_functions() {
+        let p = setup_arithmetic(case_expression());
+        assert_eq!(
+            p.node().description(true),
+            "π[0, 1, CASE WHEN (0 > (lit: 10)) AND (coalesce(1, (lit: \"\")) != (lit: \"\")) \
+             THEN upper(1) ELSE coalesce(1, (lit: \"none\")) END]"
+        );
+    }
+
+    #[test]
+    fn it_forwards_functions() {
+        let mut p = setup_arithmetic(case_expression());
+        let rec = vec![20.into(), "ab".into()];
+        assert_eq!(
+            p.narrow_one_row(rec, false),
+            vec![vec![20.into(), "ab".into(), "AB".into()]].into()
+        );
+
+        let rec = vec![5.into(), "ab".into()];
+        assert_eq!(
+            p.narrow_one_row(rec, false),
+            vec![vec![5.into(), "ab".into(), "ab".into()]].into()
+        );
+
+        // NULL inputs don't select the branch
+        let rec = vec![DataType::None, DataType::None];
+        assert_eq!(
+            p.narrow_one_row(rec, false),
+            vec![vec![DataType::None, DataType::None, "none".into()]].into()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "upper can't take 2 arguments")]
+    fn it_checks_function_arity() {
+        ProjectExpression::call(
+            BuiltinFunction::Upper,
+            vec![ProjectExpression::Column(0), ProjectExpression::Column(1)],
+        );
+    }
+
     fn setup_query_through(
         mut state: Box<dyn State>,
         permutation: &[usize],
@@ -617,11 +995,11 @@ mod tests {
     #[test]
     fn it_queries_through_w_arithmetic_and_literals() {
         let additional = Some(vec![DataType::Int(42)]);
-        let expressions = Some(vec![ProjectExpression {
-            left: ProjectExpressionBase::Column(0),
-            right: ProjectExpressionBase::Column(1),
-            op: ArithmeticOperator::Add,
-        }]);
+        let expressions = Some(vec![ProjectExpression::new(
+            ArithmeticOperator::Add,
+            ProjectExpression::Column(0),
+            ProjectExpression::Column(1),
+        )]);
 
         let state = Box::new(MemoryState::default());
         let (p, states) = setup_query_through(state, &[1], additional, expressions);
@@ -632,11 +1010,11 @@ mod tests {
     #[test]
     fn it_queries_through_w_arithmetic_and_literals_persistent() {
         let additional = Some(vec![DataType::Int(42)]);
-        let expressions = Some(vec![ProjectExpression {
-            left: ProjectExpressionBase::Column(0),
-            right: ProjectExpressionBase::Column(1),
-            op: ArithmeticOperator::Add,
-        }]);
+        let expressions = Some(vec![ProjectExpression::new(
+            ArithmeticOperator::Add,
+            ProjectExpression::Column(0),
+            ProjectExpression::Column(1),
+        )]);
 
         let state = Box::new(PersistentState::new(
             String::from("it_queries_through_w_arithmetic_and_literals_persistent"),
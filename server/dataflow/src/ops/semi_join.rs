@@ -0,0 +1,517 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::prelude::*;
+
+/// Kind of semi-join
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SemiJoinType {
+    /// Keep the left rows that have a match on the right
+    Semi,
+    /// Keep the left rows that have no match on the right
+    Anti,
+    /// Keep the left rows whose key is not among the right's keys, like SQL's `NOT IN`. Comparing
+    /// with a NULL is unknown rather than false, so while the right has a row with a NULL key no
+    /// left row is kept, and left rows with a NULL key are only kept while the right is empty.
+    NotIn,
+}
+
+/// The number of rows the right parent of a `NOT IN` semi-join has, in all and with a NULL key.
+#[derive(Debug, Clone, Copy, Default)]
+struct RightRows {
+    total: isize,
+    nulls: isize,
+}
+
+/// SemiJoin emits the rows of its left parent depending on whether they match any row of its
+/// right parent; none of the right parent's columns are emitted.
+///
+/// Rows match when they agree on all of the key columns, and a row with a NULL key column never
+/// matches. When a key gains its first or loses its last row on the right, the left rows with
+/// that key are added or retracted accordingly. A `NOT IN` semi-join also keeps track of how many
+/// rows the right parent has, and reconsiders all of the left rows when it gains its first row or
+/// its first row with a NULL key, or loses its last one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SemiJoin {
+    left: IndexPair,
+    right: IndexPair,
+
+    // Key columns in the left and right parents respectively
+    on: Vec<(usize, usize)>,
+
+    kind: SemiJoinType,
+
+    // The right parent's rows for a `NOT IN` semi-join. Counted from the right parent's state the
+    // first time they are needed.
+    #[serde(skip)]
+    right_rows: Option<RightRows>,
+}
+
+impl SemiJoin {
+    /// Create a new semi-join.
+    ///
+    /// `on` gives the `(left_parent_column, right_parent_column)` pairs that rows must agree on to
+    /// match.
+    pub fn new(
+        left: NodeIndex,
+        right: NodeIndex,
+        kind: SemiJoinType,
+        on: Vec<(usize, usize)>,
+    ) -> Self {
+        assert!(
+            !on.is_empty(),
+            "semi-join must have at least one key column"
+        );
+        assert!(
+            kind != SemiJoinType::NotIn || on.len() == 1,
+            "NOT IN semi-join must have a single key column"
+        );
+
+        SemiJoin {
+            left: left.into(),
+            right: right.into(),
+            on,
+            kind,
+            right_rows: None,
+        }
+    }
+
+    fn on_left(&self) -> Vec<usize> {
+        self.on.iter().map(|&(l, _)| l).collect()
+    }
+
+    fn on_right(&self) -> Vec<usize> {
+        self.on.iter().map(|&(_, r)| r).collect()
+    }
+
+    /// Whether left rows with a key that has `matches` rows on the right are emitted, given the
+    /// rows that the right has in all. A NULL key is given as `None`.
+    fn keeps(&self, matches: Option<usize>, right: RightRows) -> bool {
+        match self.kind {
+            SemiJoinType::Semi => matches.unwrap_or(0) != 0,
+            SemiJoinType::Anti => matches.unwrap_or(0) == 0,
+            SemiJoinType::NotIn => right.total == 0 || (right.nulls == 0 && matches == Some(0)),
+        }
+    }
+
+    /// The right parent's rows, which are only counted for a `NOT IN` semi-join.
+    fn right_rows(&mut self, state: &StateMap) -> RightRows {
+        if self.kind != SemiJoinType::NotIn {
+            return RightRows::default();
+        }
+        if let Some(rows) = self.right_rows {
+            return rows;
+        }
+
+        let col = self.on[0].1;
+        let rows = state
+            .get(*self.right)
+            .expect("semi-join parents must be fully materialized")
+            .cloned_records();
+        let rows = RightRows {
+            total: rows.len() as isize,
+            nulls: rows.iter().filter(|r| r[col].is_none()).count() as isize,
+        };
+        self.right_rows = Some(rows);
+        rows
+    }
+
+    /// The number of rows `parent` has for `key`.
+    fn count(
+        &self,
+        parent: LocalNodeIndex,
+        cols: &[usize],
+        key: &[DataType],
+        nodes: &DomainNodes,
+        state: &StateMap,
+    ) -> usize {
+        self.lookup(parent, cols, &KeyType::from(key), nodes, state)
+            .flatten()
+            .expect("semi-join parents must be fully materialized")
+            .count()
+    }
+}
+
+impl Ingredient for SemiJoin {
+    fn take(&mut self) -> NodeOperator {
+        Clone::clone(self).into()
+    }
+
+    fn ancestors(&self) -> Vec<NodeIndex> {
+        vec![self.left.as_global(), self.right.as_global()]
+    }
+
+    fn is_join(&self) -> bool {
+        true
+    }
+
+    fn must_replay_among(&self) -> Option<HashSet<NodeIndex>> {
+        // only the left side's rows are emitted
+        Some(Some(self.left.as_global()).into_iter().collect())
+    }
+
+    fn can_replay_partially(&self) -> bool {
+        // whether a left row is emitted depends on all of the right rows with its key, which a
+        // replay through the left side would not bring along
+        false
+    }
+
+    fn on_connected(&mut self, _g: &Graph) {}
+
+    fn on_commit(&mut self, _: NodeIndex, remap: &HashMap<NodeIndex, IndexPair>) {
+        self.left.remap(remap);
+        self.right.remap(remap);
+    }
+
+    fn on_input(
+        &mut self,
+        _: &mut dyn Executor,
+        from: LocalNodeIndex,
+        rs: Records,
+        _: Option<&[usize]>,
+        nodes: &DomainNodes,
+        state: &StateMap,
+    ) -> ProcessingResult {
+        let (left_key, right_key) = (self.on_left(), self.on_right());
+        let key_of = |r: &[DataType], cols: &[usize]| -> Option<Vec<DataType>> {
+            let key: Vec<_> = cols.iter().map(|&c| r[c].clone()).collect();
+            if key.iter().any(DataType::is_none) {
+                None
+            } else {
+                Some(key)
+            }
+        };
+
+        if from == *self.left {
+            // left rows pass through if the right side's rows for their key say so
+            let right = self.right_rows(state);
+            let mut matches = HashMap::new();
+            let results = rs
+                .into_iter()
+                .filter(|r| {
+                    let n = key_of(r, &left_key).map(|key| {
+                        *matches.entry(key).or_insert_with_key(|key| {
+                            self.count(*self.right, &right_key, key, nodes, state)
+                        })
+                    });
+                    self.keeps(n, right)
+                })
+                .collect();
+
+            return ProcessingResult {
+                results,
+                ..Default::default()
+            };
+        }
+
+        // the right parent's state already reflects these records, so the number of rows it had
+        // for each key before them is found by undoing them
+        let mut deltas: HashMap<Vec<DataType>, isize> = HashMap::new();
+        let mut delta = RightRows::default();
+        for r in rs.iter() {
+            let d = if r.is_positive() { 1 } else { -1 };
+            delta.total += d;
+            match key_of(r, &right_key) {
+                Some(key) => *deltas.entry(key).or_insert(0) += d,
+                None => delta.nulls += d,
+            }
+        }
+
+        let counted = self.right_rows.is_some();
+        let (old_right, new_right) = match (self.right_rows(state), counted) {
+            (rows, _) if self.kind != SemiJoinType::NotIn => (rows, rows),
+            (old, true) => {
+                let new = RightRows {
+                    total: old.total + delta.total,
+                    nulls: old.nulls + delta.nulls,
+                };
+                self.right_rows = Some(new);
+                (old, new)
+            }
+            (new, false) => {
+                let old = RightRows {
+                    total: new.total - delta.total,
+                    nulls: new.nulls - delta.nulls,
+                };
+                (old, new)
+            }
+        };
+
+        let mut results = Vec::new();
+        if (old_right.total == 0) != (new_right.total == 0)
+            || (old_right.nulls == 0) != (new_right.nulls == 0)
+        {
+            // whether a left row is emitted may now have changed for any key, and for NULL keys
+            let mut counts = HashMap::new();
+            for r in state
+                .get(*self.left)
+                .expect("semi-join parents must be fully materialized")
+                .cloned_records()
+            {
+                let (old, new) = match key_of(&r, &left_key) {
+                    None => (None, None),
+                    Some(key) => {
+                        let delta = deltas.get(&key).cloned().unwrap_or(0);
+                        let new = *counts.entry(key).or_insert_with_key(|key| {
+                            self.count(*self.right, &right_key, key, nodes, state)
+                        });
+                        (Some((new as isize - delta) as usize), Some(new))
+                    }
+                };
+                let keeps = self.keeps(new, new_right);
+                if self.keeps(old, old_right) != keeps {
+                    results.push((r, keeps).into());
+                }
+            }
+
+            return ProcessingResult {
+                results: results.into(),
+                ..Default::default()
+            };
+        }
+
+        for (key, delta) in deltas {
+            let new = self.count(*self.right, &right_key, &key, nodes, state);
+            let old = (new as isize - delta) as usize;
+            let keeps = self.keeps(Some(new), new_right);
+            if self.keeps(Some(old), old_right) == keeps {
+                continue;
+            }
+
+            // the key's left rows are now emitted, or no longer are
+            results.extend(
+                self.lookup(
+                    *self.left,
+                    &left_key,
+                    &KeyType::from(&key[..]),
+                    nodes,
+                    state,
+                )
+                .flatten()
+                .expect("semi-join parents must be fully materialized")
+                .map(|r| (r.into_owned(), keeps).into()),
+            );
+        }
+
+        ProcessingResult {
+            results: results.into(),
+            ..Default::default()
+        }
+    }
+
+    fn suggest_indexes(&self, _this: NodeIndex) -> HashMap<NodeIndex, Vec<usize>> {
+        vec![
+            (self.left.as_global(), self.on_left()),
+            (self.right.as_global(), self.on_right()),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn resolve(&self, col: usize) -> Option<Vec<(NodeIndex, usize)>> {
+        Some(vec![(self.left.as_global(), col)])
+    }
+
+    fn description(&self, detailed: bool) -> String {
+        let op = match self.kind {
+            SemiJoinType::Semi => "∃",
+            SemiJoinType::Anti => "▷",
+            SemiJoinType::NotIn => "∉",
+        };
+        if !detailed {
+            return String::from(op);
+        }
+
+        let on = self
+            .on
+            .iter()
+            .map(|&(l, r)| {
+                format!(
+                    "{}:{} = {}:{}",
+                    self.left.as_global().index(),
+                    l,
+                    self.right.as_global().index(),
+                    r
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ∧ ");
+
+        format!("{} {}", op, on)
+    }
+
+    fn parent_columns(&self, col: usize) -> Vec<(NodeIndex, Option<usize>)> {
+        vec![(self.left.as_global(), Some(col))]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ops;
+
+    fn setup(kind: SemiJoinType) -> (ops::test::MockGraph, IndexPair, IndexPair) {
+        let mut g = ops::test::MockGraph::new();
+        let l = g.add_base("articles", &["id", "title"]);
+        let r = g.add_base("votes", &["article", "user"]);
+
+        let j = SemiJoin::new(l.as_global(), r.as_global(), kind, vec![(0, 0)]);
+
+        g.set_op("join", &["id", "title"], j, false);
+        (g, l, r)
+    }
+
+    #[test]
+    fn it_describes() {
+        let (j, l, r) = setup(SemiJoinType::Semi);
+        assert_eq!(j.node().description(true), format!("∃ {}:0 = {}:0", l, r));
+        let (j, l, r) = setup(SemiJoinType::Anti);
+        assert_eq!(j.node().description(true), format!("▷ {}:0 = {}:0", l, r));
+    }
+
+    #[test]
+    fn it_works_as_semi_join() {
+        let (mut j, l, r) = setup(SemiJoinType::Semi);
+
+        let a_1 = vec![1.into(), "a".into()];
+        let a_2 = vec![2.into(), "b".into()];
+        j.seed(l, a_1.clone());
+        j.seed(l, a_2.clone());
+
+        // the first vote for an article emits it
+        let v_1 = vec![1.into(), "x".into()];
+        j.seed(r, v_1.clone());
+        let rs = j.one_row(r, v_1.clone(), false);
+        assert_eq!(rs, vec![(a_1.clone(), true)].into());
+
+        // but later ones don't emit it again
+        let v_2 = vec![1.into(), "y".into()];
+        j.seed(r, v_2.clone());
+        let rs = j.one_row(r, v_2.clone(), false);
+        assert!(rs.is_empty());
+
+        // new articles are only emitted if they have votes
+        let a_3 = vec![1.into(), "c".into()];
+        j.seed(l, a_3.clone());
+        let rs = j.one_row(l, a_3.clone(), false);
+        assert_eq!(rs, vec![(a_3.clone(), true)].into());
+        let a_4 = vec![3.into(), "d".into()];
+        j.seed(l, a_4.clone());
+        let rs = j.one_row(l, a_4.clone(), false);
+        assert!(rs.is_empty());
+
+        // removing the last votes retracts the article's rows
+        j.unseed(r);
+        let rs = j.one(r, vec![(v_1, false), (v_2, false)], false);
+        assert_eq!(rs.len(), 2);
+        assert!(rs.has_negative(&a_1[..]));
+        assert!(rs.has_negative(&a_3[..]));
+    }
+
+    #[test]
+    fn it_works_as_anti_join() {
+        let (mut j, l, r) = setup(SemiJoinType::Anti);
+
+        // articles without votes are emitted
+        let a_1 = vec![1.into(), "a".into()];
+        j.seed(l, a_1.clone());
+        let rs = j.one_row(l, a_1.clone(), false);
+        assert_eq!(rs, vec![(a_1.clone(), true)].into());
+
+        // and retracted once a vote for them appears
+        let v_1 = vec![1.into(), "x".into()];
+        j.seed(r, v_1.clone());
+        let rs = j.one_row(r, v_1.clone(), false);
+        assert_eq!(rs, vec![(a_1.clone(), false)].into());
+
+        // several votes in one batch only retract once
+        let a_2 = vec![2.into(), "b".into()];
+        j.seed(l, a_2.clone());
+        let v_2 = vec![2.into(), "x".into()];
+        let v_3 = vec![2.into(), "y".into()];
+        j.seed(r, v_2.clone());
+        j.seed(r, v_3.clone());
+        let rs = j.one(r, vec![v_2.clone(), v_3.clone()], false);
+        assert_eq!(rs, vec![(a_2.clone(), false)].into());
+
+        // removing the only vote brings the article back
+        j.unseed(r);
+        j.seed(r, v_2);
+        j.seed(r, v_3);
+        let rs = j.one_row(r, (v_1, false), false);
+        assert_eq!(rs, vec![(a_1.clone(), true)].into());
+
+        // rows with a NULL key never match
+        let a_3 = vec![DataType::None, "c".into()];
+        j.seed(l, a_3.clone());
+        let rs = j.one_row(l, a_3.clone(), false);
+        assert_eq!(rs, vec![(a_3.clone(), true)].into());
+    }
+
+    #[test]
+    fn it_works_as_not_in() {
+        let (mut j, l, r) = setup(SemiJoinType::NotIn);
+
+        // with no votes at all, every article is emitted, even one with a NULL key
+        let a_1 = vec![1.into(), "a".into()];
+        let a_2 = vec![2.into(), "b".into()];
+        let a_3 = vec![DataType::None, "c".into()];
+        j.seed(l, a_1.clone());
+        j.seed(l, a_2.clone());
+        j.seed(l, a_3.clone());
+        let rs = j.one(l, vec![a_1.clone(), a_2.clone(), a_3.clone()], false);
+        assert_eq!(rs.len(), 3);
+
+        // the first vote retracts the articles it votes for, and the one with a NULL key
+        let v_1 = vec![1.into(), "x".into()];
+        j.seed(r, v_1.clone());
+        let rs = j.one_row(r, v_1.clone(), false);
+        assert_eq!(rs.len(), 2);
+        assert!(rs.has_negative(&a_1[..]));
+        assert!(rs.has_negative(&a_3[..]));
+
+        // a vote with a NULL key retracts all other articles
+        let v_2 = vec![DataType::None, "y".into()];
+        j.seed(r, v_2.clone());
+        let rs = j.one_row(r, v_2.clone(), false);
+        assert_eq!(rs, vec![(a_2.clone(), false)].into());
+
+        // and keeps new ones from being emitted
+        let a_4 = vec![4.into(), "d".into()];
+        j.seed(l, a_4.clone());
+        let rs = j.one_row(l, a_4.clone(), false);
+        assert!(rs.is_empty());
+
+        // until it is removed
+        j.unseed(r);
+        j.seed(r, v_1.clone());
+        let rs = j.one_row(r, (v_2, false), false);
+        assert_eq!(rs.len(), 2);
+        assert!(rs.has_positive(&a_2[..]));
+        assert!(rs.has_positive(&a_4[..]));
+
+        // removing the last vote brings back all articles
+        j.unseed(r);
+        let rs = j.one_row(r, (v_1, false), false);
+        assert_eq!(rs.len(), 2);
+        assert!(rs.has_positive(&a_1[..]));
+        assert!(rs.has_positive(&a_3[..]));
+    }
+
+    #[test]
+    fn it_suggests_indices() {
+        let me = 2.into();
+        let (g, l, r) = setup(SemiJoinType::Anti);
+        let hm: HashMap<_, _> = vec![(l.as_global(), vec![0]), (r.as_global(), vec![0])]
+            .into_iter()
+            .collect();
+        assert_eq!(g.node().suggest_indexes(me), hm);
+    }
+
+    #[test]
+    fn it_resolves() {
+        let (g, l, _) = setup(SemiJoinType::Semi);
+        assert_eq!(g.node().resolve(0), Some(vec![(l.as_global(), 0)]));
+        assert_eq!(g.node().resolve(1), Some(vec![(l.as_global(), 1)]));
+    }
+}
@@ -0,0 +1,461 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::Bound;
+
+use crate::ops::filter::Operator;
+use crate::ops::join::JoinSource;
+use crate::prelude::*;
+
+/// A join whose rows match according to arbitrary comparisons between their columns, rather than
+/// just equality.
+///
+/// The join keeps the distinct values of the first comparison's column in each parent's rows in
+/// order, so that a new row from either side only needs to look up the rows of the other parent
+/// whose values are in the range it can match. The rows themselves are looked up in the parents'
+/// state, which is indexed on those columns.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThetaJoin {
+    left: IndexPair,
+    right: IndexPair,
+
+    // (left column, comparison, right column); rows join if all comparisons hold
+    on: Vec<(usize, Operator, usize)>,
+
+    // Which columns to emit. True means the column is from the left parent, false means from the
+    // right
+    emit: Vec<(bool, usize)>,
+
+    // The distinct values of the first comparison's left and right columns in the parents' rows.
+    // Built from the parents' state the first time they are needed.
+    #[serde(skip)]
+    left_keys: Option<BTreeSet<DataType>>,
+    #[serde(skip)]
+    right_keys: Option<BTreeSet<DataType>>,
+}
+
+impl ThetaJoin {
+    /// Create a new theta join.
+    ///
+    /// `on` gives the comparisons that must hold between a left and a right row for them to join,
+    /// as `(left_parent_column, operator, right_parent_column)`. `emit` dictates for each output
+    /// column which parent and column it comes from; since no column is shared between the
+    /// parents, it may not contain `JoinSource::B`.
+    pub fn new(
+        left: NodeIndex,
+        right: NodeIndex,
+        on: Vec<(usize, Operator, usize)>,
+        emit: Vec<JoinSource>,
+    ) -> Self {
+        assert!(
+            !on.is_empty(),
+            "theta join must have at least one comparison"
+        );
+        for &(_, ref op, _) in &on {
+            match *op {
+                Operator::Equal
+                | Operator::NotEqual
+                | Operator::Greater
+                | Operator::GreaterOrEqual
+                | Operator::Less
+                | Operator::LessOrEqual => {}
+                ref op => panic!("theta join does not support {} comparisons", op),
+            }
+        }
+
+        let emit = emit
+            .into_iter()
+            .map(|join_source| match join_source {
+                JoinSource::L(c) => (true, c),
+                JoinSource::R(c) => (false, c),
+                JoinSource::B(..) => panic!("theta join columns do not come from both parents"),
+            })
+            .collect();
+
+        ThetaJoin {
+            left: left.into(),
+            right: right.into(),
+            on,
+            emit,
+            left_keys: None,
+            right_keys: None,
+        }
+    }
+
+    fn matches(&self, left: &[DataType], right: &[DataType]) -> bool {
+        self.on.iter().all(|&(l, ref op, r)| {
+            let (l, r) = (&left[l], &right[r]);
+            if l.is_none() || r.is_none() {
+                // comparisons with NULL are never true
+                return false;
+            }
+            match *op {
+                Operator::Equal => l == r,
+                Operator::NotEqual => l != r,
+                Operator::Greater => l > r,
+                Operator::GreaterOrEqual => l >= r,
+                Operator::Less => l < r,
+                Operator::LessOrEqual => l <= r,
+                _ => unreachable!(),
+            }
+        })
+    }
+
+    fn generate_row(&self, left: &[DataType], right: &[DataType]) -> Vec<DataType> {
+        self.emit
+            .iter()
+            .map(|&(from_left, col)| {
+                if from_left {
+                    left[col].clone()
+                } else {
+                    right[col].clone()
+                }
+            })
+            .collect()
+    }
+
+    /// The range of values of the first comparison's right column that `left` can match.
+    fn right_range<'a>(&self, left: &'a [DataType]) -> (Bound<&'a DataType>, Bound<&'a DataType>) {
+        let (l, ref op, _) = self.on[0];
+        let l = &left[l];
+        match *op {
+            // l = r
+            Operator::Equal => (Bound::Included(l), Bound::Included(l)),
+            // l > r
+            Operator::Greater => (Bound::Unbounded, Bound::Excluded(l)),
+            // l >= r
+            Operator::GreaterOrEqual => (Bound::Unbounded, Bound::Included(l)),
+            // l < r
+            Operator::Less => (Bound::Excluded(l), Bound::Unbounded),
+            // l <= r
+            Operator::LessOrEqual => (Bound::Included(l), Bound::Unbounded),
+            _ => (Bound::Unbounded, Bound::Unbounded),
+        }
+    }
+
+    /// The range of values of the first comparison's left column that `right` can match.
+    fn left_range<'a>(&self, right: &'a [DataType]) -> (Bound<&'a DataType>, Bound<&'a DataType>) {
+        let (_, ref op, r) = self.on[0];
+        let r = &right[r];
+        match *op {
+            // l = r
+            Operator::Equal => (Bound::Included(r), Bound::Included(r)),
+            // l > r
+            Operator::Greater => (Bound::Excluded(r), Bound::Unbounded),
+            // l >= r
+            Operator::GreaterOrEqual => (Bound::Included(r), Bound::Unbounded),
+            // l < r
+            Operator::Less => (Bound::Unbounded, Bound::Excluded(r)),
+            // l <= r
+            Operator::LessOrEqual => (Bound::Unbounded, Bound::Included(r)),
+            _ => (Bound::Unbounded, Bound::Unbounded),
+        }
+    }
+
+    /// The distinct values of column `col` in the rows of the fully materialized `parent`.
+    fn keys_of(state: &StateMap, parent: LocalNodeIndex, col: usize) -> BTreeSet<DataType> {
+        state
+            .get(parent)
+            .expect("theta join's parents must be materialized")
+            .cloned_records()
+            .into_iter()
+            .map(|mut r| r.swap_remove(col))
+            .collect()
+    }
+
+    /// The rows of `parent` whose value for column `col` is `key`.
+    fn rows_of<'a>(
+        state: &'a StateMap,
+        parent: LocalNodeIndex,
+        col: usize,
+        key: &DataType,
+    ) -> RecordResult<'a> {
+        match state
+            .get(parent)
+            .expect("theta join's parents must be materialized")
+            .lookup(&[col], &KeyType::Single(key))
+        {
+            LookupResult::Some(rs) => rs,
+            LookupResult::Missing => unreachable!("theta join's parents are fully materialized"),
+        }
+    }
+}
+
+impl Ingredient for ThetaJoin {
+    fn take(&mut self) -> NodeOperator {
+        Clone::clone(self).into()
+    }
+
+    fn ancestors(&self) -> Vec<NodeIndex> {
+        vec![self.left.as_global(), self.right.as_global()]
+    }
+
+    fn is_join(&self) -> bool {
+        true
+    }
+
+    fn must_replay_among(&self) -> Option<HashSet<NodeIndex>> {
+        // the right side's rows are read from its state, so the left side must be replayed
+        Some(Some(self.left.as_global()).into_iter().collect())
+    }
+
+    fn can_replay_partially(&self) -> bool {
+        // rows match on ranges, so there is no single key to replay through the other side
+        false
+    }
+
+    fn on_connected(&mut self, _g: &Graph) {}
+
+    fn on_commit(&mut self, _: NodeIndex, remap: &HashMap<NodeIndex, IndexPair>) {
+        self.left.remap(remap);
+        self.right.remap(remap);
+    }
+
+    fn on_input(
+        &mut self,
+        _: &mut dyn Executor,
+        from: LocalNodeIndex,
+        rs: Records,
+        _: Option<&[usize]>,
+        _: &DomainNodes,
+        state: &StateMap,
+    ) -> ProcessingResult {
+        let (left_col, _, right_col) = self.on[0];
+
+        if self.left_keys.is_none() {
+            // the parents' state already reflects any records they just sent us, so there's no
+            // need to apply them to the keys below.
+            self.left_keys = Some(Self::keys_of(state, *self.left, left_col));
+            self.right_keys = Some(Self::keys_of(state, *self.right, right_col));
+        } else {
+            let (keys, parent, col) = if from == *self.left {
+                (self.left_keys.as_mut().unwrap(), *self.left, left_col)
+            } else {
+                (self.right_keys.as_mut().unwrap(), *self.right, right_col)
+            };
+            for r in rs.iter() {
+                let key = &r.rec()[col];
+                if r.is_positive() {
+                    keys.insert(key.clone());
+                } else if Self::rows_of(state, parent, col, key).is_empty() {
+                    keys.remove(key);
+                }
+            }
+        }
+
+        let mut results = Vec::new();
+        if from == *self.right {
+            // rows from the right only need to look at left rows in the range they can match
+            let left_keys = self.left_keys.as_ref().unwrap();
+            for r in rs {
+                let (row, positive) = r.extract();
+                for key in left_keys.range(self.left_range(&row)) {
+                    for left in Self::rows_of(state, *self.left, left_col, key) {
+                        if self.matches(&left, &row) {
+                            results.push((self.generate_row(&left, &row), positive).into());
+                        }
+                    }
+                }
+            }
+        } else {
+            // and rows from the left only at right rows in the range they can match
+            let right_keys = self.right_keys.as_ref().unwrap();
+            for r in rs {
+                let (row, positive) = r.extract();
+                for key in right_keys.range(self.right_range(&row)) {
+                    for right in Self::rows_of(state, *self.right, right_col, key) {
+                        if self.matches(&row, &right) {
+                            results.push((self.generate_row(&row, &right), positive).into());
+                        }
+                    }
+                }
+            }
+        }
+
+        ProcessingResult {
+            results: results.into(),
+            ..Default::default()
+        }
+    }
+
+    fn suggest_indexes(&self, _this: NodeIndex) -> HashMap<NodeIndex, Vec<usize>> {
+        // rows are looked up in both parents by the first comparison's columns
+        let (l, _, r) = self.on[0];
+        vec![
+            (self.left.as_global(), vec![l]),
+            (self.right.as_global(), vec![r]),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn resolve(&self, col: usize) -> Option<Vec<(NodeIndex, usize)>> {
+        let e = self.emit[col];
+        if e.0 {
+            Some(vec![(self.left.as_global(), e.1)])
+        } else {
+            Some(vec![(self.right.as_global(), e.1)])
+        }
+    }
+
+    fn description(&self, detailed: bool) -> String {
+        if !detailed {
+            return String::from("⋈θ");
+        }
+
+        let emit = self
+            .emit
+            .iter()
+            .map(|&(from_left, col)| {
+                let src = if from_left { self.left } else { self.right };
+                format!("{}:{}", src.as_global().index(), col)
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let on = self
+            .on
+            .iter()
+            .map(|&(l, ref op, r)| {
+                format!(
+                    "{}:{} {} {}:{}",
+                    self.left.as_global().index(),
+                    l,
+                    op,
+                    self.right.as_global().index(),
+                    r
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ∧ ");
+
+        format!("[{}] ⋈θ {}", emit, on)
+    }
+
+    fn parent_columns(&self, col: usize) -> Vec<(NodeIndex, Option<usize>)> {
+        let (from_left, c) = self.emit[col];
+        vec![(
+            if from_left { &self.left } else { &self.right }.as_global(),
+            Some(c),
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ops;
+
+    fn setup() -> (ops::test::MockGraph, IndexPair, IndexPair) {
+        let mut g = ops::test::MockGraph::new();
+        let l = g.add_base("events", &["id", "ts"]);
+        let r = g.add_base("windows", &["name", "start", "end"]);
+
+        use self::JoinSource::*;
+        // events.ts >= windows.start AND events.ts <= windows.end
+        let j = ThetaJoin::new(
+            l.as_global(),
+            r.as_global(),
+            vec![
+                (1, Operator::GreaterOrEqual, 1),
+                (1, Operator::LessOrEqual, 2),
+            ],
+            vec![L(0), L(1), R(0)],
+        );
+
+        g.set_op("join", &["id", "ts", "name"], j, false);
+        (g, l, r)
+    }
+
+    #[test]
+    fn it_describes() {
+        let (j, l, r) = setup();
+        assert_eq!(
+            j.node().description(true),
+            format!(
+                "[{}:0, {}:1, {}:0] ⋈θ {}:1 >= {}:1 ∧ {}:1 <= {}:2",
+                l, l, r, l, r, l, r
+            )
+        );
+    }
+
+    #[test]
+    fn it_works() {
+        let (mut j, l, r) = setup();
+
+        let w_a = vec!["a".into(), 0.into(), 10.into()];
+        let w_b = vec!["b".into(), 5.into(), 15.into()];
+        j.seed(r, w_a.clone());
+        j.seed(r, w_b.clone());
+
+        // an event joins with all windows it falls into
+        let e_1 = vec![1.into(), 7.into()];
+        j.seed(l, e_1.clone());
+        let rs = j.one_row(l, e_1.clone(), false);
+        assert_eq!(rs.len(), 2);
+        assert!(rs.has_positive(&[1.into(), 7.into(), "a".into()][..]));
+        assert!(rs.has_positive(&[1.into(), 7.into(), "b".into()][..]));
+
+        // including the edges
+        let e_2 = vec![2.into(), 15.into()];
+        j.seed(l, e_2.clone());
+        let rs = j.one_row(l, e_2.clone(), false);
+        assert_eq!(
+            rs,
+            vec![(vec![2.into(), 15.into(), "b".into()], true)].into()
+        );
+
+        // and with none if it falls outside of them
+        let e_3 = vec![3.into(), 20.into()];
+        j.seed(l, e_3.clone());
+        let rs = j.one_row(l, e_3.clone(), false);
+        assert!(rs.is_empty());
+
+        // a new window joins with all the events that fall into it
+        let w_c = vec!["c".into(), 12.into(), 30.into()];
+        j.seed(r, w_c.clone());
+        let rs = j.one_row(r, w_c.clone(), false);
+        assert_eq!(rs.len(), 2);
+        assert!(rs.has_positive(&[2.into(), 15.into(), "c".into()][..]));
+        assert!(rs.has_positive(&[3.into(), 20.into(), "c".into()][..]));
+
+        // and later events see it too
+        let e_4 = vec![4.into(), 25.into()];
+        j.seed(l, e_4.clone());
+        let rs = j.one_row(l, e_4.clone(), false);
+        assert_eq!(
+            rs,
+            vec![(vec![4.into(), 25.into(), "c".into()], true)].into()
+        );
+
+        // NULLs never match
+        let e_5 = vec![5.into(), DataType::None];
+        j.seed(l, e_5.clone());
+        let rs = j.one_row(l, e_5.clone(), false);
+        assert!(rs.is_empty());
+
+        // removing a window retracts the events that fell into it
+        let rs = j.one_row(r, (w_c.clone(), false), false);
+        assert_eq!(rs.len(), 3);
+        assert!(rs.has_negative(&[2.into(), 15.into(), "c".into()][..]));
+        assert!(rs.has_negative(&[3.into(), 20.into(), "c".into()][..]));
+        assert!(rs.has_negative(&[4.into(), 25.into(), "c".into()][..]));
+    }
+
+    #[test]
+    fn it_suggests_indices() {
+        let me = 2.into();
+        let (g, l, r) = setup();
+        let hm: HashMap<_, _> = vec![(l.as_global(), vec![1]), (r.as_global(), vec![1])]
+            .into_iter()
+            .collect();
+        assert_eq!(g.node().suggest_indexes(me), hm);
+    }
+
+    #[test]
+    fn it_resolves() {
+        let (g, l, r) = setup();
+        assert_eq!(g.node().resolve(0), Some(vec![(l.as_global(), 0)]));
+        assert_eq!(g.node().resolve(1), Some(vec![(l.as_global(), 1)]));
+        assert_eq!(g.node().resolve(2), Some(vec![(r.as_global(), 0)]));
+    }
+}
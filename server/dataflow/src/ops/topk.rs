@@ -6,10 +6,14 @@ use crate::prelude::*;
 
 use nom_sql::OrderType;
 
-#[derive(Clone, Serialize, Deserialize)]
-struct Order(Vec<(usize, OrderType)>);
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Order(Vec<(usize, OrderType)>);
 impl Order {
-    fn cmp(&self, a: &[DataType], b: &[DataType]) -> Ordering {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub(crate) fn cmp(&self, a: &[DataType], b: &[DataType]) -> Ordering {
         for &(c, ref order_type) in &self.0 {
             let result = match *order_type {
                 OrderType::OrderAscending => a[c].cmp(&b[c]),
@@ -1,6 +1,7 @@
 use petgraph;
 use serde::{Deserialize, Serialize};
 
+use crate::backlog::KeyRange;
 use crate::domain;
 use crate::prelude::*;
 use noria;
@@ -57,10 +58,18 @@ pub enum InitialState {
     },
 }
 
+/// The key range that a partial replay fills, along with all the keys that its source found in it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReplayRange {
+    pub range: KeyRange,
+    pub keys: HashSet<Vec<DataType>>,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub enum ReplayPieceContext {
     Partial {
         for_keys: HashSet<Vec<DataType>>,
+        for_range: Option<ReplayRange>,
         requesting_shard: usize,
         unishard: bool,
         ignore: bool,
@@ -85,7 +94,9 @@ pub enum Packet {
     Input {
         inner: LocalOrNot<Input>,
         src: Option<SourceChannelIdentifier>,
-        senders: Vec<SourceChannelIdentifier>,
+        /// The clients whose inputs were merged into this one, along with how many of the merged
+        /// operations each of them sent.
+        senders: Vec<(SourceChannelIdentifier, usize)>,
     },
 
     /// Regular data-flow update.
@@ -147,6 +158,13 @@ pub enum Packet {
         column: usize,
     },
 
+    /// Renames an existing column of a `Base` node.
+    RenameBaseColumn {
+        node: LocalNodeIndex,
+        column: usize,
+        field: String,
+    },
+
     /// Update Egress node.
     UpdateEgress {
         node: LocalNodeIndex,
@@ -200,6 +218,21 @@ pub enum Packet {
         keys: Vec<Vec<DataType>>,
     },
 
+    /// Ask domain to replay all keys in a particular set of key ranges.
+    RequestRangeReplay {
+        tag: Tag,
+        ranges: Vec<KeyRange>,
+        unishard: bool,
+        requesting_shard: usize,
+    },
+
+    /// Ask domain to replay all keys in a particular set of key ranges into an ordered Reader.
+    RequestReaderRangeReplay {
+        node: LocalNodeIndex,
+        cols: Vec<usize>,
+        ranges: Vec<KeyRange>,
+    },
+
     /// Instruct domain to replay the state of a particular node along an existing replay path.
     StartReplay {
         tag: Tag,
@@ -339,6 +372,12 @@ impl fmt::Debug for Packet {
             Packet::RequestPartialReplay { ref tag, .. } => {
                 write!(f, "Packet::RequestPartialReplay({:?})", tag)
             }
+            Packet::RequestReaderRangeReplay { ref ranges, .. } => {
+                write!(f, "Packet::RequestReaderRangeReplay({:?})", ranges)
+            }
+            Packet::RequestRangeReplay { ref tag, .. } => {
+                write!(f, "Packet::RequestRangeReplay({:?})", tag)
+            }
             Packet::ReplayPiece {
                 ref link,
                 ref tag,
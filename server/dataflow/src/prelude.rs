@@ -16,7 +16,7 @@ pub(crate) type Edge = ();
 
 // dataflow types
 pub(crate) use crate::payload::{ReplayPathSegment, SourceChannelIdentifier};
-pub(crate) use noria::Input;
+pub(crate) use noria::{Input, WriteError, WriteReply};
 
 // domain local state
 pub(crate) use crate::state::{
@@ -41,7 +41,7 @@ pub use crate::PersistenceParameters;
 /// Channel coordinator type specialized for domains
 pub type ChannelCoordinator = noria::channel::ChannelCoordinator<(DomainIndex, usize), Box<Packet>>;
 pub trait Executor {
-    fn ack(&mut self, tag: SourceChannelIdentifier);
+    fn ack(&mut self, tag: SourceChannelIdentifier, reply: WriteReply);
     fn create_universe(&mut self, req: HashMap<String, DataType>);
     fn send(&mut self, dest: ReplicaAddr, m: Box<Packet>);
 }
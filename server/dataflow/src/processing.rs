@@ -95,7 +95,7 @@ pub(crate) enum ReplayContext<'a> {
 }
 
 impl<'a> ReplayContext<'a> {
-    fn key(&self) -> Option<&'a [usize]> {
+    pub(crate) fn key(&self) -> Option<&'a [usize]> {
         if let ReplayContext::Partial { key_cols, .. } = *self {
             Some(key_cols)
         } else {
@@ -148,6 +148,10 @@ where
     ///    𝛴    |  Sum
     ///    ⋈    |  Join
     ///    ⋉    |  Left join
+    ///    ⟗    |  Full outer join
+    ///    ⋈θ   |  Theta join
+    ///    ∃    |  Semi-join
+    ///    ▷    |  Anti-join
     ///    ⋃    |  Union
     ///    σ    |  Filter
     ///    π    |  Projection
@@ -279,4 +283,16 @@ where
     fn requires_full_materialization(&self) -> bool {
         false
     }
+
+    /// Returns false if replaying a single key through this operator's ancestors can produce an
+    /// incomplete result for that key, which rules out partial materialization below it.
+    fn can_replay_partially(&self) -> bool {
+        true
+    }
+
+    /// The number of bytes of auxiliary state the operator keeps in addition to its
+    /// materialization, which counts towards the node's memory use.
+    fn auxiliary_state_size(&self) -> u64 {
+        0
+    }
 }
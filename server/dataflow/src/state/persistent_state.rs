@@ -684,6 +684,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn persistent_state_binary_types() {
+        let mut state = setup_persistent("persistent_state_binary_types");
+        let columns = &[0, 1];
+        let hash: DataType = vec![0u8, 0xff, 0, 42].into();
+        let id: DataType = noria::Uuid::from_u128(0x67e5_5044_10b1_426f_9247_bb68_0e5f_e0c8).into();
+        let row: Vec<DataType> = vec![id.clone(), hash.clone(), true.into()];
+        state.add_key(columns, None);
+        state.add_key(&[2], None);
+        insert(&mut state, row.clone());
+
+        match state.lookup(columns, &KeyType::Double((id.clone(), vec![0u8].into()))) {
+            LookupResult::Some(RecordResult::Owned(rows)) => assert_eq!(rows.len(), 0),
+            _ => unreachable!(),
+        };
+
+        match state.lookup(columns, &KeyType::Double((id, hash))) {
+            LookupResult::Some(RecordResult::Owned(rows)) => assert_eq!(rows, vec![row.clone()]),
+            _ => unreachable!(),
+        }
+
+        match state.lookup(&[2], &KeyType::Single(&true.into())) {
+            LookupResult::Some(RecordResult::Owned(rows)) => assert_eq!(rows, vec![row]),
+            _ => unreachable!(),
+        }
+    }
+
     #[test]
     fn persistent_state_multiple_indices() {
         let mut state = setup_persistent("persistent_state_multiple_indices");
@@ -0,0 +1,178 @@
+use std::fmt::{Display, Error, Formatter};
+
+use crate::Column;
+use common::DataType;
+use dataflow::ops::function::BuiltinFunction;
+use nom_sql::{ArithmeticBase, ArithmeticExpression, ArithmeticOperator, Operator};
+
+/// A scalar expression computed over the columns of a node's parent.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    Column(Column),
+    Literal(DataType),
+    Arithmetic {
+        op: ArithmeticOperator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    Negate(Box<Expression>),
+    Condition {
+        op: Operator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    Call {
+        function: BuiltinFunction,
+        arguments: Vec<Expression>,
+    },
+    Case {
+        branches: Vec<(Expression, Expression)>,
+        otherwise: Box<Expression>,
+    },
+}
+
+impl Expression {
+    pub fn arithmetic(op: ArithmeticOperator, left: Expression, right: Expression) -> Self {
+        Expression::Arithmetic {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    pub fn condition(op: Operator, left: Expression, right: Expression) -> Self {
+        Expression::Condition {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    pub fn call(function: BuiltinFunction, arguments: Vec<Expression>) -> Self {
+        Expression::Call {
+            function,
+            arguments,
+        }
+    }
+
+    pub fn case(branches: Vec<(Expression, Expression)>, otherwise: Expression) -> Self {
+        Expression::Case {
+            branches,
+            otherwise: Box::new(otherwise),
+        }
+    }
+
+    /// The parent columns that the expression refers to.
+    pub fn columns(&self) -> Vec<&Column> {
+        match *self {
+            Expression::Column(ref c) => vec![c],
+            Expression::Literal(_) => vec![],
+            Expression::Arithmetic {
+                ref left,
+                ref right,
+                ..
+            }
+            | Expression::Condition {
+                ref left,
+                ref right,
+                ..
+            } => {
+                let mut columns = left.columns();
+                columns.extend(right.columns());
+                columns
+            }
+            Expression::Negate(ref e) => e.columns(),
+            Expression::Call { ref arguments, .. } => {
+                arguments.iter().flat_map(Expression::columns).collect()
+            }
+            Expression::Case {
+                ref branches,
+                ref otherwise,
+            } => branches
+                .iter()
+                .flat_map(|(cond, e)| cond.columns().into_iter().chain(e.columns()))
+                .chain(otherwise.columns())
+                .collect(),
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            Expression::Arithmetic { .. } | Expression::Condition { .. } => write!(f, "({})", self),
+            _ => write!(f, "{}", self),
+        }
+    }
+}
+
+impl<'a> From<&'a ArithmeticBase> for Expression {
+    fn from(b: &'a ArithmeticBase) -> Self {
+        match *b {
+            ArithmeticBase::Column(ref c) => Expression::Column(Column::from(c)),
+            ArithmeticBase::Scalar(ref l) => Expression::Literal(DataType::from(l)),
+        }
+    }
+}
+
+impl<'a> From<&'a ArithmeticExpression> for Expression {
+    fn from(e: &'a ArithmeticExpression) -> Self {
+        Expression::arithmetic(
+            e.op.clone(),
+            Expression::from(&e.left),
+            Expression::from(&e.right),
+        )
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match *self {
+            Expression::Column(ref c) => write!(f, "{}", c.name),
+            Expression::Literal(ref l) => write!(f, "{}", l),
+            Expression::Arithmetic {
+                ref op,
+                ref left,
+                ref right,
+            } => {
+                left.fmt_operand(f)?;
+                write!(f, " {} ", op)?;
+                right.fmt_operand(f)
+            }
+            Expression::Negate(ref e) => {
+                write!(f, "-")?;
+                e.fmt_operand(f)
+            }
+            Expression::Condition {
+                ref op,
+                ref left,
+                ref right,
+            } => {
+                left.fmt_operand(f)?;
+                write!(f, " {} ", op)?;
+                right.fmt_operand(f)
+            }
+            Expression::Call {
+                ref function,
+                ref arguments,
+            } => write!(
+                f,
+                "{}({})",
+                function,
+                arguments
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Expression::Case {
+                ref branches,
+                ref otherwise,
+            } => {
+                write!(f, "CASE")?;
+                for (cond, e) in branches {
+                    write!(f, " WHEN {} THEN {}", cond, e)?;
+                }
+                write!(f, " ELSE {} END", otherwise)
+            }
+        }
+    }
+}
@@ -8,6 +8,7 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 mod column;
+mod expression;
 pub mod node;
 mod optimize;
 pub mod query;
@@ -18,6 +19,7 @@ pub mod visualize;
 pub type MirNodeRef = Rc<RefCell<node::MirNode>>;
 
 pub use column::Column;
+pub use expression::Expression;
 
 #[derive(Clone, Debug)]
 pub enum FlowNode {
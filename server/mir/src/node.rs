@@ -1,17 +1,19 @@
-use nom_sql::{ArithmeticExpression, ColumnSpecification, Literal, OrderType};
+use nom_sql::{ColumnSpecification, Literal, Operator, OrderType};
 use petgraph::graph::NodeIndex;
 use std::cell::RefCell;
 use std::fmt::{Debug, Display, Error, Formatter};
 use std::rc::Rc;
 
 use crate::column::Column;
+use crate::expression::Expression;
 use crate::{FlowNode, MirNodeRef};
 use common::DataType;
 use dataflow::ops;
-use dataflow::ops::filter::FilterCondition;
+use dataflow::ops::filter::{FilterCondition, Predicate};
 use dataflow::ops::grouped::aggregate::Aggregation as AggregationKind;
 use dataflow::ops::grouped::extremum::Extremum as ExtremumKind;
 use dataflow::ops::grouped::filteraggregate::FilterAggregation as FilterAggregationKind;
+use dataflow::ops::semi_join::SemiJoinType;
 use std::collections::HashMap;
 
 /// Helper enum to avoid having separate `make_aggregation_node` and `make_extremum_node` functions
@@ -72,6 +74,7 @@ impl MirNode {
             MirNodeType::Base {
                 ref column_specs,
                 ref keys,
+                ref unique_keys,
                 ..
             } => {
                 let new_column_specs: Vec<(ColumnSpecification, Option<usize>)> = column_specs
@@ -98,6 +101,7 @@ impl MirNode {
                 let new_inner = MirNodeType::Base {
                     column_specs: new_column_specs,
                     keys: keys.clone(),
+                    unique_keys: unique_keys.clone(),
                     adapted_over: Some(BaseNodeAdaptation {
                         over: node.clone(),
                         columns_added: added_cols.into_iter().cloned().collect(),
@@ -360,8 +364,16 @@ impl MirNode {
                     columns.push(on.clone());
                 }
             }
-            MirNodeType::Project { ref emit, .. } => {
-                for c in emit {
+            MirNodeType::Project {
+                ref emit,
+                ref arithmetic,
+                ..
+            } => {
+                // need the emitted columns and those the expressions are computed from
+                for c in emit
+                    .iter()
+                    .chain(arithmetic.iter().flat_map(|&(_, ref e)| e.columns()))
+                {
                     if !columns.contains(&c) {
                         columns.push(c.clone());
                     }
@@ -403,10 +415,11 @@ pub enum MirNodeType {
         group_by: Vec<Column>,
         kind: AggregationKind,
     },
-    /// column specifications, keys (non-compound), tx flag, adapted base
+    /// column specifications, keys (non-compound), unique keys, adapted base
     Base {
         column_specs: Vec<(ColumnSpecification, Option<usize>)>,
         keys: Vec<Column>,
+        unique_keys: Vec<Vec<Column>>,
         adapted_over: Option<BaseNodeAdaptation>,
     },
     /// over column, group_by columns
@@ -415,9 +428,9 @@ pub enum MirNodeType {
         group_by: Vec<Column>,
         kind: ExtremumKind,
     },
-    /// filter conditions (one for each parent column)
+    /// predicate over the parent's columns
     Filter {
-        conditions: Vec<(usize, FilterCondition)>,
+        predicate: Predicate,
     },
     /// filter condition and grouping
     FilterAggregation {
@@ -446,6 +459,23 @@ pub enum MirNodeType {
         on_right: Vec<Column>,
         project: Vec<Column>,
     },
+    /// on left column, on right column, emit columns
+    OuterJoin {
+        on_left: Vec<Column>,
+        on_right: Vec<Column>,
+        project: Vec<Column>,
+    },
+    /// (left column, comparison, right column) conditions, emit columns
+    ThetaJoin {
+        on: Vec<(Column, Operator, Column)>,
+        project: Vec<Column>,
+    },
+    /// on left columns, on right columns, kind; emits the left node's columns
+    SemiJoin {
+        on_left: Vec<Column>,
+        on_right: Vec<Column>,
+        kind: SemiJoinType,
+    },
     /// group columns
     // currently unused
     #[allow(dead_code)]
@@ -455,7 +485,7 @@ pub enum MirNodeType {
     /// emit columns
     Project {
         emit: Vec<Column>,
-        arithmetic: Vec<(String, ArithmeticExpression)>,
+        arithmetic: Vec<(String, Expression)>,
         literals: Vec<(String, DataType)>,
     },
     /// emit columns
@@ -477,10 +507,15 @@ pub enum MirNodeType {
     Reuse {
         node: MirNodeRef,
     },
-    /// leaf (reader) node, keys
+    /// leaf (reader) node, keys, comparisons against parameters that follow the keys, the
+    /// positions in each lookup of the key and parameter values (if they are not in order), and
+    /// the order in which to return the rows for a key
     Leaf {
         node: MirNodeRef,
         keys: Vec<Column>,
+        filters: Vec<(Column, Operator, usize)>,
+        lookup_order: Vec<usize>,
+        order: Option<Vec<(Column, OrderType)>>,
     },
     /// Rewrite node
     Rewrite {
@@ -519,6 +554,12 @@ impl MirNodeType {
             }
             | MirNodeType::LeftJoin {
                 ref mut project, ..
+            }
+            | MirNodeType::OuterJoin {
+                ref mut project, ..
+            }
+            | MirNodeType::ThetaJoin {
+                ref mut project, ..
             } => {
                 project.push(c);
             }
@@ -590,12 +631,14 @@ impl MirNodeType {
             MirNodeType::Base {
                 column_specs: ref our_column_specs,
                 keys: ref our_keys,
+                unique_keys: ref our_unique_keys,
                 adapted_over: ref our_adapted_over,
             } => {
                 match *other {
                     MirNodeType::Base {
                         ref column_specs,
                         ref keys,
+                        ref unique_keys,
                         ..
                     } => {
                         // if we are instructed to adapt an earlier base node, we cannot reuse
@@ -611,7 +654,9 @@ impl MirNodeType {
                         // note that as long as we are not adapting a previous base node,
                         // we do *not* need `adapted_over` to *match*, since current reuse
                         // does not depend on how base node was created from an earlier one
-                        our_column_specs == column_specs && our_keys == keys
+                        our_column_specs == column_specs
+                            && our_keys == keys
+                            && our_unique_keys == unique_keys
                     }
                     _ => false,
                 }
@@ -629,9 +674,9 @@ impl MirNodeType {
                 _ => false,
             },
             MirNodeType::Filter {
-                conditions: ref our_conditions,
+                predicate: ref our_predicate,
             } => match *other {
-                MirNodeType::Filter { ref conditions } => our_conditions == conditions,
+                MirNodeType::Filter { ref predicate } => our_predicate == predicate,
                 _ => false,
             },
             MirNodeType::FilterAggregation {
@@ -692,6 +737,46 @@ impl MirNodeType {
                     _ => false,
                 }
             }
+            MirNodeType::OuterJoin {
+                on_left: ref our_on_left,
+                on_right: ref our_on_right,
+                project: ref our_project,
+            } => {
+                match *other {
+                    MirNodeType::OuterJoin {
+                        ref on_left,
+                        ref on_right,
+                        ref project,
+                    } => {
+                        // TODO(malte): column order does not actually need to match, but this only
+                        // succeeds if it does.
+                        our_on_left == on_left && our_on_right == on_right && our_project == project
+                    }
+                    _ => false,
+                }
+            }
+            MirNodeType::ThetaJoin {
+                on: ref our_on,
+                project: ref our_project,
+            } => match *other {
+                MirNodeType::ThetaJoin {
+                    ref on,
+                    ref project,
+                } => our_on == on && our_project == project,
+                _ => false,
+            },
+            MirNodeType::SemiJoin {
+                on_left: ref our_on_left,
+                on_right: ref our_on_right,
+                kind: ref our_kind,
+            } => match *other {
+                MirNodeType::SemiJoin {
+                    ref on_left,
+                    ref on_right,
+                    ref kind,
+                } => our_on_left == on_left && our_on_right == on_right && our_kind == kind,
+                _ => false,
+            },
             MirNodeType::Project {
                 emit: ref our_emit,
                 literals: ref our_literals,
@@ -742,9 +827,24 @@ impl MirNodeType {
                 _ => false,
             },
             MirNodeType::Leaf {
-                keys: ref our_keys, ..
+                keys: ref our_keys,
+                filters: ref our_filters,
+                lookup_order: ref our_lookup_order,
+                order: ref our_order,
+                ..
             } => match *other {
-                MirNodeType::Leaf { ref keys, .. } => keys == our_keys,
+                MirNodeType::Leaf {
+                    ref keys,
+                    ref filters,
+                    ref lookup_order,
+                    ref order,
+                    ..
+                } => {
+                    keys == our_keys
+                        && filters == our_filters
+                        && lookup_order == our_lookup_order
+                        && order == our_order
+                }
                 _ => false,
             },
             MirNodeType::Union { emit: ref our_emit } => match *other {
@@ -807,6 +907,11 @@ impl Debug for MirNodeType {
                 let op_string = match *kind {
                     AggregationKind::COUNT => format!("|*|({})", on.name.as_str()),
                     AggregationKind::SUM => format!("𝛴({})", on.name.as_str()),
+                    AggregationKind::AVG => format!("avg({})", on.name.as_str()),
+                    AggregationKind::VARIANCE => format!("var({})", on.name.as_str()),
+                    AggregationKind::STDDEV => format!("stddev({})", on.name.as_str()),
+                    AggregationKind::BOOL_AND => format!("bool_and({})", on.name.as_str()),
+                    AggregationKind::BOOL_OR => format!("bool_or({})", on.name.as_str()),
                 };
                 let group_cols = group_by
                     .iter()
@@ -848,7 +953,7 @@ impl Debug for MirNodeType {
                     .join(", ");
                 write!(f, "{} γ[{}]", op_string, group_cols)
             }
-            MirNodeType::Filter { ref conditions } => {
+            MirNodeType::Filter { ref predicate } => {
                 use regex::Regex;
 
                 let escape = |s: &str| {
@@ -857,28 +962,7 @@ impl Debug for MirNodeType {
                         .replace_all(s, "\\$1")
                         .to_string()
                 };
-                write!(
-                    f,
-                    "σ[{}]",
-                    conditions
-                        .iter()
-                        .filter_map(|(i, ref cond)| match *cond {
-                            FilterCondition::Comparison(ref op, ref x) => {
-                                Some(format!("f{} {} {:?}", i, escape(&format!("{}", op)), x))
-                            }
-                            FilterCondition::In(ref xs) => Some(format!(
-                                "f{} IN ({})",
-                                i,
-                                xs.iter()
-                                    .map(|d| format!("{}", d))
-                                    .collect::<Vec<_>>()
-                                    .join(", ")
-                            )),
-                        })
-                        .collect::<Vec<_>>()
-                        .as_slice()
-                        .join(", ")
-                )
+                write!(f, "σ[{}]", escape(&format!("{}", predicate)))
             }
             MirNodeType::FilterAggregation {
                 ref on,
@@ -925,13 +1009,26 @@ impl Debug for MirNodeType {
                     jc
                 )
             }
-            MirNodeType::Leaf { ref keys, .. } => {
+            MirNodeType::Leaf {
+                ref keys,
+                ref filters,
+                ..
+            } => {
                 let key_cols = keys
                     .iter()
                     .map(|k| k.name.clone())
                     .collect::<Vec<_>>()
                     .join(", ");
-                write!(f, "Leaf [⚷: {}]", key_cols)
+                if filters.is_empty() {
+                    write!(f, "Leaf [⚷: {}]", key_cols)
+                } else {
+                    let filter_cols = filters
+                        .iter()
+                        .map(|&(ref c, ref op, _)| format!("{} {} ?", c.name, op))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    write!(f, "Leaf [⚷: {}; σ: {}]", key_cols, filter_cols)
+                }
             }
             MirNodeType::LeftJoin {
                 ref on_left,
@@ -955,6 +1052,66 @@ impl Debug for MirNodeType {
                     jc
                 )
             }
+            MirNodeType::OuterJoin {
+                ref on_left,
+                ref on_right,
+                ref project,
+            } => {
+                let jc = on_left
+                    .iter()
+                    .zip(on_right)
+                    .map(|(l, r)| format!("{}:{}", l.name, r.name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(
+                    f,
+                    "⟗ [{} on {}]",
+                    project
+                        .iter()
+                        .map(|c| c.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    jc
+                )
+            }
+            MirNodeType::ThetaJoin {
+                ref on,
+                ref project,
+            } => {
+                let jc = on
+                    .iter()
+                    .map(|&(ref l, ref op, ref r)| format!("{} {} {}", l.name, op, r.name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(
+                    f,
+                    "⋈θ [{} on {}]",
+                    project
+                        .iter()
+                        .map(|c| c.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    jc
+                )
+            }
+            MirNodeType::SemiJoin {
+                ref on_left,
+                ref on_right,
+                ref kind,
+            } => {
+                let jc = on_left
+                    .iter()
+                    .zip(on_right)
+                    .map(|(l, r)| format!("{}:{}", l.name, r.name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let op = match *kind {
+                    SemiJoinType::Semi => "∃",
+                    SemiJoinType::Anti => "▷",
+                    SemiJoinType::NotIn => "∉",
+                };
+                write!(f, "{} [on {}]", op, jc)
+            }
             MirNodeType::Latest { ref group_by } => {
                 let key_cols = group_by
                     .iter()
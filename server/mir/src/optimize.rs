@@ -1,7 +1,7 @@
 use crate::node::{MirNode, MirNodeType};
 use crate::query::MirQuery;
 use crate::MirNodeRef;
-use dataflow::ops::filter::FilterCondition;
+use dataflow::ops::filter::Predicate;
 use dataflow::ops::grouped::aggregate::Aggregation;
 use dataflow::ops::grouped::filteraggregate::FilterAggregation;
 
@@ -69,20 +69,28 @@ fn find_and_merge_filter_aggregates(q: &mut MirQuery) -> Vec<MirNodeRef> {
         // now scan for candidacy
         let mut candidate = false;
         match n.borrow().inner {
-            MirNodeType::Filter { .. } => {
+            MirNodeType::Filter { ref predicate } => {
                 // if the child is an aggregation and it has exactly one parent,
-                // then this is a candidate
-                if let MirNodeType::Aggregation { .. } = child.inner {
-                    if child.ancestors.len() == 1 {
+                // then this is a candidate, as long as the filter is a plain conjunction
+                if let MirNodeType::Aggregation { ref kind, .. } = child.inner {
+                    if child.ancestors.len() == 1
+                        && has_filter_aggregation(kind)
+                        && predicate.conditions().is_some()
+                    {
                         candidate = true;
                     }
                 }
             }
-            MirNodeType::Aggregation { ref on, .. } => {
+            MirNodeType::Aggregation {
+                ref on, ref kind, ..
+            } => {
                 // if the child is a filter and it has exactly one parent,
                 // then this is a candidate
-                if let MirNodeType::Filter { ref conditions } = child.inner {
-                    if child.ancestors.len() != 1 {
+                if let MirNodeType::Filter { ref predicate } = child.inner {
+                    if child.ancestors.len() != 1
+                        || !has_filter_aggregation(kind)
+                        || predicate.conditions().is_none()
+                    {
                         continue;
                     }
                     candidate = true;
@@ -98,11 +106,8 @@ fn find_and_merge_filter_aggregates(q: &mut MirQuery) -> Vec<MirNodeRef> {
                             {
                                 // this column may be the aggregation result
                                 // so if we're filtering on it, we're not a candidate
-                                for (j, _cond) in conditions {
-                                    if *j == i {
-                                        candidate = false;
-                                        break;
-                                    }
+                                if predicate.columns().contains(&i) {
+                                    candidate = false;
                                 }
                             }
                             _ => {}
@@ -130,15 +135,15 @@ fn find_and_merge_filter_aggregates(q: &mut MirQuery) -> Vec<MirNodeRef> {
         // determine which is the aggregation, and update the conditions if the second isn't filter
         let (cond, agg) = match n.borrow().inner {
             MirNodeType::Aggregation { .. } => {
-                if let MirNodeType::Filter { ref conditions } = child.inner {
+                if let MirNodeType::Filter { ref predicate } = child.inner {
                     // for each column in filter's conditions, we need to figure out
                     // which column in input to aggregation has the same name so we
                     // can reindex the condition to that column
                     let mut conds = Vec::new();
-                    for (i, cond) in conditions {
+                    for (i, cond) in predicate.conditions().unwrap() {
                         let mut found = 0;
                         // conditions are on the parent columns of the filter node, i.e. agg's columns
-                        let cond_col = n.borrow().columns[*i].clone();
+                        let cond_col = n.borrow().columns[i].clone();
                         // we need to reindex them onto the new filteragg's parent columns, i.e. agg's parent's columns
                         assert!(n.borrow().ancestors.len() == 1);
                         let parent = temp.ancestors.first().unwrap().borrow();
@@ -157,7 +162,7 @@ fn find_and_merge_filter_aggregates(q: &mut MirQuery) -> Vec<MirNodeRef> {
                     unreachable!()
                 }
             }
-            MirNodeType::Filter { ref conditions } => (conditions.clone(), c.clone()),
+            MirNodeType::Filter { ref predicate } => (predicate.conditions().unwrap(), c.clone()),
             _ => unreachable!(),
         };
 
@@ -172,6 +177,7 @@ fn find_and_merge_filter_aggregates(q: &mut MirQuery) -> Vec<MirNodeRef> {
                     match kind {
                         Aggregation::COUNT => FilterAggregation::COUNT,
                         Aggregation::SUM => FilterAggregation::SUM,
+                        _ => unreachable!(),
                     },
                 )
             } else {
@@ -226,6 +232,14 @@ fn find_and_merge_filter_aggregates(q: &mut MirQuery) -> Vec<MirNodeRef> {
     new_nodes
 }
 
+// Only some aggregations have a filtering counterpart they can be merged into
+fn has_filter_aggregation(kind: &Aggregation) -> bool {
+    match *kind {
+        Aggregation::COUNT | Aggregation::SUM => true,
+        _ => false,
+    }
+}
+
 #[allow(dead_code)]
 fn find_and_merge_filter_chains(q: &MirQuery) {
     let mut chained_filters = Vec::new();
@@ -303,14 +317,14 @@ fn end_filter_chain(chained_filters: &mut Vec<MirNodeRef>) {
 
         let prev_node = first_node.borrow().ancestors.first().unwrap().clone();
         let fields = prev_node.borrow().columns().to_vec();
-        let merged_conditions = to_conditions(chained_filters);
+        let merged_predicate = to_predicate(chained_filters);
 
         let merged_filter = MirNode::new(
             name.as_str(),
             schema_version,
             fields,
             MirNodeType::Filter {
-                conditions: merged_conditions.clone(),
+                predicate: merged_predicate,
             },
             vec![prev_node],
             vec![],
@@ -332,22 +346,23 @@ fn end_filter_chain(chained_filters: &mut Vec<MirNodeRef>) {
     chained_filters.clear();
 }
 
-fn to_conditions(chained_filters: &[MirNodeRef]) -> Vec<(usize, FilterCondition)> {
-    let mut merged_conditions = Vec::new();
+fn to_predicate(chained_filters: &[MirNodeRef]) -> Predicate {
+    let mut merged_predicates = Vec::new();
     for filter in chained_filters {
         match filter.borrow().inner {
-            MirNodeType::Filter { ref conditions } => {
-                // Note that this assumes that there is only ever one column being filtered on for
-                // each filter that is being merged.
-                for (i, cond) in conditions {
-                    merged_conditions.push((i.clone(), cond.clone()));
+            MirNodeType::Filter { ref predicate } => {
+                // Note that this assumes that all filters in the chain have the same columns, so
+                // that their predicates refer to the same column indices.
+                match *predicate {
+                    Predicate::And(ref ps) => merged_predicates.extend(ps.iter().cloned()),
+                    ref p => merged_predicates.push(p.clone()),
                 }
             }
             _ => unreachable!(),
         }
     }
 
-    merged_conditions
+    Predicate::And(merged_predicates)
 }
 
 // currently unused
@@ -3,10 +3,10 @@ use std::fmt::{self, Write};
 
 use crate::node::{MirNode, MirNodeType};
 use crate::query::MirQuery;
-use dataflow::ops::filter::FilterCondition;
 use dataflow::ops::grouped::aggregate::Aggregation as AggregationKind;
 use dataflow::ops::grouped::extremum::Extremum as ExtremumKind;
 use dataflow::ops::grouped::filteraggregate::FilterAggregation as FilterAggregationKind;
+use dataflow::ops::semi_join::SemiJoinType;
 
 pub trait GraphViz {
     fn to_graphviz(&self) -> Result<String, fmt::Error>;
@@ -107,6 +107,11 @@ impl GraphViz for MirNodeType {
                 let op_string = match *kind {
                     AggregationKind::COUNT => format!("\\|*\\|({})", print_col(on)),
                     AggregationKind::SUM => format!("𝛴({})", print_col(on)),
+                    AggregationKind::AVG => format!("avg({})", print_col(on)),
+                    AggregationKind::VARIANCE => format!("var({})", print_col(on)),
+                    AggregationKind::STDDEV => format!("stddev({})", print_col(on)),
+                    AggregationKind::BOOL_AND => format!("bool_and({})", print_col(on)),
+                    AggregationKind::BOOL_OR => format!("bool_or({})", print_col(on)),
                 };
                 let group_cols = group_by
                     .iter()
@@ -167,7 +172,7 @@ impl GraphViz for MirNodeType {
                     .join(", ");
                 write!(out, "{} | γ: {}", op_string, group_cols)?;
             }
-            MirNodeType::Filter { ref conditions } => {
+            MirNodeType::Filter { ref predicate } => {
                 use regex::Regex;
 
                 let escape = |s: &str| {
@@ -176,28 +181,7 @@ impl GraphViz for MirNodeType {
                         .replace_all(s, "\\$1")
                         .to_string()
                 };
-                write!(
-                    out,
-                    "σ: {}",
-                    conditions
-                        .iter()
-                        .filter_map(|(i, ref cond)| match *cond {
-                            FilterCondition::Comparison(ref op, ref x) => {
-                                Some(format!("f{} {} {}", i, escape(&format!("{}", op)), x))
-                            }
-                            FilterCondition::In(ref xs) => Some(format!(
-                                "f{} IN ({})",
-                                i,
-                                xs.iter()
-                                    .map(|d| format!("{}", d))
-                                    .collect::<Vec<_>>()
-                                    .join(", ")
-                            )),
-                        })
-                        .collect::<Vec<_>>()
-                        .as_slice()
-                        .join(", ")
-                )?;
+                write!(out, "σ: {}", escape(&format!("{}", predicate)))?;
             }
             MirNodeType::GroupConcat {
                 ref on,
@@ -221,13 +205,25 @@ impl GraphViz for MirNodeType {
                     .join(", ");
                 write!(out, "⋈  | on: {}", jc)?;
             }
-            MirNodeType::Leaf { ref keys, .. } => {
+            MirNodeType::Leaf {
+                ref keys,
+                ref filters,
+                ..
+            } => {
                 let key_cols = keys
                     .iter()
                     .map(|k| print_col(k))
                     .collect::<Vec<_>>()
                     .join(", ");
                 write!(out, "Leaf | ⚷: {}", key_cols)?;
+                if !filters.is_empty() {
+                    let filter_cols = filters
+                        .iter()
+                        .map(|&(ref c, ref op, _)| format!("{} {} ?", print_col(c), op))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    write!(out, " | σ: {}", filter_cols)?;
+                }
             }
             MirNodeType::LeftJoin {
                 ref on_left,
@@ -242,6 +238,47 @@ impl GraphViz for MirNodeType {
                     .join(", ");
                 write!(out, "⋉  | on: {}", jc)?;
             }
+            MirNodeType::OuterJoin {
+                ref on_left,
+                ref on_right,
+                ..
+            } => {
+                let jc = on_left
+                    .iter()
+                    .zip(on_right)
+                    .map(|(l, r)| format!("{}:{}", print_col(l), print_col(r)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(out, "⟗  | on: {}", jc)?;
+            }
+            MirNodeType::ThetaJoin { ref on, .. } => {
+                let jc = on
+                    .iter()
+                    .map(|&(ref l, ref op, ref r)| {
+                        format!("{} {} {}", print_col(l), op, print_col(r))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(out, "⋈θ  | on: {}", jc)?;
+            }
+            MirNodeType::SemiJoin {
+                ref on_left,
+                ref on_right,
+                ref kind,
+            } => {
+                let jc = on_left
+                    .iter()
+                    .zip(on_right)
+                    .map(|(l, r)| format!("{}:{}", print_col(l), print_col(r)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let op = match *kind {
+                    SemiJoinType::Semi => "∃",
+                    SemiJoinType::Anti => "▷",
+                    SemiJoinType::NotIn => "∉",
+                };
+                write!(out, "{}  | on: {}", op, jc)?;
+            }
             MirNodeType::Latest { ref group_by } => {
                 let key_cols = group_by
                     .iter()
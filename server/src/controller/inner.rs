@@ -14,7 +14,8 @@ use noria::builders::*;
 use noria::channel::tcp::{SendError, TcpSender};
 use noria::consensus::{Authority, Epoch, STATE_KEY};
 use noria::debug::stats::{DomainStats, GraphStats, NodeStats};
-use noria::ActivationResult;
+use noria::error::RecipeError;
+use noria::{ActivationResult, MigrationPlan, QueryPlan, RecipeVersion};
 use petgraph::visit::Bfs;
 use slog::Logger;
 use std::collections::{BTreeMap, HashMap, HashSet};
@@ -265,18 +266,22 @@ impl ControllerInner {
             (Method::POST, "/view_builder") => json::from_slice(&body)
                 .map_err(|_| StatusCode::BAD_REQUEST)
                 .map(|args| Ok(json::to_string(&self.view_builder(args)).unwrap())),
+            // recipe changes that fail return a `RecipeError` for the client to inspect
             (Method::POST, "/extend_recipe") => json::from_slice(&body)
                 .map_err(|_| StatusCode::BAD_REQUEST)
-                .map(|args| {
-                    self.extend_recipe(authority, args)
-                        .map(|r| json::to_string(&r).unwrap())
-                }),
+                .map(|args| Ok(json::to_string(&self.extend_recipe(authority, args)).unwrap())),
             (Method::POST, "/install_recipe") => json::from_slice(&body)
                 .map_err(|_| StatusCode::BAD_REQUEST)
-                .map(|args| {
-                    self.install_recipe(authority, args)
-                        .map(|r| json::to_string(&r).unwrap())
-                }),
+                .map(|args| Ok(json::to_string(&self.install_recipe(authority, args)).unwrap())),
+            (Method::POST, "/plan_recipe") => json::from_slice(&body)
+                .map_err(|_| StatusCode::BAD_REQUEST)
+                .map(|args| Ok(json::to_string(&self.plan_recipe(args)).unwrap())),
+            (Method::POST, "/recipe_history") => Ok(self
+                .recipe_history(authority)
+                .map(|r| json::to_string(&r).unwrap())),
+            (Method::POST, "/rollback_to") => json::from_slice(&body)
+                .map_err(|_| StatusCode::BAD_REQUEST)
+                .map(|args| Ok(json::to_string(&self.rollback_to(authority, args)).unwrap())),
             (Method::POST, "/set_security_config") => json::from_slice(&body)
                 .map_err(|_| StatusCode::BAD_REQUEST)
                 .map(|args| {
@@ -325,13 +330,12 @@ impl ControllerInner {
             if let Some((recipes, recipe_version)) = self.pending_recovery.take() {
                 assert_eq!(self.workers.len(), self.quorum);
                 assert_eq!(self.recipe.version(), 0);
-                assert!(recipe_version + 1 >= recipes.len());
+                assert!(recipe_version >= recipes.len());
 
                 info!(self.log, "Restoring graph configuration");
-                self.recipe = Recipe::with_version(
-                    recipe_version + 1 - recipes.len(),
-                    Some(self.log.clone()),
-                );
+                // each of the recipes bumps the version, which ends up at the persisted one
+                self.recipe =
+                    Recipe::with_version(recipe_version - recipes.len(), Some(self.log.clone()));
                 for r in recipes {
                     self.apply_recipe(self.recipe.clone().extend(&r).unwrap())
                         .unwrap();
@@ -658,6 +662,7 @@ impl ControllerInner {
             mainline: self,
             added: Default::default(),
             columns: Default::default(),
+            originals: Default::default(),
             readers: Default::default(),
             context,
             start: time::Instant::now(),
@@ -680,6 +685,7 @@ impl ControllerInner {
             mainline: self,
             added: Default::default(),
             columns: Default::default(),
+            originals: Default::default(),
             readers: Default::default(),
             context: Default::default(),
             start: time::Instant::now(),
@@ -690,6 +696,56 @@ impl ControllerInner {
         r
     }
 
+    /// Perform a new query schema migration that may fail. The migration is committed if `f`
+    /// succeeds, and aborted if it fails, in which case the graph is left as it was.
+    fn try_migrate<F, T, E>(&mut self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Migration) -> Result<T, E>,
+    {
+        info!(self.log, "starting migration");
+        let miglog = self.log.new(o!());
+        let mut m = Migration {
+            mainline: self,
+            added: Default::default(),
+            columns: Default::default(),
+            originals: Default::default(),
+            readers: Default::default(),
+            context: Default::default(),
+            start: time::Instant::now(),
+            log: miglog,
+        };
+        let r = f(&mut m);
+        if r.is_ok() {
+            m.commit();
+        } else {
+            m.abort();
+        }
+        r
+    }
+
+    /// Perform a new query schema migration, but abort it rather than commit it, so that the graph
+    /// is left as it was.
+    fn dry_run<F, T>(&mut self, f: F) -> T
+    where
+        F: FnOnce(&mut Migration) -> T,
+    {
+        info!(self.log, "starting dry run of migration");
+        let miglog = self.log.new(o!("dry-run" => true));
+        let mut m = Migration {
+            mainline: self,
+            added: Default::default(),
+            columns: Default::default(),
+            originals: Default::default(),
+            readers: Default::default(),
+            context: Default::default(),
+            start: time::Instant::now(),
+            log: miglog,
+        };
+        let r = f(&mut m);
+        m.abort();
+        r
+    }
+
     #[cfg(test)]
     pub(crate) fn graph(&self) -> &Graph {
         &self.ingredients
@@ -770,6 +826,14 @@ impl ControllerInner {
         self.find_view_for(node, name).map(|r| {
             let domain = self.ingredients[r].domain();
             let columns = self.ingredients[r].fields().to_vec();
+            let key = self.ingredients[r]
+                .with_reader(|r| r.key().map(Vec::from))
+                .unwrap()
+                .unwrap_or_default();
+            let key_position = self.ingredients[r]
+                .with_reader(|r| r.lookup_order().first().cloned())
+                .unwrap()
+                .unwrap_or(0);
             let schema = self.view_schema(r);
             let shards = (0..self.domains[&domain].shards())
                 .map(|i| self.read_addrs[&self.domains[&domain].assignment(i)])
@@ -778,6 +842,8 @@ impl ControllerInner {
             ViewBuilder {
                 node: r,
                 columns,
+                key,
+                key_position,
                 schema,
                 shards,
             }
@@ -1006,11 +1072,8 @@ impl ControllerInner {
         Ok(())
     }
 
-    fn apply_recipe(&mut self, mut new: Recipe) -> Result<ActivationResult, String> {
-        let r = self.migrate(|mig| {
-            new.activate(mig)
-                .map_err(|e| format!("failed to activate recipe: {}", e))
-        });
+    fn apply_recipe(&mut self, mut new: Recipe) -> Result<ActivationResult, RecipeError> {
+        let r = self.try_migrate(|mig| new.activate(mig));
 
         match r {
             Ok(ref ra) => {
@@ -1058,47 +1121,87 @@ impl ControllerInner {
             }
             Err(ref e) => {
                 crit!(self.log, "failed to apply recipe: {}", e);
-                // TODO(malte): a little yucky, since we don't really need the blank recipe
-                let recipe = mem::replace(&mut self.recipe, Recipe::blank(None));
-                self.recipe = recipe.revert();
+                self.recipe = new.revert();
             }
         }
 
         r
     }
 
+    /// Works out how extending the recipe with `add_txt` would change the graph, leaving both the
+    /// recipe and the graph as they are.
+    fn plan_recipe(&mut self, add_txt: String) -> Result<MigrationPlan, RecipeError> {
+        // the memory size of existing state, some of which new state may be filled from
+        let mut state_sizes = HashMap::new();
+        for (_, (_, nodes)) in self.get_statistics().domains {
+            for (ni, stats) in nodes {
+                *state_sizes.entry(ni).or_insert(0) += stats.mem_size;
+            }
+        }
+
+        let old = mem::replace(&mut self.recipe, Recipe::blank(None));
+        let mut new = match old.extend(&add_txt) {
+            Ok(new) => new,
+            Err((old, e)) => {
+                self.recipe = old;
+                return Err(e);
+            }
+        };
+
+        let r = self.dry_run(|mig| {
+            let (ra, queries) = new.dry_run(mig)?;
+            let mut plan = mig.plan(&state_sizes);
+            plan.removed_leaves = ra.removed_leaves;
+            plan.queries = queries
+                .into_iter()
+                .map(|(name, qfp)| {
+                    let query = QueryPlan {
+                        reused_nodes: qfp.reused_nodes,
+                        new_nodes: qfp.new_nodes.len(),
+                    };
+                    (name, query)
+                })
+                .collect();
+            Ok(plan)
+        });
+        self.recipe = new.revert();
+        r
+    }
+
     fn extend_recipe<A: Authority + 'static>(
         &mut self,
         authority: &Arc<A>,
         add_txt: String,
-    ) -> Result<ActivationResult, String> {
+    ) -> Result<ActivationResult, RecipeError> {
         // needed because self.apply_recipe needs to mutate self.recipe, so can't have it borrowed
         let new = mem::replace(&mut self.recipe, Recipe::blank(None));
         match new.extend(&add_txt) {
             Ok(new) => {
-                let activation_result = self.apply_recipe(new);
+                let activation_result = self.apply_recipe(new)?;
+                // recipes that fail to apply are reverted, and must not be persisted
                 if authority
                     .read_modify_write(STATE_KEY, |state: Option<ControllerState>| match state {
                         None => unreachable!(),
                         Some(ref state) if state.epoch > self.epoch => Err(()),
                         Some(mut state) => {
-                            state.recipe_version = self.recipe.version();
-                            state.recipes.push(add_txt.clone());
+                            let mut recipes = mem::replace(&mut state.recipes, vec![]);
+                            recipes.push(add_txt.clone());
+                            state.set_recipe(self.recipe.version(), recipes);
                             Ok(state)
                         }
                     })
                     .is_err()
                 {
-                    return Err("Failed to persist recipe extension".to_owned());
+                    return Err(String::from("Failed to persist recipe extension").into());
                 }
 
-                activation_result
+                Ok(activation_result)
             }
             Err((old, e)) => {
                 // need to restore the old recipe
-                crit!(self.log, "failed to extend recipe: {:?}", e);
+                crit!(self.log, "failed to extend recipe: {}", e);
                 self.recipe = old;
-                Err("failed to extend recipe".to_owned())
+                Err(e)
             }
         }
     }
@@ -1107,35 +1210,83 @@ impl ControllerInner {
         &mut self,
         authority: &Arc<A>,
         r_txt: String,
-    ) -> Result<ActivationResult, String> {
+    ) -> Result<ActivationResult, RecipeError> {
         match Recipe::from_str(&r_txt, Some(self.log.clone())) {
             Ok(r) => {
                 let old = mem::replace(&mut self.recipe, Recipe::blank(None));
                 let new = old.replace(r).unwrap();
-                let activation_result = self.apply_recipe(new);
+                let activation_result = self.apply_recipe(new)?;
                 if authority
                     .read_modify_write(STATE_KEY, |state: Option<ControllerState>| match state {
                         None => unreachable!(),
                         Some(ref state) if state.epoch > self.epoch => Err(()),
                         Some(mut state) => {
-                            state.recipe_version = self.recipe.version();
-                            state.recipes = vec![r_txt.clone()];
+                            state.set_recipe(self.recipe.version(), vec![r_txt.clone()]);
                             Ok(state)
                         }
                     })
                     .is_err()
                 {
-                    return Err("Failed to persist recipe installation".to_owned());
+                    return Err(String::from("Failed to persist recipe installation").into());
                 }
-                activation_result
+                Ok(activation_result)
             }
             Err(e) => {
-                crit!(self.log, "failed to parse recipe: {:?}", e);
-                Err("failed to parse recipe".to_owned())
+                crit!(self.log, "failed to parse recipe: {}", e);
+                Err(e)
             }
         }
     }
 
+    fn recipe_history<A: Authority + 'static>(
+        &self,
+        authority: &Arc<A>,
+    ) -> Result<Vec<RecipeVersion>, String> {
+        let state = authority
+            .try_read(STATE_KEY)
+            .map_err(|e| format!("failed to read controller state: {}", e))?
+            .ok_or_else(|| String::from("controller state is missing"))?;
+        let state: ControllerState = serde_json::from_slice(&state)
+            .map_err(|e| format!("failed to parse controller state: {}", e))?;
+        Ok(state.recipe_history.versions())
+    }
+
+    fn rollback_to<A: Authority + 'static>(
+        &mut self,
+        authority: &Arc<A>,
+        version: usize,
+    ) -> Result<ActivationResult, RecipeError> {
+        let recipes = self
+            .recipe_history(authority)?
+            .into_iter()
+            .find(|r| r.version == version)
+            .map(|r| r.recipes)
+            .ok_or_else(|| format!("recipe version {} is not in the recipe history", version))?;
+
+        // rebuild the recipe from the texts that made it up, and install it as a new version
+        let r = Recipe::from_texts(&recipes, Some(self.log.clone()))?;
+        // replacing a table would start it over empty, and renamed columns would not be renamed
+        // back, so only queries and views can be rolled back
+        self.recipe.check_same_tables(&r)?;
+        let old = mem::replace(&mut self.recipe, Recipe::blank(None));
+        let new = old.replace(r).unwrap();
+        let activation_result = self.apply_recipe(new)?;
+        if authority
+            .read_modify_write(STATE_KEY, |state: Option<ControllerState>| match state {
+                None => unreachable!(),
+                Some(ref state) if state.epoch > self.epoch => Err(()),
+                Some(mut state) => {
+                    state.set_recipe(self.recipe.version(), recipes.clone());
+                    Ok(state)
+                }
+            })
+            .is_err()
+        {
+            return Err(String::from("Failed to persist recipe rollback").into());
+        }
+        Ok(activation_result)
+    }
+
     fn graphviz(&self, detailed: bool) -> String {
         graphviz(&self.ingredients, detailed, &self.materializations)
     }
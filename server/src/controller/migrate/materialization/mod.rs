@@ -12,6 +12,7 @@ use crate::controller::{
 };
 use crate::controller::{Worker, WorkerIdentifier};
 use dataflow::prelude::*;
+use noria::PlannedState;
 use petgraph;
 use petgraph::graph::NodeIndex;
 use slog::Logger;
@@ -168,6 +169,13 @@ impl Materializations {
                 indices.insert(ni, (vec![0], true));
             }
 
+            let mut unique_keys = Vec::new();
+            if let Some(b) = n.get_base() {
+                // bases check inserts against their unique keys, so they need to be able to look
+                // up rows by each of them.
+                unique_keys.extend(b.unique_keys().iter().cloned());
+            }
+
             for (ni, (cols, lookup)) in indices {
                 trace!(self.log, "new indexing obligation";
                        "node" => ni.index(),
@@ -186,6 +194,12 @@ impl Materializations {
                         .insert(cols);
                 }
             }
+            for cols in unique_keys {
+                lookup_obligations
+                    .entry(ni)
+                    .or_insert_with(HashSet::new)
+                    .insert(cols);
+            }
         }
 
         // map all the indices to the corresponding columns in the parent
@@ -312,6 +326,10 @@ impl Materializations {
         // if the view was marked as partial, we add the necessary indexes to self.added for the
         // parent views, and keep walking. this is the reason we need the reverse topological
         // order: if we didn't, a node could receive additional indexes after we've checked it!
+        //
+        // partial ordered readers replay whole key ranges from their nearest materializations,
+        // which must then be full, since partial state can't tell which keys fall in a range.
+        let mut range_sources = HashSet::new();
         for ni in ordered {
             let indexes = match replay_obligations.remove(&ni) {
                 Some(idxs) => idxs,
@@ -335,6 +353,25 @@ impl Materializations {
                 able = false;
             }
 
+            if graph[ni].is_internal() && !graph[ni].can_replay_partially() {
+                warn!(self.log, "full because node cannot be partially replayed"; "node" => ni.index());
+                able = false;
+            }
+
+            // lookups against ranged readers give key prefixes, which range replays don't fill
+            if let Ok(true) = graph[ni].with_reader(|r| r.is_ranged()) {
+                warn!(self.log, "full because reader is ranged"; "node" => ni.index());
+                able = false;
+            }
+
+            if range_sources.contains(&ni) {
+                warn!(self.log, "full because an ordered reader below replays ranges from it"; "node" => ni.index());
+                able = false;
+            }
+
+            let replays_ranges = graph[ni].with_reader(|r| r.is_ordered()).unwrap_or(false);
+            let mut replays_ranges_from = Vec::new();
+
             // we are already fully materialized, so can't be made partial
             if !new.contains(&ni)
                 && self.added.get(&ni).map(|i| i.len()).unwrap_or(0)
@@ -386,8 +423,29 @@ impl Materializations {
 
                 let paths = keys::provenance_of(graph, ni, &index[..], plan::Plan::on_join(graph));
 
+                if replays_ranges && paths.len() != 1 {
+                    warn!(self.log, "full because ordered reader would replay ranges along many paths";
+                          "node" => ni.index());
+                    able = false;
+                    break 'attempt;
+                }
+
                 for path in paths {
                     for (pni, cols) in path.into_iter().skip(1) {
+                        if graph[pni].is_internal() && !graph[pni].can_replay_partially() {
+                            warn!(self.log, "full because path cannot be partially replayed";
+                                  "node" => ni.index(), "broken at" => pni.index());
+                            able = false;
+                            break 'attempt;
+                        }
+                        if replays_ranges
+                            && (graph[pni].is_sharder() || graph[pni].is_shard_merger())
+                        {
+                            warn!(self.log, "full because ordered reader would replay ranges across shards";
+                                  "node" => ni.index(), "broken at" => pni.index());
+                            able = false;
+                            break 'attempt;
+                        }
                         if let Some(p) = cols.iter().position(Option::is_none) {
                             warn!(self.log, "full because column {} does not resolve", index[p];
                                   "node" => ni.index(), "broken at" => pni.index());
@@ -396,6 +454,15 @@ impl Materializations {
                         }
                         let index: Vec<_> = cols.into_iter().map(Option::unwrap).collect();
                         if let Some(m) = self.have.get(&pni) {
+                            if replays_ranges {
+                                if self.partial.contains(&pni) {
+                                    warn!(self.log, "full because ordered reader would replay ranges from partial view";
+                                          "node" => ni.index(), "view" => pni.index());
+                                    able = false;
+                                    break 'attempt;
+                                }
+                                replays_ranges_from.push(pni);
+                            }
                             if !m.contains(&index) {
                                 // we'd need to add an index to this view,
                                 add.entry(pni)
@@ -412,6 +479,7 @@ impl Materializations {
                 // we can do partial if we add all those indices!
                 self.partial.insert(ni);
                 warn!(self.log, "using partial materialization for {}", ni.index());
+                range_sources.extend(replays_ranges_from);
                 for (mi, indices) in add {
                     let m = replay_obligations.entry(mi).or_default();
                     for index in indices {
@@ -452,6 +520,85 @@ impl Materializations {
         assert!(replay_obligations.is_empty());
     }
 
+    /// Works out which state committing the nodes in `new` would create or add indices to,
+    /// without committing to any of it. `state_sizes` holds the memory size of existing state.
+    pub(super) fn plan(
+        &self,
+        graph: &Graph,
+        new: &HashSet<NodeIndex>,
+        state_sizes: &HashMap<NodeIndex, u64>,
+    ) -> Vec<PlannedState> {
+        let mut m = Materializations {
+            log: self.log.new(o!("dry-run" => true)),
+
+            have: self.have.clone(),
+            added: self.added.clone(),
+
+            partial: self.partial.clone(),
+            partial_enabled: self.partial_enabled,
+            frontier_strategy: self.frontier_strategy.clone(),
+
+            tag_generator: AtomicUsize::new(self.tag_generator.load(Ordering::SeqCst)),
+        };
+        m.extend(graph, new);
+
+        // readers hold state for their key, but are not tracked in `added`
+        let readers = new
+            .iter()
+            .filter_map(|&ni| match graph[ni].with_reader(|r| r.key()) {
+                Ok(Some(key)) => Some((ni, vec![Vec::from(key)].into_iter().collect())),
+                _ => None,
+            });
+        let mut planned: Vec<_> = m
+            .added
+            .iter()
+            .map(|(&ni, indices)| (ni, indices.clone()))
+            .chain(readers)
+            .map(|(ni, indices)| {
+                let partial = m.partial.contains(&ni);
+                // existing state is indexed in place, and new bases start out empty
+                let backfill = !partial && !self.have.contains_key(&ni) && !graph[ni].is_base();
+                let replay_bytes = if backfill {
+                    self.replay_sources(graph, ni)
+                        .iter()
+                        .map(|src| state_sizes.get(src).cloned().unwrap_or(0))
+                        .sum()
+                } else {
+                    0
+                };
+                let mut indices: Vec<_> = indices.into_iter().collect();
+                indices.sort();
+                PlannedState {
+                    node: graph[ni].name().to_owned(),
+                    existing: if new.contains(&ni) { None } else { Some(ni) },
+                    indices,
+                    partial,
+                    backfill,
+                    replay_bytes,
+                }
+            })
+            .collect();
+        planned.sort_by_key(|s| (s.existing.is_none(), s.node.clone()));
+        planned
+    }
+
+    /// Returns the closest ancestors of `ni` that hold state, from which a full replay to `ni`
+    /// would read.
+    fn replay_sources(&self, graph: &Graph, ni: NodeIndex) -> HashSet<NodeIndex> {
+        let mut sources = HashSet::new();
+        let mut stack: Vec<_> = graph
+            .neighbors_directed(ni, petgraph::EdgeDirection::Incoming)
+            .collect();
+        while let Some(pi) = stack.pop() {
+            if self.have.contains_key(&pi) {
+                sources.insert(pi);
+            } else {
+                stack.extend(graph.neighbors_directed(pi, petgraph::EdgeDirection::Incoming));
+            }
+        }
+        sources
+    }
+
     /// Retrieves the materialization status of a given node, or None
     /// if the node isn't materialized.
     pub(in crate::controller) fn get_status(
@@ -23,6 +23,8 @@
 use crate::controller::ControllerInner;
 use dataflow::prelude::*;
 use dataflow::{node, prelude::Packet};
+use nom_sql::OrderType;
+use noria::MigrationPlan;
 use std::collections::{HashMap, HashSet};
 use std::time::Instant;
 
@@ -39,17 +41,21 @@ mod sharding;
 pub(super) enum ColumnChange {
     Add(String, DataType),
     Drop(usize),
+    Rename(usize, String),
 }
 
 /// A `Migration` encapsulates a number of changes to the Soup data flow graph.
 ///
 /// Only one `Migration` can be in effect at any point in time. No changes are made to the running
-/// graph until the `Migration` is committed (using `Migration::commit`).
+/// graph until the `Migration` is committed (using `Migration::commit`), and a `Migration` that is
+/// aborted instead (using `Migration::abort`) leaves no trace in the graph.
 // crate viz for tests
 pub struct Migration<'a> {
     pub(super) mainline: &'a mut ControllerInner,
     pub(super) added: HashSet<NodeIndex>,
     pub(super) columns: Vec<(NodeIndex, ColumnChange)>,
+    /// Existing nodes as they were before this migration changed their columns.
+    pub(super) originals: HashMap<NodeIndex, node::Node>,
     pub(super) readers: HashMap<NodeIndex, NodeIndex>,
 
     pub(super) start: Instant,
@@ -191,6 +197,7 @@ impl<'a> Migration<'a> {
         let field = field.to_string();
         let base = &mut self.mainline.ingredients[node];
         assert!(base.is_base());
+        self.originals.entry(node).or_insert_with(|| base.clone());
 
         // we need to tell the base about its new column and its default, so that old writes that
         // do not have it get the additional value added to them.
@@ -215,6 +222,7 @@ impl<'a> Migration<'a> {
 
         let base = &mut self.mainline.ingredients[node];
         assert!(base.is_base());
+        self.originals.entry(node).or_insert_with(|| base.clone());
 
         // we need to tell the base about the dropped column, so that old writes that contain that
         // column will have it filled in with default values (this is done in Mutator).
@@ -225,6 +233,23 @@ impl<'a> Migration<'a> {
         self.columns.push((node, ColumnChange::Drop(column)));
     }
 
+    /// Rename a column of a base node.
+    // crate viz for tests
+    pub fn rename_column<S: ToString>(&mut self, node: NodeIndex, column: usize, field: S) {
+        // not allowed to rename columns of new nodes
+        assert!(!self.added.contains(&node));
+
+        let field = field.to_string();
+        let base = &mut self.mainline.ingredients[node];
+        assert!(base.is_base());
+        self.originals.entry(node).or_insert_with(|| base.clone());
+        base.rename_column(column, &field);
+
+        // also eventually propagate to domain clone
+        self.columns
+            .push((node, ColumnChange::Rename(column, field)));
+    }
+
     #[cfg(test)]
     pub(crate) fn graph(&self) -> &Graph {
         self.mainline.graph()
@@ -277,6 +302,123 @@ impl<'a> Migration<'a> {
             .unwrap();
     }
 
+    /// Like `maintain`, but have the reader filter the rows it finds for each key according to
+    /// `filters`. The values for the filters are given after the key in each lookup.
+    pub fn maintain_filtered(
+        &mut self,
+        name: String,
+        n: NodeIndex,
+        key: &[usize],
+        filters: Vec<node::special::PostLookupFilter>,
+    ) {
+        self.maintain(name, n, key);
+
+        let ri = self.readers[&n];
+        self.mainline.ingredients[ri]
+            .with_reader_mut(|r| r.set_post_lookup_filters(filters))
+            .unwrap();
+    }
+
+    /// Like `maintain_filtered`, but have the reader look up ranges of values of the last column
+    /// of `key` rather than single values, as bounded by the filters that compare that column.
+    /// Each lookup then gives only the other key columns, followed by the filters' parameter
+    /// values.
+    ///
+    /// Note that a ranged view is always fully materialized.
+    pub fn maintain_ranged(
+        &mut self,
+        name: String,
+        n: NodeIndex,
+        key: &[usize],
+        filters: Vec<node::special::PostLookupFilter>,
+    ) {
+        self.maintain_filtered(name, n, key, filters);
+
+        let ri = self.readers[&n];
+        self.mainline.ingredients[ri]
+            .with_reader_mut(|r| r.set_ranged())
+            .unwrap();
+    }
+
+    /// Have the reader for the given node (added with one of the `maintain` methods) take the
+    /// values of each lookup from the given positions: first the key values, and then the
+    /// parameter values for its post-lookup filters.
+    pub fn reorder_reader_lookups(&mut self, n: NodeIndex, lookup_order: Vec<usize>) {
+        let ri = self.readers[&n];
+        self.mainline.ingredients[ri]
+            .with_reader_mut(|r| r.set_lookup_order(lookup_order))
+            .unwrap();
+    }
+
+    /// Have the reader for the given node (added with one of the `maintain` methods) return the
+    /// rows for each key sorted by the given columns.
+    pub fn sort_reader(&mut self, n: NodeIndex, order: Vec<(usize, OrderType)>) {
+        let ri = self.readers[&n];
+        self.mainline.ingredients[ri]
+            .with_reader_mut(|r| r.set_order(order))
+            .unwrap();
+    }
+
+    /// Like `maintain`, but keep the maintained state ordered by `key` so that it can efficiently
+    /// answer range lookups.
+    ///
+    /// If the view is partially materialized, range lookups that miss replay the missing ranges
+    /// from the view's nearest materialization, which is then kept fully materialized.
+    pub fn maintain_ordered(&mut self, name: String, n: NodeIndex, key: &[usize]) {
+        self.maintain(name, n, key);
+
+        let ri = self.readers[&n];
+        self.mainline.ingredients[ri]
+            .with_reader_mut(|r| r.set_ordered())
+            .unwrap();
+    }
+
+    /// Work out how committing this `Migration` would change the graph, without changing it.
+    ///
+    /// The plan covers the domains and state that committing would create; `state_sizes` holds
+    /// the memory size of the state of existing nodes, from which new state may be filled.
+    pub(super) fn plan(&self, state_sizes: &HashMap<NodeIndex, u64>) -> MigrationPlan {
+        let mainline = &*self.mainline;
+
+        // the steps of `commit` that shape the graph change it, so they plan on a copy of it
+        let mut graph = mainline.ingredients.clone();
+        let mut new = self.added.clone();
+        let mut topo = mainline.topo_order(&new);
+        if let Some(shards) = mainline.sharding {
+            topo = sharding::shard(&self.log, &mut graph, &mut new, &topo, shards).0;
+        }
+        let mut ndomains = mainline.ndomains;
+        assignment::assign(&self.log, &mut graph, &topo, &mut ndomains);
+        routing::add(&self.log, &mut graph, mainline.source, &mut new, &topo);
+
+        let materializations = mainline.materializations.plan(&graph, &new, state_sizes);
+        MigrationPlan {
+            new_domains: ndomains - mainline.ndomains,
+            replay_bytes: materializations.iter().map(|s| s.replay_bytes).sum(),
+            materializations,
+            ..Default::default()
+        }
+    }
+
+    /// Discard the changes introduced by this `Migration`, leaving the graph as it was before the
+    /// migration started.
+    pub(super) fn abort(self) {
+        info!(self.log, "aborting migration"; "#nodes" => self.added.len());
+
+        let mainline = self.mainline;
+        for (ni, n) in self.originals {
+            mainline.ingredients[ni] = n;
+        }
+
+        // removing a node moves the last node into its place. the new nodes are the last ones in
+        // the graph, so removing them from the back leaves the indices of all other nodes intact.
+        let mut added: Vec<_> = self.added.into_iter().collect();
+        added.sort();
+        for ni in added.into_iter().rev() {
+            mainline.ingredients.remove_node(ni);
+        }
+    }
+
     /// Commit the changes introduced by this `Migration` to the master `Soup`.
     ///
     /// This will spin up an execution thread for each new thread domain, and hook those new
@@ -533,8 +675,8 @@ impl<'a> Migration<'a> {
                     })
                     .collect()
             } else {
-                // ingress nodes don't need to know about deleted columns, because those are only
-                // relevant when new writes enter the graph.
+                // ingress nodes don't need to know about deleted or renamed columns, because those
+                // are only relevant when new writes enter the graph.
                 Vec::new()
             };
             inform.push(ni);
@@ -551,6 +693,11 @@ impl<'a> Migration<'a> {
                         node: n.local_addr(),
                         column,
                     }),
+                    ColumnChange::Rename(column, field) => Box::new(Packet::RenameBaseColumn {
+                        node: n.local_addr(),
+                        column,
+                        field,
+                    }),
                 };
 
                 let domain = mainline.domains.get_mut(&n.domain()).unwrap();
@@ -26,6 +26,17 @@ pub fn shard(
             .map(|ni| (ni, graph[ni].sharded_by()))
             .collect();
 
+        if graph[node].is_base() && !graph[node].get_base().unwrap().unique_keys().is_empty() {
+            // two rows with the same unique key may hash to different shards, and would then
+            // never be checked against each other.
+            info!(log, "not sharding base with unique keys"; "node" => ?node);
+            graph
+                .node_weight_mut(node)
+                .unwrap()
+                .shard_by(Sharding::ForcedNone);
+            continue;
+        }
+
         let mut need_sharding = if graph[node].is_internal() || graph[node].is_base() {
             // suggest_indexes is okay because `node` *must* be new, and therefore will return
             // global node indices.
@@ -1,18 +1,19 @@
-use nom_sql::{
-    ArithmeticBase, ArithmeticExpression, ColumnConstraint, ColumnSpecification, Literal, OrderType,
-};
+use nom_sql::{ColumnConstraint, ColumnSpecification, Literal, Operator, OrderType};
 use std::collections::HashMap;
 
 use crate::controller::Migration;
 use common::DataType;
-use dataflow::ops::filter::FilterCondition;
+use dataflow::node::special::PostLookupFilter;
+use dataflow::ops::filter::{FilterCondition, Predicate};
 use dataflow::ops::join::{Join, JoinType};
 use dataflow::ops::latest::Latest;
-use dataflow::ops::project::{Project, ProjectExpression, ProjectExpressionBase};
+use dataflow::ops::project::{Project, ProjectExpression};
+use dataflow::ops::semi_join::{SemiJoin, SemiJoinType};
+use dataflow::ops::theta_join::ThetaJoin;
 use dataflow::{node, ops};
 use mir::node::{GroupedNodeType, MirNode, MirNodeType};
 use mir::query::{MirQuery, QueryFlowParts};
-use mir::{Column, FlowNode, MirNodeRef};
+use mir::{Column, Expression, FlowNode, MirNodeRef};
 use petgraph::graph::NodeIndex;
 
 pub(super) fn mir_query_to_flow_parts(
@@ -104,9 +105,12 @@ fn mir_node_to_flow_parts(
                 MirNodeType::Base {
                     ref mut column_specs,
                     ref keys,
+                    ref unique_keys,
                     ref adapted_over,
                 } => match *adapted_over {
-                    None => make_base_node(&name, column_specs.as_mut_slice(), keys, mig),
+                    None => {
+                        make_base_node(&name, column_specs.as_mut_slice(), keys, unique_keys, mig)
+                    }
                     Some(ref bna) => adapt_base_node(
                         bna.over.clone(),
                         mig,
@@ -157,10 +161,10 @@ fn mir_node_to_flow_parts(
                         Some(conditions),
                     )
                 }
-                MirNodeType::Filter { ref conditions } => {
+                MirNodeType::Filter { ref predicate } => {
                     assert_eq!(mir_node.ancestors.len(), 1);
                     let parent = mir_node.ancestors[0].clone();
-                    make_filter_node(&name, parent, mir_node.columns.as_slice(), conditions, mig)
+                    make_filter_node(&name, parent, mir_node.columns.as_slice(), predicate, mig)
                 }
                 MirNodeType::GroupConcat {
                     ref on,
@@ -212,10 +216,16 @@ fn mir_node_to_flow_parts(
                     let parent = mir_node.ancestors[0].clone();
                     make_latest_node(&name, parent, mir_node.columns.as_slice(), group_by, mig)
                 }
-                MirNodeType::Leaf { ref keys, .. } => {
+                MirNodeType::Leaf {
+                    ref keys,
+                    ref filters,
+                    ref lookup_order,
+                    ref order,
+                    ..
+                } => {
                     assert_eq!(mir_node.ancestors.len(), 1);
                     let parent = mir_node.ancestors[0].clone();
-                    materialize_leaf_node(&parent, name, keys, mig);
+                    materialize_leaf_node(&parent, name, keys, filters, lookup_order, order, mig);
                     // TODO(malte): below is yucky, but required to satisfy the type system:
                     // each match arm must return a `FlowNode`, so we use the parent's one
                     // here.
@@ -245,6 +255,62 @@ fn mir_node_to_flow_parts(
                         mig,
                     )
                 }
+                MirNodeType::OuterJoin {
+                    ref on_left,
+                    ref on_right,
+                    ref project,
+                } => {
+                    assert_eq!(mir_node.ancestors.len(), 2);
+                    let left = mir_node.ancestors[0].clone();
+                    let right = mir_node.ancestors[1].clone();
+                    make_join_node(
+                        &name,
+                        left,
+                        right,
+                        mir_node.columns.as_slice(),
+                        on_left,
+                        on_right,
+                        project,
+                        JoinType::Full,
+                        mig,
+                    )
+                }
+                MirNodeType::ThetaJoin {
+                    ref on,
+                    ref project,
+                } => {
+                    assert_eq!(mir_node.ancestors.len(), 2);
+                    let left = mir_node.ancestors[0].clone();
+                    let right = mir_node.ancestors[1].clone();
+                    make_theta_join_node(
+                        &name,
+                        left,
+                        right,
+                        mir_node.columns.as_slice(),
+                        on,
+                        project,
+                        mig,
+                    )
+                }
+                MirNodeType::SemiJoin {
+                    ref on_left,
+                    ref on_right,
+                    ref kind,
+                } => {
+                    assert_eq!(mir_node.ancestors.len(), 2);
+                    let left = mir_node.ancestors[0].clone();
+                    let right = mir_node.ancestors[1].clone();
+                    make_semi_join_node(
+                        &name,
+                        left,
+                        right,
+                        mir_node.columns.as_slice(),
+                        on_left,
+                        on_right,
+                        kind.clone(),
+                        mig,
+                    )
+                }
                 MirNodeType::Project {
                     ref emit,
                     ref literals,
@@ -404,6 +470,7 @@ fn make_base_node(
     name: &str,
     column_specs: &mut [(ColumnSpecification, Option<usize>)],
     pkey_columns: &[Column],
+    unique_keys: &[Vec<Column>],
     mig: &mut Migration,
 ) -> FlowNode {
     // remember the absolute base column ID for potential later removal
@@ -431,21 +498,46 @@ fn make_base_node(
         })
         .collect::<Vec<DataType>>();
 
-    let base = if !pkey_columns.is_empty() {
-        let pkey_column_ids = pkey_columns
-            .iter()
-            .map(|pkc| {
-                //assert_eq!(pkc.table.as_ref().unwrap(), name);
+    let column_ids = |key: &[Column]| -> Vec<usize> {
+        key.iter()
+            .map(|kc| {
+                //assert_eq!(kc.table.as_ref().unwrap(), name);
                 column_specs
                     .iter()
-                    .position(|&(ref cs, _)| Column::from(&cs.column) == *pkc)
+                    .position(|&(ref cs, _)| Column::from(&cs.column) == *kc)
                     .unwrap()
             })
-            .collect();
-        node::special::Base::new(default_values).with_key(pkey_column_ids)
+            .collect()
+    };
+
+    let base = if !pkey_columns.is_empty() {
+        node::special::Base::new(default_values).with_key(column_ids(pkey_columns))
     } else {
         node::special::Base::new(default_values)
     };
+    let base = base.with_unique_keys(unique_keys.iter().map(|k| column_ids(k)).collect());
+
+    // AUTO_INCREMENT values and defaults like CURRENT_TIMESTAMP are generated as rows come in
+    let auto_increment = column_specs
+        .iter()
+        .position(|&(ref cs, _)| cs.constraints.contains(&ColumnConstraint::AutoIncrement));
+    let base = match auto_increment {
+        Some(col) => base.with_auto_increment(col),
+        None => base,
+    };
+    let default_exprs = column_specs
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &(ref cs, _))| {
+            cs.constraints.iter().find_map(|c| match *c {
+                ColumnConstraint::DefaultValue(ref dv) if noria::is_default_expr(dv) => {
+                    Some((i, dv.clone()))
+                }
+                _ => None,
+            })
+        })
+        .collect();
+    let base = base.with_default_exprs(default_exprs);
 
     FlowNode::New(mig.add_base(name, column_names.as_slice(), base))
 }
@@ -513,7 +605,7 @@ fn make_filter_node(
     name: &str,
     parent: MirNodeRef,
     columns: &[Column],
-    conditions: &[(usize, FilterCondition)],
+    predicate: &Predicate,
     mig: &mut Migration,
 ) -> FlowNode {
     let parent_na = parent.borrow().flow_node_addr().unwrap();
@@ -521,7 +613,7 @@ fn make_filter_node(
     let node = mig.add_ingredient(
         String::from(name),
         column_names.as_slice(),
-        ops::filter::Filter::new(parent_na, conditions),
+        ops::filter::Filter::with_predicate(parent_na, predicate.clone()),
     );
     FlowNode::New(node)
 }
@@ -654,9 +746,6 @@ fn make_join_node(
         proj_cols.len()
     );
 
-    assert_eq!(on_left.len(), 1, "no support for multiple column joins");
-    assert_eq!(on_right.len(), 1, "no support for multiple column joins");
-
     // this assumes the columns we want to join on appear first in the list
     // of projected columns. this is fine for joins against different tables
     // since we assume unique column names in each table. however, this is
@@ -666,30 +755,37 @@ fn make_join_node(
     // the `r1.a = r2.b` join predicate will create a join node with columns: r1.a, r1.b, r2.a, r2,b
     // however, because the way we deal with aliases, we can't distinguish between `r1.a` and `r2.a`
     // at this point in the codebase, so the `r2.a = r1.b` will join on the wrong `a` column.
-    let left_join_col_id = left
-        .borrow()
-        .columns
+    let join_col_ids: Vec<(usize, usize)> = on_left
         .iter()
-        .position(|lc| lc == on_left.first().unwrap())
-        .unwrap_or_else(|| {
-            panic!(
-                "missing left-side join column {:#?} in {:#?}",
-                on_left.first().unwrap(),
-                left.borrow().columns
-            )
-        });
-    let right_join_col_id = right
-        .borrow()
-        .columns
-        .iter()
-        .position(|rc| rc == on_right.first().unwrap())
-        .unwrap_or_else(|| {
-            panic!(
-                "missing right-side join column {:#?} in {:#?}",
-                on_left.first().unwrap(),
-                right.borrow().columns
-            )
-        });
+        .zip(on_right)
+        .map(|(l, r)| {
+            let left_join_col_id = left
+                .borrow()
+                .columns
+                .iter()
+                .position(|lc| lc == l)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "missing left-side join column {:#?} in {:#?}",
+                        l,
+                        left.borrow().columns
+                    )
+                });
+            let right_join_col_id = right
+                .borrow()
+                .columns
+                .iter()
+                .position(|rc| rc == r)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "missing right-side join column {:#?} in {:#?}",
+                        r,
+                        right.borrow().columns
+                    )
+                });
+            (left_join_col_id, right_join_col_id)
+        })
+        .collect();
 
     let mut from_left = 0;
     let mut from_right = 0;
@@ -699,9 +795,9 @@ fn make_join_node(
         .iter()
         .enumerate()
         .filter_map(|(i, c)| {
-            if i == left_join_col_id {
+            if let Some(&(_, r)) = join_col_ids.iter().find(|&&(l, _)| l == i) {
                 from_left += 1;
-                Some(JoinSource::B(i, right_join_col_id))
+                Some(JoinSource::B(i, r))
             } else if projected_cols_left.contains(c) {
                 from_left += 1;
                 Some(JoinSource::L(i))
@@ -731,10 +827,114 @@ fn make_join_node(
     let left_na = left.borrow().flow_node_addr().unwrap();
     let right_na = right.borrow().flow_node_addr().unwrap();
 
-    let j = match kind {
-        JoinType::Inner => Join::new(left_na, right_na, JoinType::Inner, join_config),
-        JoinType::Left => Join::new(left_na, right_na, JoinType::Left, join_config),
+    let j = Join::new(left_na, right_na, kind, join_config);
+    let n = mig.add_ingredient(String::from(name), column_names.as_slice(), j);
+
+    FlowNode::New(n)
+}
+
+fn make_theta_join_node(
+    name: &str,
+    left: MirNodeRef,
+    right: MirNodeRef,
+    columns: &[Column],
+    on: &[(Column, Operator, Column)],
+    proj_cols: &[Column],
+    mig: &mut Migration,
+) -> FlowNode {
+    use dataflow::ops::join::JoinSource;
+
+    let column_names = column_names(columns);
+
+    let find_col = |n: &MirNodeRef, c: &Column, side: &str| {
+        n.borrow()
+            .columns
+            .iter()
+            .position(|nc| nc == c)
+            .unwrap_or_else(|| {
+                panic!(
+                    "missing {}-side join column {:#?} in {:#?}",
+                    side,
+                    c,
+                    n.borrow().columns
+                )
+            })
     };
+
+    let on = on
+        .iter()
+        .map(|&(ref l, ref op, ref r)| {
+            (
+                find_col(&left, l, "left"),
+                op.clone(),
+                find_col(&right, r, "right"),
+            )
+        })
+        .collect();
+
+    // unlike an equi-join, no column is shared between the two sides, so all projected columns
+    // come from exactly one parent.
+    let emit: Vec<_> = left
+        .borrow()
+        .columns
+        .iter()
+        .enumerate()
+        .filter(|&(_, c)| proj_cols.contains(c))
+        .map(|(i, _)| JoinSource::L(i))
+        .chain(
+            right
+                .borrow()
+                .columns
+                .iter()
+                .enumerate()
+                .filter(|&(_, c)| proj_cols.contains(c))
+                .map(|(i, _)| JoinSource::R(i)),
+        )
+        .collect();
+    assert_eq!(
+        emit.len(),
+        proj_cols.len(),
+        "could not resolve output columns projected from theta join"
+    );
+
+    let left_na = left.borrow().flow_node_addr().unwrap();
+    let right_na = right.borrow().flow_node_addr().unwrap();
+
+    let j = ThetaJoin::new(left_na, right_na, on, emit);
+    let n = mig.add_ingredient(String::from(name), column_names.as_slice(), j);
+
+    FlowNode::New(n)
+}
+
+fn make_semi_join_node(
+    name: &str,
+    left: MirNodeRef,
+    right: MirNodeRef,
+    columns: &[Column],
+    on_left: &[Column],
+    on_right: &[Column],
+    kind: SemiJoinType,
+    mig: &mut Migration,
+) -> FlowNode {
+    assert_eq!(on_left.len(), on_right.len());
+
+    let column_names = column_names(columns);
+
+    let on = on_left
+        .iter()
+        .zip(on_right)
+        .map(|(l, r)| {
+            (
+                left.borrow().column_id_for_column(l, None),
+                right.borrow().column_id_for_column(r, None),
+            )
+        })
+        .collect();
+
+    let left_na = left.borrow().flow_node_addr().unwrap();
+    let right_na = right.borrow().flow_node_addr().unwrap();
+
+    let j = SemiJoin::new(left_na, right_na, kind, on);
     let n = mig.add_ingredient(String::from(name), column_names.as_slice(), j);
 
     FlowNode::New(n)
@@ -765,19 +965,60 @@ fn make_latest_node(
     FlowNode::New(na)
 }
 
-// Converts a nom_sql::ArithmeticBase into a project::ProjectExpressionBase:
-fn generate_projection_base(parent: &MirNodeRef, base: &ArithmeticBase) -> ProjectExpressionBase {
-    match *base {
-        ArithmeticBase::Column(ref column) => {
-            let column_id = parent
-                .borrow()
-                .column_id_for_column(&Column::from(column), None);
-            ProjectExpressionBase::Column(column_id)
+// Converts a mir::Expression into a project::ProjectExpression over the parent's columns:
+fn generate_projection_expression(parent: &MirNodeRef, expr: &Expression) -> ProjectExpression {
+    match *expr {
+        Expression::Column(ref column) => {
+            let column_id = parent.borrow().column_id_for_column(column, None);
+            ProjectExpression::Column(column_id)
         }
-        ArithmeticBase::Scalar(ref literal) => {
-            let data: DataType = literal.into();
-            ProjectExpressionBase::Literal(data)
+        Expression::Literal(ref data) => ProjectExpression::Literal(data.clone()),
+        Expression::Arithmetic {
+            ref op,
+            ref left,
+            ref right,
+        } => ProjectExpression::new(
+            op.clone(),
+            generate_projection_expression(parent, left),
+            generate_projection_expression(parent, right),
+        ),
+        Expression::Negate(ref e) => {
+            ProjectExpression::negate(generate_projection_expression(parent, e))
         }
+        Expression::Condition {
+            ref op,
+            ref left,
+            ref right,
+        } => ProjectExpression::condition(
+            op.clone(),
+            generate_projection_expression(parent, left),
+            generate_projection_expression(parent, right),
+        ),
+        Expression::Call {
+            function,
+            ref arguments,
+        } => ProjectExpression::call(
+            function,
+            arguments
+                .iter()
+                .map(|a| generate_projection_expression(parent, a))
+                .collect(),
+        ),
+        Expression::Case {
+            ref branches,
+            ref otherwise,
+        } => ProjectExpression::case(
+            branches
+                .iter()
+                .map(|(cond, e)| {
+                    (
+                        generate_projection_expression(parent, cond),
+                        generate_projection_expression(parent, e),
+                    )
+                })
+                .collect(),
+            generate_projection_expression(parent, otherwise),
+        ),
     }
 }
 
@@ -786,7 +1027,7 @@ fn make_project_node(
     parent: MirNodeRef,
     columns: &[Column],
     emit: &[Column],
-    arithmetic: &[(String, ArithmeticExpression)],
+    arithmetic: &[(String, Expression)],
     literals: &[(String, DataType)],
     mig: &mut Migration,
     table_mapping: Option<&HashMap<(String, Option<String>), String>>,
@@ -803,13 +1044,7 @@ fn make_project_node(
 
     let projected_arithmetic: Vec<ProjectExpression> = arithmetic
         .iter()
-        .map(|&(_, ref e)| {
-            ProjectExpression::new(
-                e.op.clone(),
-                generate_projection_base(&parent, &e.left),
-                generate_projection_base(&parent, &e.right),
-            )
-        })
+        .map(|&(_, ref e)| generate_projection_expression(&parent, e))
         .collect();
 
     let n = mig.add_ingredient(
@@ -918,6 +1153,9 @@ fn materialize_leaf_node(
     parent: &MirNodeRef,
     name: String,
     key_cols: &[Column],
+    filters: &[(Column, Operator, usize)],
+    lookup_order: &[usize],
+    order: &Option<Vec<(Column, OrderType)>>,
     mig: &mut Migration,
 ) {
     let na = parent.borrow().flow_node_addr().unwrap();
@@ -929,14 +1167,58 @@ fn materialize_leaf_node(
 
     // TODO(malte): consider the case when the projected columns need reordering
 
-    if !key_cols.is_empty() {
-        let key_cols: Vec<_> = key_cols
+    let keyed_on_bogokey = key_cols.len() == 1 && key_cols[0].name == "bogokey";
+    let mut key_cols: Vec<_> = key_cols
+        .iter()
+        .map(|c| parent.borrow().column_id_for_column(c, None))
+        .collect();
+    if !filters.is_empty() {
+        // parameters that aren't compared for equality are evaluated by the reader
+        let filters: Vec<_> = filters
             .iter()
-            .map(|c| parent.borrow().column_id_for_column(c, None))
+            .map(|&(ref c, ref operator, arity)| PostLookupFilter {
+                column: parent.borrow().column_id_for_column(c, None),
+                operator: operator.clone(),
+                arity,
+            })
             .collect();
+        let range_column = filters
+            .iter()
+            .find(|f| match f.operator {
+                Operator::Less
+                | Operator::LessOrEqual
+                | Operator::Greater
+                | Operator::GreaterOrEqual => true,
+                _ => false,
+            })
+            .map(|f| f.column);
+        match range_column {
+            Some(c) if keyed_on_bogokey => {
+                // all rows share the one bogokey value, so rather than have the reader filter all
+                // of them on each lookup, keep them ordered by the compared column and only look
+                // at those within the range that the parameters allow.
+                key_cols.push(c);
+                mig.maintain_ranged(name, na, &key_cols[..], filters);
+            }
+            _ => mig.maintain_filtered(name, na, &key_cols[..], filters),
+        }
+    } else if !key_cols.is_empty() {
         mig.maintain(name, na, &key_cols[..]);
     } else {
         // if no key specified, default to the first column
         mig.maintain(name, na, &[0]);
     }
+
+    if !lookup_order.is_empty() {
+        // lookups give parameter values in the order their placeholders appear in the query
+        mig.reorder_reader_lookups(na, Vec::from(lookup_order));
+    }
+
+    if let Some(ref order) = *order {
+        let order = order
+            .iter()
+            .map(|&(ref c, ref ot)| (parent.borrow().column_id_for_column(c, None), ot.clone()))
+            .collect();
+        mig.sort_reader(na, order);
+    }
 }
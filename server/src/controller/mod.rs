@@ -16,7 +16,7 @@ use futures_util::{
 use hyper::{self, StatusCode};
 use noria::channel::TcpSender;
 use noria::consensus::{Authority, Epoch, STATE_KEY};
-use noria::ControllerDescriptor;
+use noria::{ControllerDescriptor, RecipeVersion};
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
@@ -34,6 +34,9 @@ mod schema;
 mod security;
 pub(crate) mod sql; // crate viz for tests
 
+/// The number of applied recipes that the controller remembers for rollbacks.
+const RECIPE_HISTORY_LEN: usize = 16;
+
 #[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct ControllerState {
     pub(crate) config: Config,
@@ -41,6 +44,95 @@ pub(crate) struct ControllerState {
 
     recipe_version: usize,
     recipes: Vec<String>,
+    #[serde(default)]
+    recipe_history: RecipeHistory,
+}
+
+impl ControllerState {
+    /// Records that the recipe at `version` consists of `recipes`, and adds it to the recipe
+    /// history.
+    fn set_recipe(&mut self, version: usize, recipes: Vec<String>) {
+        self.recipe_history.push(version, &recipes);
+        self.recipe_version = version;
+        self.recipes = recipes;
+    }
+}
+
+/// The most recently applied recipes, of which there are at most `RECIPE_HISTORY_LEN`.
+///
+/// Successive recipes mostly consist of the same texts, as extending a recipe only adds a text to
+/// it, so every text is stored only once, and each recipe refers to the texts that make it up.
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct RecipeHistory {
+    texts: Vec<String>,
+    /// The version of each recipe, and the indices into `texts` of the texts that make it up,
+    /// oldest recipe first.
+    versions: Vec<(usize, Vec<usize>)>,
+}
+
+impl RecipeHistory {
+    /// Adds the recipe at `version`, forgetting the oldest recipe if there are too many.
+    fn push(&mut self, version: usize, recipes: &[String]) {
+        let texts = recipes
+            .iter()
+            .map(|r| match self.texts.iter().position(|t| t == r) {
+                Some(i) => i,
+                None => {
+                    self.texts.push(r.clone());
+                    self.texts.len() - 1
+                }
+            })
+            .collect();
+        self.versions.push((version, texts));
+
+        let excess = self.versions.len().saturating_sub(RECIPE_HISTORY_LEN);
+        if excess != 0 {
+            self.versions.drain(..excess);
+            self.forget_unused_texts();
+        }
+    }
+
+    /// Removes the texts that none of the remembered recipes consist of.
+    fn forget_unused_texts(&mut self) {
+        let mut used = vec![false; self.texts.len()];
+        for &(_, ref texts) in &self.versions {
+            for &i in texts {
+                used[i] = true;
+            }
+        }
+
+        // the index that each text that is still used will have once the others are removed
+        let mut renumbered = Vec::with_capacity(used.len());
+        let mut next = 0;
+        for &u in &used {
+            renumbered.push(next);
+            if u {
+                next += 1;
+            }
+        }
+
+        let mut i = 0;
+        self.texts.retain(|_| {
+            i += 1;
+            used[i - 1]
+        });
+        for &mut (_, ref mut texts) in &mut self.versions {
+            for t in texts {
+                *t = renumbered[*t];
+            }
+        }
+    }
+
+    /// The remembered recipes, oldest first.
+    fn versions(&self) -> Vec<RecipeVersion> {
+        self.versions
+            .iter()
+            .map(|&(version, ref texts)| RecipeVersion {
+                version,
+                recipes: texts.iter().map(|&i| self.texts[i].clone()).collect(),
+            })
+            .collect()
+    }
 }
 
 struct Worker {
@@ -262,6 +354,7 @@ fn instance_campaign<A: Authority + 'static>(
                         epoch,
                         recipe_version: 0,
                         recipes: vec![],
+                        recipe_history: RecipeHistory::default(),
                     }),
                     Some(ref state) if state.epoch > epoch => Err(()),
                     Some(mut state) => {
@@ -303,3 +396,34 @@ fn instance_campaign<A: Authority + 'static>(
         })
         .unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recipe_history_stores_texts_once() {
+        let mut history = RecipeHistory::default();
+        let texts: Vec<String> = (0..20)
+            .map(|i| format!("q_{}: SELECT * FROM t;", i))
+            .collect();
+        for n in 1..=20 {
+            history.push(n, &texts[..n]);
+        }
+
+        // only the last recipes are remembered, but their texts are not repeated
+        let versions = history.versions();
+        assert_eq!(versions.len(), RECIPE_HISTORY_LEN);
+        assert_eq!(history.texts, texts);
+        assert_eq!(versions[0].version, 20 - RECIPE_HISTORY_LEN + 1);
+        assert_eq!(versions[0].recipes, &texts[..20 - RECIPE_HISTORY_LEN + 1]);
+        assert_eq!(versions.last().unwrap().recipes, texts);
+
+        // texts that no remembered recipe has are forgotten
+        for n in 21..=20 + RECIPE_HISTORY_LEN {
+            history.push(n, &texts[19..]);
+        }
+        assert_eq!(history.texts, &texts[19..]);
+        assert!(history.versions().iter().all(|v| v.recipes == &texts[19..]));
+    }
+}
@@ -0,0 +1,337 @@
+//! `ALTER TABLE` support for recipes.
+//!
+//! nom-sql does not know how to parse `ALTER TABLE`, so the recipe parser handles the column
+//! changes we support here, and applies them to the `CREATE TABLE` statement of the altered table.
+//! The resulting statement then replaces the original one in the recipe, and the usual base node
+//! adaptation takes care of adding and dropping columns.
+
+use nom_sql::{
+    Column, ColumnConstraint, ColumnSpecification, CreateTableStatement, SqlQuery, TableKey,
+};
+use std::collections::HashSet;
+
+/// A single column change made by an `ALTER TABLE` statement.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) enum AlterColumn {
+    /// `ADD [COLUMN] <column definition>`
+    Add(ColumnSpecification),
+    /// `DROP [COLUMN] <column>`
+    Drop(String),
+    /// `RENAME COLUMN <column> TO <column>`
+    Rename(String, String),
+}
+
+/// A parsed `ALTER TABLE` statement.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct AlterTableStatement {
+    pub(super) table: String,
+    pub(super) changes: Vec<AlterColumn>,
+}
+
+fn bare_identifier(input: &str) -> nom::IResult<&str, &str> {
+    nom::combinator::verify(super::ident, |s: &str| !s.is_empty())(input)
+}
+
+pub(super) fn identifier(input: &str) -> nom::IResult<&str, &str> {
+    use nom::branch::alt;
+    use nom::character::complete::char;
+    use nom::sequence::delimited;
+    alt((
+        delimited(char('`'), bare_identifier, char('`')),
+        bare_identifier,
+    ))(input)
+}
+
+/// Returns the text of a column definition, which extends up to the next comma or semicolon that
+/// is not enclosed in parentheses or quotes.
+fn column_definition(input: &str) -> nom::IResult<&str, &str> {
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, c) in input.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None => match c {
+                '\'' | '"' | '`' => quote = Some(c),
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' | ';' if depth == 0 => return Ok((&input[i..], input[..i].trim_end())),
+                _ => {}
+            },
+        }
+    }
+    Ok(("", input.trim_end()))
+}
+
+/// Parses a column definition by wrapping it into a single-column `CREATE TABLE` statement, which
+/// also attaches the table name to the column, just like for the original table definition.
+fn column_specification(table: &str, definition: &str) -> Option<ColumnSpecification> {
+    match nom_sql::parse_query(format!("CREATE TABLE `{}` ({})", table, definition)) {
+        Ok(SqlQuery::CreateTable(mut ctq)) if ctq.fields.len() == 1 && ctq.keys.is_none() => {
+            ctq.fields.pop()
+        }
+        _ => None,
+    }
+}
+
+fn alter_column<'a>(table: &str, input: &'a str) -> nom::IResult<&'a str, AlterColumn> {
+    use nom::bytes::complete::tag_no_case;
+    use nom::character::complete::{multispace0, multispace1};
+    use nom::combinator::opt;
+    use nom::sequence::{pair, terminated};
+
+    if let Ok((input, _)) = pair(tag_no_case("add"), multispace1)(input) {
+        let (input, _) = opt(terminated(tag_no_case("column"), multispace1))(input)?;
+        let (rest, definition) = column_definition(input)?;
+        return match column_specification(table, definition) {
+            Some(spec) => Ok((rest, AlterColumn::Add(spec))),
+            None => Err(nom::Err::Error((input, nom::error::ErrorKind::Verify))),
+        };
+    }
+    if let Ok((input, _)) = pair(tag_no_case("drop"), multispace1)(input) {
+        let (input, _) = opt(terminated(tag_no_case("column"), multispace1))(input)?;
+        let (input, column) = identifier(input)?;
+        return Ok((input, AlterColumn::Drop(column.to_owned())));
+    }
+    let (input, _) = pair(tag_no_case("rename"), multispace1)(input)?;
+    let (input, _) = pair(tag_no_case("column"), multispace1)(input)?;
+    let (input, from) = identifier(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, _) = pair(tag_no_case("to"), multispace1)(input)?;
+    let (input, to) = identifier(input)?;
+    Ok((input, AlterColumn::Rename(from.to_owned(), to.to_owned())))
+}
+
+/// Parses `ALTER TABLE <table> <change>[, <change> ...][;]`.
+pub(super) fn alter_table(input: &str) -> nom::IResult<&str, AlterTableStatement> {
+    use nom::bytes::complete::tag_no_case;
+    use nom::character::complete::{char, multispace0, multispace1};
+    use nom::combinator::opt;
+    use nom::sequence::tuple;
+
+    let (input, _) = tuple((
+        tag_no_case("alter"),
+        multispace1,
+        tag_no_case("table"),
+        multispace1,
+    ))(input)?;
+    let (input, table) = identifier(input)?;
+    let (mut input, _) = multispace1(input)?;
+
+    let mut changes = Vec::new();
+    loop {
+        let (i, change) = alter_column(table, input)?;
+        changes.push(change);
+        let (i, _) = multispace0(i)?;
+        match char::<_, (&str, nom::error::ErrorKind)>(',')(i) {
+            Ok((i, _)) => {
+                let (i, _) = multispace0(i)?;
+                input = i;
+            }
+            Err(_) => {
+                input = i;
+                break;
+            }
+        }
+    }
+
+    let (input, _) = opt(char(';'))(input)?;
+    let (input, _) = multispace0(input)?;
+    Ok((
+        input,
+        AlterTableStatement {
+            table: table.to_owned(),
+            changes,
+        },
+    ))
+}
+
+fn key_columns(key: &mut TableKey) -> &mut Vec<Column> {
+    match *key {
+        TableKey::PrimaryKey(ref mut cols)
+        | TableKey::UniqueKey(_, ref mut cols)
+        | TableKey::FulltextKey(_, ref mut cols)
+        | TableKey::Key(_, ref mut cols) => cols,
+    }
+}
+
+/// Bases enforce keys and generate `AUTO_INCREMENT` values by column index, so we cannot add or
+/// drop such columns on an existing base.
+fn is_key_column(ctq: &CreateTableStatement, spec: &ColumnSpecification) -> bool {
+    let constrained = spec.constraints.iter().any(|c| {
+        matches!(
+            *c,
+            ColumnConstraint::PrimaryKey
+                | ColumnConstraint::Unique
+                | ColumnConstraint::AutoIncrement
+        )
+    });
+    constrained
+        || ctq.keys.iter().flatten().any(|k| match *k {
+            TableKey::PrimaryKey(ref cols)
+            | TableKey::UniqueKey(_, ref cols)
+            | TableKey::FulltextKey(_, ref cols)
+            | TableKey::Key(_, ref cols) => cols.iter().any(|c| c.name == spec.column.name),
+        })
+}
+
+/// Applies the column changes in `changes` to the table definition in `ctq`, in order.
+///
+/// Returns the altered table definition, along with the `(old, new)` names of any existing columns
+/// that were renamed; unlike added and dropped columns, renames cannot be inferred by comparing
+/// the old and new definitions.
+pub(super) fn apply(
+    ctq: &CreateTableStatement,
+    changes: &[AlterColumn],
+) -> Result<(CreateTableStatement, Vec<(String, String)>), String> {
+    let mut ctq = ctq.clone();
+    let table = ctq.table.name.clone();
+    let position = |ctq: &CreateTableStatement, column: &str| {
+        ctq.fields.iter().position(|cs| cs.column.name == column)
+    };
+
+    let mut added = HashSet::new();
+    let mut dropped = HashSet::new();
+    let mut renames = Vec::new();
+    for change in changes {
+        match *change {
+            AlterColumn::Add(ref spec) => {
+                if position(&ctq, &spec.column.name).is_some() {
+                    return Err(format!(
+                        "column \"{}\" already exists in table \"{}\"",
+                        spec.column.name, table
+                    ));
+                }
+                if is_key_column(&ctq, spec) {
+                    return Err(format!(
+                        "cannot add key or AUTO_INCREMENT column \"{}\" to existing table \"{}\"",
+                        spec.column.name, table
+                    ));
+                }
+                ctq.fields.push(spec.clone());
+                added.insert(spec.column.name.clone());
+            }
+            AlterColumn::Drop(ref column) => {
+                let pos = position(&ctq, column)
+                    .ok_or_else(|| format!("table \"{}\" has no column \"{}\"", table, column))?;
+                let spec = ctq.fields[pos].clone();
+                if is_key_column(&ctq, &spec) {
+                    return Err(format!(
+                        "cannot drop key or AUTO_INCREMENT column \"{}\" from table \"{}\"",
+                        column, table
+                    ));
+                }
+                if ctq.fields.len() == 1 {
+                    return Err(format!("cannot drop all columns from table \"{}\"", table));
+                }
+                ctq.fields.remove(pos);
+                if !added.remove(column) {
+                    dropped.insert(column.clone());
+                }
+            }
+            AlterColumn::Rename(ref from, ref to) => {
+                let pos = position(&ctq, from)
+                    .ok_or_else(|| format!("table \"{}\" has no column \"{}\"", table, from))?;
+                if position(&ctq, to).is_some() {
+                    return Err(format!(
+                        "column \"{}\" already exists in table \"{}\"",
+                        to, table
+                    ));
+                }
+                // renames are applied to the existing base before the other changes, so a renamed
+                // column must not take the place of a column that is dropped alongside it.
+                if dropped.contains(to) {
+                    return Err(format!(
+                        "cannot rename column \"{}\" to the name of dropped column \"{}\"",
+                        from, to
+                    ));
+                }
+                ctq.fields[pos].column.name = to.clone();
+                if added.remove(from) {
+                    added.insert(to.clone());
+                } else {
+                    renames.push((from.clone(), to.clone()));
+                }
+                for k in ctq.keys.iter_mut().flatten() {
+                    for c in key_columns(k).iter_mut().filter(|c| c.name == *from) {
+                        c.name = to.clone();
+                    }
+                }
+            }
+        }
+    }
+
+    Ok((ctq, renames))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_table(q: &str) -> CreateTableStatement {
+        match nom_sql::parse_query(q).unwrap() {
+            SqlQuery::CreateTable(ctq) => ctq,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn it_parses_alter_table() {
+        let (rest, alter) = alter_table(
+            "ALTER TABLE users ADD COLUMN bio varchar(255) DEFAULT 'a, b', DROP age, \
+             RENAME COLUMN name TO `nickname`; SELECT 1",
+        )
+        .unwrap();
+        assert_eq!(rest, "SELECT 1");
+        assert_eq!(alter.table, "users");
+        assert_eq!(alter.changes.len(), 3);
+        match alter.changes[0] {
+            AlterColumn::Add(ref spec) => {
+                assert_eq!(spec.column.name, "bio");
+                assert_eq!(spec.column.table, Some("users".to_owned()));
+                assert_eq!(spec.constraints.len(), 1);
+            }
+            ref c => panic!("unexpected change {:?}", c),
+        }
+        assert_eq!(alter.changes[1], AlterColumn::Drop("age".to_owned()));
+        assert_eq!(
+            alter.changes[2],
+            AlterColumn::Rename("name".to_owned(), "nickname".to_owned())
+        );
+
+        assert!(alter_table("ALTER TABLE users ADD COLUMN").is_err());
+        assert!(alter_table("ALTER TABLE users MODIFY age int").is_err());
+    }
+
+    #[test]
+    fn it_applies_alter_table() {
+        let ctq =
+            create_table("CREATE TABLE users (id int, name text, age int, PRIMARY KEY (id));");
+        let (_, alter) = alter_table(
+            "ALTER TABLE users DROP COLUMN age, ADD bio text, RENAME COLUMN id TO uid, \
+             ADD x int, RENAME COLUMN x TO y, DROP y",
+        )
+        .unwrap();
+        let (altered, renames) = apply(&ctq, &alter.changes).unwrap();
+        assert_eq!(renames, vec![("id".to_owned(), "uid".to_owned())]);
+        let names: Vec<_> = altered.fields.iter().map(|cs| &cs.column.name).collect();
+        assert_eq!(names, vec!["uid", "name", "bio"]);
+        match altered.keys.as_ref().unwrap()[0] {
+            TableKey::PrimaryKey(ref cols) => assert_eq!(cols[0].name, "uid"),
+            ref k => panic!("unexpected key {:?}", k),
+        }
+
+        let fails = |q: &str| {
+            let (_, alter) = alter_table(q).unwrap();
+            apply(&ctq, &alter.changes).is_err()
+        };
+        assert!(fails("ALTER TABLE users DROP COLUMN id"));
+        assert!(fails("ALTER TABLE users DROP COLUMN nope"));
+        assert!(fails("ALTER TABLE users ADD COLUMN name text"));
+        assert!(fails("ALTER TABLE users ADD COLUMN x int UNIQUE"));
+        assert!(fails("ALTER TABLE users RENAME COLUMN name TO age"));
+        assert!(fails(
+            "ALTER TABLE users DROP age, RENAME COLUMN name TO age"
+        ));
+    }
+}
@@ -0,0 +1,124 @@
+//! `DROP TABLE` and `DROP VIEW` support for recipes.
+//!
+//! Dropping a table or view removes its definition from the recipe, and activating the resulting
+//! recipe then removes the corresponding nodes. nom-sql parses `DROP TABLE`, but not `DROP VIEW`,
+//! which the recipe parser therefore handles here.
+
+use nom_sql::SqlQuery;
+use std::fmt;
+
+/// The kind of relation that a `DROP` statement removes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum DropKind {
+    Table,
+    View,
+}
+
+impl DropKind {
+    /// Returns true if the recipe expression `q`, named `query_name`, defines the table or view
+    /// called `name`.
+    pub(super) fn is_defined_by(self, name: &str, query_name: Option<&str>, q: &SqlQuery) -> bool {
+        match (self, q) {
+            (DropKind::Table, SqlQuery::CreateTable(ctq)) => ctq.table.name == name,
+            (DropKind::Table, _) | (DropKind::View, SqlQuery::CreateTable(_)) => false,
+            (DropKind::View, SqlQuery::CreateView(cvq)) if cvq.name == name => true,
+            (DropKind::View, _) => query_name == Some(name),
+        }
+    }
+}
+
+impl fmt::Display for DropKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DropKind::Table => write!(f, "table"),
+            DropKind::View => write!(f, "view"),
+        }
+    }
+}
+
+/// A parsed `DROP TABLE` or `DROP VIEW` statement.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct DropStatement {
+    pub(super) kind: DropKind,
+    pub(super) names: Vec<String>,
+    /// Whether names that do not exist are ignored (`IF EXISTS`).
+    pub(super) if_exists: bool,
+}
+
+/// Parses `DROP VIEW [IF EXISTS] <view>[, <view> ...][;]`.
+pub(super) fn drop_view(input: &str) -> nom::IResult<&str, DropStatement> {
+    use nom::bytes::complete::tag_no_case;
+    use nom::character::complete::{char, multispace0, multispace1};
+    use nom::combinator::opt;
+    use nom::multi::separated_nonempty_list;
+    use nom::sequence::{delimited, tuple};
+
+    let (input, _) = tuple((
+        tag_no_case("drop"),
+        multispace1,
+        tag_no_case("view"),
+        multispace1,
+    ))(input)?;
+    let (input, if_exists) = opt(tuple((
+        tag_no_case("if"),
+        multispace1,
+        tag_no_case("exists"),
+        multispace1,
+    )))(input)?;
+    let (input, names) = separated_nonempty_list(
+        delimited(multispace0, char(','), multispace0),
+        super::alter::identifier,
+    )(input)?;
+    let (input, _) = multispace0(input)?;
+    let (input, _) = opt(char(';'))(input)?;
+    let (input, _) = multispace0(input)?;
+    Ok((
+        input,
+        DropStatement {
+            kind: DropKind::View,
+            names: names.into_iter().map(String::from).collect(),
+            if_exists: if_exists.is_some(),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_drop_view() {
+        let (rest, drop) = drop_view("DROP VIEW votes;").unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(
+            drop,
+            DropStatement {
+                kind: DropKind::View,
+                names: vec!["votes".into()],
+                if_exists: false,
+            }
+        );
+
+        let (rest, drop) = drop_view("drop view if exists `a` , b").unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(drop.names, vec![String::from("a"), String::from("b")]);
+        assert!(drop.if_exists);
+
+        assert!(drop_view("DROP TABLE votes;").is_err());
+        assert!(drop_view("DROP VIEW;").is_err());
+    }
+
+    #[test]
+    fn it_matches_definitions() {
+        let table = nom_sql::parse_query("CREATE TABLE votes (aid int, uid int);").unwrap();
+        let view = nom_sql::parse_query("CREATE VIEW total AS SELECT aid FROM votes;").unwrap();
+        let query = nom_sql::parse_query("SELECT aid FROM votes;").unwrap();
+
+        assert!(DropKind::Table.is_defined_by("votes", None, &table));
+        assert!(!DropKind::View.is_defined_by("votes", Some("votes"), &table));
+        assert!(DropKind::View.is_defined_by("total", None, &view));
+        assert!(!DropKind::Table.is_defined_by("total", None, &view));
+        assert!(DropKind::View.is_defined_by("q", Some("q"), &query));
+        assert!(!DropKind::View.is_defined_by("q", None, &query));
+    }
+}
@@ -7,17 +7,38 @@ use dataflow::ops::trigger::TriggerEvent;
 use dataflow::prelude::DataType;
 use nom_sql::parser as sql_parser;
 use nom_sql::SqlQuery;
+use noria::error::RecipeError;
 use noria::ActivationResult;
 use petgraph::graph::NodeIndex;
 
+use ::mir::query::QueryFlowParts;
 use nom_sql::CreateTableStatement;
 use slog;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::str;
 use std::vec::Vec;
 
+mod alter;
+mod drop;
+mod outer_join;
+
+use self::alter::{AlterColumn, AlterTableStatement};
+use self::drop::{DropKind, DropStatement};
+
 type QueryID = u64;
 
+/// The statements parsed from a recipe text.
+struct ParsedRecipe {
+    /// Query expressions as (name, query, public).
+    queries: Vec<(Option<String>, SqlQuery, bool)>,
+    /// Changes to tables that the text does not define.
+    alterations: Vec<AlterTableStatement>,
+    /// Tables and views to drop that the text does not define.
+    drops: Vec<DropStatement>,
+    /// Lines at which the query expressions start in the recipe text.
+    positions: HashMap<QueryID, usize>,
+}
+
 /// Represents a Soup recipe.
 #[derive(Clone, Debug)]
 // crate viz for tests
@@ -30,6 +51,10 @@ pub(crate) struct Recipe {
     aliases: HashMap<String, QueryID>,
     /// Security configuration
     security_config: Option<SecurityConfig>,
+    /// Columns of existing tables renamed by this recipe revision, as (table, old, new).
+    column_renames: Vec<(String, String, String)>,
+    /// Lines at which the expressions added by this recipe revision start in its recipe text.
+    positions: HashMap<QueryID, usize>,
 
     /// Recipe revision.
     version: usize,
@@ -61,6 +86,15 @@ pub(super) enum Schema {
     View(Vec<String>),
 }
 
+/// Returns an error for the recipe expression, table or view called `name`.
+fn error_for(name: &str, reason: String) -> RecipeError {
+    RecipeError {
+        query: Some(name.to_owned()),
+        position: None,
+        reason,
+    }
+}
+
 fn hash_query(q: &SqlQuery) -> QueryID {
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
@@ -96,22 +130,25 @@ fn query_prefix(input: &str) -> nom::IResult<&str, (bool, Option<&str>)> {
     Ok((input, (public.is_some(), name)))
 }
 
+/// Parses a (possibly named) query expression, whose right and full joins are first marked for
+/// nom-sql to parse (see `outer_join`).
 fn query_expr(input: &str) -> nom::IResult<&str, (bool, Option<&str>, SqlQuery)> {
     use nom::character::complete::multispace0;
     use nom::combinator::opt;
     let (input, prefix) = opt(query_prefix)(input)?;
+    let marked = outer_join::mark_outer_joins(input);
+    // continue in the input where the parser leaves off in the marked text
+    let rest = |i: &[u8]| &input[marked.input_position(marked.text.len() - i.len())..];
     // NOTE: some massaging since nom_sql operates on &[u8], not &str
-    let (input, expr) = match sql_parser::sql_query(input.as_bytes()) {
-        Ok((i, e)) => Ok((std::str::from_utf8(i).unwrap(), e)),
+    let (rest, expr) = match sql_parser::sql_query(marked.text.as_bytes()) {
+        Ok((i, e)) => Ok((rest(i), e)),
         Err(nom::Err::Incomplete(n)) => Err(nom::Err::Incomplete(n)),
-        Err(nom::Err::Error((i, e))) => Err(nom::Err::Error((std::str::from_utf8(i).unwrap(), e))),
-        Err(nom::Err::Failure((i, e))) => {
-            Err(nom::Err::Error((std::str::from_utf8(i).unwrap(), e)))
-        }
+        Err(nom::Err::Error((i, e))) => Err(nom::Err::Error((rest(i), e))),
+        Err(nom::Err::Failure((i, e))) => Err(nom::Err::Error((rest(i), e))),
     }?;
-    let (input, _) = multispace0(input)?;
+    let (rest, _) = multispace0(rest)?;
     Ok((
-        input,
+        rest,
         match prefix {
             None => (false, None, expr),
             Some((public, name)) => (public, name, expr),
@@ -119,8 +156,119 @@ fn query_expr(input: &str) -> nom::IResult<&str, (bool, Option<&str>, SqlQuery)>
     ))
 }
 
-fn query_exprs(input: &str) -> nom::IResult<&str, Vec<(bool, Option<&str>, SqlQuery)>> {
-    nom::multi::many1(query_expr)(input)
+/// A recipe statement: a (possibly named) query expression, a change to a table, or the removal
+/// of tables or views.
+#[allow(clippy::large_enum_variant)]
+enum Statement<'a> {
+    Query(bool, Option<&'a str>, SqlQuery),
+    Alter(AlterTableStatement),
+    Drop(DropStatement),
+}
+
+fn statement(input: &str) -> nom::IResult<&str, Statement> {
+    use nom::branch::alt;
+    use nom::combinator::map;
+    alt((
+        map(alter::alter_table, Statement::Alter),
+        map(drop::drop_view, Statement::Drop),
+        map(query_expr, |(public, name, expr)| match expr {
+            SqlQuery::DropTable(dt) => Statement::Drop(DropStatement {
+                kind: DropKind::Table,
+                names: dt.tables.into_iter().map(|t| t.name).collect(),
+                if_exists: dt.if_exists,
+            }),
+            expr => Statement::Query(public, name, expr),
+        }),
+    ))(input)
+}
+
+fn query_exprs(input: &str) -> nom::IResult<&str, Vec<Statement>> {
+    nom::multi::many1(statement)(input)
+}
+
+/// Returns true if `q` gives LIMIT or OFFSET as a query parameter, which the SQL parser rejects.
+fn has_limit_parameter(q: &str) -> bool {
+    let words: Vec<_> = q
+        .split(|c: char| c.is_whitespace() || c == ';')
+        .filter(|w| !w.is_empty())
+        .collect();
+    words.windows(2).any(|w| {
+        (w[0].eq_ignore_ascii_case("limit") || w[0].eq_ignore_ascii_case("offset"))
+            && w[1].starts_with('?')
+    })
+}
+
+/// Aggregations and scalar functions that dataflow operators implement, but that the SQL parser
+/// doesn't recognize.
+const UNPARSED_FUNCTIONS: &[&str] = &[
+    "STDDEV",
+    "VARIANCE",
+    "BOOL_AND",
+    "BOOL_OR",
+    "COALESCE",
+    "IFNULL",
+    "CONCAT",
+    "LOWER",
+    "UPPER",
+    "SUBSTRING",
+    "LENGTH",
+    "DATE",
+    "UNIX_TIMESTAMP",
+    "DATE_ADD",
+];
+
+/// Returns the first function called in `q` that the SQL parser doesn't recognize, if any.
+fn unparsed_function(q: &str) -> Option<&'static str> {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut rest = q;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        let word = &rest[start..];
+        let end = word.find(|c: char| !is_ident(c)).unwrap_or(word.len());
+        let (word, after) = word.split_at(end);
+        if after.trim_start().starts_with('(') {
+            if let Some(f) = UNPARSED_FUNCTIONS
+                .iter()
+                .find(|f| f.eq_ignore_ascii_case(word))
+            {
+                return Some(*f);
+            }
+        }
+        rest = after;
+    }
+    None
+}
+
+/// Returns true if `q` selects all columns of `table` using `*` or `table.*`.
+fn selects_all_from(q: &SqlQuery, table: &str) -> bool {
+    use nom_sql::{FieldDefinitionExpression, SelectSpecification, SelectStatement};
+    let selects_all = |sq: &SelectStatement| {
+        sq.fields.iter().any(|f| match *f {
+            FieldDefinitionExpression::All => sq.tables.iter().any(|t| t.name == table),
+            FieldDefinitionExpression::AllInTable(ref t) => t == table,
+            _ => false,
+        })
+    };
+    match *q {
+        SqlQuery::Select(ref sq) => selects_all(sq),
+        SqlQuery::CompoundSelect(ref csq) => csq.selects.iter().any(|&(_, ref sq)| selects_all(sq)),
+        SqlQuery::CreateView(ref cvq) => match *cvq.definition {
+            SelectSpecification::Simple(ref sq) => selects_all(sq),
+            SelectSpecification::Compound(ref csq) => {
+                csq.selects.iter().any(|&(_, ref sq)| selects_all(sq))
+            }
+        },
+        _ => false,
+    }
+}
+
+/// Returns the name under which the incorporator knows the nodes of a recipe expression, if any.
+fn relation_name<'a>(n: &'a Option<String>, q: &'a SqlQuery) -> Option<&'a str> {
+    match (n, q) {
+        (_, SqlQuery::CreateTable(ctq)) => Some(&ctq.table.name),
+        (_, SqlQuery::CreateView(cvq)) => Some(&cvq.name),
+        (Some(name), _) => Some(name),
+        (None, _) => None,
+    }
 }
 
 #[allow(unused)]
@@ -157,6 +305,8 @@ impl Recipe {
                 Some(log) => log,
             },
             security_config: None,
+            column_renames: Vec::default(),
+            positions: HashMap::default(),
         }
     }
 
@@ -238,20 +388,58 @@ impl Recipe {
     /// Note that the recipe is not backed by a Soup data-flow graph until `activate` is called on
     /// it.
     // crate viz for tests
-    pub(crate) fn from_str(recipe_text: &str, log: Option<slog::Logger>) -> Result<Recipe, String> {
-        // remove comment lines
-        let lines: Vec<String> = recipe_text
+    pub(crate) fn from_str(
+        recipe_text: &str,
+        log: Option<slog::Logger>,
+    ) -> Result<Recipe, RecipeError> {
+        let parsed = Recipe::parse_text(recipe_text)?;
+        if let Some(alter) = parsed.alterations.first() {
+            let reason = String::from("cannot alter a table that the recipe does not define");
+            return Err(error_for(&alter.table, reason));
+        }
+        if let Some(drop) = parsed.drops.iter().find(|d| !d.if_exists) {
+            let reason = format!(
+                "cannot drop a {} that the recipe does not define",
+                drop.kind
+            );
+            return Err(error_for(&drop.names[0], reason));
+        }
+
+        let mut recipe = Recipe::from_queries(parsed.queries, log)?;
+        recipe.positions = parsed.positions;
+        Ok(recipe)
+    }
+
+    /// Creates a recipe from the text of an installed recipe, followed by the texts of the
+    /// extensions applied to it, in order.
+    pub(super) fn from_texts(
+        recipe_texts: &[String],
+        log: Option<slog::Logger>,
+    ) -> Result<Recipe, RecipeError> {
+        let (first, extensions) = match recipe_texts.split_first() {
+            Some(texts) => texts,
+            None => return Ok(Recipe::blank(log)),
+        };
+        let mut recipe = Recipe::from_str(first, log)?;
+        for text in extensions {
+            recipe = recipe.extend(text).map_err(|(_, e)| e)?;
+        }
+        // the columns were renamed when the extensions were first applied
+        recipe.column_renames.clear();
+        Ok(recipe)
+    }
+
+    /// Parses the statements in a recipe text, ignoring comment lines.
+    fn parse_text(recipe_text: &str) -> Result<ParsedRecipe, RecipeError> {
+        // blank out comment lines, keeping the line numbers of the others intact
+        let lines: Vec<&str> = recipe_text
             .lines()
             .map(str::trim)
-            .filter(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with("--"))
-            .map(String::from)
+            .map(|l| if l.starts_with("--") { "" } else { l })
             .collect();
         let cleaned_recipe_text = lines.join("\n");
 
-        // parse and compute differences to current recipe
-        let parsed_queries = Recipe::parse(&cleaned_recipe_text)?;
-
-        Ok(Recipe::from_queries(parsed_queries, log))
+        Recipe::parse(&cleaned_recipe_text)
     }
 
     /// Creates a recipe from a set of pre-parsed `SqlQuery` structures.
@@ -260,33 +448,27 @@ impl Recipe {
     fn from_queries(
         qs: Vec<(Option<String>, SqlQuery, bool)>,
         log: Option<slog::Logger>,
-    ) -> Recipe {
+    ) -> Result<Recipe, RecipeError> {
         let mut aliases = HashMap::default();
         let mut expression_order = Vec::new();
         let mut duplicates = 0;
-        let expressions = qs
-            .into_iter()
-            .map(|(n, q, is_leaf)| {
-                let qid = hash_query(&q);
-                if !expression_order.contains(&qid) {
-                    expression_order.push(qid);
-                } else {
-                    duplicates += 1;
-                }
-                match n {
-                    None => (),
-                    Some(ref name) => {
-                        assert!(
-                            !aliases.contains_key(name) || aliases[name] == qid,
-                            "Query name exists but existing query is different: {}",
-                            name
-                        );
-                        aliases.insert(name.clone(), qid);
-                    }
+        let mut expressions = HashMap::new();
+        for (n, q, is_leaf) in qs {
+            let qid = hash_query(&q);
+            if !expression_order.contains(&qid) {
+                expression_order.push(qid);
+            } else {
+                duplicates += 1;
+            }
+            if let Some(ref name) = n {
+                if aliases.get(name).map_or(false, |&other| other != qid) {
+                    let reason = String::from("a different query with this name already exists");
+                    return Err(error_for(name, reason));
                 }
-                (qid, (n, q, is_leaf))
-            })
-            .collect::<HashMap<_, _>>();
+                aliases.insert(name.clone(), qid);
+            }
+            expressions.insert(qid, (n, q, is_leaf));
+        }
 
         let inc = match log {
             None => SqlIncorporator::default(),
@@ -300,16 +482,18 @@ impl Recipe {
 
         debug!(log, "{} duplicate queries", duplicates; "version" => 0);
 
-        Recipe {
+        Ok(Recipe {
             expressions,
             expression_order,
             aliases,
             security_config: None,
+            column_renames: Vec::default(),
+            positions: HashMap::default(),
             version: 0,
             prior: None,
             inc: Some(inc),
             log,
-        }
+        })
     }
 
     /// Creates a new security universe
@@ -383,12 +567,84 @@ impl Recipe {
     /// Activate the recipe by migrating the Soup data-flow graph wrapped in `mig` to the recipe.
     /// This causes all necessary changes to said graph to be applied; however, it is the caller's
     /// responsibility to call `mig.commit()` afterwards.
+    ///
+    /// If activation fails, the recipe's incorporator state is returned to what it was before,
+    /// and the caller must abort `mig` instead.
     // crate viz for tests
-    pub(crate) fn activate(&mut self, mig: &mut Migration) -> Result<ActivationResult, String> {
+    pub(crate) fn activate(
+        &mut self,
+        mig: &mut Migration,
+    ) -> Result<ActivationResult, RecipeError> {
         debug!(self.log, "{} queries, {} of which are named",
                                  self.expressions.len(),
                                  self.aliases.len(); "version" => self.version);
 
+        let inc = self.inc.clone();
+        let result = self.apply_changes(mig);
+        if result.is_err() {
+            self.discard_changes(inc, mig);
+        }
+        result.map(|(result, _)| result)
+    }
+
+    /// Work out how activating the recipe would change the Soup data-flow graph wrapped in `mig`,
+    /// without keeping any of the changes. Returns the activation's result along with the flow
+    /// parts of each query that it adds.
+    ///
+    /// The recipe's incorporator state is returned to what it was before, and it is the caller's
+    /// responsibility to abort `mig` afterwards.
+    pub(super) fn dry_run(
+        &mut self,
+        mig: &mut Migration,
+    ) -> Result<(ActivationResult, HashMap<String, QueryFlowParts>), RecipeError> {
+        let inc = self.inc.clone();
+        let result = self.apply_changes(mig);
+        self.discard_changes(inc, mig);
+        result
+    }
+
+    /// Returns the incorporator to its state `inc` from before an activation that used `mig`, and
+    /// whose changes are discarded.
+    fn discard_changes(&mut self, mut inc: Option<SqlIncorporator>, mig: &Migration) {
+        // the copy of the incorporator shares its MIR nodes with the one that the activation
+        // changed, so changes to the nodes themselves must be undone
+        if let Some(ref mut inc) = inc {
+            for &(ref table, ref from, ref to) in self.column_renames.iter().rev() {
+                inc.revert_column_rename(table, from, to);
+            }
+            inc.detach_discarded_nodes(&mig.added);
+        }
+        self.inc = inc;
+    }
+
+    /// Adds the recipe expression `qid` to the graph, and returns its nodes.
+    fn add_expression(
+        &mut self,
+        qid: &QueryID,
+        mig: &mut Migration,
+    ) -> Result<QueryFlowParts, RecipeError> {
+        let (n, q, is_leaf) = self.expressions[qid].clone();
+        let inc = self.inc.as_mut().unwrap();
+        // any changes made before planning fails are discarded along with the migration
+        let reason = match inc.add_parsed_query(q, n, is_leaf, mig) {
+            Ok(qfp) => return Ok(qfp),
+            Err(reason) => reason,
+        };
+
+        let (ref n, ref q, _) = self.expressions[qid];
+        Err(RecipeError {
+            query: relation_name(n, q).map(String::from),
+            position: self.positions.get(qid).cloned(),
+            reason,
+        })
+    }
+
+    /// Applies the changes to the prior recipe to the graph wrapped in `mig`. Returns the
+    /// activation's result along with the flow parts of each query that it adds.
+    fn apply_changes(
+        &mut self,
+        mig: &mut Migration,
+    ) -> Result<(ActivationResult, HashMap<String, QueryFlowParts>), RecipeError> {
         let (added, removed) = match self.prior {
             None => self.compute_delta(&Recipe::blank(None)),
             Some(ref pr) => {
@@ -403,6 +659,52 @@ impl Recipe {
             expressions_added: added.len(),
             expressions_removed: removed.len(),
         };
+        let mut queries = HashMap::new();
+
+        // tables whose definition changed are adapted in place, rather than removed and re-added
+        let table_name = |r: &Recipe, qid: &QueryID| match r.expressions[qid].1 {
+            SqlQuery::CreateTable(ref ctq) => Some(ctq.table.name.clone()),
+            _ => None,
+        };
+        let altered_tables: HashSet<String> = match self.prior {
+            None => HashSet::new(),
+            Some(ref pr) => removed
+                .iter()
+                .filter_map(|qid| table_name(pr, qid))
+                .filter(|t| {
+                    added
+                        .iter()
+                        .any(|qid| table_name(self, qid).as_ref() == Some(t))
+                })
+                .collect(),
+        };
+
+        // tables and views can only be removed along with all the queries that depend on them
+        if let Some(ref pr) = self.prior {
+            let removed_names: Vec<&str> = removed
+                .iter()
+                .filter_map(|qid| {
+                    let (ref n, ref q, _) = pr.expressions[qid];
+                    relation_name(n, q)
+                })
+                .filter(|name| !altered_tables.contains(*name))
+                .collect();
+            let inc = self.inc.as_ref().unwrap();
+            for name in &removed_names {
+                let dependents: Vec<_> = inc
+                    .dependent_queries(name)
+                    .into_iter()
+                    .filter(|d| !removed_names.contains(&d.as_str()))
+                    .collect();
+                if !dependents.is_empty() {
+                    let reason = format!(
+                        "cannot be removed, since it is used by {}",
+                        dependents.join(", ")
+                    );
+                    return Err(error_for(name, reason));
+                }
+            }
+        }
 
         // upgrade schema version *before* applying changes, so that new queries are correctly
         // tagged with the new version. If this recipe was just created, there is no need to
@@ -417,19 +719,19 @@ impl Recipe {
                 self.log,
                 "Found a security configuration, bootstrapping groups..."
             );
-            let config = self.security_config.take().unwrap();
+            let config = self.security_config.clone().unwrap();
             for group in config.groups.values() {
                 info!(
                     self.log,
                     "Creating membership view for group {}",
                     group.name()
                 );
-                let qfp = self.inc.as_mut().unwrap().add_parsed_query(
-                    group.membership(),
-                    Some(group.name()),
-                    true,
-                    mig,
-                )?;
+                let qfp = self
+                    .inc
+                    .as_mut()
+                    .unwrap()
+                    .add_parsed_query(group.membership(), Some(group.name()), true, mig)
+                    .map_err(|e| error_for(&group.name(), e))?;
 
                 /// Add trigger node below group membership views
                 let group_creation = TriggerEvent::GroupCreation {
@@ -444,66 +746,91 @@ impl Recipe {
 
                 result.new_nodes.insert(group.name(), qfp.query_leaf);
             }
+        }
 
-            self.security_config = Some(config);
+        // renamed columns keep their data, so rename them on the existing bases before the bases
+        // are adapted to their new definitions below.
+        for &(ref table, ref from, ref to) in &self.column_renames {
+            self.inc
+                .as_mut()
+                .unwrap()
+                .rename_base_column(table, from, to, mig)
+                .map_err(|e| error_for(table, e))?;
         }
 
         // add new queries to the Soup graph carried by `mig`, and reflect state in the
         // incorporator in `inc`. `NodeIndex`es for new nodes are collected in `new_nodes` to be
         // returned to the caller (who may use them to obtain mutators and getters)
-        for qid in added {
-            let (n, q, is_leaf) = self.expressions[&qid].clone();
-
+        for qid in &added {
             // add the query
-            let qfp = self
-                .inc
-                .as_mut()
-                .unwrap()
-                .add_parsed_query(q, n.clone(), is_leaf, mig)?;
+            let qfp = self.add_expression(qid, mig)?;
 
             // If the user provided us with a query name, use that.
             // If not, use the name internally used by the QFP.
-            let query_name = match n {
-                Some(name) => name,
+            let query_name = match self.expressions[qid].0 {
+                Some(ref name) => name.clone(),
                 None => qfp.name.clone(),
             };
 
-            result.new_nodes.insert(query_name, qfp.query_leaf);
+            result.new_nodes.insert(query_name.clone(), qfp.query_leaf);
+            queries.insert(query_name, qfp);
         }
 
-        result.removed_leaves = removed
-            .iter()
-            .filter_map(|qid| {
-                let (ref n, ref q, _) = self.prior.as_ref().unwrap().expressions[qid];
-                match q {
-                    SqlQuery::CreateTable(ref ctq) => {
-                        // a base may have many dependent queries, including ones that also lost
-                        // nodes; the code handling `removed_leaves` therefore needs to take care
-                        // not to remove bases while they still have children, or to try removing
-                        // them twice.
-                        self.inc.as_mut().unwrap().remove_base(&ctq.table.name);
-                        match self.prior.as_ref().unwrap().node_addr_for(&ctq.table.name) {
-                            Ok(ni) => Some(ni),
-                            Err(e) => {
-                                crit!(
-                                    self.log,
-                                    "failed to remove base {} whose  address could not be resolved",
-                                    ctq.table.name
-                                );
-                                unimplemented!()
-                            }
+        // queries that select all columns of an altered table are planned again, so that they
+        // pick up the table's new columns. Queries that other queries build upon must keep their
+        // existing nodes, and therefore also keep their existing columns.
+        let mut replanned_leaves = Vec::new();
+        for qid in self.expression_order.clone() {
+            let (ref n, ref q, _) = self.expressions[&qid];
+            if added.contains(&qid) || !altered_tables.iter().any(|t| selects_all_from(q, t)) {
+                continue;
+            }
+            let name = match relation_name(n, q) {
+                Some(name) => name.to_owned(),
+                None => continue,
+            };
+
+            let inc = self.inc.as_mut().unwrap();
+            if !inc.can_replan_query(&name) {
+                warn!(self.log, "cannot replan query {} over altered table", name);
+                continue;
+            }
+            info!(self.log, "replanning query {} over altered table", name);
+            replanned_leaves.extend(inc.remove_query(&name, mig));
+            let qfp = self.add_expression(&qid, mig)?;
+            result.new_nodes.insert(name.clone(), qfp.query_leaf);
+            queries.insert(name, qfp);
+        }
+
+        let mut removed_leaves = Vec::new();
+        for qid in &removed {
+            let (ref n, ref q, _) = self.prior.as_ref().unwrap().expressions[qid];
+            let inc = self.inc.as_mut().unwrap();
+            match q {
+                // the new definition of an altered table has adapted the existing base
+                SqlQuery::CreateTable(ref ctq) if altered_tables.contains(&ctq.table.name) => (),
+                SqlQuery::CreateTable(ref ctq) => {
+                    // a base may have many dependent queries, including ones that also lost
+                    // nodes; the code handling `removed_leaves` therefore needs to take care
+                    // not to remove bases while they still have children, or to try removing
+                    // them twice.
+                    let ni = match inc.get_query_address(&ctq.table.name) {
+                        Some(ni) => ni,
+                        None => {
+                            let reason = String::from("address of table could not be resolved");
+                            return Err(error_for(&ctq.table.name, reason));
                         }
-                    }
-                    _ => self
-                        .inc
-                        .as_mut()
-                        .unwrap()
-                        .remove_query(n.as_ref().unwrap(), mig),
+                    };
+                    inc.remove_base(&ctq.table.name)
+                        .map_err(|e| error_for(&ctq.table.name, e))?;
+                    removed_leaves.push(ni);
                 }
-            })
-            .collect();
+                _ => removed_leaves.extend(inc.remove_query(relation_name(n, q).unwrap(), mig)),
+            }
+        }
+        result.removed_leaves = removed_leaves.into_iter().chain(replanned_leaves).collect();
 
-        Ok(result)
+        Ok((result, queries))
     }
 
     /// Work out the delta between two recipes.
@@ -541,13 +868,87 @@ impl Recipe {
     /// recipe; use `replace` if removal of unused expressions is desired.
     /// Consumes `self` and returns a replacement recipe.
     // crate viz for tests
-    pub(crate) fn extend(mut self, additions: &str) -> Result<Recipe, (Recipe, String)> {
+    pub(crate) fn extend(mut self, additions: &str) -> Result<Recipe, (Recipe, RecipeError)> {
         // parse and compute differences to current recipe
-        let add_rp = match Recipe::from_str(additions, None) {
-            Ok(rp) => rp,
+        let parsed = match Recipe::parse_text(additions) {
+            Ok(parsed) => parsed,
+            Err(e) => return Err((self, e)),
+        };
+        let add_rp = match Recipe::from_queries(parsed.queries, None) {
+            Ok(add_rp) => add_rp,
             Err(e) => return Err((self, e)),
         };
-        let (added, _) = add_rp.compute_delta(&self);
+
+        // apply any ALTER TABLE statements to the existing definitions of their tables
+        let mut changes: Vec<(String, Vec<AlterColumn>)> = Vec::new();
+        for alter in parsed.alterations {
+            match changes.iter_mut().find(|(t, _)| *t == alter.table) {
+                Some((_, cs)) => cs.extend(alter.changes),
+                None => changes.push((alter.table, alter.changes)),
+            }
+        }
+        let mut altered = Vec::new();
+        let mut column_renames = Vec::new();
+        for (table, changes) in changes {
+            let qid = match self.table_definition(&table) {
+                Some(qid) => qid,
+                None => {
+                    let e = error_for(
+                        &table,
+                        String::from("cannot alter a table that does not exist"),
+                    );
+                    return Err((self, e));
+                }
+            };
+            let (ref n, ref q, is_leaf) = self.expressions[&qid];
+            let ctq = match *q {
+                SqlQuery::CreateTable(ref ctq) => alter::apply(ctq, &changes),
+                _ => unreachable!(),
+            };
+            match ctq {
+                Ok((ctq, renames)) => {
+                    altered.push((qid, (n.clone(), SqlQuery::CreateTable(ctq), is_leaf)));
+                    column_renames.extend(
+                        renames
+                            .into_iter()
+                            .map(|(from, to)| (table.clone(), from, to)),
+                    );
+                }
+                Err(e) => return Err((self, error_for(&table, e))),
+            }
+        }
+
+        // DROP statements remove the existing definitions of tables and views
+        let mut dropped = Vec::new();
+        for drop in parsed.drops {
+            for name in drop.names {
+                let qid = match drop.kind {
+                    DropKind::Table => self.table_definition(&name),
+                    DropKind::View => self.view_definition(&name),
+                };
+                let redefined = add_rp
+                    .expressions
+                    .values()
+                    .any(|(n, q, _)| relation_name(n, q) == Some(name.as_str()));
+                match qid {
+                    // activation removes old nodes only after adding new ones, so the extension
+                    // cannot define the dropped table or view again
+                    Some(_) if redefined => {
+                        let reason = format!(
+                            "cannot drop a {} and define it again in the same extension",
+                            drop.kind
+                        );
+                        return Err((self, error_for(&name, reason)));
+                    }
+                    Some(_) => dropped.push((drop.kind, name)),
+                    None if drop.if_exists => (),
+                    None => {
+                        let reason = format!("cannot drop a {} that does not exist", drop.kind);
+                        return Err((self, error_for(&name, reason)));
+                    }
+                }
+            }
+        }
 
         // move the incorporator state from the old recipe to the new one
         let prior_inc = self.inc.take();
@@ -561,23 +962,50 @@ impl Recipe {
             inc: prior_inc,
             log: self.log.clone(),
             security_config: self.security_config.clone(),
+            column_renames,
+            positions: parsed.positions,
             // retain the old recipe for future reference
             prior: Some(Box::new(self)),
         };
 
+        // altered tables keep their place in the recipe, ahead of the queries that use them
+        for (old_qid, q) in altered {
+            let qid = hash_query(&q.1);
+            new.expressions.remove(&old_qid);
+            new.expressions.insert(qid, q);
+            for id in new.expression_order.iter_mut().filter(|id| **id == old_qid) {
+                *id = qid;
+            }
+            for id in new.aliases.values_mut().filter(|id| **id == old_qid) {
+                *id = qid;
+            }
+        }
+
+        for (kind, name) in dropped {
+            new.remove_definition(kind, &name);
+        }
+
         // apply changes
+        let (added, _) = add_rp.compute_delta(&new);
         for qid in added {
             let q = add_rp.expressions[&qid].clone();
             new.expressions.insert(qid, q);
             new.expression_order.push(qid);
         }
 
-        for (n, qid) in &add_rp.aliases {
-            assert!(
-                !new.aliases.contains_key(n) || new.aliases[n] == *qid,
-                "Query name exists but existing query is different: {}",
-                n
-            );
+        // report the first name in the extension that is already taken by a different query
+        let conflict = add_rp
+            .aliases
+            .iter()
+            .filter(|&(n, qid)| new.aliases.get(n).map_or(false, |id| id != qid))
+            .min_by_key(|&(n, qid)| (new.positions.get(qid), n));
+        if let Some((n, qid)) = conflict {
+            let e = RecipeError {
+                query: Some(n.clone()),
+                position: new.positions.get(qid).cloned(),
+                reason: String::from("a different query with this name already exists"),
+            };
+            return Err((new.revert(), e));
         }
         new.aliases.extend(add_rp.aliases);
 
@@ -585,6 +1013,91 @@ impl Recipe {
         Ok(new)
     }
 
+    /// Returns the `QueryID` of the `CREATE TABLE` expression that defines `table`, if any.
+    fn table_definition(&self, table: &str) -> Option<QueryID> {
+        self.expression_order
+            .iter()
+            .rev()
+            .find(|qid| match self.expressions[qid].1 {
+                SqlQuery::CreateTable(ref ctq) => ctq.table.name == table,
+                _ => false,
+            })
+            .cloned()
+    }
+
+    /// Checks that `other` defines the same tables as this recipe, so that replacing this recipe
+    /// with `other` leaves the tables and the data in them alone.
+    pub(super) fn check_same_tables(&self, other: &Recipe) -> Result<(), RecipeError> {
+        let tables = |r: &Recipe| -> Vec<(QueryID, String)> {
+            r.expression_order
+                .iter()
+                .filter_map(|qid| match r.expressions[qid].1 {
+                    SqlQuery::CreateTable(ref ctq) => Some((*qid, ctq.table.name.clone())),
+                    _ => None,
+                })
+                .collect()
+        };
+        let changed = tables(self)
+            .into_iter()
+            .filter(|(qid, _)| !other.expressions.contains_key(qid))
+            .chain(
+                tables(other)
+                    .into_iter()
+                    .filter(|(qid, _)| !self.expressions.contains_key(qid)),
+            )
+            .next();
+        match changed {
+            Some((_, table)) => Err(error_for(
+                &table,
+                String::from("cannot roll back across a change to this table"),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the `QueryID` of the expression that defines the view or named query `name`, if any.
+    fn view_definition(&self, name: &str) -> Option<QueryID> {
+        match self.aliases.get(name) {
+            Some(qid) => match self.expressions[qid].1 {
+                SqlQuery::CreateTable(_) => None,
+                _ => Some(*qid),
+            },
+            None => self
+                .expression_order
+                .iter()
+                .rev()
+                .find(|qid| {
+                    let (ref n, ref q, _) = self.expressions[qid];
+                    DropKind::View.is_defined_by(name, n.as_deref(), q)
+                })
+                .cloned(),
+        }
+    }
+
+    /// Removes the definition of table or view `name` from the recipe. A view whose query other
+    /// views share only loses its name, since the query stays in the recipe for them.
+    fn remove_definition(&mut self, kind: DropKind, name: &str) {
+        let qid = match kind {
+            DropKind::Table => self.table_definition(name),
+            DropKind::View => self.view_definition(name),
+        };
+        let qid = match qid {
+            Some(qid) => qid,
+            None => return,
+        };
+
+        match kind {
+            DropKind::Table => self.aliases.retain(|_, id| *id != qid),
+            DropKind::View => {
+                self.aliases.remove(name);
+            }
+        }
+        if !self.aliases.values().any(|id| *id == qid) {
+            self.expressions.remove(&qid);
+            self.expression_order.retain(|id| *id != qid);
+        }
+    }
+
     /// Helper method to reparent a recipe. This is needed for the recovery logic to build
     /// recovery and original recipe (see `make_recovery`).
     pub(in crate::controller) fn set_prior(&mut self, new_prior: Recipe) {
@@ -601,24 +1114,31 @@ impl Recipe {
         self.inc = Some(new_inc);
     }
 
-    fn parse(recipe_text: &str) -> Result<Vec<(Option<String>, SqlQuery, bool)>, String> {
-        let lines: Vec<&str> = recipe_text
+    fn parse(recipe_text: &str) -> Result<ParsedRecipe, RecipeError> {
+        let lines: Vec<(usize, &str)> = recipe_text
             .lines()
-            .filter(|l| !l.is_empty() && !l.starts_with('#'))
-            .map(|l| {
+            .enumerate()
+            .filter(|&(_, l)| !l.is_empty() && !l.starts_with('#'))
+            .map(|(i, l)| {
                 // remove inline comments, too
-                match l.find('#') {
+                let l = match l.find('#') {
                     None => l.trim(),
                     Some(pos) => &l[0..pos - 1].trim(),
-                }
+                };
+                (i + 1, l)
             })
             .collect();
+        // query strings along with the lines at which they start
         let mut query_strings = Vec::new();
         let mut q = String::new();
+        let mut first_line = 0;
 
         let linecount = lines.len();
         let mut i = 1;
-        for l in lines {
+        for (line, l) in lines {
+            if q.is_empty() {
+                first_line = line;
+            }
             if !l.ends_with(';') && i < linecount {
                 q.push_str(l);
                 q.push_str(" ");
@@ -626,43 +1146,142 @@ impl Recipe {
                 // either line ends with semicolor, or it does not and this is the last line
                 // in both cases, we're at the end of the query
                 q.push_str(l);
-                query_strings.push(q);
+                query_strings.push((first_line, q));
                 q = String::new();
             }
             i += 1;
         }
 
-        let parsed_queries = query_strings.iter().fold(
-            Vec::new(),
-            |mut acc: Vec<Result<(bool, Option<&str>, SqlQuery), String>>, q| {
-                match query_exprs(q) {
-                    Result::Err(e) => {
-                        // we got a parse error
-                        acc.push(Err(format!("Query \"{}\", parse error: {}", q, e)));
+        let mut parsed_queries = Vec::new();
+        for (line, q) in &query_strings {
+            let error = |input: &str, reason: String| RecipeError {
+                query: query_prefix(input)
+                    .ok()
+                    .and_then(|(_, (_, name))| name)
+                    .map(String::from),
+                position: Some(*line),
+                reason,
+            };
+            if has_limit_parameter(q) {
+                let reason = String::from(
+                    "LIMIT and OFFSET only take integer literals, not query parameters; \
+                     use View::lookup_page to page through a view's results instead",
+                );
+                return Err(error(q, reason));
+            }
+            if let Some(op) = outer_join::unsupported_join(q) {
+                return Err(error(q, format!("{} is not supported", op)));
+            }
+            let parsed = query_exprs(q);
+            let complete = match parsed {
+                Ok((remainder, _)) => remainder.is_empty(),
+                Err(_) => false,
+            };
+            if !complete {
+                if let Some(f) = unparsed_function(q) {
+                    let reason = format!(
+                        "{} is not supported in SQL, as the SQL parser doesn't recognize it; the \
+                         operator that computes it can only be set up through the migration API",
+                        f
+                    );
+                    return Err(error(q, reason));
+                }
+            }
+            match parsed {
+                Err(e) => {
+                    // we got a parse error
+                    return Err(error(q, format!("parse error in \"{}\": {}", q, e)));
+                }
+                // should have consumed all input
+                Ok((remainder, _)) if !remainder.is_empty() => {
+                    let reason = format!("failed to parse \"{}\"", remainder);
+                    return Err(error(remainder, reason));
+                }
+                Ok((_, parsed)) => parsed_queries.extend(parsed.into_iter().map(|p| (*line, p))),
+            }
+        }
+
+        // ALTER TABLE and DROP statements apply to the latest definition of their table or view in
+        // the same recipe text; any others are returned for the caller to apply to existing ones.
+        let mut queries: Vec<(Option<String>, SqlQuery, bool)> = Vec::new();
+        // the lines at which the `queries` start
+        let mut lines: Vec<usize> = Vec::new();
+        let mut alterations = Vec::new();
+        let mut drops = Vec::new();
+        for (line, statement) in parsed_queries {
+            match statement {
+                Statement::Query(public, name, expr) => {
+                    if let Some(name) = name {
+                        if queries
+                            .iter()
+                            .any(|(n, q, _)| n.as_deref() == Some(name) && *q != expr)
+                        {
+                            return Err(RecipeError {
+                                query: Some(name.to_owned()),
+                                position: Some(line),
+                                reason: String::from(
+                                    "a different query with this name already exists",
+                                ),
+                            });
+                        }
                     }
-                    Result::Ok((remainder, parsed)) => {
-                        // should have consumed all input
-                        assert!(
-                            remainder.is_empty(),
-                            format!(
-                                "failed to parse the complete recipe; left with: {}",
-                                remainder
-                            )
-                        );
-                        acc.extend(parsed.into_iter().map(|p| Ok(p)).collect::<Vec<_>>());
+                    queries.push((name.map(String::from), expr, public));
+                    lines.push(line);
+                }
+                Statement::Alter(alter) => {
+                    let ctq = queries.iter_mut().rev().find_map(|(_, q, _)| match *q {
+                        SqlQuery::CreateTable(ref mut ctq) if ctq.table.name == alter.table => {
+                            Some(ctq)
+                        }
+                        _ => None,
+                    });
+                    match ctq {
+                        Some(ctq) => match alter::apply(ctq, &alter.changes) {
+                            Ok((altered, _)) => *ctq = altered,
+                            Err(reason) => {
+                                return Err(RecipeError {
+                                    query: Some(alter.table),
+                                    position: Some(line),
+                                    reason,
+                                });
+                            }
+                        },
+                        None => alterations.push(alter),
                     }
                 }
-                acc
-            },
-        );
+                Statement::Drop(mut drop) => {
+                    let kind = drop.kind;
+                    drop.names.retain(|name| {
+                        let defined = queries
+                            .iter()
+                            .rposition(|(n, q, _)| kind.is_defined_by(name, n.as_deref(), q));
+                        match defined {
+                            Some(i) => {
+                                queries.remove(i);
+                                lines.remove(i);
+                                false
+                            }
+                            None => true,
+                        }
+                    });
+                    if !drop.names.is_empty() {
+                        drops.push(drop);
+                    }
+                }
+            }
+        }
 
-        Ok(parsed_queries
-            .into_iter()
-            .map(|pr| {
-                let pr = pr.unwrap();
-                (pr.1.map(String::from), pr.2, pr.0)
-            })
-            .collect::<Vec<_>>())
+        let mut positions = HashMap::new();
+        for ((_, q, _), line) in queries.iter().zip(lines) {
+            positions.entry(hash_query(q)).or_insert(line);
+        }
+
+        Ok(ParsedRecipe {
+            queries,
+            alterations,
+            drops,
+            positions,
+        })
     }
 
     /// Returns the predecessor from which this `Recipe` was migrated to.
@@ -721,9 +1340,10 @@ impl Recipe {
         self.version
     }
 
-    /// Reverts to prior version of recipe
+    /// Reverts to prior version of recipe, which takes back the incorporator state.
     pub(super) fn revert(self) -> Recipe {
-        if let Some(prior) = self.prior {
+        if let Some(mut prior) = self.prior {
+            prior.inc = self.inc;
             *prior
         } else {
             Recipe::blank(Some(self.log))
@@ -748,6 +1368,7 @@ impl Recipe {
 
         let mut recovery = self.clone();
         recovery.prior = Some(Box::new(self.clone()));
+        recovery.column_renames.clear();
         recovery.next();
 
         // remove from recipe
@@ -759,6 +1380,7 @@ impl Recipe {
         }
 
         let mut original = self.clone();
+        original.column_renames.clear();
         original.next();
         original.next();
 
@@ -780,7 +1402,7 @@ mod tests {
         let q1_id = hash_query(&q1);
 
         let pq_a = vec![(None, q0.clone(), true), (None, q1.clone(), true)];
-        let r1 = Recipe::from_queries(pq_a, None);
+        let r1 = Recipe::from_queries(pq_a, None).unwrap();
 
         // delta from empty recipe
         let (added, removed) = r1.compute_delta(&r0);
@@ -798,7 +1420,7 @@ mod tests {
         let q2 = sql_parser::parse_query("SELECT c FROM b;").unwrap();
         let q2_id = hash_query(&q2);
         let pq_b = vec![(None, q0, true), (None, q2.clone(), true)];
-        let r2 = Recipe::from_queries(pq_b, None);
+        let r2 = Recipe::from_queries(pq_b, None).unwrap();
 
         // delta should show addition and removal
         let (added, removed) = r2.compute_delta(&r1);
@@ -834,6 +1456,114 @@ mod tests {
         assert_eq!(r2.prior, Some(Box::new(r1_copy)));
     }
 
+    #[test]
+    fn it_alters_tables() {
+        let names = |r: &Recipe, qid: &QueryID| match r.expressions[qid].1 {
+            SqlQuery::CreateTable(ref ctq) => ctq
+                .fields
+                .iter()
+                .map(|cs| cs.column.name.clone())
+                .collect::<Vec<_>>(),
+            _ => unreachable!(),
+        };
+
+        // alterations of tables defined in the same text are applied right away
+        let r0 = Recipe::from_str(
+            "CREATE TABLE b (a int, c int, d int);\nALTER TABLE b DROP COLUMN d;\n\
+             QUERY q: SELECT * FROM b;",
+            None,
+        )
+        .unwrap();
+        assert_eq!(r0.expressions.len(), 2);
+        assert_eq!(names(&r0, &r0.expression_order[0]), vec!["a", "c"]);
+        assert!(Recipe::from_str("ALTER TABLE b DROP COLUMN a;", None).is_err());
+
+        // others replace the existing table definition, which keeps its place in the recipe
+        let r1 = r0
+            .extend("ALTER TABLE b ADD COLUMN e text;\nALTER TABLE b RENAME COLUMN c TO x;")
+            .unwrap();
+        assert_eq!(r1.expressions.len(), 2);
+        let (added, removed) = r1.compute_delta(r1.prior().unwrap());
+        assert_eq!(added.len(), 1);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(r1.expression_order[0], added[0]);
+        assert_eq!(names(&r1, &added[0]), vec!["a", "x", "e"]);
+        assert_eq!(
+            r1.column_renames,
+            vec![("b".to_owned(), "c".to_owned(), "x".to_owned())]
+        );
+
+        // invalid alterations leave the recipe unchanged
+        let (r1, _) = r1.extend("ALTER TABLE nope DROP COLUMN a;").unwrap_err();
+        let (r1, _) = r1.extend("ALTER TABLE b DROP COLUMN c;").unwrap_err();
+        assert_eq!(r1.version, 1);
+    }
+
+    #[test]
+    fn it_checks_for_table_changes() {
+        let r0 = Recipe::from_str(
+            "CREATE TABLE b (a int, c int);\nQUERY q: SELECT a FROM b;",
+            None,
+        )
+        .unwrap();
+        let r1 = Recipe::from_texts(
+            &[
+                "CREATE TABLE b (a int, c int);".to_owned(),
+                "QUERY r: SELECT c FROM b;".to_owned(),
+            ],
+            None,
+        )
+        .unwrap();
+        assert!(r0.check_same_tables(&r1).is_ok());
+
+        // renamed columns, and tables that only one of the recipes has, are changes
+        let r2 = r1.extend("ALTER TABLE b RENAME COLUMN c TO x;").unwrap();
+        let e = r0.check_same_tables(&r2).unwrap_err();
+        assert_eq!(e.query, Some("b".to_owned()));
+        let r3 = Recipe::from_str(
+            "CREATE TABLE b (a int, c int);\nCREATE TABLE d (a int);",
+            None,
+        )
+        .unwrap();
+        let e = r3.check_same_tables(&r0).unwrap_err();
+        assert_eq!(e.query, Some("d".to_owned()));
+    }
+
+    #[test]
+    fn it_drops_tables_and_views() {
+        // drops of tables and views defined in the same text are applied right away
+        let r0 = Recipe::from_str(
+            "CREATE TABLE b (a int, c int);\nCREATE TABLE d (a int);\nDROP TABLE d;\n\
+             QUERY q: SELECT a FROM b;\nQUERY r: SELECT c FROM b;\n\
+             CREATE VIEW v AS SELECT a, c FROM b;\nDROP VIEW IF EXISTS r, nope;",
+            None,
+        )
+        .unwrap();
+        assert_eq!(r0.expressions.len(), 3);
+        assert!(r0.table_definition("d").is_none());
+        assert!(r0.view_definition("r").is_none());
+        assert!(Recipe::from_str("DROP VIEW q;", None).is_err());
+        assert!(Recipe::from_str("DROP TABLE IF EXISTS b;", None).is_ok());
+
+        // others remove the existing definitions
+        let r1 = r0.extend("DROP VIEW q, v;").unwrap();
+        assert_eq!(r1.expressions.len(), 1);
+        let (added, removed) = r1.compute_delta(r1.prior().unwrap());
+        assert!(added.is_empty());
+        assert_eq!(removed.len(), 2);
+        assert!(!r1.aliases.contains_key("q"));
+
+        // tables and views that do not exist cannot be dropped, and ones that do cannot be defined
+        // again in the same extension
+        let (r1, _) = r1.extend("DROP VIEW b;").unwrap_err();
+        let (r1, _) = r1.extend("DROP TABLE q;").unwrap_err();
+        let (r1, _) = r1
+            .extend("DROP TABLE b;\nCREATE TABLE b (a int);")
+            .unwrap_err();
+        assert_eq!(r1.version, 1);
+        assert!(r1.extend("DROP TABLE IF EXISTS q;").is_ok());
+    }
+
     #[test]
     fn it_handles_aliasing() {
         let r0 = Recipe::blank(None);
@@ -845,10 +1575,19 @@ mod tests {
         assert_eq!(r1.expressions.len(), 1);
         assert_eq!(r1.aliases.len(), 2);
         assert_eq!(r1.resolve_alias("q_1"), r1.resolve_alias("q_0"));
+
+        // but one name can't refer to two different queries
+        let q0 = sql_parser::parse_query("SELECT a FROM b;").unwrap();
+        let q1 = sql_parser::parse_query("SELECT c FROM b;").unwrap();
+        let pq = vec![
+            (Some("q_0".into()), q0, true),
+            (Some("q_0".into()), q1, true),
+        ];
+        let e = Recipe::from_queries(pq, None).unwrap_err();
+        assert_eq!(e.query, Some(String::from("q_0")));
     }
 
     #[test]
-    #[should_panic(expected = "Query name exists but existing query is different")]
     fn it_avoids_spurious_aliasing() {
         let r0 = Recipe::blank(None);
 
@@ -859,11 +1598,63 @@ mod tests {
         assert_eq!(r1.expressions.len(), 2);
 
         let r2_txt = "q_0: SELECT a, c FROM b WHERE x = 21;\nq_1: SELECT c FROM b;";
-        // we expect this to panic, since both q_0 and q_1 already exist with a different
+        // we expect this to fail, since both q_0 and q_1 already exist with a different
         // definition
-        let r2 = r1.extend(r2_txt).unwrap();
-        assert_eq!(r2.version, 2);
-        assert_eq!(r2.expressions.len(), 4);
+        let (r1, e) = r1.extend(r2_txt).unwrap_err();
+        assert_eq!(e.query, Some(String::from("q_0")));
+        assert_eq!(e.position, Some(1));
+        assert_eq!(r1.version, 1);
+        assert_eq!(r1.expressions.len(), 2);
+    }
+
+    #[test]
+    fn it_reports_error_positions() {
+        let r0_txt = "# tables\nCREATE TABLE b (a int, c int);\n\nq_0: SELECT a\nFROM b;\n-- done";
+        let r0 = Recipe::from_str(r0_txt, None).unwrap();
+        assert_eq!(r0.positions[&r0.aliases["q_0"]], 4);
+
+        let r1_txt = "CREATE TABLE b (a int);\nq_0: SELEC a FROM b;";
+        let e = Recipe::from_str(r1_txt, None).unwrap_err();
+        assert_eq!(e.query, Some(String::from("q_0")));
+        assert_eq!(e.position, Some(2));
+
+        let e = Recipe::from_str("\nq_0: SELECT a FROM b; q_1 SELECT", None).unwrap_err();
+        assert_eq!(e.position, Some(2));
+
+        let r2_txt = "q_0: SELECT a FROM b;\nq_0: SELECT c FROM b;";
+        let e = Recipe::from_str(r2_txt, None).unwrap_err();
+        assert_eq!(e.query, Some(String::from("q_0")));
+        assert_eq!(e.position, Some(2));
+
+        let e = Recipe::from_str("q_0: SELECT Stddev (a) AS s FROM b;", None).unwrap_err();
+        assert_eq!(e.query, Some(String::from("q_0")));
+        assert!(e.reason.starts_with("STDDEV is not supported"));
+
+        let e = Recipe::from_str("q_0: SELECT a FROM b WHERE lower(a) = ?;", None).unwrap_err();
+        assert_eq!(e.query, Some(String::from("q_0")));
+        assert!(e.reason.starts_with("LOWER is not supported"));
+
+        let r3_txt = "CREATE TABLE b (a int);\nq_0: SELECT a FROM b ORDER BY a LIMIT ? OFFSET ?;";
+        let e = Recipe::from_str(r3_txt, None).unwrap_err();
+        assert_eq!(e.query, Some(String::from("q_0")));
+        assert_eq!(e.position, Some(2));
+        assert!(e.reason.contains("LIMIT and OFFSET"));
+    }
+
+    #[test]
+    fn it_handles_outer_joins() {
+        let r0_txt = "q_0: SELECT a.x, b.y FROM a RIGHT JOIN b ON (a.x = b.x);\n\
+                      q_1: SELECT a.x, b.y FROM a LEFT JOIN b ON (a.x = b.x);\n\
+                      q_2: SELECT a.x FROM a JOIN b USING (x) FULL OUTER JOIN c USING (x);";
+        let r0 = Recipe::from_str(r0_txt, None).unwrap();
+        assert_eq!(r0.expressions.len(), 3);
+        assert_ne!(r0.resolve_alias("q_1"), r0.resolve_alias("q_0"));
+
+        let r1_txt = "CREATE TABLE a (x int);\nq_0: SELECT a.x FROM a CROSS JOIN b;";
+        let e = Recipe::from_str(r1_txt, None).unwrap_err();
+        assert_eq!(e.query, Some(String::from("q_0")));
+        assert_eq!(e.position, Some(2));
+        assert!(e.reason.contains("CROSS JOIN"));
     }
 
     #[test]
@@ -0,0 +1,175 @@
+//! `RIGHT JOIN` and `FULL JOIN` support for recipes.
+//!
+//! nom-sql only has operators for inner and left joins. The recipe parser therefore writes
+//! `RIGHT [OUTER] JOIN` as `CROSS JOIN` and `FULL [OUTER] JOIN` as `STRAIGHT_JOIN` before parsing
+//! a query. nom-sql parses those, but Noria doesn't support them otherwise, so the query graph
+//! takes them to be right and full joins (see `sql::query_graph`). Queries that really use
+//! `CROSS JOIN` or `STRAIGHT_JOIN` are rejected, so that they aren't mistaken for outer joins.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum JoinKeyword {
+    Right,
+    Full,
+    Cross,
+    Straight,
+}
+
+fn is_ident(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+/// Find the `RIGHT [OUTER] JOIN`, `FULL [OUTER] JOIN`, `CROSS JOIN` and `STRAIGHT_JOIN` keywords
+/// in `input`, outside of quoted strings and identifiers, along with the byte ranges they span.
+fn find_joins(input: &str) -> Vec<(usize, usize, JoinKeyword)> {
+    let bytes = input.as_bytes();
+    let word_at = |i: usize| {
+        let end = bytes[i..]
+            .iter()
+            .position(|&b| !is_ident(b))
+            .map(|n| i + n)
+            .unwrap_or_else(|| bytes.len());
+        (&input[i..end], end)
+    };
+    let skip_space = |i: usize| {
+        bytes[i..]
+            .iter()
+            .position(|b| !b.is_ascii_whitespace())
+            .map(|n| i + n)
+            .unwrap_or_else(|| bytes.len())
+    };
+
+    let mut found = Vec::new();
+    let mut quote = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None if b == b'\'' || b == b'"' || b == b'`' => quote = Some(b),
+            None if is_ident(b) => {
+                let (word, end) = word_at(i);
+                if word.eq_ignore_ascii_case("straight_join") {
+                    found.push((i, end, JoinKeyword::Straight));
+                    i = end;
+                    continue;
+                }
+
+                let kind = if word.eq_ignore_ascii_case("right") {
+                    Some(JoinKeyword::Right)
+                } else if word.eq_ignore_ascii_case("full") {
+                    Some(JoinKeyword::Full)
+                } else if word.eq_ignore_ascii_case("cross") {
+                    Some(JoinKeyword::Cross)
+                } else {
+                    None
+                };
+                if let Some(kind) = kind {
+                    let (mut next, mut next_end) = word_at(skip_space(end));
+                    if next.eq_ignore_ascii_case("outer") && kind != JoinKeyword::Cross {
+                        let (n, e) = word_at(skip_space(next_end));
+                        next = n;
+                        next_end = e;
+                    }
+                    if next.eq_ignore_ascii_case("join") {
+                        found.push((i, next_end, kind));
+                    }
+                }
+                i = end;
+                continue;
+            }
+            None => {}
+        }
+        i += 1;
+    }
+    found
+}
+
+/// Returns the join operator that `input` uses, but that Noria doesn't support, if any.
+pub(super) fn unsupported_join(input: &str) -> Option<&'static str> {
+    find_joins(input)
+        .into_iter()
+        .find_map(|(_, _, kind)| match kind {
+            JoinKeyword::Cross => Some("CROSS JOIN"),
+            JoinKeyword::Straight => Some("STRAIGHT_JOIN"),
+            JoinKeyword::Right | JoinKeyword::Full => None,
+        })
+}
+
+/// A query text in which right and full joins are written with operators that nom-sql parses.
+pub(super) struct MarkedJoins {
+    pub(super) text: String,
+    /// The byte ranges of the rewritten joins in the input, and in `text`.
+    rewritten: Vec<((usize, usize), (usize, usize))>,
+}
+
+impl MarkedJoins {
+    /// The position in the input that corresponds to position `at` in `text`.
+    pub(super) fn input_position(&self, at: usize) -> usize {
+        match self
+            .rewritten
+            .iter()
+            .rev()
+            .find(|&&(_, (start, _))| start < at)
+        {
+            // positions within a rewritten join map to its start
+            Some(&((start, _), (_, end))) if at < end => start,
+            Some(&((_, input_end), (_, end))) => input_end + (at - end),
+            None => at,
+        }
+    }
+}
+
+/// Rewrite each `RIGHT [OUTER] JOIN` in `input` into a `CROSS JOIN`, and each `FULL [OUTER] JOIN`
+/// into a `STRAIGHT_JOIN`.
+pub(super) fn mark_outer_joins(input: &str) -> MarkedJoins {
+    let mut text = String::with_capacity(input.len());
+    let mut rewritten = Vec::new();
+    let mut copied = 0;
+    for (start, end, kind) in find_joins(input) {
+        let marker = match kind {
+            JoinKeyword::Right => "CROSS JOIN",
+            JoinKeyword::Full => "STRAIGHT_JOIN",
+            JoinKeyword::Cross | JoinKeyword::Straight => continue,
+        };
+        text.push_str(&input[copied..start]);
+        rewritten.push(((start, end), (text.len(), text.len() + marker.len())));
+        text.push_str(marker);
+        copied = end;
+    }
+    text.push_str(&input[copied..]);
+    MarkedJoins { text, rewritten }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_marks_outer_joins() {
+        let q = "SELECT * FROM a RIGHT JOIN b ON (a.x = b.x) WHERE a.y = 'right join'";
+        let marked = mark_outer_joins(q);
+        assert_eq!(
+            marked.text,
+            "SELECT * FROM a CROSS JOIN b ON (a.x = b.x) WHERE a.y = 'right join'"
+        );
+
+        let q = "SELECT * FROM a full outer join b USING (x) WHERE a.y = 1";
+        let marked = mark_outer_joins(q);
+        assert_eq!(
+            marked.text,
+            "SELECT * FROM a STRAIGHT_JOIN b USING (x) WHERE a.y = 1"
+        );
+        // positions after the rewritten join map to the same text in the input
+        let at = marked.text.find("b USING").unwrap();
+        assert_eq!(&q[marked.input_position(at)..], "b USING (x) WHERE a.y = 1");
+        assert_eq!(marked.input_position(20), q.find("full").unwrap());
+        assert_eq!(marked.input_position(3), 3);
+
+        assert_eq!(
+            unsupported_join("SELECT * FROM a CROSS JOIN b"),
+            Some("CROSS JOIN")
+        );
+        assert_eq!(unsupported_join("SELECT cross FROM a RIGHT JOIN b"), None);
+    }
+}
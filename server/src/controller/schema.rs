@@ -24,6 +24,14 @@ fn to_sql_type(d: &DataType) -> Option<SqlType> {
         // type), so caller must handle appropriately.
         DataType::None => None,
         DataType::Timestamp(_) => Some(SqlType::Timestamp),
+        DataType::Bool(_) => Some(SqlType::Bool),
+        DataType::ByteArray(_) => Some(SqlType::Blob),
+        DataType::Uuid(_) => Some(SqlType::Binary(16)),
+        DataType::Decimal(_, scale) => Some(SqlType::Decimal(18, *scale)),
+        DataType::Date(_) => Some(SqlType::Date),
+        // nom-sql has no TIME type, so times are reported as the text MySQL formats them as
+        DataType::Time(_) => Some(SqlType::Varchar(17)),
+        DataType::TimestampTz(_) => Some(SqlType::Timestamp),
     }
 }
 
@@ -52,7 +60,36 @@ fn type_for_internal_column(
                 to_sql_type(&emits.1[off])
             }
         }
-        ops::NodeOperator::Sum(_) | ops::NodeOperator::FilterSum(_) => {
+        ops::NodeOperator::Sum(ref o) => {
+            use dataflow::ops::grouped::aggregate::Aggregation;
+
+            // computed column is always emitted last
+            if column_index == node.fields().len() - 1 {
+                match *o.operation().kind() {
+                    // averages and deviations are real-valued
+                    Aggregation::AVG | Aggregation::VARIANCE | Aggregation::STDDEV => {
+                        Some(SqlType::Real)
+                    }
+                    // sums of decimals are exact decimals with the same scale
+                    Aggregation::SUM => {
+                        let over_columns = o.over_columns();
+                        match column_schema(graph, next_node_on_path, recipe, over_columns[0], log)
+                            .map(|cs| cs.sql_type)
+                        {
+                            Some(SqlType::Decimal(_, scale)) => Some(SqlType::Decimal(18, scale)),
+                            _ => Some(SqlType::Bigint(64)),
+                        }
+                    }
+                    // everything else produces integral columns
+                    _ => Some(SqlType::Bigint(64)),
+                }
+            } else {
+                // no column that isn't the aggregation result column should ever trace
+                // back to an aggregation.
+                unreachable!();
+            }
+        }
+        ops::NodeOperator::FilterSum(_) => {
             // computed column is always emitted last
             if column_index == node.fields().len() - 1 {
                 // counts and sums always produce integral columns
@@ -80,7 +117,9 @@ fn type_for_internal_column(
                 unreachable!();
             }
         }
-        ops::NodeOperator::Join(_) => {
+        ops::NodeOperator::Join(_)
+        | ops::NodeOperator::ThetaJoin(_)
+        | ops::NodeOperator::SemiJoin(_) => {
             // join doesn't "generate" columns, but they may come from one of the other
             // ancestors; so keep iterating to try the other paths
             None
@@ -9,7 +9,7 @@ use nom_sql::{
 use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
 
-fn target_columns_from_computed_column(computed_col: &nom_sql::Column) -> Column {
+fn target_columns_from_computed_column(computed_col: &nom_sql::Column) -> Result<Column, String> {
     use nom_sql::FunctionExpression::*;
 
     match *computed_col.function.as_ref().unwrap().deref() {
@@ -32,12 +32,12 @@ fn target_columns_from_computed_column(computed_col: &nom_sql::Column) -> Column
             }),
             _,
         )
-        | Sum(FunctionArguments::Column(ref col), _) => Column::from(col),
+        | Sum(FunctionArguments::Column(ref col), _) => Ok(Column::from(col)),
         CountStar => {
             // see comment re COUNT(*) rewriting in make_aggregation_node
-            panic!("COUNT(*) should have been rewritten earlier!")
+            Err(String::from("COUNT(*) should have been rewritten earlier"))
         }
-        _ => unreachable!(),
+        ref f => Err(format!("unsupported aggregate: {}", f)),
     }
 }
 
@@ -50,7 +50,7 @@ pub(super) fn make_predicates_above_grouped<'a>(
     node_count: usize,
     column_to_predicates: &HashMap<Column, Vec<&'a ConditionExpression>>,
     prev_node: &mut Option<MirNodeRef>,
-) -> (Vec<&'a ConditionExpression>, Vec<MirNodeRef>) {
+) -> Result<(Vec<&'a ConditionExpression>, Vec<MirNodeRef>), String> {
     let mut created_predicates = Vec::new();
     let mut predicates_above_group_by_nodes = Vec::new();
     let mut node_count = node_count;
@@ -62,7 +62,7 @@ pub(super) fn make_predicates_above_grouped<'a>(
                 // whenever we have a column getting aggregated (i.e. an over column
                 // rather than a group by column) we won't be able to filter on it
                 // later, so any filters involving it need to get moved above
-                let over_col = target_columns_from_computed_column(ccol);
+                let over_col = target_columns_from_computed_column(ccol)?;
                 let over_table = over_col.table.as_ref().unwrap().as_str();
 
                 if column_to_predicates.contains_key(&over_col) {
@@ -77,7 +77,7 @@ pub(super) fn make_predicates_above_grouped<'a>(
                         over_col,
                         parent,
                         &mut created_predicates,
-                    );
+                    )?;
 
                     node_count += predicates_above_group_by_nodes.len();
                     *prev_node = Some(new_mpns.last().unwrap().clone());
@@ -87,7 +87,7 @@ pub(super) fn make_predicates_above_grouped<'a>(
         }
     }
 
-    (created_predicates, predicates_above_group_by_nodes)
+    Ok((created_predicates, predicates_above_group_by_nodes))
 }
 
 pub(super) fn make_grouped(
@@ -98,7 +98,7 @@ pub(super) fn make_grouped(
     node_count: usize,
     prev_node: &mut Option<MirNodeRef>,
     is_reconcile: bool,
-) -> Vec<MirNodeRef> {
+) -> Result<Vec<MirNodeRef>, String> {
     let mut func_nodes: Vec<MirNodeRef> = Vec::new();
     let mut node_count = node_count;
 
@@ -109,7 +109,11 @@ pub(super) fn make_grouped(
                 .edges
                 .values()
                 .filter(|e| match **e {
-                    QueryGraphEdge::Join(_) | QueryGraphEdge::LeftJoin(_) => false,
+                    QueryGraphEdge::Join(_)
+                    | QueryGraphEdge::LeftJoin(_)
+                    | QueryGraphEdge::FullJoin(_)
+                    | QueryGraphEdge::SemiJoin(_)
+                    | QueryGraphEdge::AntiJoin(_) => false,
                     QueryGraphEdge::GroupBy(_) => true,
                 })
                 .collect();
@@ -148,7 +152,9 @@ pub(super) fn make_grouped(
                                 nom_sql::Column::from(colname.as_ref()),
                             ))
                         }
-                        _ => unimplemented!(),
+                        ref f => {
+                            return Err(format!("{} cannot be combined across universes", f));
+                        }
                     };
 
                     nom_sql::Column {
@@ -162,7 +168,7 @@ pub(super) fn make_grouped(
                 };
 
                 // We must also push parameter columns through the group by
-                let over_col = target_columns_from_computed_column(&computed_col);
+                let over_col = target_columns_from_computed_column(&computed_col)?;
                 let over_table = over_col.table.as_ref().unwrap().as_str();
 
                 let parent_node = match *prev_node {
@@ -244,7 +250,7 @@ pub(super) fn make_grouped(
                         // output, we make one up a group column by adding an extra
                         // projection node
                         let proj_name = format!("{}_prj_hlpr", name);
-                        let fn_col = target_columns_from_computed_column(&computed_col);
+                        let fn_col = target_columns_from_computed_column(&computed_col)?;
 
                         let proj =
                             mir_converter.make_projection_helper(&proj_name, parent_node, &fn_col);
@@ -272,7 +278,7 @@ pub(super) fn make_grouped(
                     &Column::from(computed_col),
                     group_cols.iter().collect(),
                     parent_node,
-                );
+                )?;
 
                 *prev_node = Some(nodes.last().unwrap().clone());
                 node_count += nodes.len();
@@ -281,5 +287,5 @@ pub(super) fn make_grouped(
         }
     }
 
-    func_nodes
+    Ok(func_nodes)
 }
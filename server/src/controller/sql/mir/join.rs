@@ -1,8 +1,9 @@
 use crate::controller::sql::mir::SqlToMirConverter;
 use crate::controller::sql::query_graph::{JoinRef, QueryGraph, QueryGraphEdge};
 use dataflow::ops::join::JoinType;
+use dataflow::ops::semi_join::SemiJoinType;
 use mir::MirNodeRef;
-use nom_sql::ConditionTree;
+use nom_sql::{ConditionTree, Operator};
 use std::collections::{HashMap, HashSet};
 
 struct JoinChain {
@@ -30,29 +31,46 @@ impl JoinChain {
 // If a predicate's parent tables haven't been used by any previous predicate,
 // a new join chain is started for the current predicate. And we assume that
 // a future predicate will bring these chains together.
+// All predicates between the same pair of tables go into a single join node
+// that joins on all of their columns.
 pub(super) fn make_joins(
     mir_converter: &SqlToMirConverter,
     name: &str,
     qg: &QueryGraph,
     node_for_rel: &HashMap<&str, MirNodeRef>,
     node_count: usize,
-) -> Vec<MirNodeRef> {
+) -> Result<Vec<MirNodeRef>, String> {
     let mut join_nodes: Vec<MirNodeRef> = Vec::new();
     let mut join_chains = Vec::new();
     let mut node_count = node_count;
+    let mut joined_edges = HashSet::new();
 
     for jref in qg.join_order.iter() {
-        let (join_type, jp) = from_join_ref(jref, &qg);
+        if !joined_edges.insert((&jref.src, &jref.dst)) {
+            // all of this edge's predicates went into the join node made for its first one
+            continue;
+        }
+
+        let (join_kind, jps) = from_join_ref(jref, &qg);
         let (left_chain, right_chain) =
             pick_join_chains(&jref.src, &jref.dst, &mut join_chains, node_for_rel);
 
-        let jn = mir_converter.make_join_node(
-            &format!("{}_n{}", name, node_count),
-            jp,
-            left_chain.last_node.clone(),
-            right_chain.last_node.clone(),
-            join_type,
-        );
+        let jn = match join_kind {
+            JoinKind::Join(join_type) => mir_converter.make_join_node(
+                &format!("{}_n{}", name, node_count),
+                jps,
+                left_chain.last_node.clone(),
+                right_chain.last_node.clone(),
+                join_type,
+            )?,
+            JoinKind::SemiJoin(semi_join_type) => mir_converter.make_semi_join_node(
+                &format!("{}_n{}", name, node_count),
+                jps,
+                left_chain.last_node.clone(),
+                right_chain.last_node.clone(),
+                semi_join_type,
+            )?,
+        };
 
         // merge node chains
         let new_chain = left_chain.merge_chain(right_chain, jn.clone());
@@ -63,13 +81,25 @@ pub(super) fn make_joins(
         join_nodes.push(jn);
     }
 
-    join_nodes
+    Ok(join_nodes)
+}
+
+enum JoinKind {
+    Join(JoinType),
+    SemiJoin(SemiJoinType),
 }
 
-fn from_join_ref<'a>(jref: &JoinRef, qg: &'a QueryGraph) -> (JoinType, &'a ConditionTree) {
+fn from_join_ref<'a>(jref: &JoinRef, qg: &'a QueryGraph) -> (JoinKind, &'a [ConditionTree]) {
     match qg.edges[&(jref.src.clone(), jref.dst.clone())] {
-        QueryGraphEdge::Join(ref jps) => (JoinType::Inner, &jps[jref.index]),
-        QueryGraphEdge::LeftJoin(ref jps) => (JoinType::Left, &jps[jref.index]),
+        QueryGraphEdge::Join(ref jps) => (JoinKind::Join(JoinType::Inner), &jps[..]),
+        QueryGraphEdge::LeftJoin(ref jps) => (JoinKind::Join(JoinType::Left), &jps[..]),
+        QueryGraphEdge::FullJoin(ref jps) => (JoinKind::Join(JoinType::Full), &jps[..]),
+        QueryGraphEdge::SemiJoin(ref jps) => (JoinKind::SemiJoin(SemiJoinType::Semi), &jps[..]),
+        // anti-joins compare with `IN` for `NOT IN` subqueries, whose NULLs behave differently
+        QueryGraphEdge::AntiJoin(ref jps) if jps.iter().any(|jp| jp.operator == Operator::In) => {
+            (JoinKind::SemiJoin(SemiJoinType::NotIn), &jps[..])
+        }
+        QueryGraphEdge::AntiJoin(ref jps) => (JoinKind::SemiJoin(SemiJoinType::Anti), &jps[..]),
         QueryGraphEdge::GroupBy(_) => unreachable!(),
     }
 }
@@ -1,25 +1,27 @@
 use mir::node::{GroupedNodeType, MirNode, MirNodeType};
 use mir::query::MirQuery;
-use mir::{Column, MirNodeRef};
+use mir::{Column, Expression, MirNodeRef};
 use noria::DataType;
 use petgraph::graph::NodeIndex;
 // TODO(malte): remove if possible
-use dataflow::ops::filter::FilterCondition;
+use dataflow::ops::filter::{FilterCondition, Predicate};
 use dataflow::ops::join::JoinType;
+use dataflow::ops::semi_join::SemiJoinType;
 
 use crate::controller::sql::query_graph::{OutputColumn, QueryGraph};
 use crate::controller::sql::query_signature::Signature;
 use nom_sql::{
-    ArithmeticExpression, CaseWhenExpression, ColumnOrLiteral, ColumnSpecification,
-    CompoundSelectOperator, ConditionBase, ConditionExpression, ConditionTree, Literal, Operator,
-    SqlQuery, TableKey,
+    CaseWhenExpression, ColumnOrLiteral, ColumnSpecification, CompoundSelectOperator,
+    ConditionBase, ConditionExpression, ConditionTree, CreateTableStatement, Literal, Operator,
+    SqlType, TableKey,
 };
-use nom_sql::{LimitClause, OrderClause, SelectStatement};
+use nom_sql::{LimitClause, OrderClause, OrderType, SelectStatement};
 
 use slog;
 use std::collections::{HashMap, HashSet};
 
 use std::ops::Deref;
+use std::rc::Rc;
 use std::vec::Vec;
 
 use crate::controller::sql::security::Universe;
@@ -36,6 +38,46 @@ fn sanitize_leaf_column(c: &mut Column, view_name: &str) {
     c.aliases = vec![];
 }
 
+/// Whether the values of columns of type `t` can be added up.
+fn is_numeric(t: &SqlType) -> bool {
+    match *t {
+        SqlType::Bool
+        | SqlType::Tinyint(_)
+        | SqlType::UnsignedTinyint(_)
+        | SqlType::Int(_)
+        | SqlType::UnsignedInt(_)
+        | SqlType::Bigint(_)
+        | SqlType::UnsignedBigint(_)
+        | SqlType::Double
+        | SqlType::Float
+        | SqlType::Real
+        | SqlType::Decimal(..) => true,
+        _ => false,
+    }
+}
+
+/// Returns the columns that a join predicate compares.
+fn join_columns(jp: &ConditionTree) -> Result<(Column, Column), String> {
+    match (&*jp.left, &*jp.right) {
+        (
+            ConditionExpression::Base(ConditionBase::Field(ref l)),
+            ConditionExpression::Base(ConditionBase::Field(ref r)),
+        ) => Ok((Column::from(l), Column::from(r))),
+        _ => Err(format!("join predicate {} must compare two columns", jp)),
+    }
+}
+
+/// Returns the order in which a reader should return the rows for each key, given a query's ORDER
+/// BY clause.
+pub(super) fn leaf_order(order: &Option<OrderClause>) -> Option<Vec<(Column, OrderType)>> {
+    order.as_ref().map(|o| {
+        o.columns
+            .iter()
+            .map(|&(ref c, ref ot)| (Column::from(c), ot.clone()))
+            .collect()
+    })
+}
+
 /// Returns all collumns used in a predicate
 fn predicate_columns(ce: &ConditionExpression) -> HashSet<Column> {
     use nom_sql::ConditionExpression::*;
@@ -49,10 +91,9 @@ fn predicate_columns(ce: &ConditionExpression) -> HashSet<Column> {
         Base(ConditionBase::Field(ref c)) => {
             cols.insert(Column::from(c));
         }
-        Bracketed(ref ce) => {
+        Bracketed(ref ce) | NegationOp(ref ce) => {
             cols.extend(predicate_columns(&ce));
         }
-        NegationOp(_) => unreachable!("negations should have been eliminated"),
         _ => (),
     }
 
@@ -168,34 +209,81 @@ impl SqlToMirConverter {
         ct: &ConditionTree,
         columns: &mut Vec<Column>,
         n: &MirNodeRef,
-    ) -> Vec<(usize, FilterCondition)> {
+    ) -> Result<Vec<(usize, FilterCondition)>, String> {
         match ct.operator {
             Operator::And => {
                 let mut left_filter = match ct.left.as_ref() {
                     ConditionExpression::LogicalOp(ref ct2) => {
-                        self.logical_op_to_conditions(ct2, columns, n)
+                        self.logical_op_to_conditions(ct2, columns, n)?
                     }
                     ConditionExpression::ComparisonOp(ref ct2) => {
-                        self.to_conditions(ct2, columns, n)
+                        self.to_conditions(ct2, columns, n)?
                     }
-                    _ => unimplemented!(),
+                    ce => return Err(format!("unsupported condition: {}", ce)),
                 };
                 let mut right_filter = match ct.right.as_ref() {
                     ConditionExpression::LogicalOp(ref ct2) => {
-                        self.logical_op_to_conditions(ct2, columns, n)
+                        self.logical_op_to_conditions(ct2, columns, n)?
                     }
                     ConditionExpression::ComparisonOp(ref ct2) => {
-                        self.to_conditions(ct2, columns, n)
+                        self.to_conditions(ct2, columns, n)?
                     }
-                    _ => unimplemented!(),
+                    ce => return Err(format!("unsupported condition: {}", ce)),
                 };
                 left_filter.append(&mut right_filter);
-                left_filter
+                Ok(left_filter)
             }
-            _ => unimplemented!(),
+            ref op => Err(format!(
+                "conditions can only be combined with AND, not {}",
+                op
+            )),
         }
     }
 
+    /// Converts a condition expression into a predicate tree, combining the conditions that
+    /// `to_conditions` produces for each comparison.
+    fn to_predicate(
+        &self,
+        ce: &ConditionExpression,
+        columns: &mut Vec<Column>,
+        n: &MirNodeRef,
+    ) -> Result<Predicate, String> {
+        Ok(match *ce {
+            ConditionExpression::ComparisonOp(ref ct) => {
+                let (i, cond) = self.to_conditions(ct, columns, n)?.pop().unwrap();
+                Predicate::Condition(i, cond)
+            }
+            ConditionExpression::LogicalOp(ref ct) => {
+                let left = self.to_predicate(&ct.left, columns, n)?;
+                let right = self.to_predicate(&ct.right, columns, n)?;
+                // flatten nested operators of the same kind, so that `a OR b OR c` becomes a
+                // single disjunction
+                let mut operands = Vec::new();
+                for p in vec![left, right] {
+                    match (&ct.operator, p) {
+                        (Operator::And, Predicate::And(ps)) | (Operator::Or, Predicate::Or(ps)) => {
+                            operands.extend(ps)
+                        }
+                        (_, p) => operands.push(p),
+                    }
+                }
+                match ct.operator {
+                    Operator::And => Predicate::And(operands),
+                    Operator::Or => Predicate::Or(operands),
+                    _ => unreachable!("LogicalOp operator is {:?}", ct.operator),
+                }
+            }
+            ConditionExpression::NegationOp(ref inner) => {
+                Predicate::Not(Box::new(self.to_predicate(inner, columns, n)?))
+            }
+            ConditionExpression::Bracketed(ref inner) => self.to_predicate(inner, columns, n)?,
+            ConditionExpression::Base(_) => unreachable!("dangling base predicate"),
+            ConditionExpression::Arithmetic(ref ae) => {
+                return Err(format!("arithmetic expression {} is not a condition", ae));
+            }
+        })
+    }
+
     /// Converts a condition tree stored in the `ConditionExpr` returned by the SQL parser
     /// and adds its to a vector of conditions.
     fn to_conditions(
@@ -203,13 +291,13 @@ impl SqlToMirConverter {
         ct: &ConditionTree,
         columns: &mut Vec<Column>,
         n: &MirNodeRef,
-    ) -> Vec<(usize, FilterCondition)> {
+    ) -> Result<Vec<(usize, FilterCondition)>, String> {
         use std::cmp::max;
 
         // TODO(malte): we only support one level of condition nesting at this point :(
         let l = match *ct.left.as_ref() {
             ConditionExpression::Base(ConditionBase::Field(ref f)) => f.clone(),
-            _ => unimplemented!(),
+            _ => return Err(format!("the left side of {} must be a column", ct)),
         };
         use dataflow::ops::filter;
         let f = match *ct.right.as_ref() {
@@ -219,6 +307,15 @@ impl SqlToMirConverter {
                     filter::Value::Constant(DataType::from(*i)),
                 )
             }
+            ConditionExpression::Base(ConditionBase::Literal(Literal::String(ref s)))
+                if ct.operator == Operator::Like || ct.operator == Operator::NotLike =>
+            {
+                // LIKE patterns always translate into valid regular expressions
+                let negated = ct.operator == Operator::NotLike;
+                FilterCondition::Pattern(
+                    filter::Pattern::new(filter::PatternOperator::Like, negated, s).unwrap(),
+                )
+            }
             ConditionExpression::Base(ConditionBase::Literal(Literal::String(ref s))) => {
                 FilterCondition::Comparison(
                     ct.operator.clone(),
@@ -241,7 +338,7 @@ impl SqlToMirConverter {
                 let fi = columns.iter().rposition(|c| *c.name == f.name).unwrap();
                 FilterCondition::Comparison(ct.operator.clone(), filter::Value::Column(fi))
             }
-            _ => unimplemented!(),
+            _ => return Err(format!("unsupported comparison: {}", ct)),
         };
 
         let absolute_column_ids: Vec<usize> = columns
@@ -274,7 +371,7 @@ impl SqlToMirConverter {
             }
         }
 
-        filters
+        Ok(filters)
     }
 
     pub(super) fn add_leaf_below(
@@ -282,6 +379,8 @@ impl SqlToMirConverter {
         prior_leaf: MirNodeRef,
         name: &str,
         params: &[Column],
+        lookup_order: Vec<usize>,
+        order: Option<Vec<(Column, OrderType)>>,
         project_columns: Option<Vec<Column>>,
     ) -> MirQuery {
         // hang off the previous logical leaf node
@@ -335,6 +434,9 @@ impl SqlToMirConverter {
             MirNodeType::Leaf {
                 node: parent.clone(),
                 keys: Vec::from(params),
+                filters: vec![],
+                lookup_order,
+                order,
             },
             vec![n],
             vec![],
@@ -361,7 +463,7 @@ impl SqlToMirConverter {
         order: &Option<OrderClause>,
         limit: &Option<LimitClause>,
         has_leaf: bool,
-    ) -> MirQuery {
+    ) -> Result<MirQuery, String> {
         let union_name = if !has_leaf && limit.is_none() {
             String::from(name)
         } else {
@@ -371,8 +473,8 @@ impl SqlToMirConverter {
             CompoundSelectOperator::Union => self.make_union_node(
                 &union_name,
                 &sqs.iter().map(|mq| mq.leaf.clone()).collect::<Vec<_>>()[..],
-            ),
-            _ => unimplemented!(),
+            )?,
+            _ => return Err(format!("{} is not supported", op)),
         };
         let node_id = (union_name, self.schema_version);
         self.nodes
@@ -419,6 +521,9 @@ impl SqlToMirConverter {
                 MirNodeType::Leaf {
                     node: final_node.clone(),
                     keys: vec![],
+                    filters: vec![],
+                    lookup_order: vec![],
+                    order: None,
                 },
                 vec![final_node.clone()],
                 vec![],
@@ -435,14 +540,14 @@ impl SqlToMirConverter {
             .entry(node_id)
             .or_insert_with(|| leaf_node.clone());
 
-        MirQuery {
+        Ok(MirQuery {
             name: String::from(name),
             roots: sqs.iter().fold(Vec::new(), |mut acc, mq| {
                 acc.extend(mq.roots.iter().cloned());
                 acc
             }),
             leaf: leaf_node,
-        }
+        })
     }
 
     // pub(super) viz for tests
@@ -463,49 +568,93 @@ impl SqlToMirConverter {
         }
     }
 
-    pub(super) fn named_base_to_mir(&mut self, name: &str, query: &SqlQuery) -> MirQuery {
-        match *query {
-            SqlQuery::CreateTable(ref ctq) => {
-                assert_eq!(name, ctq.table.name);
-                let n = self.make_base_node(&name, &ctq.fields, ctq.keys.as_ref());
-                let node_id = (String::from(name), self.schema_version);
-                use std::collections::hash_map::Entry;
-                if let Entry::Vacant(e) = self.nodes.entry(node_id) {
-                    self.current.insert(String::from(name), self.schema_version);
-                    e.insert(n.clone());
-                }
-                MirQuery::singleton(name, n)
-            }
-            _ => panic!("expected CREATE TABLE query!"),
+    pub(super) fn named_base_to_mir(&mut self, name: &str, ctq: &CreateTableStatement) -> MirQuery {
+        assert_eq!(name, ctq.table.name);
+        let n = self.make_base_node(&name, &ctq.fields, ctq.keys.as_ref());
+        let node_id = (String::from(name), self.schema_version);
+        use std::collections::hash_map::Entry;
+        if let Entry::Vacant(e) = self.nodes.entry(node_id) {
+            self.current.insert(String::from(name), self.schema_version);
+            e.insert(n.clone());
         }
+        MirQuery::singleton(name, n)
     }
 
+    /// Removes the MIR nodes of query `name` that no other query builds upon, and unregisters them.
     pub(super) fn remove_query(&mut self, name: &str, mq: &MirQuery) {
         use std::collections::VecDeque;
 
-        let v = self
-            .current
-            .remove(name)
-            .unwrap_or_else(|| panic!("no query named \"{}\"?", name));
+        if let Some(v) = self.current.remove(name) {
+            self.nodes.remove(&(name.to_owned(), v));
+        }
+
+        // traverse the MIR query backwards from its leaf. Nodes that still have children are
+        // shared with other queries and stay, as do bases, which `remove_base` removes.
+        let mut q = VecDeque::new();
+        q.push_back(mq.leaf.clone());
+
+        while let Some(mnr) = q.pop_front() {
+            let (ancestors, reused) = {
+                let n = mnr.borrow();
+                match n.inner {
+                    MirNodeType::Base { .. } => continue,
+                    _ if !n.children().is_empty() => continue,
+                    _ => (n.ancestors().to_vec(), n.is_reused()),
+                }
+            };
+            for a in &ancestors {
+                a.borrow_mut().remove_child(mnr.clone());
+            }
 
-        let nodeid = (name.to_owned(), v);
-        let leaf_mn = self.nodes.remove(&nodeid).unwrap();
+            // reuse nodes are registered under the name of the node they reuse, which stays
+            if !reused {
+                let registered: Vec<_> = self
+                    .nodes
+                    .iter()
+                    .filter(|&(_, n)| Rc::ptr_eq(n, &mnr))
+                    .map(|(id, _)| id.clone())
+                    .collect();
+                for (name, v) in registered {
+                    if self.current.get(&name) == Some(&v) {
+                        self.current.remove(&name);
+                    }
+                    self.nodes.remove(&(name, v));
+                }
+            }
 
-        assert_eq!(leaf_mn.borrow().name, mq.leaf.borrow().name);
+            q.extend(ancestors);
+        }
+    }
 
-        // traverse the MIR query backwards, removing any nodes that we still have registered.
-        let mut q = VecDeque::new();
-        q.push_back(leaf_mn);
-
-        while !q.is_empty() {
-            let mnr = q.pop_front().unwrap();
-            let n = mnr.borrow_mut();
-            q.extend(n.ancestors.clone());
-            // node may not be registered, so don't bother checking return
-            match n.inner {
-                MirNodeType::Reuse { .. } | MirNodeType::Base { .. } => (),
-                _ => {
-                    self.nodes.remove(&(n.name.to_owned(), v));
+    /// Hands the MIR state registered for query `name` over to `other`, an identical query that
+    /// shares its leaf, so that the nodes are removed along with `other` instead.
+    pub(super) fn transfer_query(&mut self, name: &str, other: &str) {
+        if let Some(v) = self.current.remove(name) {
+            if let Some(leaf) = self.nodes.remove(&(name.to_owned(), v)) {
+                if !self.current.contains_key(other) {
+                    self.current.insert(other.to_owned(), v);
+                    self.nodes.insert((other.to_owned(), v), leaf);
+                }
+            }
+        }
+    }
+
+    /// Detaches the nodes that an aborted migration added below existing nodes: nodes that never
+    /// made it into the flow graph, and nodes whose flow nodes are among `discarded`. Nodes that
+    /// the migration removed from their ancestors are attached to them again.
+    pub(super) fn detach_discarded_nodes(&mut self, discarded: &HashSet<NodeIndex>) {
+        for node in self.nodes.values() {
+            node.borrow_mut()
+                .children
+                .retain(|c| match c.borrow().flow_node {
+                    Some(ref flow_node) => !discarded.contains(&flow_node.address()),
+                    None => false,
+                });
+        }
+        for node in self.nodes.values() {
+            for a in node.borrow().ancestors() {
+                if !a.borrow().children().iter().any(|c| Rc::ptr_eq(c, node)) {
+                    a.borrow_mut().add_child(node.clone());
                 }
             }
         }
@@ -514,6 +663,8 @@ impl SqlToMirConverter {
     pub(super) fn remove_base(&mut self, name: &str, mq: &MirQuery) {
         info!(self.log, "Removing base {} from SqlTomirconverter", name);
         self.remove_query(name, mq);
+        // all versions of the base go, including the ones that adapted it to schema changes
+        self.nodes.retain(|&(ref n, _), _| n != name);
         if self.base_schemas.remove(name).is_none() {
             warn!(
                 self.log,
@@ -522,6 +673,72 @@ impl SqlToMirConverter {
         }
     }
 
+    /// Renames a column of the current version of base `name` in place, and returns the address
+    /// of the base's flow node along with the column's index in it.
+    pub(super) fn rename_base_column(
+        &mut self,
+        name: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<(NodeIndex, usize), String> {
+        let v = *self
+            .current
+            .get(name)
+            .ok_or_else(|| format!("no base table named \"{}\"", name))?;
+
+        // keep the latest schema in sync, so that the renamed table definition matches it
+        if let Some(schema) = self
+            .base_schemas
+            .get_mut(name)
+            .and_then(|schemas| schemas.iter_mut().max_by_key(|s| s.0))
+        {
+            for cs in schema.1.iter_mut().filter(|cs| cs.column.name == from) {
+                cs.column.name = to.to_owned();
+            }
+        }
+
+        let rename = |cols: &mut Vec<Column>| {
+            for c in cols.iter_mut().filter(|c| c.name == from) {
+                c.name = to.to_owned();
+            }
+        };
+
+        // the current node for the base may be a reuse of the actual base node
+        let mut node = self.nodes[&(name.to_owned(), v)].clone();
+        loop {
+            let next = {
+                let mut n = node.borrow_mut();
+                let na = n.flow_node_addr();
+                rename(&mut n.columns);
+                match n.inner {
+                    MirNodeType::Reuse { ref node } => node.clone(),
+                    MirNodeType::Base {
+                        ref mut column_specs,
+                        ref mut keys,
+                        ref mut unique_keys,
+                        ..
+                    } => {
+                        rename(keys);
+                        for uk in unique_keys.iter_mut() {
+                            rename(uk);
+                        }
+                        let spec = column_specs
+                            .iter_mut()
+                            .find(|(cs, _)| cs.column.name == from)
+                            .ok_or_else(|| {
+                                format!("base table \"{}\" has no column \"{}\"", name, from)
+                            })?;
+                        spec.0.column.name = to.to_owned();
+                        let cid = spec.1.expect("base column ID must be set to rename column");
+                        return Ok((na?, cid));
+                    }
+                    _ => unreachable!("base table {} is not a base node", name),
+                }
+            };
+            node = next;
+        }
+    }
+
     pub(super) fn named_query_to_mir(
         &mut self,
         name: &str,
@@ -721,6 +938,18 @@ impl SqlToMirConverter {
         };
         assert!(primary_keys.len() <= 1);
 
+        // likewise, column-level UNIQUE constraints have been coalesced into unique keys.
+        let unique_keys: Vec<Vec<Column>> = keys
+            .into_iter()
+            .flatten()
+            .filter_map(|k| match *k {
+                TableKey::UniqueKey(_, ref key_cols) => {
+                    Some(key_cols.iter().map(Column::from).collect())
+                }
+                _ => None,
+            })
+            .collect();
+
         // remember the schema for this version
         let base_schemas = self.base_schemas.entry(String::from(name)).or_default();
         base_schemas.push((self.schema_version, cols.to_vec()));
@@ -746,6 +975,7 @@ impl SqlToMirConverter {
                         MirNodeType::Base {
                             column_specs: cols.iter().map(|cs| (cs.clone(), None)).collect(),
                             keys: key_cols.iter().map(Column::from).collect(),
+                            unique_keys,
                             adapted_over: None,
                         },
                         vec![],
@@ -762,6 +992,7 @@ impl SqlToMirConverter {
                 MirNodeType::Base {
                     column_specs: cols.iter().map(|cs| (cs.clone(), None)).collect(),
                     keys: vec![],
+                    unique_keys,
                     adapted_over: None,
                 },
                 vec![],
@@ -770,7 +1001,7 @@ impl SqlToMirConverter {
         }
     }
 
-    fn make_union_node(&self, name: &str, ancestors: &[MirNodeRef]) -> MirNodeRef {
+    fn make_union_node(&self, name: &str, ancestors: &[MirNodeRef]) -> Result<MirNodeRef, String> {
         let mut emit: Vec<Vec<Column>> = Vec::new();
         assert!(ancestors.len() > 1, "union must have more than 1 ancestors");
 
@@ -790,11 +1021,11 @@ impl SqlToMirConverter {
             {
                 selected_cols.insert(c.name.clone());
             } else {
-                panic!(
+                return Err(format!(
                     "column with name '{}' not found all union ancestors: all ancestors' \
                      output columns must have the same names",
                     c.name
-                );
+                ));
             }
         }
         assert_eq!(
@@ -822,14 +1053,14 @@ impl SqlToMirConverter {
             selected_cols
         );
 
-        MirNode::new(
+        Ok(MirNode::new(
             name,
             self.schema_version,
             emit.first().unwrap().clone(),
             MirNodeType::Union { emit },
             ancestors.to_vec(),
             vec![],
-        )
+        ))
     }
 
     // Creates union node for universe creation - returns the resulting node ref and a universe table mapping
@@ -941,44 +1172,29 @@ impl SqlToMirConverter {
         )
     }
 
-    fn make_union_from_same_base(
+    fn make_filter_node(
         &self,
         name: &str,
-        ancestors: Vec<MirNodeRef>,
-        columns: Vec<Column>,
-    ) -> MirNodeRef {
-        assert!(ancestors.len() > 1, "union must have more than 1 ancestors");
-        trace!(self.log, "Added union node wiht columns {:?}", columns);
-        let emit = ancestors.iter().map(|_| columns.clone()).collect();
-
-        MirNode::new(
-            name,
-            self.schema_version,
-            columns,
-            MirNodeType::Union { emit },
-            ancestors.clone(),
-            vec![],
-        )
-    }
-
-    fn make_filter_node(&self, name: &str, parent: MirNodeRef, cond: &ConditionTree) -> MirNodeRef {
+        parent: MirNodeRef,
+        cond: &ConditionExpression,
+    ) -> Result<MirNodeRef, String> {
         let mut fields = parent.borrow().columns().to_vec();
 
-        let filter = self.to_conditions(cond, &mut fields, &parent);
+        let predicate = self.to_predicate(cond, &mut fields, &parent)?;
         trace!(
             self.log,
-            "Added filter node {} with condition {:?}",
+            "Added filter node {} with predicate {:?}",
             name,
-            filter
+            predicate
         );
-        MirNode::new(
+        Ok(MirNode::new(
             name,
             self.schema_version,
             fields,
-            MirNodeType::Filter { conditions: filter },
+            MirNodeType::Filter { predicate },
             vec![parent.clone()],
             vec![],
-        )
+        ))
     }
 
     fn make_function_node(
@@ -987,7 +1203,7 @@ impl SqlToMirConverter {
         func_col: &Column,
         group_cols: Vec<&Column>,
         parent: MirNodeRef,
-    ) -> Vec<MirNodeRef> {
+    ) -> Result<Vec<MirNodeRef>, String> {
         use dataflow::ops::grouped::aggregate::Aggregation;
         use dataflow::ops::grouped::extremum::Extremum;
         use dataflow::ops::grouped::filteraggregate::FilterAggregation;
@@ -1015,8 +1231,8 @@ impl SqlToMirConverter {
                     group_cols,
                     t,
                     cond,
-                ));
-                out_nodes
+                )?);
+                Ok(out_nodes)
             } else {
                 out_nodes.push(self.make_grouped_node(
                     name,
@@ -1025,20 +1241,33 @@ impl SqlToMirConverter {
                     group_cols,
                     t,
                     cond,
-                ));
-                out_nodes
+                )?);
+                Ok(out_nodes)
             }
         };
 
         let func = func_col.function.as_ref().unwrap();
+        // the values of base table columns have the column's type, so we can tell whether adding
+        // them up will work
+        let check_numeric = |col: &Column| match self.base_column_type(col) {
+            Some(t) if !is_numeric(t) => Err(format!(
+                "cannot compute {} over column {} of type {}",
+                func, col.name, t
+            )),
+            _ => Ok(()),
+        };
         match *func.deref() {
-            Sum(FunctionArguments::Column(ref col), distinct) => mknode(
-                &Column::from(col),
-                None,
-                GroupedNodeType::Aggregation(Aggregation::SUM),
-                distinct,
-                None,
-            ),
+            Sum(FunctionArguments::Column(ref col), distinct) => {
+                let over = Column::from(col);
+                check_numeric(&over)?;
+                mknode(
+                    &over,
+                    None,
+                    GroupedNodeType::Aggregation(Aggregation::SUM),
+                    distinct,
+                    None,
+                )
+            }
             Sum(
                 FunctionArguments::Conditional(CaseWhenExpression {
                     ref condition,
@@ -1081,7 +1310,7 @@ impl SqlToMirConverter {
                 // faithful to COUNT(*) semantics, because COUNT(*) is supposed to count all
                 // rows including those with NULL values, and we don't have a mechanism to do that
                 // (but we also don't have a NULL value, so maybe we're okay).
-                panic!("COUNT(*) should have been rewritten earlier!")
+                Err(String::from("COUNT(*) should have been rewritten earlier"))
             }
             Count(
                 FunctionArguments::Conditional(CaseWhenExpression {
@@ -1111,6 +1340,17 @@ impl SqlToMirConverter {
                 false,
                 Some(condition),
             ),
+            Avg(FunctionArguments::Column(ref col), distinct) => {
+                let over = Column::from(col);
+                check_numeric(&over)?;
+                mknode(
+                    &over,
+                    None,
+                    GroupedNodeType::Aggregation(Aggregation::AVG),
+                    distinct,
+                    None,
+                )
+            }
             Max(FunctionArguments::Column(ref col)) => mknode(
                 &Column::from(col),
                 None,
@@ -1132,10 +1372,20 @@ impl SqlToMirConverter {
                 false,
                 None,
             ),
-            _ => unimplemented!(),
+            ref f => Err(format!("unsupported aggregate: {}", f)),
         }
     }
 
+    /// The SQL type of `c` if it is a column of a base table.
+    fn base_column_type(&self, c: &Column) -> Option<&SqlType> {
+        let schemas = self.base_schemas.get(c.table.as_ref()?)?;
+        let (_, columns) = schemas.iter().max_by_key(|&&(sv, _)| sv)?;
+        columns
+            .iter()
+            .find(|cs| cs.column.name == c.name)
+            .map(|cs| &cs.sql_type)
+    }
+
     fn make_grouped_node(
         &self,
         name: &str,
@@ -1144,7 +1394,7 @@ impl SqlToMirConverter {
         group_by: Vec<&Column>,
         node_type: GroupedNodeType,
         condition: Option<&ConditionExpression>,
-    ) -> MirNodeRef {
+    ) -> Result<MirNodeRef, String> {
         let parent_node = over.0;
 
         // Resolve column IDs in parent
@@ -1160,7 +1410,7 @@ impl SqlToMirConverter {
         combined_columns.push(computed_col.clone());
 
         // make the new operator
-        match node_type {
+        Ok(match node_type {
             GroupedNodeType::Aggregation(agg) => MirNode::new(
                 name,
                 self.schema_version,
@@ -1192,13 +1442,12 @@ impl SqlToMirConverter {
                 let mut fields = parent_node.borrow().columns().to_vec();
                 let filter = match *cond {
                     LogicalOp(ref ct) => {
-                        self.logical_op_to_conditions(ct, &mut fields, &parent_node)
+                        self.logical_op_to_conditions(ct, &mut fields, &parent_node)?
+                    }
+                    ComparisonOp(ref ct) => self.to_conditions(ct, &mut fields, &parent_node)?,
+                    Bracketed(_) | NegationOp(_) | Base(_) | Arithmetic(_) => {
+                        return Err(format!("unsupported condition in aggregation: {}", cond));
                     }
-                    ComparisonOp(ref ct) => self.to_conditions(ct, &mut fields, &parent_node),
-                    Bracketed(_) => unimplemented!(),
-                    NegationOp(_) => unreachable!("negation should have been removed earlier"),
-                    Base(_) => unreachable!("dangling base predicate"),
-                    Arithmetic(_) => unimplemented!(),
                 };
                 MirNode::new(
                     name,
@@ -1226,71 +1475,72 @@ impl SqlToMirConverter {
                 vec![parent_node.clone()],
                 vec![],
             ),
-        }
+        })
     }
 
     fn make_join_node(
         &self,
         name: &str,
-        jp: &ConditionTree,
+        jps: &[ConditionTree],
         left_node: MirNodeRef,
         right_node: MirNodeRef,
         kind: JoinType,
-    ) -> MirNodeRef {
+    ) -> Result<MirNodeRef, String> {
         // TODO(malte): this is where we overproject join columns in order to increase reuse
         // opportunities. Technically, we need to only project those columns here that the query
         // actually needs; at a minimum, we could start with just the join colums, relying on the
         // automatic column pull-down to retrieve the remaining columns required.
         let projected_cols_left = left_node.borrow().columns().to_vec();
         let projected_cols_right = right_node.borrow().columns().to_vec();
-        let fields = projected_cols_left
+        let mut fields = projected_cols_left
             .into_iter()
             .chain(projected_cols_right.into_iter())
             .collect::<Vec<Column>>();
 
+        if jps
+            .iter()
+            .any(|jp| jp.operator != Operator::Equal && jp.operator != Operator::In)
+        {
+            return self.make_theta_join_node(name, jps, left_node, right_node, kind, fields);
+        }
+
         // join columns need us to generate join group configs for the operator
         // TODO(malte): no multi-level joins yet
         let mut left_join_columns = Vec::new();
         let mut right_join_columns = Vec::new();
 
-        // equi-join only
-        assert!(jp.operator == Operator::Equal || jp.operator == Operator::In);
-        let mut l_col = match *jp.left {
-            ConditionExpression::Base(ConditionBase::Field(ref f)) => Column::from(f),
-            _ => unimplemented!(),
-        };
-        let r_col = match *jp.right {
-            ConditionExpression::Base(ConditionBase::Field(ref f)) => Column::from(f),
-            _ => unimplemented!(),
-        };
+        // each predicate contributes one pair of join columns; rows must match on all of them
+        for jp in jps {
+            let (mut l_col, r_col) = join_columns(jp)?;
 
-        // don't duplicate the join column in the output, but instead add aliases to the columns
-        // that represent it going forward (viz., the left-side join column)
-        l_col.add_alias(&r_col);
-        // add the alias to all instances of `l_col` in `fields` (there might be more than one
-        // if `l_col` is explicitly projected multiple times)
-        let fields: Vec<Column> = fields
-            .into_iter()
-            .filter_map(|mut f| {
-                if f == r_col {
-                    // drop instances of right-side column
-                    None
-                } else if f == l_col {
-                    // add alias for right-side column to any left-side column
-                    // N.B.: since `l_col` is already aliased, need to check this *after* checking
-                    // for equivalence with `r_col` (by now, `l_col` == `r_col` via alias), so
-                    // `f == l_col` also triggers if `f` is in `l_col.aliases`.
-                    f.add_alias(&r_col);
-                    Some(f)
-                } else {
-                    // keep unaffected columns
-                    Some(f)
-                }
-            })
-            .collect();
+            // don't duplicate the join column in the output, but instead add aliases to the
+            // columns that represent it going forward (viz., the left-side join column)
+            l_col.add_alias(&r_col);
+            // add the alias to all instances of `l_col` in `fields` (there might be more than one
+            // if `l_col` is explicitly projected multiple times)
+            fields = fields
+                .into_iter()
+                .filter_map(|mut f| {
+                    if f == r_col {
+                        // drop instances of right-side column
+                        None
+                    } else if f == l_col {
+                        // add alias for right-side column to any left-side column
+                        // N.B.: since `l_col` is already aliased, need to check this *after*
+                        // checking for equivalence with `r_col` (by now, `l_col` == `r_col` via
+                        // alias), so `f == l_col` also triggers if `f` is in `l_col.aliases`.
+                        f.add_alias(&r_col);
+                        Some(f)
+                    } else {
+                        // keep unaffected columns
+                        Some(f)
+                    }
+                })
+                .collect();
 
-        left_join_columns.push(l_col);
-        right_join_columns.push(r_col);
+            left_join_columns.push(l_col);
+            right_join_columns.push(r_col);
+        }
 
         assert_eq!(left_join_columns.len(), right_join_columns.len());
         let inner = match kind {
@@ -1304,16 +1554,107 @@ impl SqlToMirConverter {
                 on_right: right_join_columns,
                 project: fields.clone(),
             },
+            JoinType::Full => MirNodeType::OuterJoin {
+                on_left: left_join_columns,
+                on_right: right_join_columns,
+                project: fields.clone(),
+            },
         };
         trace!(self.log, "Added join node {:?}", inner);
-        MirNode::new(
+        Ok(MirNode::new(
             name,
             self.schema_version,
             fields,
             inner,
             vec![left_node.clone(), right_node.clone()],
             vec![],
-        )
+        ))
+    }
+
+    /// Joins on arbitrary column comparisons. Since the join columns need not be equal, both sides
+    /// are kept in the output.
+    fn make_theta_join_node(
+        &self,
+        name: &str,
+        jps: &[ConditionTree],
+        left_node: MirNodeRef,
+        right_node: MirNodeRef,
+        kind: JoinType,
+        fields: Vec<Column>,
+    ) -> Result<MirNodeRef, String> {
+        if kind != JoinType::Inner {
+            return Err(String::from(
+                "only inner joins can use non-equality join predicates",
+            ));
+        }
+
+        let mut on = jps
+            .iter()
+            .map(|jp| {
+                let (l_col, r_col) = join_columns(jp)?;
+                let op = match jp.operator {
+                    Operator::In => Operator::Equal,
+                    ref op => op.clone(),
+                };
+                Ok((l_col, op, r_col))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        // the operator indexes on its first condition, and equalities narrow things down the most
+        on.sort_by_key(|&(_, ref op, _)| *op != Operator::Equal);
+
+        let inner = MirNodeType::ThetaJoin {
+            on,
+            project: fields.clone(),
+        };
+        trace!(self.log, "Added theta join node {:?}", inner);
+        Ok(MirNode::new(
+            name,
+            self.schema_version,
+            fields,
+            inner,
+            vec![left_node.clone(), right_node.clone()],
+            vec![],
+        ))
+    }
+
+    /// Keeps the rows of the left node depending on whether they have a match in the right node,
+    /// which contributes no columns of its own.
+    fn make_semi_join_node(
+        &self,
+        name: &str,
+        jps: &[ConditionTree],
+        left_node: MirNodeRef,
+        right_node: MirNodeRef,
+        kind: SemiJoinType,
+    ) -> Result<MirNodeRef, String> {
+        let mut on_left = Vec::new();
+        let mut on_right = Vec::new();
+        for jp in jps {
+            if jp.operator != Operator::Equal && jp.operator != Operator::In {
+                return Err(String::from(
+                    "semi-joins only support equality join predicates",
+                ));
+            }
+            let (l_col, r_col) = join_columns(jp)?;
+            on_left.push(l_col);
+            on_right.push(r_col);
+        }
+
+        let fields = left_node.borrow().columns().to_vec();
+        let inner = MirNodeType::SemiJoin {
+            on_left,
+            on_right,
+            kind,
+        };
+        trace!(self.log, "Added semi-join node {:?}", inner);
+        Ok(MirNode::new(
+            name,
+            self.schema_version,
+            fields,
+            inner,
+            vec![left_node.clone(), right_node.clone()],
+            vec![],
+        ))
     }
 
     fn make_projection_helper(
@@ -1337,7 +1678,7 @@ impl SqlToMirConverter {
         name: &str,
         parent_node: MirNodeRef,
         proj_cols: Vec<&Column>,
-        arithmetic: Vec<(String, ArithmeticExpression)>,
+        arithmetic: Vec<(String, Expression)>,
         literals: Vec<(String, DataType)>,
         is_leaf: bool,
     ) -> MirNodeRef {
@@ -1450,71 +1791,40 @@ impl SqlToMirConverter {
         parent: MirNodeRef,
         ce: &ConditionExpression,
         nc: usize,
-    ) -> Vec<MirNodeRef> {
+    ) -> Result<Vec<MirNodeRef>, String> {
         use nom_sql::ConditionExpression::*;
 
         let mut pred_nodes: Vec<MirNodeRef> = Vec::new();
-        let output_cols = parent.borrow().columns().to_vec();
         match *ce {
-            LogicalOp(ref ct) => {
-                let (left, right);
-                match ct.operator {
-                    Operator::And => {
-                        left = self.make_predicate_nodes(name, parent.clone(), &*ct.left, nc);
-
-                        right = self.make_predicate_nodes(
-                            name,
-                            left.last().unwrap().clone(),
-                            &*ct.right,
-                            nc + left.len(),
-                        );
-
-                        pred_nodes.extend(left.clone());
-                        pred_nodes.extend(right.clone());
-                    }
-                    Operator::Or => {
-                        left = self.make_predicate_nodes(name, parent.clone(), &*ct.left, nc);
+            LogicalOp(ref ct) if ct.operator == Operator::And => {
+                let left = self.make_predicate_nodes(name, parent.clone(), &*ct.left, nc)?;
 
-                        right = self.make_predicate_nodes(
-                            name,
-                            parent.clone(),
-                            &*ct.right,
-                            nc + left.len(),
-                        );
-
-                        debug!(self.log, "Creating union node for `or` predicate");
-
-                        let last_left = left.last().unwrap().clone();
-                        let last_right = right.last().unwrap().clone();
-                        let union = self.make_union_from_same_base(
-                            &format!("{}_un", name),
-                            vec![last_left, last_right],
-                            output_cols,
-                        );
+                let right = self.make_predicate_nodes(
+                    name,
+                    left.last().unwrap().clone(),
+                    &*ct.right,
+                    nc + left.len(),
+                )?;
 
-                        pred_nodes.extend(left.clone());
-                        pred_nodes.extend(right.clone());
-                        pred_nodes.push(union);
-                    }
-                    _ => unreachable!("LogicalOp operator is {:?}", ct.operator),
-                }
+                pred_nodes.extend(left);
+                pred_nodes.extend(right);
             }
-            ComparisonOp(ref ct) => {
-                // currently, we only support filter-like
-                // comparison operations, no nested-selections
-                let f = self.make_filter_node(&format!("{}_f{}", name, nc), parent, ct);
+            LogicalOp(_) | ComparisonOp(_) | NegationOp(_) => {
+                // a single filter evaluates the whole predicate, including any disjunctions in it
+                let f = self.make_filter_node(&format!("{}_f{}", name, nc), parent, ce)?;
 
                 pred_nodes.push(f);
             }
             Bracketed(ref inner) => {
-                pred_nodes.extend(self.make_predicate_nodes(name, parent, &*inner, nc));
+                pred_nodes.extend(self.make_predicate_nodes(name, parent, &*inner, nc)?);
             }
-            NegationOp(_) => unreachable!("negation should have been removed earlier"),
             Base(_) => unreachable!("dangling base predicate"),
-            Arithmetic(_) => unimplemented!(),
+            Arithmetic(ref ae) => {
+                return Err(format!("arithmetic expression {} is not a condition", ae));
+            }
         }
 
-        pred_nodes
+        Ok(pred_nodes)
     }
 
     fn predicates_above_group_by<'a>(
@@ -1524,7 +1834,7 @@ impl SqlToMirConverter {
         over_col: Column,
         parent: MirNodeRef,
         created_predicates: &mut Vec<&'a ConditionExpression>,
-    ) -> Vec<MirNodeRef> {
+    ) -> Result<Vec<MirNodeRef>, String> {
         let mut predicates_above_group_by_nodes = Vec::new();
         let mut prev_node = parent.clone();
 
@@ -1536,7 +1846,7 @@ impl SqlToMirConverter {
                     prev_node.clone(),
                     ce,
                     0,
-                );
+                )?;
                 assert!(!mpns.is_empty());
                 prev_node = mpns.last().unwrap().clone();
                 predicates_above_group_by_nodes.extend(mpns);
@@ -1544,7 +1854,7 @@ impl SqlToMirConverter {
             }
         }
 
-        predicates_above_group_by_nodes
+        Ok(predicates_above_group_by_nodes)
     }
 
     fn make_value_project_node(
@@ -1558,17 +1868,16 @@ impl SqlToMirConverter {
             value_columns_needed_for_predicates(&qg.columns, &qg.global_predicates);
 
         if !arith_and_lit_columns_needed.is_empty() {
-            let projected_arithmetic: Vec<(String, ArithmeticExpression)> =
-                arith_and_lit_columns_needed
-                    .iter()
-                    .filter_map(|&(_, ref oc)| match oc {
-                        OutputColumn::Arithmetic(ref ac) => {
-                            Some((ac.name.clone(), ac.expression.clone()))
-                        }
-                        OutputColumn::Data(_) => None,
-                        OutputColumn::Literal(_) => None,
-                    })
-                    .collect();
+            let projected_arithmetic: Vec<(String, Expression)> = arith_and_lit_columns_needed
+                .iter()
+                .filter_map(|&(_, ref oc)| match oc {
+                    OutputColumn::Arithmetic(ref ac) => {
+                        Some((ac.name.clone(), Expression::from(&ac.expression)))
+                    }
+                    OutputColumn::Data(_) => None,
+                    OutputColumn::Literal(_) => None,
+                })
+                .collect();
             let projected_literals: Vec<(String, DataType)> = arith_and_lit_columns_needed
                 .iter()
                 .filter_map(|&(_, ref oc)| match oc {
@@ -1666,7 +1975,7 @@ impl SqlToMirConverter {
                 qg,
                 &node_for_rel,
                 new_node_count,
-            );
+            )?;
 
             new_node_count += join_nodes.len();
 
@@ -1719,7 +2028,7 @@ impl SqlToMirConverter {
                     new_node_count,
                     &column_to_predicates,
                     &mut prev_node,
-                );
+                )?;
 
             new_node_count += predicates_above_group_by_nodes.len();
 
@@ -1784,7 +2093,7 @@ impl SqlToMirConverter {
                     new_node_count,
                     &mut prev_node,
                     false,
-                );
+                )?;
 
                 new_node_count += func_nodes.len();
 
@@ -1827,7 +2136,7 @@ impl SqlToMirConverter {
                                 parent,
                                 p,
                                 0,
-                            );
+                            )?;
 
                             assert!(!fns.is_empty());
                             new_node_count += fns.len();
@@ -1856,7 +2165,7 @@ impl SqlToMirConverter {
                     }
 
                     let parent = match prev_node {
-                        None => unimplemented!(),
+                        None => return Err(String::from("global predicates need a relation")),
                         Some(pn) => pn,
                     };
 
@@ -1871,7 +2180,7 @@ impl SqlToMirConverter {
                         parent,
                         p,
                         0,
-                    );
+                    )?;
 
                     assert!(!fns.is_empty());
                     new_node_count += fns.len();
@@ -1948,7 +2257,7 @@ impl SqlToMirConverter {
                     &ancestors,
                     new_node_count,
                     sec_round,
-                );
+                )?;
 
                 if sec_round {
                     table_mapping = tables;
@@ -1987,19 +2296,33 @@ impl SqlToMirConverter {
                     projected_columns.push(pc);
                 }
             }
+            for pf in &qg.parameter_filters {
+                let pc = Column::from(&pf.col);
+                if !projected_columns.contains(&pc) {
+                    projected_columns.push(pc);
+                }
+            }
+
+            // the reader sorts the rows for each key, so it needs the ORDER BY columns too
+            let order = leaf_order(&st.order);
+            for &(ref oc, _) in order.iter().flatten() {
+                if !projected_columns.contains(oc) && final_node_cols.contains(oc) {
+                    projected_columns.push(oc.clone());
+                }
+            }
 
             // We may already have added some of the arithmetic and literal columns
             let (_, already_computed): (Vec<_>, Vec<_>) =
                 value_columns_needed_for_predicates(&qg.columns, &qg.global_predicates)
                     .into_iter()
                     .unzip();
-            let projected_arithmetic: Vec<(String, ArithmeticExpression)> = qg
+            let projected_arithmetic: Vec<(String, Expression)> = qg
                 .columns
                 .iter()
                 .filter_map(|oc| match *oc {
                     OutputColumn::Arithmetic(ref ac) => {
                         if !already_computed.contains(oc) {
-                            Some((ac.name.clone(), ac.expression.clone()))
+                            Some((ac.name.clone(), Expression::from(&ac.expression)))
                         } else {
                             projected_columns.push(Column::new(None, &ac.name));
                             None
@@ -2074,6 +2397,26 @@ impl SqlToMirConverter {
                     qg.parameters().into_iter().map(Column::from).collect()
                 };
 
+                let filters = qg
+                    .parameter_filters
+                    .iter()
+                    .map(|pf| (Column::from(&pf.col), pf.operator.clone(), pf.arity))
+                    .collect();
+
+                let order = order.filter(|order| {
+                    let leaf_columns = leaf_project_node.borrow().columns().to_vec();
+                    let found = order.iter().all(|&(ref oc, _)| leaf_columns.contains(oc));
+                    if !found {
+                        warn!(
+                            self.log,
+                            "Not all ORDER BY columns of query \"{}\" are available to its \
+                             reader, so its results will be unordered",
+                            name
+                        );
+                    }
+                    found
+                });
+
                 let leaf_node = MirNode::new(
                     name,
                     self.schema_version,
@@ -2081,6 +2424,9 @@ impl SqlToMirConverter {
                     MirNodeType::Leaf {
                         node: leaf_project_node.clone(),
                         keys: query_params,
+                        filters,
+                        lookup_order: qg.lookup_order(has_bogokey),
+                        order,
                     },
                     vec![leaf_project_node.clone()],
                     vec![],
@@ -14,11 +14,14 @@ pub trait SecurityBoundary {
         ancestors: &[MirNodeRef],
         node_count: usize,
         sec: bool,
-    ) -> (
-        Vec<MirNodeRef>,
-        Option<HashMap<(String, Option<String>), String>>,
+    ) -> Result<
+        (
+            Vec<MirNodeRef>,
+            Option<HashMap<(String, Option<String>), String>>,
+            String,
+        ),
         String,
-    );
+    >;
 
     fn make_security_boundary(
         &self,
@@ -36,11 +39,14 @@ impl SecurityBoundary for SqlToMirConverter {
         ancestors: &[MirNodeRef],
         node_count: usize,
         sec: bool,
-    ) -> (
-        Vec<MirNodeRef>,
-        Option<HashMap<(String, Option<String>), String>>,
+    ) -> Result<
+        (
+            Vec<MirNodeRef>,
+            Option<HashMap<(String, Option<String>), String>>,
+            String,
+        ),
         String,
-    ) {
+    > {
         use crate::controller::sql::mir::grouped::make_grouped;
 
         let mut nodes_added = Vec::new();
@@ -54,7 +60,7 @@ impl SecurityBoundary for SqlToMirConverter {
         // First, union the results from all ancestors
         let (union, mapping) = if !sec {
             (
-                Some(self.make_union_node(&format!("{}_n{}", name, node_count), &ancestors)),
+                Some(self.make_union_node(&format!("{}_n{}", name, node_count), &ancestors)?),
                 None,
             )
         } else {
@@ -79,14 +85,12 @@ impl SecurityBoundary for SqlToMirConverter {
                     node_count,
                     &mut Some(node.clone()),
                     true,
-                );
+                )?;
 
                 nodes_added.extend(grouped);
-                (nodes_added, mapping, n)
-            }
-            None => {
-                panic!("union not computed correctly");
+                Ok((nodes_added, mapping, n))
             }
+            None => Err(String::from("union not computed correctly")),
         }
     }
 
@@ -217,7 +221,7 @@ fn make_security_nodes(
                     prev_node.expect("empty previous node"),
                     pred,
                     0,
-                );
+                )?;
 
                 prev_node = Some(
                     new_nodes
@@ -239,7 +243,7 @@ fn make_security_nodes(
             qg,
             &local_node_for_rel,
             node_count,
-        );
+        )?;
 
         node_count += join_nodes.len();
 
@@ -19,12 +19,12 @@ use ::mir::Column;
 use ::mir::MirNodeRef;
 use dataflow::prelude::DataType;
 use nom_sql::parser as sql_parser;
-use nom_sql::{ArithmeticBase, CreateTableStatement, SqlQuery};
+use nom_sql::{ArithmeticBase, CreateTableStatement, OrderType, SqlQuery};
 use nom_sql::{CompoundSelectOperator, CompoundSelectStatement, SelectStatement};
 use petgraph::graph::NodeIndex;
 
 use slog;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::str;
 use std::vec::Vec;
 
@@ -34,8 +34,14 @@ type UniverseId = (DataType, Option<DataType>);
 enum QueryGraphReuse {
     ExactMatch(MirNodeRef),
     ExtendExisting(Vec<(u64, UniverseId)>),
-    /// (node, columns to re-project if necessary, parameters)
-    ReaderOntoExisting(MirNodeRef, Option<Vec<Column>>, Vec<Column>),
+    /// (node, columns to re-project if necessary, parameters, order of rows for each key)
+    ReaderOntoExisting(
+        MirNodeRef,
+        Option<Vec<Column>>,
+        Vec<Column>,
+        Vec<usize>,
+        Option<Vec<(Column, OrderType)>>,
+    ),
     None,
 }
 
@@ -190,20 +196,17 @@ impl SqlIncorporator {
         query_name: &str,
         universe: UniverseId,
         st: &SelectStatement,
-    ) -> (QueryGraph, QueryGraphReuse) {
+    ) -> Result<(QueryGraph, QueryGraphReuse), String> {
         debug!(self.log, "Making QG for \"{}\"", query_name);
         trace!(self.log, "Query \"{}\": {:#?}", query_name, st);
 
-        let mut qg = match to_query_graph(st) {
-            Ok(qg) => qg,
-            Err(e) => panic!(e),
-        };
+        let mut qg = to_query_graph(st)?;
 
         trace!(self.log, "QG for \"{}\": {:#?}", query_name, qg);
 
         // if reuse is disabled, we're done
         if self.reuse_type == ReuseConfigType::NoReuse {
-            return (qg, QueryGraphReuse::None);
+            return Ok((qg, QueryGraphReuse::None));
         }
 
         // Do we already have this exact query or a subset of it in the same universe?
@@ -212,6 +215,15 @@ impl SqlIncorporator {
         match self.mir_queries.get(&(qg_hash, universe.clone())) {
             None => (),
             Some(ref mir_query) => {
+                // the query graph doesn't capture ORDER BY, so check that the existing reader
+                // returns its rows in the order this query wants
+                use ::mir::node::MirNodeType;
+                let order = self::mir::leaf_order(&st.order);
+                let same_order = match mir_query.leaf.borrow().inner {
+                    MirNodeType::Leaf { order: ref o, .. } => *o == order,
+                    _ => order.is_none(),
+                };
+
                 let existing_qg = self
                     .query_graphs
                     .get(&qg_hash)
@@ -221,6 +233,7 @@ impl SqlIncorporator {
                 if existing_qg.signature() == qg.signature()
                     && existing_qg.parameters() == qg.parameters()
                     && existing_qg.exact_hash() == qg.exact_hash()
+                    && same_order
                 {
                     // we already have this exact query, down to the exact same reader key columns
                     // in exactly the same order
@@ -238,9 +251,12 @@ impl SqlIncorporator {
                         existing_qg,
                     );
 
-                    return (qg, QueryGraphReuse::ExactMatch(mir_query.leaf.clone()));
+                    return Ok((qg, QueryGraphReuse::ExactMatch(mir_query.leaf.clone())));
                 } else if existing_qg.signature() == qg.signature()
                     && existing_qg.parameters() != qg.parameters()
+                    && existing_qg.parameter_filters.is_empty()
+                    && qg.parameter_filters.is_empty()
+                    && same_order
                 {
                     use self::query_graph::OutputColumn;
 
@@ -253,7 +269,10 @@ impl SqlIncorporator {
                     // and literals (col/col is a join predicate and associated with the join edge,
                     // col/param is stored in qg.params), we will not be inhibited by the fact that
                     // the queries have different parameters.
-                    let mut predicates_match = true;
+                    // Global predicates (which include those of a HAVING clause) may compare
+                    // computed columns, so they have to be identical.
+                    let mut predicates_match =
+                        existing_qg.global_predicates == qg.global_predicates;
                     for (r, n) in qg.relations.iter() {
                         for p in n.predicates.iter() {
                             if !existing_qg.relations.contains_key(r)
@@ -303,10 +322,15 @@ impl SqlIncorporator {
                         // move on to other reuse options.
                         let params: Vec<_> =
                             qg.parameters().into_iter().map(Column::from).collect();
+                        let lookup_order = qg.lookup_order(false);
+                        let needed: Vec<_> = params
+                            .iter()
+                            .chain(order.iter().flatten().map(|&(ref c, _)| c))
+                            .cloned()
+                            .collect();
                         if let Some(mn) =
-                            mir_reuse::rewind_until_columns_found(mir_query.leaf.clone(), &params)
+                            mir_reuse::rewind_until_columns_found(mir_query.leaf.clone(), &needed)
                         {
-                            use ::mir::node::MirNodeType;
                             let project_columns = match mn.borrow().inner {
                                 MirNodeType::Project { .. } => None,
                                 _ => {
@@ -331,10 +355,16 @@ impl SqlIncorporator {
                                     Some(project_columns)
                                 }
                             };
-                            return (
+                            return Ok((
                                 qg,
-                                QueryGraphReuse::ReaderOntoExisting(mn, project_columns, params),
-                            );
+                                QueryGraphReuse::ReaderOntoExisting(
+                                    mn,
+                                    project_columns,
+                                    params,
+                                    lookup_order,
+                                    order,
+                                ),
+                            ));
                         }
                     }
                 }
@@ -372,18 +402,20 @@ impl SqlIncorporator {
                 mir_queries.extend(mqs);
             }
 
-            return (qg, QueryGraphReuse::ExtendExisting(mir_queries));
+            return Ok((qg, QueryGraphReuse::ExtendExisting(mir_queries)));
         } else {
             info!(self.log, "No reuse opportunity, adding fresh query");
         }
 
-        (qg, QueryGraphReuse::None)
+        Ok((qg, QueryGraphReuse::None))
     }
 
     fn add_leaf_to_existing_query(
         &mut self,
         query_name: &str,
         params: &[Column],
+        lookup_order: Vec<usize>,
+        order: Option<Vec<(Column, OrderType)>>,
         final_query_node: MirNodeRef,
         project_columns: Option<Vec<Column>>,
         mut mig: &mut Migration,
@@ -394,6 +426,8 @@ impl SqlIncorporator {
             final_query_node,
             query_name,
             params,
+            lookup_order,
+            order,
             project_columns,
         );
 
@@ -411,11 +445,11 @@ impl SqlIncorporator {
     fn add_base_via_mir(
         &mut self,
         query_name: &str,
-        query: &SqlQuery,
+        ctq: &CreateTableStatement,
         mut mig: &mut Migration,
     ) -> QueryFlowParts {
         // first, compute the MIR representation of the SQL query
-        let mut mir = self.mir_converter.named_base_to_mir(query_name, query);
+        let mut mir = self.mir_converter.named_base_to_mir(query_name, ctq);
 
         trace!(self.log, "Base node MIR: {:#?}", mir);
 
@@ -428,11 +462,7 @@ impl SqlIncorporator {
         // on base table schema change, we will overwrite the existing schema here.
         // TODO(malte): this means that requests for this will always return the *latest* schema
         // for a base.
-        if let SqlQuery::CreateTable(ref ctq) = query {
-            self.base_schemas.insert(query_name.to_owned(), ctq.clone());
-        } else {
-            unimplemented!();
-        }
+        self.base_schemas.insert(query_name.to_owned(), ctq.clone());
 
         self.register_query(query_name, None, &mir, mig.universe());
 
@@ -465,7 +495,7 @@ impl SqlIncorporator {
             &query.order,
             &query.limit,
             is_leaf,
-        );
+        )?;
 
         let qfp = mir_query_to_flow_parts(&mut combined_mir_query, &mut mig, None);
 
@@ -483,7 +513,7 @@ impl SqlIncorporator {
         is_leaf: bool,
         mig: &mut Migration,
     ) -> Result<(QueryFlowParts, Option<MirQuery>), String> {
-        let (qg, reuse) = self.consider_query_graph(&query_name, mig.universe(), sq);
+        let (qg, reuse) = self.consider_query_graph(&query_name, mig.universe(), sq)?;
         Ok(match reuse {
             QueryGraphReuse::ExactMatch(mn) => {
                 let flow_node = mn.borrow().flow_node.as_ref().unwrap().address();
@@ -499,9 +529,22 @@ impl SqlIncorporator {
                 let qfp = self.extend_existing_query(&query_name, sq, qg, mqs, is_leaf, mig)?;
                 (qfp, None)
             }
-            QueryGraphReuse::ReaderOntoExisting(mn, project_columns, params) => {
-                let qfp =
-                    self.add_leaf_to_existing_query(&query_name, &params, mn, project_columns, mig);
+            QueryGraphReuse::ReaderOntoExisting(
+                mn,
+                project_columns,
+                params,
+                lookup_order,
+                order,
+            ) => {
+                let qfp = self.add_leaf_to_existing_query(
+                    &query_name,
+                    &params,
+                    lookup_order,
+                    order,
+                    mn,
+                    project_columns,
+                    mig,
+                );
                 (qfp, None)
             }
             QueryGraphReuse::None => {
@@ -552,7 +595,9 @@ impl SqlIncorporator {
                     mir = mir.make_universe_naming_consistent(x, base_name);
                 }
                 None => {
-                    panic!("Missing table mapping when reconciling universe table names!");
+                    return Err(String::from(
+                        "missing table mapping when reconciling universe table names",
+                    ));
                 }
             }
         }
@@ -566,57 +611,73 @@ impl SqlIncorporator {
         Ok((qfp, mir))
     }
 
+    /// Removes a query, along with the MIR nodes and reuse state that no other query needs.
+    /// Returns the query's leaf node if the leaf should be removed from the graph as well.
     pub(super) fn remove_query(&mut self, query_name: &str, mig: &Migration) -> Option<NodeIndex> {
         let nodeid = self
             .leaf_addresses
             .remove(query_name)
             .expect("tried to remove unknown query");
 
-        let qg_hash = self
-            .named_queries
-            .remove(query_name)
-            .unwrap_or_else(|| panic!("missing query hash for named query \"{}\"", query_name));
-        let mir = &self.mir_queries[&(qg_hash, mig.universe())];
-
-        // traverse self.leaf__addresses
-        if self
+        let other = self
             .leaf_addresses
-            .values()
-            .find(|&id| *id == nodeid)
-            .is_none()
-        {
-            // ok to remove
-
-            // remove local state for query
+            .iter()
+            .find(|&(_, id)| *id == nodeid)
+            .map(|(name, _)| name.clone());
+        if let Some(other) = other {
+            // more than one query uses this leaf, so the remaining query takes over the state
+            // that was registered for this one, and no nodes are removed
+            if let Some(qg_hash) = self.named_queries.remove(query_name) {
+                self.named_queries.entry(other.clone()).or_insert(qg_hash);
+            }
+            if let Some(mir) = self.base_mir_queries.remove(query_name) {
+                self.base_mir_queries.entry(other.clone()).or_insert(mir);
+            }
+            if let Some(fields) = self.view_schemas.remove(query_name) {
+                self.view_schemas.entry(other.clone()).or_insert(fields);
+            }
+            self.mir_converter.transfer_query(query_name, &other);
+            return None;
+        }
 
-            // traverse and remove MIR nodes
-            // TODO(malte): implement this
+        // queries without a query graph (e.g., compound queries) are registered by name
+        let mir = self
+            .unregister_query(query_name, mig.universe())
+            .or_else(|| self.base_mir_queries.remove(query_name));
+        if let Some(ref mir) = mir {
             self.mir_converter.remove_query(query_name, mir);
+        }
+        self.view_schemas.remove(query_name);
 
-            // clean up local state
-            self.mir_queries.remove(&(qg_hash, mig.universe())).unwrap();
-            self.query_graphs.remove(&qg_hash).unwrap();
-            self.view_schemas.remove(query_name).unwrap();
-
-            // trigger reader node removal
-            Some(nodeid)
-        } else {
-            // more than one query uses this leaf
-            // don't remove node yet!
-
-            // TODO(malte): implement this
-            self.mir_converter.remove_query(query_name, mir);
+        // compound queries registered each of their subqueries as well
+        let prefix = format!("{}_csq_", query_name);
+        let subqueries: Vec<_> = self
+            .named_queries
+            .keys()
+            .filter(|n| n.starts_with(&prefix))
+            .cloned()
+            .collect();
+        for sq in subqueries {
+            self.unregister_query(&sq, mig.universe());
+            self.view_schemas.remove(&sq);
+        }
 
-            // clean up state for this query
-            self.mir_queries.remove(&(qg_hash, mig.universe())).unwrap();
-            self.query_graphs.remove(&qg_hash).unwrap();
-            self.view_schemas.remove(query_name).unwrap();
+        // trigger reader node removal
+        Some(nodeid)
+    }
 
-            None
+    /// Forgets the query graph and MIR query of a named query, so that later queries no longer
+    /// reuse them. Returns the query's MIR query, if it had a query graph.
+    fn unregister_query(&mut self, query_name: &str, universe: UniverseId) -> Option<MirQuery> {
+        let qg_hash = self.named_queries.remove(query_name)?;
+        // queries in other universes may share the query graph
+        if !self.named_queries.values().any(|h| *h == qg_hash) {
+            self.query_graphs.remove(&qg_hash);
         }
+        self.mir_queries.remove(&(qg_hash, universe))
     }
 
-    pub(super) fn remove_base(&mut self, name: &str) {
+    pub(super) fn remove_base(&mut self, name: &str) -> Result<(), String> {
         info!(self.log, "Removing base {} from SqlIncorporator", name);
         if self.base_schemas.remove(name).is_none() {
             warn!(
@@ -627,9 +688,92 @@ impl SqlIncorporator {
 
         let mir = self
             .base_mir_queries
-            .get(name)
-            .unwrap_or_else(|| panic!("tried to remove unknown base {}", name));
-        self.mir_converter.remove_base(name, mir)
+            .remove(name)
+            .ok_or_else(|| format!("tried to remove unknown base {}", name))?;
+        self.mir_converter.remove_base(name, &mir);
+        self.leaf_addresses.remove(name);
+        Ok(())
+    }
+
+    /// Returns the names of the queries that read from the named base table or query.
+    pub(super) fn dependent_queries(&self, name: &str) -> Vec<String> {
+        let na = match self.get_query_address(name) {
+            None => return vec![],
+            Some(na) => na,
+        };
+        let reads_from_na = |mir: &MirQuery| {
+            let mut q = vec![mir.leaf.clone()];
+            while let Some(mn) = q.pop() {
+                let mn = mn.borrow();
+                if mn.flow_node_addr() == Ok(na) {
+                    return true;
+                }
+                q.extend(mn.ancestors().iter().cloned());
+            }
+            false
+        };
+
+        // identical queries share a leaf, so go by the leaves of the dependent MIR queries
+        let dependent_leaves: HashSet<NodeIndex> = self
+            .mir_queries
+            .values()
+            .chain(self.base_mir_queries.values())
+            .filter(|mir| reads_from_na(mir))
+            .filter_map(|mir| mir.leaf.borrow().flow_node_addr().ok())
+            .collect();
+        let mut dependents: Vec<_> = self
+            .leaf_addresses
+            .iter()
+            .filter(|&(_, ni)| *ni != na && dependent_leaves.contains(ni))
+            .map(|(n, _)| n.clone())
+            .collect();
+        dependents.sort();
+        dependents
+    }
+
+    /// Renames a column of an existing base table, retaining the column's data.
+    pub(super) fn rename_base_column(
+        &mut self,
+        table: &str,
+        from: &str,
+        to: &str,
+        mig: &mut Migration,
+    ) -> Result<(), String> {
+        info!(
+            self.log,
+            "Renaming column {} of base {} to {}", from, table, to
+        );
+        let (na, column) = self.mir_converter.rename_base_column(table, from, to)?;
+        mig.rename_column(na, column, to);
+        Ok(())
+    }
+
+    /// Undoes the rename of a column of an existing base table. Column renames change the MIR
+    /// nodes of the base in place, so migrations that are aborted after renaming a column need to
+    /// undo the rename even if they restore an earlier copy of the incorporator.
+    pub(super) fn revert_column_rename(&mut self, table: &str, from: &str, to: &str) {
+        // the rename may not have happened before the migration failed
+        let reverted = self.mir_converter.rename_base_column(table, to, from);
+        if reverted.is_ok() {
+            info!(
+                self.log,
+                "Reverted rename of column {} of base {} to {}", from, table, to
+            );
+        }
+    }
+
+    /// Detaches the MIR nodes that an aborted migration added below existing MIR nodes. This is
+    /// needed even if the migration restores an earlier copy of the incorporator, since that copy
+    /// shares its MIR nodes.
+    pub(super) fn detach_discarded_nodes(&mut self, discarded: &HashSet<NodeIndex>) {
+        self.mir_converter.detach_discarded_nodes(discarded);
+    }
+
+    /// Returns true if the named query can be removed and planned again, which requires that no
+    /// other queries build upon its nodes.
+    pub(super) fn can_replan_query(&self, query_name: &str) -> bool {
+        self.leaf_addresses.contains_key(query_name)
+            && self.dependent_queries(query_name).is_empty()
     }
 
     fn register_query(
@@ -730,7 +874,9 @@ impl SqlIncorporator {
                         post_reuse_opt_mir.make_universe_naming_consistent(x, base_name);
                 }
                 None => {
-                    panic!("Missing table mapping when reconciling universe table names!");
+                    return Err(String::from(
+                        "missing table mapping when reconciling universe table names",
+                    ));
                 }
             }
         }
@@ -765,7 +911,11 @@ impl SqlIncorporator {
             SqlQuery::CreateTable(ref ctq) => ctq.table.name.clone(),
             SqlQuery::CreateView(ref cvq) => cvq.name.clone(),
             SqlQuery::Select(_) | SqlQuery::CompoundSelect(_) => format!("q_{}", self.num_queries),
-            _ => panic!("only CREATE TABLE and SELECT queries can be added to the graph!"),
+            _ => {
+                return Err(String::from(
+                    "only CREATE TABLE, CREATE VIEW and SELECT queries can be added to the graph",
+                ))
+            }
         };
         self.nodes_for_named_query(q, name, is_leaf, mig)
     }
@@ -791,6 +941,24 @@ impl SqlIncorporator {
         // flattens out the query by replacing subqueries for references
         // to existing views in the graph
         let mut fq = q.clone();
+        if let SqlQuery::Select(ref mut st) = fq {
+            // scalar subqueries compared with a column are decorrelated into views that compute
+            // their value for each correlated key, and which the query then joins with
+            for mut ssq in passes::subqueries::extract_scalar_subqueries(st) {
+                ssq.decorrelate(st)?;
+                let qfp =
+                    self.add_parsed_query(SqlQuery::Select(ssq.query.clone()), None, false, mig)?;
+                st.join.push(ssq.join_clause(&qfp.name));
+            }
+
+            // `NOT IN` subqueries become anti-joins with the views that compute them
+            for mut nis in passes::subqueries::extract_not_in_subqueries(st) {
+                nis.resolve(st)?;
+                let qfp =
+                    self.add_parsed_query(SqlQuery::Select(nis.query.clone()), None, false, mig)?;
+                nis.anti_join(st, &qfp.name);
+            }
+        }
         for sq in fq.extract_subqueries() {
             use self::passes::subqueries::{
                 field_with_table_name, query_from_condition_base, Subquery,
@@ -800,22 +968,18 @@ impl SqlIncorporator {
                 Subquery::InComparison(cond_base) => {
                     let (sq, column) = query_from_condition_base(&cond_base);
 
-                    let qfp = self
-                        .add_parsed_query(sq, None, false, mig)
-                        .expect("failed to add subquery");
+                    let qfp = self.add_parsed_query(sq, None, false, mig)?;
                     *cond_base = field_with_table_name(qfp.name.clone(), column);
                 }
                 Subquery::InJoin(join_right_side) => {
                     *join_right_side = match *join_right_side {
                         JoinRightSide::NestedSelect(ref ns, ref alias) => {
-                            let qfp = self
-                                .add_parsed_query(
-                                    SqlQuery::Select((**ns).clone()),
-                                    alias.clone(),
-                                    false,
-                                    mig,
-                                )
-                                .expect("failed to add subquery in join");
+                            let qfp = self.add_parsed_query(
+                                SqlQuery::Select((**ns).clone()),
+                                alias.clone(),
+                                false,
+                                mig,
+                            )?;
                             JoinRightSide::Table(Table {
                                 name: qfp.name.clone(),
                                 alias: None,
@@ -853,13 +1017,31 @@ impl SqlIncorporator {
 
         // Run some standard rewrite passes on the query. This makes the later work easier,
         // as we no longer have to consider complications like aliases.
-        Ok(fq
-            .expand_table_aliases(mig.context())
+        let q = fq
+            .expand_table_aliases(mig.context())?
             .remove_negation()
             .coalesce_key_definitions()
             .expand_stars(&self.view_schemas)
-            .expand_implied_tables(&self.view_schemas)
-            .rewrite_count_star(&self.view_schemas))
+            .expand_implied_tables(&self.view_schemas)?
+            .rewrite_count_star(&self.view_schemas);
+
+        // the passes have resolved which tables the query's columns belong to, so columns that
+        // these tables don't have are now known to be unknown
+        if let SqlQuery::Select(ref st) = q {
+            for c in read_columns(st) {
+                let ctq = match c.table {
+                    Some(ref t) if c.function.is_none() => self.base_schemas.get(t),
+                    _ => None,
+                };
+                if let Some(ctq) = ctq {
+                    if !ctq.fields.iter().any(|cs| cs.column.name == c.name) {
+                        return Err(format!("unknown column \"{}\"", c));
+                    }
+                }
+            }
+        }
+
+        Ok(q)
     }
 
     fn nodes_for_named_query(
@@ -899,12 +1081,11 @@ impl SqlIncorporator {
                 // NOTE(malte): We can't currently reuse complete compound select queries, since
                 // our reuse logic operates on `SqlQuery` structures. Their subqueries do get
                 // reused, however.
-                self.add_compound_query(&query_name, &csq, is_leaf, mig)
-                    .unwrap()
+                self.add_compound_query(&query_name, &csq, is_leaf, mig)?
             }
             SqlQuery::Select(sq) => self.add_select_query(&query_name, &sq, is_leaf, mig)?.0,
-            ref q @ SqlQuery::CreateTable { .. } => self.add_base_via_mir(&query_name, &q, mig),
-            q => panic!("unhandled query type in recipe: {:?}", q),
+            SqlQuery::CreateTable(ref ctq) => self.add_base_via_mir(&query_name, ctq, mig),
+            _ => return Err(String::from("unsupported query type in recipe")),
         };
 
         // record info about query
@@ -927,6 +1108,26 @@ impl SqlIncorporator {
     }
 }
 
+/// Returns the columns that a query projects or filters on.
+fn read_columns(st: &SelectStatement) -> Vec<&nom_sql::Column> {
+    use nom_sql::{ConditionExpression, FieldDefinitionExpression};
+
+    let mut columns: Vec<_> = st
+        .fields
+        .iter()
+        .filter_map(|f| match *f {
+            FieldDefinitionExpression::Col(ref c) => Some(c),
+            _ => None,
+        })
+        .collect();
+    if let Some(ConditionExpression::ComparisonOp(ref ct))
+    | Some(ConditionExpression::LogicalOp(ref ct)) = st.where_clause
+    {
+        columns.extend(ct.contained_columns());
+    }
+    columns
+}
+
 /// Enables incorporation of a textual SQL query into a Soup graph.
 trait ToFlowParts {
     /// Turn a SQL query into a set of nodes inserted into the Soup graph managed by
@@ -1205,6 +1406,40 @@ mod tests {
         .await;
     }
 
+    #[tokio::test(threaded_scheduler)]
+    async fn it_only_sums_numbers() {
+        // set up graph
+        let mut g = integration::start_simple("it_only_sums_numbers").await;
+        g.migrate(|mig| {
+            let mut inc = SqlIncorporator::default();
+            assert!(inc
+                .add_query(
+                    "CREATE TABLE votes (aid int, up bool, reason text);",
+                    None,
+                    mig
+                )
+                .is_ok());
+
+            // booleans count as 0 and 1
+            assert!(inc
+                .add_query(
+                    "SELECT SUM(votes.up) AS ups FROM votes GROUP BY votes.aid;",
+                    None,
+                    mig
+                )
+                .is_ok());
+            let e = inc
+                .add_query(
+                    "SELECT AVG(votes.reason) AS r FROM votes GROUP BY votes.aid;",
+                    None,
+                    mig,
+                )
+                .unwrap_err();
+            assert!(e.contains("of type TEXT"), e);
+        })
+        .await;
+    }
+
     #[tokio::test(threaded_scheduler)]
     async fn it_does_not_reuse_if_disabled() {
         // set up graph
@@ -1266,6 +1501,199 @@ mod tests {
         .await;
     }
 
+    #[tokio::test(threaded_scheduler)]
+    async fn it_incorporates_non_equality_parameters() {
+        use dataflow::node::special::PostLookupFilter;
+        use dataflow::ops::filter::Operator;
+
+        // set up graph
+        let mut g = integration::start_simple("it_incorporates_non_equality_parameters").await;
+        g.migrate(|mig| {
+            let mut inc = SqlIncorporator::default();
+            assert!(inc
+                .add_query(
+                    "CREATE TABLE users (id int, name varchar(40), age int);",
+                    None,
+                    mig
+                )
+                .is_ok());
+
+            // the equality parameter becomes the key, the other one is checked after lookup
+            let res = inc.add_query(
+                "SELECT id, name FROM users WHERE users.age > ? AND users.name = ?;",
+                None,
+                mig,
+            );
+            assert!(res.is_ok());
+            let qfp = res.unwrap();
+            assert_eq!(
+                get_node(&inc, mig, &qfp.name).fields(),
+                &["id", "name", "age"]
+            );
+            let n = get_reader(&inc, mig, &qfp.name);
+            n.with_reader(|r| {
+                assert_eq!(r.key().unwrap(), &[1]);
+                assert_eq!(
+                    r.post_lookup_filters(),
+                    &[PostLookupFilter {
+                        column: 2,
+                        operator: Operator::Greater,
+                        arity: 1,
+                    }]
+                );
+                // lookups give the age first, as in the query
+                assert_eq!(r.lookup_order(), &[1, 0]);
+            })
+            .unwrap();
+
+            // without equality parameters, the reader is keyed on the bogokey
+            let res = inc.add_query("SELECT id FROM users WHERE users.age IN (?, ?);", None, mig);
+            assert!(res.is_ok());
+            let qfp = res.unwrap();
+            assert_eq!(
+                get_node(&inc, mig, &qfp.name).fields(),
+                &["id", "age", "bogokey"]
+            );
+            let n = get_reader(&inc, mig, &qfp.name);
+            n.with_reader(|r| {
+                assert_eq!(r.key().unwrap(), &[2]);
+                assert_eq!(
+                    r.post_lookup_filters(),
+                    &[PostLookupFilter {
+                        column: 1,
+                        operator: Operator::In,
+                        arity: 2,
+                    }]
+                );
+                assert!(!r.is_ranged());
+            })
+            .unwrap();
+
+            // ranges of values are found in a reader ordered by the compared column
+            let res = inc.add_query(
+                "SELECT id FROM users WHERE users.age >= ? AND users.age < ?;",
+                None,
+                mig,
+            );
+            assert!(res.is_ok());
+            let qfp = res.unwrap();
+            let n = get_reader(&inc, mig, &qfp.name);
+            n.with_reader(|r| {
+                assert_eq!(r.key().unwrap(), &[2, 1]);
+                assert!(r.is_ranged());
+                assert!(r.is_ordered());
+            })
+            .unwrap();
+
+            // things we can't evaluate after the lookup are rejected
+            assert!(inc
+                .add_query(
+                    "SELECT id FROM users WHERE users.age > ? ORDER BY id LIMIT 3;",
+                    None,
+                    mig
+                )
+                .is_err());
+            assert!(inc
+                .add_query(
+                    "SELECT COUNT(id) AS c FROM users WHERE users.age < ?;",
+                    None,
+                    mig
+                )
+                .is_err());
+            assert!(inc
+                .add_query(
+                    "SELECT id FROM users WHERE users.age < ? OR users.name = ?;",
+                    None,
+                    mig
+                )
+                .is_err());
+            assert!(inc
+                .add_query(
+                    "SELECT id FROM users WHERE users.age IN (?, 42);",
+                    None,
+                    mig
+                )
+                .is_err());
+        })
+        .await;
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_rejects_unsupported_queries() {
+        // set up graph
+        let mut g = integration::start_simple("it_rejects_unsupported_queries").await;
+        g.migrate(|mig| {
+            let mut inc = SqlIncorporator::default();
+            assert!(inc
+                .add_query("CREATE TABLE users (id int, age int);", None, mig)
+                .is_ok());
+            let ncount = mig.graph().node_count();
+
+            // queries we can't plan are reported as errors rather than panics, and add no nodes
+            assert!(inc
+                .add_query(
+                    "SELECT id FROM users WHERE users.age > users.id + 1;",
+                    None,
+                    mig
+                )
+                .is_err());
+            assert!(inc
+                .add_query(
+                    "SELECT users.id FROM users JOIN (users AS u1, users AS u2) \
+                     ON (users.id = u1.id);",
+                    None,
+                    mig
+                )
+                .is_err());
+            assert_eq!(mig.graph().node_count(), ncount);
+        })
+        .await;
+    }
+
+    #[tokio::test(threaded_scheduler)]
+    async fn it_orders_reader_results() {
+        use nom_sql::OrderType;
+
+        // set up graph
+        let mut g = integration::start_simple("it_orders_reader_results").await;
+        g.migrate(|mig| {
+            let mut inc = SqlIncorporator::default();
+            assert!(inc
+                .add_query(
+                    "CREATE TABLE users (id int, name varchar(40), age int);",
+                    None,
+                    mig
+                )
+                .is_ok());
+
+            let res = inc.add_query(
+                "SELECT id, name FROM users WHERE users.age = ? ORDER BY users.name DESC;",
+                None,
+                mig,
+            );
+            assert!(res.is_ok());
+            let qfp = res.unwrap();
+            let leaf = qfp.query_leaf;
+            let n = get_reader(&inc, mig, &qfp.name);
+            n.with_reader(|r| assert_eq!(r.order(), &[(1, OrderType::OrderDescending)]))
+                .unwrap();
+
+            // the same query with a different order can't use the same reader
+            let res = inc.add_query(
+                "SELECT id, name FROM users WHERE users.age = ? ORDER BY users.name ASC;",
+                None,
+                mig,
+            );
+            assert!(res.is_ok());
+            let qfp = res.unwrap();
+            assert_ne!(qfp.query_leaf, leaf);
+            let n = get_reader(&inc, mig, &qfp.name);
+            n.with_reader(|r| assert_eq!(r.order(), &[(1, OrderType::OrderAscending)]))
+                .unwrap();
+        })
+        .await;
+    }
+
     #[tokio::test(threaded_scheduler)]
     async fn it_reuses_identical_query() {
         // set up graph
@@ -8,7 +8,7 @@ use std::collections::HashMap;
 use dataflow::prelude::DataType;
 
 pub trait AliasRemoval {
-    fn expand_table_aliases(self, context: &HashMap<String, DataType>) -> SqlQuery;
+    fn expand_table_aliases(self, context: &HashMap<String, DataType>) -> Result<SqlQuery, String>;
 }
 
 fn rewrite_conditional(
@@ -67,7 +67,7 @@ fn rewrite_conditional(
 }
 
 impl AliasRemoval for SqlQuery {
-    fn expand_table_aliases(self, context: &HashMap<String, DataType>) -> SqlQuery {
+    fn expand_table_aliases(self, context: &HashMap<String, DataType>) -> Result<SqlQuery, String> {
         let mut table_aliases = HashMap::new();
 
         match self {
@@ -112,16 +112,9 @@ impl AliasRemoval for SqlQuery {
                                 None => (),
                                 Some(ref a) => add_alias(a, &t.name),
                             },
-                            JoinRightSide::Tables(ref ts) => {
-                                for t in ts {
-                                    match t.alias {
-                                        None => (),
-                                        Some(ref a) => add_alias(a, &t.name),
-                                    }
-                                }
-                            }
-                            JoinRightSide::NestedJoin(_) => unimplemented!(),
-                            _ => (),
+                            // nested selects have been replaced by their views already, and we
+                            // can't plan other nested joins
+                            ref right => return Err(format!("unsupported join with {}", right)),
                         }
                     }
                 }
@@ -168,7 +161,7 @@ impl AliasRemoval for SqlQuery {
                                     JoinRightSide::Table(t)
                                 }
                             }
-                            _ => unimplemented!(),
+                            _ => unreachable!("only tables are joined"),
                         };
                         jc.constraint = match jc.constraint {
                             JoinConstraint::On(cond) => {
@@ -184,10 +177,10 @@ impl AliasRemoval for SqlQuery {
                     None => None,
                     Some(wc) => Some(rewrite_conditional(&table_aliases, wc)),
                 };
-                SqlQuery::Select(sq)
+                Ok(SqlQuery::Select(sq))
             }
             // nothing to do for other query types, as they cannot have aliases
-            x => x,
+            x => Ok(x),
         }
     }
 }
@@ -219,7 +212,7 @@ mod tests {
         };
         let mut context = HashMap::new();
         context.insert(String::from("id"), "global".into());
-        let res = SqlQuery::Select(q).expand_table_aliases(&context);
+        let res = SqlQuery::Select(q).expand_table_aliases(&context).unwrap();
         // Table alias removed in field list
         match res {
             SqlQuery::Select(tq) => {
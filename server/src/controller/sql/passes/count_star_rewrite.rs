@@ -1,6 +1,6 @@
 use nom_sql::{
     Column, ConditionBase, ConditionExpression, ConditionTree, FieldDefinitionExpression,
-    FunctionArguments, SqlQuery, Table,
+    FunctionArguments, GroupByClause, SqlQuery, Table,
 };
 
 use std::collections::HashMap;
@@ -37,7 +37,34 @@ fn extract_condition_columns(ce: &ConditionExpression) -> Vec<Column> {
         ConditionExpression::NegationOp(ref inner) => extract_condition_columns(inner),
         ConditionExpression::Bracketed(ref inner) => extract_condition_columns(inner),
         ConditionExpression::Base(_) => unreachable!(),
-        ConditionExpression::Arithmetic(_) => unimplemented!(),
+        ConditionExpression::Arithmetic(_) => vec![],
+    }
+}
+
+fn rewrite_condition_columns<F>(ce: &mut ConditionExpression, rewrite: &F)
+where
+    F: Fn(&mut Column),
+{
+    match *ce {
+        ConditionExpression::LogicalOp(ConditionTree {
+            ref mut left,
+            ref mut right,
+            ..
+        })
+        | ConditionExpression::ComparisonOp(ConditionTree {
+            ref mut left,
+            ref mut right,
+            ..
+        }) => {
+            rewrite_condition_columns(left, rewrite);
+            rewrite_condition_columns(right, rewrite);
+        }
+        ConditionExpression::NegationOp(ref mut inner)
+        | ConditionExpression::Bracketed(ref mut inner) => {
+            rewrite_condition_columns(inner, rewrite)
+        }
+        ConditionExpression::Base(ConditionBase::Field(ref mut f)) => rewrite(f),
+        ConditionExpression::Base(_) | ConditionExpression::Arithmetic(_) => (),
     }
 }
 
@@ -96,6 +123,17 @@ impl CountStarRewrite for SqlQuery {
                         }
                     }
                 }
+                // HAVING must count the same column as the field list does, so that both refer
+                // to the same aggregate
+                if let Some(GroupByClause {
+                    having: Some(ref mut h),
+                    ..
+                }) = sq.group_by
+                {
+                    rewrite_condition_columns(h, &|c: &mut Column| {
+                        rewrite_count_star(c, &tables, &avoid_cols)
+                    });
+                }
                 // TODO: also expand function columns within WHERE clause
                 SqlQuery::Select(sq)
             }
@@ -182,4 +220,46 @@ mod tests {
             _ => panic!(),
         }
     }
+
+    #[test]
+    fn it_expands_count_star_in_having() {
+        use nom_sql::parser::parse_query;
+        use nom_sql::{ConditionBase, ConditionExpression, FunctionArguments, FunctionExpression};
+
+        // SELECT id, COUNT(*) FROM users GROUP BY id HAVING COUNT(*) > 1;
+        // -->
+        // SELECT id, COUNT(users.name) FROM users GROUP BY id HAVING COUNT(users.name) > 1;
+        let q =
+            parse_query("SELECT id, COUNT(*) FROM users GROUP BY id HAVING COUNT(*) > 1;").unwrap();
+        let mut schema = HashMap::new();
+        schema.insert(
+            "users".into(),
+            vec!["id".into(), "name".into(), "age".into()],
+        );
+
+        let res = q.rewrite_count_star(&schema);
+        match res {
+            SqlQuery::Select(tq) => {
+                let expected = Some(Box::new(FunctionExpression::Count(
+                    FunctionArguments::Column(Column::from("users.name")),
+                    false,
+                )));
+                match tq.fields[1] {
+                    FieldDefinitionExpression::Col(ref c) => assert_eq!(c.function, expected),
+                    _ => panic!(),
+                }
+                match tq.group_by.unwrap().having {
+                    Some(ConditionExpression::ComparisonOp(ref ct)) => match *ct.left {
+                        ConditionExpression::Base(ConditionBase::Field(ref c)) => {
+                            assert_eq!(c.function, expected)
+                        }
+                        _ => panic!(),
+                    },
+                    _ => panic!(),
+                }
+            }
+            // if we get anything other than a selection query back, something really weird is up
+            _ => panic!(),
+        }
+    }
 }
@@ -7,33 +7,36 @@ use nom_sql::{
 use std::collections::HashMap;
 
 pub trait ImpliedTableExpansion {
-    fn expand_implied_tables(self, write_schemas: &HashMap<String, Vec<String>>) -> SqlQuery;
+    fn expand_implied_tables(
+        self,
+        write_schemas: &HashMap<String, Vec<String>>,
+    ) -> Result<SqlQuery, String>;
 }
 
 fn rewrite_conditional<F>(
     expand_columns: &F,
     ce: ConditionExpression,
     avail_tables: &[Table],
-) -> ConditionExpression
+) -> Result<ConditionExpression, String>
 where
-    F: Fn(Column, &[Table]) -> Column,
+    F: Fn(Column, &[Table]) -> Result<Column, String>,
 {
     use nom_sql::ConditionBase::*;
     use nom_sql::ConditionExpression::*;
 
-    let translate_ct_arm = |bce: Box<ConditionExpression>| -> Box<ConditionExpression> {
+    let translate_ct_arm = |bce: Box<ConditionExpression>| -> Result<_, String> {
         let new_ce = match *bce {
-            Base(Field(f)) => Base(Field(expand_columns(f, avail_tables))),
+            Base(Field(f)) => Base(Field(expand_columns(f, avail_tables)?)),
             Base(b) => Base(b),
-            x => rewrite_conditional(expand_columns, x, avail_tables),
+            x => rewrite_conditional(expand_columns, x, avail_tables)?,
         };
-        Box::new(new_ce)
+        Ok(Box::new(new_ce))
     };
 
-    match ce {
+    Ok(match ce {
         ComparisonOp(ct) => {
-            let l = translate_ct_arm(ct.left);
-            let r = translate_ct_arm(ct.right);
+            let l = translate_ct_arm(ct.left)?;
+            let r = translate_ct_arm(ct.right)?;
             let rewritten_ct = ConditionTree {
                 operator: ct.operator,
                 left: l,
@@ -47,11 +50,11 @@ where
             right,
         }) => LogicalOp(ConditionTree {
             operator,
-            left: Box::new(rewrite_conditional(expand_columns, *left, avail_tables)),
-            right: Box::new(rewrite_conditional(expand_columns, *right, avail_tables)),
+            left: Box::new(rewrite_conditional(expand_columns, *left, avail_tables)?),
+            right: Box::new(rewrite_conditional(expand_columns, *right, avail_tables)?),
         }),
         x => x,
-    }
+    })
 }
 
 // Sets the table for the `Column` in `f`to `table`. This is mostly useful for CREATE TABLE
@@ -75,14 +78,14 @@ fn set_table(mut f: Column, table: &Table) -> Column {
 fn rewrite_selection(
     mut sq: SelectStatement,
     write_schemas: &HashMap<String, Vec<String>>,
-) -> SelectStatement {
+) -> Result<SelectStatement, String> {
     use nom_sql::FunctionExpression::*;
     use nom_sql::{GroupByClause, OrderClause};
 
     // Tries to find a table with a matching column in the `tables_in_query` (information
     // passed as `write_schemas`; this is not something the parser or the expansion pass can
-    // know on their own). Panics if no match is found or the match is ambiguous.
-    let find_table = |f: &Column, tables_in_query: &[Table]| -> Option<String> {
+    // know on their own). Returns an error if the match is ambiguous.
+    let find_table = |f: &Column, tables_in_query: &[Table]| -> Result<Option<String>, String> {
         let mut matches = write_schemas
             .iter()
             .filter(|&(t, _)| {
@@ -98,31 +101,26 @@ fn rewrite_selection(
                     true
                 }
             })
-            .filter_map(|(t, ws)| {
-                let num_matching = ws.iter().filter(|c| **c == f.name).count();
-                assert!(num_matching <= 1);
-                if num_matching == 1 {
-                    Some((*t).clone())
+            .filter(|&(_, ws)| ws.iter().any(|c| *c == f.name))
+            .map(|(t, ws)| {
+                if ws.iter().filter(|c| **c == f.name).count() > 1 {
+                    Err(format!("column {} is ambiguous in {}", f.name, t))
                 } else {
-                    None
+                    Ok(t.clone())
                 }
             })
-            .collect::<Vec<String>>();
+            .collect::<Result<Vec<String>, String>>()?;
         if matches.len() > 1 {
-            println!(
-                "Ambiguous column {} exists in tables: {} -- picking a random one",
+            matches.sort();
+            Err(format!(
+                "column {} is ambiguous: it exists in tables {}",
                 f.name,
-                matches.as_slice().join(", ")
-            );
-            Some(matches.pop().unwrap())
-        } else if matches.is_empty() {
-            // This might be an alias for a computed column, which has no
-            // implied table. So, we allow it to pass and our code should
-            // crash in the future if this is not the case.
-            None
+                matches.join(", ")
+            ))
         } else {
-            // exactly one match
-            Some(matches.pop().unwrap())
+            // if there is no match, this might be an alias for a computed column, which has no
+            // implied table; later passes reject the column if this is not the case.
+            Ok(matches.pop())
         }
     };
 
@@ -131,7 +129,7 @@ fn rewrite_selection(
     // Traverses a query and calls `find_table` on any column that has no explicit table set,
     // including computed columns. Should not be used for CREATE TABLE and INSERT queries,
     // which can use the simpler `set_table`.
-    let expand_columns = |mut f: Column, tables_in_query: &[Table]| -> Column {
+    let expand_columns = |mut f: Column, tables_in_query: &[Table]| -> Result<Column, String> {
         f.table = match f.table {
             None => {
                 match f.function {
@@ -161,19 +159,19 @@ fn rewrite_selection(
                             | Max(FunctionArguments::Column(ref mut fe))
                             | GroupConcat(FunctionArguments::Column(ref mut fe), _) => {
                                 if fe.table.is_none() {
-                                    fe.table = find_table(fe, tables_in_query);
+                                    fe.table = find_table(fe, tables_in_query)?;
                                 }
                             }
                             _ => {}
                         }
                         None
                     }
-                    None => find_table(&f, tables_in_query),
+                    None => find_table(&f, tables_in_query)?,
                 }
             }
             Some(x) => Some(x),
         };
-        f
+        Ok(f)
     };
 
     let mut tables: Vec<Table> = sq.tables.clone();
@@ -182,26 +180,27 @@ fn rewrite_selection(
         match jc.right {
             JoinRightSide::Table(ref join_table) => tables.push(join_table.clone()),
             JoinRightSide::Tables(ref join_tables) => tables.extend(join_tables.clone()),
-            _ => unimplemented!(),
+            ref right => return Err(format!("unsupported join with {}", right)),
         }
     }
     // Expand within field list
     for field in sq.fields.iter_mut() {
         match *field {
-            FieldDefinitionExpression::All => panic!(err),
-            FieldDefinitionExpression::AllInTable(_) => panic!(err),
+            FieldDefinitionExpression::All | FieldDefinitionExpression::AllInTable(_) => {
+                return Err(String::from(err))
+            }
             FieldDefinitionExpression::Value(FieldValueExpression::Literal(_)) => (),
             FieldDefinitionExpression::Value(FieldValueExpression::Arithmetic(ref mut e)) => {
                 if let ArithmeticBase::Column(ref mut c) = e.left {
-                    *c = expand_columns(c.clone(), &tables);
+                    *c = expand_columns(c.clone(), &tables)?;
                 }
 
                 if let ArithmeticBase::Column(ref mut c) = e.right {
-                    *c = expand_columns(c.clone(), &tables);
+                    *c = expand_columns(c.clone(), &tables)?;
                 }
             }
             FieldDefinitionExpression::Col(ref mut f) => {
-                *f = expand_columns(f.clone(), &tables);
+                *f = expand_columns(f.clone(), &tables)?;
                 // also need to expand any conditionals in the column, e.g. for filtered aggregations
                 match f.function {
                     Some(ref mut f) => match **f {
@@ -220,7 +219,7 @@ fn rewrite_selection(
                             _,
                         ) => {
                             *condition =
-                                rewrite_conditional(&expand_columns, condition.clone(), &tables);
+                                rewrite_conditional(&expand_columns, condition.clone(), &tables)?;
                         }
                         _ => {}
                     },
@@ -232,7 +231,7 @@ fn rewrite_selection(
     // Expand within WHERE clause
     sq.where_clause = match sq.where_clause {
         None => None,
-        Some(wc) => Some(rewrite_conditional(&expand_columns, wc, &tables)),
+        Some(wc) => Some(rewrite_conditional(&expand_columns, wc, &tables)?),
     };
     // Expand within GROUP BY clause
     sq.group_by = match sq.group_by {
@@ -242,10 +241,10 @@ fn rewrite_selection(
                 .columns
                 .into_iter()
                 .map(|f| expand_columns(f, &tables))
-                .collect(),
+                .collect::<Result<_, _>>()?,
             having: match gbc.having {
                 None => None,
-                Some(hc) => Some(rewrite_conditional(&expand_columns, hc, &tables)),
+                Some(hc) => Some(rewrite_conditional(&expand_columns, hc, &tables)?),
             },
         }),
     };
@@ -256,27 +255,30 @@ fn rewrite_selection(
             columns: oc
                 .columns
                 .into_iter()
-                .map(|(f, o)| (expand_columns(f, &tables), o))
-                .collect(),
+                .map(|(f, o)| Ok((expand_columns(f, &tables)?, o)))
+                .collect::<Result<_, String>>()?,
         }),
     };
 
-    sq
+    Ok(sq)
 }
 
 impl ImpliedTableExpansion for SqlQuery {
-    fn expand_implied_tables(self, write_schemas: &HashMap<String, Vec<String>>) -> SqlQuery {
-        match self {
+    fn expand_implied_tables(
+        self,
+        write_schemas: &HashMap<String, Vec<String>>,
+    ) -> Result<SqlQuery, String> {
+        Ok(match self {
             SqlQuery::CreateTable(..) => self,
             SqlQuery::CompoundSelect(mut csq) => {
                 csq.selects = csq
                     .selects
                     .into_iter()
-                    .map(|(op, sq)| (op, rewrite_selection(sq, write_schemas)))
-                    .collect();
+                    .map(|(op, sq)| Ok((op, rewrite_selection(sq, write_schemas)?)))
+                    .collect::<Result<_, String>>()?;
                 SqlQuery::CompoundSelect(csq)
             }
-            SqlQuery::Select(sq) => SqlQuery::Select(rewrite_selection(sq, write_schemas)),
+            SqlQuery::Select(sq) => SqlQuery::Select(rewrite_selection(sq, write_schemas)?),
             SqlQuery::Insert(mut iq) => {
                 let table = iq.table.clone();
                 // Expand within field list
@@ -286,21 +288,19 @@ impl ImpliedTableExpansion for SqlQuery {
                 SqlQuery::Insert(iq)
             }
             _ => unreachable!(),
-        }
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::ImpliedTableExpansion;
-    use nom_sql::{Column, FieldDefinitionExpression, SqlQuery, Table};
+    use nom_sql::{Column, FieldDefinitionExpression, SelectStatement, SqlQuery, Table};
     use std::collections::HashMap;
 
     #[test]
     fn it_expands_implied_tables_for_select() {
-        use nom_sql::{
-            ConditionBase, ConditionExpression, ConditionTree, Operator, SelectStatement,
-        };
+        use nom_sql::{ConditionBase, ConditionExpression, ConditionTree, Operator};
 
         let wrap = |cb| Box::new(ConditionExpression::Base(cb));
 
@@ -330,7 +330,7 @@ mod tests {
             vec!["id".into(), "title".into(), "text".into(), "author".into()],
         );
 
-        let res = SqlQuery::Select(q).expand_implied_tables(&schema);
+        let res = SqlQuery::Select(q).expand_implied_tables(&schema).unwrap();
         match res {
             SqlQuery::Select(tq) => {
                 assert_eq!(
@@ -353,4 +353,30 @@ mod tests {
             _ => panic!(),
         }
     }
+
+    #[test]
+    fn it_rejects_ambiguous_columns() {
+        // SELECT id FROM users, articles;
+        let q = SelectStatement {
+            tables: vec![Table::from("users"), Table::from("articles")],
+            fields: vec![FieldDefinitionExpression::Col(Column::from("id"))],
+            ..Default::default()
+        };
+        let mut schema = HashMap::new();
+        schema.insert("users".into(), vec!["id".into(), "name".into()]);
+        schema.insert("articles".into(), vec!["id".into(), "title".into()]);
+
+        let e = SqlQuery::Select(q.clone())
+            .expand_implied_tables(&schema)
+            .unwrap_err();
+        assert_eq!(
+            e,
+            "column id is ambiguous: it exists in tables articles, users"
+        );
+
+        // a view can have several columns of the same name, too
+        schema.remove("articles");
+        schema.insert("users".into(), vec!["id".into(), "id".into()]);
+        assert!(SqlQuery::Select(q).expand_implied_tables(&schema).is_err());
+    }
 }
@@ -8,7 +8,6 @@ impl KeyDefinitionCoalescing for SqlQuery {
     fn coalesce_key_definitions(self) -> SqlQuery {
         match self {
             SqlQuery::CreateTable(mut ctq) => {
-                // TODO(malte): only handles primary keys so far!
                 let pkeys: Vec<&ColumnSpecification> = ctq
                     .fields
                     .iter()
@@ -18,16 +17,21 @@ impl KeyDefinitionCoalescing for SqlQuery {
                 for cs in pkeys {
                     pk.push(cs.column.clone())
                 }
+                let mut new_keys = vec![];
                 if !pk.is_empty() {
-                    ctq.keys = match ctq.keys {
-                        None => Some(vec![TableKey::PrimaryKey(pk)]),
-                        Some(mut ks) => {
-                            let new_key = TableKey::PrimaryKey(pk);
-                            if !ks.contains(&new_key) {
-                                ks.push(new_key);
-                            }
-                            Some(ks)
-                        }
+                    new_keys.push(TableKey::PrimaryKey(pk));
+                }
+                // every column-level UNIQUE is a single-column unique key
+                new_keys.extend(
+                    ctq.fields
+                        .iter()
+                        .filter(|cs| cs.constraints.contains(&ColumnConstraint::Unique))
+                        .map(|cs| TableKey::UniqueKey(None, vec![cs.column.clone()])),
+                );
+                for new_key in new_keys {
+                    let ks = ctq.keys.get_or_insert_with(Vec::new);
+                    if !ks.contains(&new_key) {
+                        ks.push(new_key);
                     }
                 }
                 SqlQuery::CreateTable(ctq)
@@ -88,4 +92,41 @@ mod tests {
             _ => panic!(),
         }
     }
+
+    #[test]
+    fn it_coalesces_unique_columns() {
+        use nom_sql::CreateTableStatement;
+
+        // CREATE TABLE t (id int PRIMARY KEY, email text UNIQUE)
+        // -->
+        // CREATE TABLE t (id int, email text, PRIMARY KEY (id), UNIQUE KEY (email))
+        let q = CreateTableStatement {
+            table: Table::from("t"),
+            fields: vec![
+                ColumnSpecification::with_constraints(
+                    Column::from("t.id"),
+                    SqlType::Int(32),
+                    vec![ColumnConstraint::PrimaryKey],
+                ),
+                ColumnSpecification::with_constraints(
+                    Column::from("t.email"),
+                    SqlType::Text,
+                    vec![ColumnConstraint::Unique],
+                ),
+            ],
+            keys: None,
+        };
+
+        let res = SqlQuery::CreateTable(q).coalesce_key_definitions();
+        match res {
+            SqlQuery::CreateTable(ctq) => assert_eq!(
+                ctq.keys,
+                Some(vec![
+                    TableKey::PrimaryKey(vec![Column::from("t.id")]),
+                    TableKey::UniqueKey(None, vec![Column::from("t.email")]),
+                ])
+            ),
+            _ => panic!(),
+        }
+    }
 }
@@ -1,5 +1,6 @@
 use nom_sql::{
-    ConditionBase, ConditionExpression, ConditionTree, JoinConstraint, Literal, Operator, SqlQuery,
+    ConditionBase, ConditionExpression, ConditionTree, GroupByClause, JoinConstraint, Literal,
+    Operator, SqlQuery,
 };
 
 use std::mem;
@@ -39,6 +40,8 @@ fn normalize_condition_expr(ce: &mut ConditionExpression, negate: bool) {
                     Operator::GreaterOrEqual => Operator::Less,
                     Operator::Less => Operator::GreaterOrEqual,
                     Operator::LessOrEqual => Operator::Greater,
+                    Operator::Like => Operator::NotLike,
+                    Operator::NotLike => Operator::Like,
                     _ => unreachable!(),
                 };
             }
@@ -61,8 +64,7 @@ fn normalize_condition_expr(ce: &mut ConditionExpression, negate: bool) {
         ConditionExpression::Bracketed(ref mut inner) => {
             normalize_condition_expr(inner, negate);
         }
-        ConditionExpression::Base(_) => {}
-        ConditionExpression::Arithmetic(_) => unimplemented!(),
+        ConditionExpression::Base(_) | ConditionExpression::Arithmetic(_) => {}
     }
 }
 
@@ -73,6 +75,14 @@ impl NegationRemoval for SqlQuery {
                 normalize_condition_expr(w, false);
             }
 
+            if let Some(GroupByClause {
+                having: Some(ref mut h),
+                ..
+            }) = s.group_by
+            {
+                normalize_condition_expr(h, false);
+            }
+
             for j in s.join.iter_mut() {
                 if let JoinConstraint::On(ref mut ce) = j.constraint {
                     normalize_condition_expr(ce, false);
@@ -1,5 +1,10 @@
+use crate::controller::sql::query_graph::flip_comparison;
 use nom_sql::ConditionExpression::*;
-use nom_sql::{Column, ConditionBase, ConditionExpression, JoinRightSide, SqlQuery};
+use nom_sql::{
+    Column, ConditionBase, ConditionExpression, ConditionTree, FieldDefinitionExpression,
+    FunctionExpression, GroupByClause, JoinClause, JoinConstraint, JoinOperator, JoinRightSide,
+    Operator, SelectStatement, SqlQuery, Table,
+};
 
 #[derive(Debug, PartialEq)]
 pub enum Subquery<'a> {
@@ -26,7 +31,7 @@ fn extract_subqueries_from_condition(ce: &mut ConditionExpression) -> Vec<Subque
             NestedSelect(_) => vec![Subquery::InComparison(cb)],
             _ => vec![],
         },
-        Arithmetic(_) => unimplemented!(),
+        Arithmetic(_) => vec![],
     }
 }
 
@@ -62,6 +67,445 @@ pub fn query_from_condition_base(cond: &ConditionBase) -> (SqlQuery, Column) {
     (sq, column)
 }
 
+/// A subquery that yields a single value, compared with a column of the outer query in one of
+/// the conjuncts of its WHERE clause, e.g. `articles.score > (SELECT AVG(...) FROM ...)`.
+#[derive(Debug, PartialEq)]
+pub struct ScalarSubquery {
+    /// The column of the outer query that the subquery's value is compared with.
+    pub column: Column,
+    pub operator: Operator,
+    pub query: SelectStatement,
+    /// Pairs of (inner, outer) columns that the subquery was correlated on.
+    pub correlation: Vec<(Column, Column)>,
+}
+
+fn conjuncts(ce: ConditionExpression) -> Vec<ConditionExpression> {
+    match ce {
+        LogicalOp(ConditionTree {
+            operator: Operator::And,
+            left,
+            right,
+        }) => {
+            let mut ces = conjuncts(*left);
+            ces.extend(conjuncts(*right));
+            ces
+        }
+        ce => vec![ce],
+    }
+}
+
+fn conjunction(ces: Vec<ConditionExpression>) -> Option<ConditionExpression> {
+    ces.into_iter().fold(None, |acc, ce| match acc {
+        None => Some(ce),
+        Some(left) => Some(LogicalOp(ConditionTree {
+            operator: Operator::And,
+            left: Box::new(left),
+            right: Box::new(ce),
+        })),
+    })
+}
+
+fn condition_columns(ce: &ConditionExpression) -> Vec<&Column> {
+    match *ce {
+        ComparisonOp(ref ct) | LogicalOp(ref ct) => condition_columns(&ct.left)
+            .into_iter()
+            .chain(condition_columns(&ct.right).into_iter())
+            .collect(),
+        NegationOp(ref bce) | Bracketed(ref bce) => condition_columns(bce),
+        Base(ConditionBase::Field(ref c)) => vec![c],
+        Base(_) | Arithmetic(_) => vec![],
+    }
+}
+
+/// All tables a query reads from, whether listed in FROM or joined.
+fn tables_in(st: &SelectStatement) -> Vec<&Table> {
+    let mut tables: Vec<_> = st.tables.iter().collect();
+    for jc in &st.join {
+        match jc.right {
+            JoinRightSide::Table(ref t) => tables.push(t),
+            JoinRightSide::Tables(ref ts) => tables.extend(ts),
+            _ => (),
+        }
+    }
+    tables
+}
+
+/// Finds the table named `name`, either directly or through its alias.
+fn find_table<'a>(tables: &[&'a Table], name: &str) -> Option<&'a Table> {
+    tables
+        .iter()
+        .find(|t| t.name == name || t.alias.as_ref().map(|a| a == name).unwrap_or(false))
+        .cloned()
+}
+
+/// Removes comparisons between columns and scalar subqueries from the top-level conjunction of
+/// the WHERE clause of `st`, and returns them.
+pub fn extract_scalar_subqueries(st: &mut SelectStatement) -> Vec<ScalarSubquery> {
+    let mut subqueries = Vec::new();
+    let ces = match st.where_clause {
+        None => return subqueries,
+        Some(ref ce) => conjuncts(ce.clone()),
+    };
+
+    let mut remaining = Vec::new();
+    for ce in ces {
+        let ct = match ce {
+            ComparisonOp(ref ct) if ct.operator != Operator::In => ct.clone(),
+            ce => {
+                remaining.push(ce);
+                continue;
+            }
+        };
+        let ct = if matches!(*ct.left, Base(ConditionBase::NestedSelect(_))) {
+            flip_comparison(ct)
+        } else {
+            ct
+        };
+        match (*ct.left, *ct.right) {
+            (Base(ConditionBase::Field(column)), Base(ConditionBase::NestedSelect(query))) => {
+                subqueries.push(ScalarSubquery {
+                    column,
+                    operator: ct.operator,
+                    query: *query,
+                    correlation: Vec::new(),
+                })
+            }
+            (left, right) => remaining.push(ComparisonOp(ConditionTree {
+                operator: ct.operator,
+                left: Box::new(left),
+                right: Box::new(right),
+            })),
+        }
+    }
+
+    // leave queries without scalar subqueries untouched
+    if !subqueries.is_empty() {
+        st.where_clause = conjunction(remaining);
+    }
+    subqueries
+}
+
+impl ScalarSubquery {
+    /// Turns a correlated subquery into one that computes its value for every combination of
+    /// values of the outer columns it refers to. Equality predicates between its own columns
+    /// and those of the `outer` query are removed from the subquery, which instead projects
+    /// and groups by its side of them; the outer query then joins with it on those columns.
+    pub fn decorrelate(&mut self, outer: &SelectStatement) -> Result<(), String> {
+        let outer_tables = tables_in(outer);
+        if self.column.table.is_none() {
+            if outer_tables.len() != 1 {
+                return Err(format!(
+                    "column {} compared with a subquery must name its table",
+                    self.column
+                ));
+            }
+            let t = outer_tables[0];
+            self.column.table = Some(t.alias.clone().unwrap_or_else(|| t.name.clone()));
+        }
+
+        let query = &mut self.query;
+        if query.fields.len() != 1 {
+            return Err(format!("subquery must return a single column: {}", query));
+        }
+        let value = match query.fields[0] {
+            FieldDefinitionExpression::Col(ref mut c) => c,
+            _ => return Err(format!("subquery must return a column: {}", query)),
+        };
+        // the outer query joins with the subquery's rows, and a count over no rows has none
+        // rather than being 0
+        if let Some(FunctionExpression::Count(..)) | Some(FunctionExpression::CountStar) =
+            value.function.as_ref().map(|f| &**f)
+        {
+            return Err(format!(
+                "COUNT is not supported in subqueries compared with a column: {}",
+                query
+            ));
+        }
+        if value.alias.is_none() {
+            value.alias = Some(value.name.clone());
+        }
+
+        let inner_tables: Vec<Table> = tables_in(query).into_iter().cloned().collect();
+        let inner_tables: Vec<&Table> = inner_tables.iter().collect();
+        let is_outer = |c: &Column| match c.table {
+            Some(ref t) => {
+                find_table(&inner_tables, t).is_none() && find_table(&outer_tables, t).is_some()
+            }
+            None => false,
+        };
+
+        let mut remaining = Vec::new();
+        for ce in query.where_clause.take().map(conjuncts).unwrap_or_default() {
+            if !condition_columns(&ce).into_iter().any(&is_outer) {
+                remaining.push(ce);
+                continue;
+            }
+            let pair = match ce {
+                ComparisonOp(ConditionTree {
+                    operator: Operator::Equal,
+                    ref left,
+                    ref right,
+                }) => match (&**left, &**right) {
+                    (Base(ConditionBase::Field(ref l)), Base(ConditionBase::Field(ref r))) => {
+                        if is_outer(l) && !is_outer(r) {
+                            Some((r.clone(), l.clone()))
+                        } else if is_outer(r) && !is_outer(l) {
+                            Some((l.clone(), r.clone()))
+                        } else {
+                            None
+                        }
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+            match pair {
+                Some((mut inner, outer)) => {
+                    // the subquery's own rewrite passes don't resolve aliases in GROUP BY
+                    if let Some(t) = inner
+                        .table
+                        .as_ref()
+                        .and_then(|t| find_table(&inner_tables, t))
+                    {
+                        inner.table = Some(t.name.clone());
+                    }
+                    self.correlation.push((inner, outer));
+                }
+                None => {
+                    return Err(format!(
+                        "unsupported correlated predicate in subquery: {}",
+                        ce
+                    ))
+                }
+            }
+        }
+        query.where_clause = conjunction(remaining);
+
+        // the join with the outer query can only involve one of its tables
+        let outer_table = |c: &Column| {
+            c.table
+                .as_ref()
+                .and_then(|t| find_table(&outer_tables, t))
+                .map(|t| &t.name)
+        };
+        if self
+            .correlation
+            .iter()
+            .any(|&(_, ref o)| outer_table(o) != outer_table(&self.column))
+        {
+            return Err(format!(
+                "subquery compared with {} must not refer to other tables of the query",
+                self.column
+            ));
+        }
+
+        if !self.correlation.is_empty() {
+            let inner: Vec<_> = self
+                .correlation
+                .iter()
+                .map(|&(ref i, _)| i.clone())
+                .collect();
+            let value = query.fields.pop().unwrap();
+            query.fields = inner
+                .iter()
+                .cloned()
+                .map(FieldDefinitionExpression::Col)
+                .chain(Some(value))
+                .collect();
+            match query.group_by {
+                Some(ref mut gbc) => gbc.columns.extend(inner),
+                None => {
+                    query.group_by = Some(GroupByClause {
+                        columns: inner,
+                        having: None,
+                    })
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The join through which the outer query compares with the subquery's value, once the
+    /// subquery has been added as the view named `view`.
+    pub fn join_clause(&self, view: &str) -> JoinClause {
+        let view_column = |name: &str| {
+            Box::new(Base(ConditionBase::Field(Column {
+                name: name.to_owned(),
+                alias: None,
+                table: Some(view.to_owned()),
+                function: None,
+            })))
+        };
+        let value = match self.query.fields.last() {
+            Some(FieldDefinitionExpression::Col(ref c)) => c.alias.as_ref().unwrap(),
+            _ => unreachable!(),
+        };
+
+        // equality comparisons go first, so that the join can look rows up by them
+        let ces = self
+            .correlation
+            .iter()
+            .map(|&(ref inner, ref outer)| {
+                ComparisonOp(ConditionTree {
+                    operator: Operator::Equal,
+                    left: Box::new(Base(ConditionBase::Field(outer.clone()))),
+                    right: view_column(&inner.name),
+                })
+            })
+            .chain(Some(ComparisonOp(ConditionTree {
+                operator: self.operator.clone(),
+                left: Box::new(Base(ConditionBase::Field(self.column.clone()))),
+                right: view_column(value),
+            })))
+            .collect();
+
+        JoinClause {
+            operator: JoinOperator::Join,
+            right: JoinRightSide::Table(Table::from(view)),
+            constraint: JoinConstraint::On(conjunction(ces).unwrap()),
+        }
+    }
+}
+
+/// A `column NOT IN (SELECT ...)` conjunct of a query's WHERE clause.
+#[derive(Debug, PartialEq)]
+pub struct NotInSubquery {
+    /// The column of the outer query that must not appear in the subquery's results.
+    pub column: Column,
+    pub query: SelectStatement,
+}
+
+fn unbracket(ce: &ConditionExpression) -> &ConditionExpression {
+    match *ce {
+        Bracketed(ref inner) => unbracket(inner),
+        _ => ce,
+    }
+}
+
+/// Matches both `x NOT IN (SELECT ...)` and `NOT (x IN (SELECT ...))`.
+fn not_in_subquery(ce: &ConditionExpression) -> Option<NotInSubquery> {
+    let (column, subquery) = match *unbracket(ce) {
+        ComparisonOp(ConditionTree {
+            operator: Operator::In,
+            ref left,
+            ref right,
+        }) => match (&**left, unbracket(right)) {
+            (Base(ConditionBase::Field(ref c)), NegationOp(ref inner)) => (c, &**inner),
+            _ => return None,
+        },
+        NegationOp(ref inner) => match *unbracket(inner) {
+            ComparisonOp(ConditionTree {
+                operator: Operator::In,
+                ref left,
+                ref right,
+            }) => match **left {
+                Base(ConditionBase::Field(ref c)) => (c, &**right),
+                _ => return None,
+            },
+            _ => return None,
+        },
+        _ => return None,
+    };
+    match *unbracket(subquery) {
+        Base(ConditionBase::NestedSelect(ref query)) => Some(NotInSubquery {
+            column: column.clone(),
+            query: (**query).clone(),
+        }),
+        _ => None,
+    }
+}
+
+/// Removes `NOT IN` comparisons between columns and subqueries from the top-level conjunction of
+/// the WHERE clause of `st`, and returns them.
+pub fn extract_not_in_subqueries(st: &mut SelectStatement) -> Vec<NotInSubquery> {
+    let mut subqueries = Vec::new();
+    let ces = match st.where_clause {
+        None => return subqueries,
+        Some(ref ce) => conjuncts(ce.clone()),
+    };
+
+    let mut remaining = Vec::new();
+    for ce in ces {
+        match not_in_subquery(&ce) {
+            Some(sq) => subqueries.push(sq),
+            None => remaining.push(ce),
+        }
+    }
+
+    // leave queries without `NOT IN` subqueries untouched
+    if !subqueries.is_empty() {
+        st.where_clause = conjunction(remaining);
+    }
+    subqueries
+}
+
+impl NotInSubquery {
+    /// Checks that the subquery can be turned into an anti-join with the `outer` query, and
+    /// gives the compared column its table if the query only has one.
+    pub fn resolve(&mut self, outer: &SelectStatement) -> Result<(), String> {
+        if self.column.table.is_none() {
+            let outer_tables = tables_in(outer);
+            if outer_tables.len() != 1 {
+                return Err(format!(
+                    "column {} compared with a subquery must name its table",
+                    self.column
+                ));
+            }
+            let t = outer_tables[0];
+            self.column.table = Some(t.alias.clone().unwrap_or_else(|| t.name.clone()));
+        }
+
+        match self.query.fields[..] {
+            [FieldDefinitionExpression::Col(_)] => Ok(()),
+            _ => Err(format!(
+                "subquery must return a single column: {}",
+                self.query
+            )),
+        }
+    }
+
+    /// Rewrites the outer query `st` to keep only the rows whose column has no match in the
+    /// subquery, once the subquery has been added as the view named `view`. This left joins
+    /// with the view and checks that the join found no match, which the query graph turns into
+    /// an anti-join. The join compares with `IN` rather than `=`, so that the anti-join follows
+    /// the NULL semantics of `NOT IN` rather than those of `NOT EXISTS`.
+    pub fn anti_join(&self, st: &mut SelectStatement, view: &str) {
+        let value = match self.query.fields[0] {
+            FieldDefinitionExpression::Col(ref c) => Column {
+                name: c.name.clone(),
+                alias: None,
+                table: Some(view.to_owned()),
+                function: None,
+            },
+            _ => unreachable!(),
+        };
+
+        st.join.push(JoinClause {
+            operator: JoinOperator::LeftJoin,
+            right: JoinRightSide::Table(Table::from(view)),
+            constraint: JoinConstraint::On(ComparisonOp(ConditionTree {
+                operator: Operator::In,
+                left: Box::new(Base(ConditionBase::Field(self.column.clone()))),
+                right: Box::new(Base(ConditionBase::Field(value.clone()))),
+            })),
+        });
+
+        let no_match = ComparisonOp(ConditionTree {
+            operator: Operator::Equal,
+            left: Box::new(Base(ConditionBase::Field(value))),
+            right: Box::new(Base(ConditionBase::Literal(nom_sql::Literal::Null))),
+        });
+        st.where_clause = conjunction(
+            st.where_clause
+                .take()
+                .into_iter()
+                .chain(Some(no_match))
+                .collect(),
+        );
+    }
+}
+
 impl SubQueries for SqlQuery {
     fn extract_subqueries(&mut self) -> Vec<Subquery> {
         let mut subqueries = Vec::new();
@@ -186,4 +630,120 @@ mod tests {
 
         assert_eq!(res, expected);
     }
+
+    fn select(sql: &str) -> SelectStatement {
+        match nom_sql::parse_query(sql).unwrap() {
+            SqlQuery::Select(st) => st,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn it_decorrelates_scalar_subqueries() {
+        let mut st = select(
+            "SELECT a.id FROM articles a WHERE a.author = 1 AND a.score > \
+             (SELECT AVG(v.stars) AS avg_stars FROM votes v WHERE v.aid = a.id AND v.stars > 0)",
+        );
+
+        let mut subqueries = extract_scalar_subqueries(&mut st);
+        assert_eq!(
+            st.where_clause,
+            select("SELECT a.id FROM articles a WHERE a.author = 1").where_clause
+        );
+        assert_eq!(subqueries.len(), 1);
+
+        let mut ssq = subqueries.pop().unwrap();
+        assert_eq!(ssq.column, Column::from("a.score"));
+        assert_eq!(ssq.operator, Operator::Greater);
+
+        // the subquery now computes its value for every article it has votes for
+        ssq.decorrelate(&st).unwrap();
+        assert_eq!(
+            ssq.query,
+            select(
+                "SELECT votes.aid, AVG(v.stars) AS avg_stars FROM votes v \
+                 WHERE v.stars > 0 GROUP BY votes.aid"
+            )
+        );
+
+        let jc = ssq.join_clause("q_1");
+        assert_eq!(jc.right, JoinRightSide::Table(Table::from("q_1")));
+        assert_eq!(
+            Some(jc.constraint),
+            select("SELECT a.id FROM articles a WHERE a.id = q_1.aid AND a.score > q_1.avg_stars")
+                .where_clause
+                .map(JoinConstraint::On)
+        );
+    }
+
+    #[test]
+    fn it_turns_not_in_subqueries_into_anti_joins() {
+        let mut st = select(
+            "SELECT a.id FROM articles a WHERE a.author = 1 AND \
+             a.id NOT IN (SELECT votes.aid FROM votes WHERE votes.stars > 3)",
+        );
+
+        let mut subqueries = extract_not_in_subqueries(&mut st);
+        assert_eq!(subqueries.len(), 1);
+        let mut nis = subqueries.pop().unwrap();
+        assert_eq!(nis.column, Column::from("a.id"));
+        assert_eq!(
+            nis.query,
+            select("SELECT votes.aid FROM votes WHERE votes.stars > 3")
+        );
+
+        nis.resolve(&st).unwrap();
+        nis.anti_join(&mut st, "q_1");
+        assert_eq!(st.join.len(), 1);
+        assert_eq!(st.join[0].operator, JoinOperator::LeftJoin);
+        assert_eq!(st.join[0].right, JoinRightSide::Table(Table::from("q_1")));
+        // `a.id IN q_1.aid` can't be parsed
+        assert_eq!(
+            st.join[0].constraint,
+            JoinConstraint::On(ComparisonOp(ConditionTree {
+                operator: Operator::In,
+                left: Box::new(Base(ConditionBase::Field(Column::from("a.id")))),
+                right: Box::new(Base(ConditionBase::Field(Column::from("q_1.aid")))),
+            }))
+        );
+        assert_eq!(
+            st.where_clause,
+            select("SELECT a.id FROM articles a WHERE a.author = 1 AND q_1.aid IS NULL")
+                .where_clause
+        );
+
+        // negating the whole comparison means the same thing
+        let mut st =
+            select("SELECT id FROM articles WHERE NOT (id IN (SELECT votes.aid FROM votes))");
+        let mut subqueries = extract_not_in_subqueries(&mut st);
+        assert_eq!(st.where_clause, None);
+        assert_eq!(subqueries.len(), 1);
+        subqueries[0].resolve(&st).unwrap();
+        assert_eq!(subqueries[0].column, Column::from("articles.id"));
+    }
+
+    #[test]
+    fn it_rejects_unsupported_correlated_predicates() {
+        let mut st = select(
+            "SELECT articles.id FROM articles WHERE articles.score > \
+             (SELECT MAX(votes.stars) FROM votes WHERE votes.aid > articles.id)",
+        );
+
+        let mut subqueries = extract_scalar_subqueries(&mut st);
+        assert_eq!(subqueries.len(), 1);
+        assert!(subqueries[0].decorrelate(&st).is_err());
+    }
+
+    #[test]
+    fn it_rejects_counting_subqueries() {
+        // articles without votes would have no count to compare with, rather than 0
+        let mut st = select(
+            "SELECT articles.id FROM articles WHERE articles.score > \
+             (SELECT COUNT(votes.uid) FROM votes WHERE votes.aid = articles.id)",
+        );
+
+        let mut subqueries = extract_scalar_subqueries(&mut st);
+        assert_eq!(subqueries.len(), 1);
+        assert!(subqueries[0].decorrelate(&st).is_err());
+    }
 }
@@ -1,8 +1,8 @@
 use nom_sql::SelectStatement;
 use nom_sql::{
     ArithmeticBase, ArithmeticExpression, Column, ConditionBase, ConditionExpression,
-    ConditionTree, FieldDefinitionExpression, FieldValueExpression, JoinConstraint, JoinOperator,
-    JoinRightSide, Literal, Operator, Table,
+    ConditionTree, FieldDefinitionExpression, FieldValueExpression, GroupByClause, JoinConstraint,
+    JoinOperator, JoinRightSide, Literal, Operator, Table,
 };
 
 use std::cmp::Ordering;
@@ -135,6 +135,23 @@ pub struct JoinRef {
     pub index: usize,
 }
 
+/// A comparison between a column and one or more query parameter placeholders.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct QueryParameter {
+    pub col: Column,
+    pub operator: Operator,
+    /// The number of placeholders compared against; only more than one for `IN (?, ?, ...)`.
+    pub arity: usize,
+}
+
+impl QueryParameter {
+    /// Parameters compared for equality become part of the reader key; all others are evaluated
+    /// by the reader on the rows it finds for a key.
+    pub fn is_equality(&self) -> bool {
+        self.operator == Operator::Equal || (self.operator == Operator::In && self.arity == 1)
+    }
+}
+
 #[derive(Clone, Debug, Hash, PartialEq)]
 pub struct QueryGraphNode {
     pub rel_name: String,
@@ -147,9 +164,28 @@ pub struct QueryGraphNode {
 pub enum QueryGraphEdge {
     Join(Vec<ConditionTree>),
     LeftJoin(Vec<ConditionTree>),
+    /// Keeps all rows of both relations, padding those without a match with NULLs. Right joins
+    /// are instead turned around into left joins.
+    FullJoin(Vec<ConditionTree>),
+    /// Keeps the rows of the source relation that have a match in the destination relation.
+    SemiJoin(Vec<ConditionTree>),
+    /// Keeps the rows of the source relation that have no match in the destination relation.
+    /// Predicates that compare with `IN` give it the NULL semantics of `NOT IN`.
+    AntiJoin(Vec<ConditionTree>),
     GroupBy(Vec<Column>),
 }
 
+impl QueryGraphEdge {
+    /// Whether this edge keeps rows without a match, whose results thus depend on the order in
+    /// which the relations are joined.
+    pub fn is_outer_join(&self) -> bool {
+        matches!(
+            *self,
+            QueryGraphEdge::LeftJoin(_) | QueryGraphEdge::FullJoin(_)
+        )
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct QueryGraph {
     /// Relations mentioned in the query.
@@ -164,6 +200,11 @@ pub struct QueryGraph {
     pub join_order: Vec<JoinRef>,
     /// Global predicates (not associated with a particular relation)
     pub global_predicates: Vec<ConditionExpression>,
+    /// Non-equality comparisons against query parameters, in the order they appear in the query.
+    pub parameter_filters: Vec<QueryParameter>,
+    /// All query parameters, in the order their placeholders appear in the query. Lookups give
+    /// the parameter values in this order.
+    pub parameter_order: Vec<QueryParameter>,
 }
 
 impl QueryGraph {
@@ -174,18 +215,48 @@ impl QueryGraph {
             columns: Vec::new(),
             join_order: Vec::new(),
             global_predicates: Vec::new(),
+            parameter_filters: Vec::new(),
+            parameter_order: Vec::new(),
         }
     }
 
-    /// Returns the set of columns on which this query is parameterized. They can come from
-    /// multiple tables involved in the query.
-    pub fn parameters<'a>(&'a self) -> Vec<&'a Column> {
-        self.relations
-            .values()
-            .fold(Vec::new(), |mut acc: Vec<&'a Column>, qgn| {
-                acc.extend(qgn.parameters.iter());
-                acc
-            })
+    /// Returns the set of columns on which this query is parameterized, in the order their
+    /// placeholders appear in the query. They can come from multiple tables involved in the query.
+    pub fn parameters(&self) -> Vec<&Column> {
+        self.parameter_order
+            .iter()
+            .filter(|p| p.is_equality())
+            .map(|p| &p.col)
+            .collect()
+    }
+
+    /// Returns the position in each lookup of each key value, and then of each value of the
+    /// parameter filters, for a reader keyed on `parameters()` (or just on the bogokey, which
+    /// lookups give first, if `bogokey` is set). Returns an empty vector if lookups give the values
+    /// in that order already.
+    pub fn lookup_order(&self, bogokey: bool) -> Vec<usize> {
+        let mut starts = Vec::with_capacity(self.parameter_order.len());
+        let mut next = if bogokey { 1 } else { 0 };
+        for p in &self.parameter_order {
+            starts.push(next);
+            next += p.arity;
+        }
+
+        let mut order = Vec::with_capacity(next);
+        if bogokey {
+            order.push(0);
+        }
+        let (keys, filters): (Vec<_>, Vec<_>) =
+            (0..self.parameter_order.len()).partition(|&i| self.parameter_order[i].is_equality());
+        for i in keys.into_iter().chain(filters) {
+            order.extend(starts[i]..starts[i] + self.parameter_order[i].arity);
+        }
+
+        if order.iter().enumerate().all(|(i, &p)| i == p) {
+            vec![]
+        } else {
+            order
+        }
     }
 
     pub fn exact_hash(&self) -> u64 {
@@ -215,6 +286,24 @@ impl Hash for QueryGraph {
         self.columns.hash(state);
         self.join_order.hash(state);
         self.global_predicates.hash(state);
+        self.parameter_filters.hash(state);
+        self.parameter_order.hash(state);
+    }
+}
+
+/// Swaps the sides of a comparison, adjusting the operator so that it still means the same thing
+pub fn flip_comparison(ct: ConditionTree) -> ConditionTree {
+    let operator = match ct.operator {
+        Operator::Less => Operator::Greater,
+        Operator::LessOrEqual => Operator::GreaterOrEqual,
+        Operator::Greater => Operator::Less,
+        Operator::GreaterOrEqual => Operator::LessOrEqual,
+        op => op,
+    };
+    ConditionTree {
+        operator,
+        left: ct.right,
+        right: ct.left,
     }
 }
 
@@ -255,16 +344,16 @@ fn classify_conditionals(
     local: &mut HashMap<String, Vec<ConditionExpression>>,
     join: &mut Vec<ConditionTree>,
     global: &mut Vec<ConditionExpression>,
-    params: &mut Vec<Column>,
-) {
+    params: &mut Vec<QueryParameter>,
+) -> Result<(), String> {
     // Handling OR and AND expressions requires some care as there are some corner cases.
     //    a) we don't support OR expressions with predicates with placeholder parameters,
     //       because these expressions are meaningless in the Soup context.
     //    b) we don't support OR expressions with join predicates because they are weird and
     //       too hard.
-    //    c) we don't support OR expressions between different tables (e.g table1.x = 1 OR
-    //       table2.y= 42). this is a global predicate according to finkelstein algorithm
-    //       and we don't support these yet.
+    //    c) OR expressions between different tables (e.g table1.x = 1 OR table2.y= 42) are
+    //       global predicates according to finkelstein algorithm, and are evaluated by a
+    //       single filter after the join.
 
     match *ce {
         ConditionExpression::LogicalOp(ref ct) => {
@@ -287,7 +376,7 @@ fn classify_conditionals(
                 &mut new_join,
                 &mut new_global,
                 &mut new_params,
-            );
+            )?;
             classify_conditionals(
                 ct.right.as_ref(),
                 tables,
@@ -295,17 +384,16 @@ fn classify_conditionals(
                 &mut new_join,
                 &mut new_global,
                 &mut new_params,
-            );
+            )?;
 
             match ct.operator {
                 Operator::And => {
                     //
                     for (t, ces) in new_local {
                         // conjunction, check if either side had a local predicate
-                        assert!(
-                            ces.len() <= 2,
-                            "can only combine two or fewer ConditionExpression's"
-                        );
+                        if ces.len() > 2 {
+                            return Err(format!("unsupported conjunction: {}", ce));
+                        }
                         if ces.len() == 2 {
                             let new_ce = ConditionExpression::LogicalOp(ConditionTree {
                                 operator: Operator::And,
@@ -326,18 +414,24 @@ fn classify_conditionals(
                     global.extend(new_global);
                 }
                 Operator::Or => {
-                    assert!(
-                        new_join.is_empty(),
-                        "can't handle OR expressions between join predicates"
-                    );
-                    assert!(
-                        new_params.is_empty(),
-                        "can't handle OR expressions between query parameter predicates"
-                    );
+                    if !new_join.is_empty() {
+                        return Err(format!(
+                            "join predicates cannot be used in OR expressions: {}",
+                            ce
+                        ));
+                    }
+                    if !new_params.is_empty() {
+                        return Err(format!(
+                            "query parameters cannot be used in OR expressions: {}",
+                            ce
+                        ));
+                    }
                     if new_local.keys().len() == 1 && new_global.is_empty() {
                         // OR over a single table => local predicate
                         let (t, ces) = new_local.into_iter().next().unwrap();
-                        assert_eq!(ces.len(), 2, "should combine only 2 ConditionExpressions");
+                        if ces.len() != 2 {
+                            return Err(format!("unsupported disjunction: {}", ce));
+                        }
                         let new_ce = ConditionExpression::LogicalOp(ConditionTree {
                             operator: Operator::Or,
                             left: Box::new(ces.first().unwrap().clone()),
@@ -359,6 +453,10 @@ fn classify_conditionals(
         }
         ConditionExpression::ComparisonOp(ref ct) => {
             // atomic selection predicate
+            match (ct.left.as_ref(), ct.right.as_ref()) {
+                (ConditionExpression::Base(_), ConditionExpression::Base(_)) => (),
+                _ => return Err(format!("unsupported comparison: {}", ce)),
+            }
             if let ConditionExpression::Base(ref l) = *ct.left.as_ref() {
                 if let ConditionExpression::Base(ref r) = *ct.right.as_ref() {
                     match *r {
@@ -375,20 +473,29 @@ fn classify_conditionals(
                                         .contains(&Table::from(rf.table.as_ref().unwrap().as_str()))
                                 {
                                     // both columns' tables appear in table list --> comma join
-                                    if ct.operator == Operator::Equal || ct.operator == Operator::In
-                                    {
-                                        // equi-join between two tables
-                                        let mut join_ct = ct.clone();
-                                        if let Ordering::Less =
-                                            rf.table.as_ref().cmp(&lf.table.as_ref())
-                                        {
-                                            use std::mem;
-                                            mem::swap(&mut join_ct.left, &mut join_ct.right);
+                                    match ct.operator {
+                                        Operator::Equal
+                                        | Operator::In
+                                        | Operator::NotEqual
+                                        | Operator::Less
+                                        | Operator::LessOrEqual
+                                        | Operator::Greater
+                                        | Operator::GreaterOrEqual => {
+                                            // (theta-)join between two tables
+                                            let mut join_ct = ct.clone();
+                                            if let Ordering::Less =
+                                                rf.table.as_ref().cmp(&lf.table.as_ref())
+                                            {
+                                                join_ct = flip_comparison(join_ct);
+                                            }
+                                            join.push(join_ct);
+                                        }
+                                        ref op => {
+                                            return Err(format!(
+                                                "unsupported join comparison: {} {} {}",
+                                                lf, op, rf
+                                            ))
                                         }
-                                        join.push(join_ct);
-                                    } else {
-                                        // non-equi-join?
-                                        unimplemented!();
                                     }
                                 } else {
                                     // not a comma join, just an ordinary comparison with a
@@ -398,13 +505,36 @@ fn classify_conditionals(
                                     global.push(ce.clone());
                                 }
                             } else {
-                                panic!("left hand side of comparison must be field");
+                                return Err(format!(
+                                    "left-hand side of comparison must be a column: {}",
+                                    ce
+                                ));
                             }
                         }
                         // right-hand side is a placeholder, so this must be a query parameter
                         ConditionBase::Literal(Literal::Placeholder) => {
                             if let ConditionBase::Field(ref lf) = *l {
-                                params.push(lf.clone());
+                                match ct.operator {
+                                    Operator::Equal
+                                    | Operator::In
+                                    | Operator::NotEqual
+                                    | Operator::Less
+                                    | Operator::LessOrEqual
+                                    | Operator::Greater
+                                    | Operator::GreaterOrEqual
+                                    | Operator::Like
+                                    | Operator::NotLike => params.push(QueryParameter {
+                                        col: lf.clone(),
+                                        operator: ct.operator.clone(),
+                                        arity: 1,
+                                    }),
+                                    ref op => {
+                                        return Err(format!(
+                                            "unsupported comparison with query parameter: {} {} ?",
+                                            lf, op
+                                        ))
+                                    }
+                                }
                             }
                         }
                         // right-hand side is a non-placeholder literal, so this is a predicate
@@ -422,8 +552,30 @@ fn classify_conditionals(
                                 }
                             }
                         }
-                        ConditionBase::LiteralList(_) => (),
-                        ConditionBase::NestedSelect(_) => unimplemented!(),
+                        ConditionBase::LiteralList(ref ll) => {
+                            let placeholders =
+                                ll.iter().filter(|l| **l == Literal::Placeholder).count();
+                            if placeholders > 0 {
+                                // IN (?, ?, ...) is a query parameter that takes several values
+                                if placeholders != ll.len() || ct.operator != Operator::In {
+                                    return Err(format!(
+                                        "query parameters can only be used in lists of \
+                                         placeholders compared with IN: {}",
+                                        ce
+                                    ));
+                                }
+                                if let ConditionBase::Field(ref lf) = *l {
+                                    params.push(QueryParameter {
+                                        col: lf.clone(),
+                                        operator: Operator::In,
+                                        arity: placeholders,
+                                    });
+                                }
+                            }
+                        }
+                        ConditionBase::NestedSelect(_) => {
+                            return Err(format!("unsupported nested SELECT in condition: {}", ce))
+                        }
                     }
                 };
             };
@@ -439,19 +591,156 @@ fn classify_conditionals(
                 &mut new_join,
                 global,
                 &mut new_params,
-            );
+            )?;
             join.extend(new_join);
             params.extend(new_params);
         }
         ConditionExpression::Base(_) => {
-            // don't expect to see a base here: we ought to exit when classifying its
-            // parent selection predicate
-            panic!("encountered unexpected standalone base of condition expression");
+            // we only expect to see a base when classifying its parent selection predicate
+            return Err(format!("condition is not a predicate: {}", ce));
         }
         ConditionExpression::NegationOp(_) => {
-            panic!("negation should have been removed earlier");
+            return Err(format!("unsupported negation in condition: {}", ce));
+        }
+        ConditionExpression::Arithmetic(_) => {
+            return Err(format!("unsupported arithmetic in condition: {}", ce));
+        }
+    }
+
+    Ok(())
+}
+
+/// Makes the columns of a HAVING clause refer to the outputs of the query: aggregates and aliases
+/// are resolved to the projected field they name. Aggregates that aren't projected are added to
+/// `hidden`, since they must still be computed for the predicate to be evaluated.
+fn resolve_having_columns(
+    ce: &mut ConditionExpression,
+    fields: &[FieldDefinitionExpression],
+    hidden: &mut Vec<Column>,
+) -> Result<(), String> {
+    match *ce {
+        ConditionExpression::LogicalOp(ref mut ct)
+        | ConditionExpression::ComparisonOp(ref mut ct) => {
+            resolve_having_columns(&mut ct.left, fields, hidden)?;
+            resolve_having_columns(&mut ct.right, fields, hidden)
+        }
+        ConditionExpression::Bracketed(ref mut inner)
+        | ConditionExpression::NegationOp(ref mut inner) => {
+            resolve_having_columns(inner, fields, hidden)
+        }
+        ConditionExpression::Base(ConditionBase::Field(ref mut c)) => {
+            let mut projected = fields.iter().filter_map(|f| match *f {
+                FieldDefinitionExpression::Col(ref fc) => Some(fc),
+                _ => None,
+            });
+            if c.function.is_some() {
+                match projected.find(|fc| fc.function == c.function) {
+                    Some(fc) => *c = fc.clone(),
+                    None => {
+                        if !hidden.contains(c) {
+                            hidden.push(c.clone());
+                        }
+                    }
+                }
+            } else if c.table.is_none() {
+                match projected.find(|fc| fc.alias.as_ref() == Some(&c.name)) {
+                    Some(fc) => *c = fc.clone(),
+                    None => return Err(format!("unknown column in HAVING clause: {}", c)),
+                }
+            }
+            Ok(())
+        }
+        ConditionExpression::Base(_) => Ok(()),
+        ConditionExpression::Arithmetic(_) => Err(String::from(
+            "arithmetic is not supported in HAVING clauses",
+        )),
+    }
+}
+
+/// Whether the right side `dst` of a left join on `jps` is only used to check that one of its
+/// join columns IS NULL, i.e., that the left side's row had no match.
+fn is_anti_join(dst: &str, jps: &[ConditionTree], qg: &QueryGraph, st: &SelectStatement) -> bool {
+    let rel = &qg.relations[dst];
+    let refers_to_dst = |c: &Column| c.table.as_ref().map(String::as_str) == Some(dst);
+    let used_elsewhere = qg
+        .edges
+        .keys()
+        .filter(|&&(ref s, ref d)| s == dst || d == dst)
+        .count()
+        > 1
+        || !rel.columns.is_empty()
+        || !rel.parameters.is_empty()
+        || qg.parameter_filters.iter().any(|p| refers_to_dst(&p.col))
+        || qg.global_predicates.iter().any(|ce| match *ce {
+            ConditionExpression::ComparisonOp(ref ct) | ConditionExpression::LogicalOp(ref ct) => {
+                ct.contained_columns().into_iter().any(refers_to_dst)
+            }
+            _ => true,
+        })
+        || st
+            .group_by
+            .as_ref()
+            .map(|gbc| gbc.columns.iter().any(refers_to_dst))
+            .unwrap_or(false)
+        || st
+            .order
+            .as_ref()
+            .map(|oc| oc.columns.iter().any(|&(ref c, _)| refers_to_dst(c)))
+            .unwrap_or(false);
+    if used_elsewhere || rel.predicates.len() != 1 {
+        return false;
+    }
+
+    // only the join columns are known to be non-NULL in every row that matched
+    if jps
+        .iter()
+        .any(|jp| jp.operator != Operator::Equal && jp.operator != Operator::In)
+    {
+        return false;
+    }
+    match rel.predicates[0] {
+        ConditionExpression::ComparisonOp(ConditionTree {
+            operator: Operator::Equal,
+            ref left,
+            ref right,
+        }) => {
+            **right == ConditionExpression::Base(ConditionBase::Literal(Literal::Null))
+                && jps.iter().any(|jp| jp.right == *left)
+        }
+        _ => false,
+    }
+}
+
+/// Returns the query parameter a HAVING predicate compares a column with, if any.
+fn having_parameter(ce: &ConditionExpression) -> Option<QueryParameter> {
+    if let ConditionExpression::ComparisonOp(ref ct) = *ce {
+        if let ConditionExpression::Base(ConditionBase::Field(ref c)) = *ct.left {
+            if *ct.right == ConditionExpression::Base(ConditionBase::Literal(Literal::Placeholder))
+            {
+                return Some(QueryParameter {
+                    col: c.clone(),
+                    operator: ct.operator.clone(),
+                    arity: 1,
+                });
+            }
+        }
+    }
+    None
+}
+
+fn contains_placeholder(ce: &ConditionExpression) -> bool {
+    match *ce {
+        ConditionExpression::LogicalOp(ref ct) | ConditionExpression::ComparisonOp(ref ct) => {
+            contains_placeholder(&ct.left) || contains_placeholder(&ct.right)
+        }
+        ConditionExpression::NegationOp(ref inner) | ConditionExpression::Bracketed(ref inner) => {
+            contains_placeholder(inner)
+        }
+        ConditionExpression::Base(ConditionBase::Literal(ref l)) => *l == Literal::Placeholder,
+        ConditionExpression::Base(ConditionBase::LiteralList(ref ll)) => {
+            ll.contains(&Literal::Placeholder)
         }
-        ConditionExpression::Arithmetic(_) => unimplemented!(),
+        ConditionExpression::Base(_) | ConditionExpression::Arithmetic(_) => false,
     }
 }
 
@@ -481,7 +770,8 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
                                     match c.function {
                                         // XXX(malte): don't drop aggregation columns
                                         Some(_) => None,
-                                        None => panic!(
+                                        // columns without a table were rejected above
+                                        None => unreachable!(
                                             "No table name set for column {} on {}",
                                             c.name, rel
                                         ),
@@ -502,6 +792,15 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
             }
         };
 
+    // the implied table expansion pass leaves the table of columns that no table has unset
+    for field in &st.fields {
+        if let FieldDefinitionExpression::Col(ref c) = *field {
+            if c.table.is_none() && c.function.is_none() {
+                return Err(format!("unknown column \"{}\"", c.name));
+            }
+        }
+    }
+
     // 1. Add any relations mentioned in the query to the query graph.
     // This is needed so that we don't end up with an empty query graph when there are no
     // conditionals, but rather with a one-node query graph that has no predicates.
@@ -521,7 +820,7 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
                     );
                 }
             }
-            _ => unimplemented!(),
+            ref right => return Err(format!("unsupported join with {}", right)),
         }
     }
 
@@ -535,6 +834,8 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
     // 2a. Explicit joins
     // The table specified in the query is available for USING joins.
     let prev_table = Some(st.tables.last().as_ref().unwrap().name.clone());
+    // the edges of the explicit joins, in the order the query gives them
+    let mut explicit_edges = Vec::new();
     for jc in &st.join {
         match jc.right {
             JoinRightSide::Table(ref table) => {
@@ -542,7 +843,7 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
                 let left_table;
                 let right_table;
 
-                let join_pred = match jc.constraint {
+                let join_preds: Vec<ConditionTree> = match jc.constraint {
                     JoinConstraint::On(ref cond) => {
                         use crate::controller::sql::query_utils::ReferredTables;
 
@@ -551,82 +852,131 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
                         let mut tables_mentioned: Vec<String> =
                             cond.referred_tables().into_iter().map(|t| t.name).collect();
 
-                        match *cond {
-                            ConditionExpression::ComparisonOp(ref ct) => {
-                                if tables_mentioned.len() == 2 {
-                                    // tables can appear in any order in the join predicate, but
-                                    // we cannot just rely on that order, since it may lead us to
-                                    // flip LEFT JOINs by accident (yes, this happened)
-                                    if tables_mentioned[1] != table.name {
-                                        // tables are in the wrong order in join predicate, swap
-                                        tables_mentioned.swap(0, 1);
-                                        assert_eq!(tables_mentioned[1], table.name);
-                                    }
-                                    left_table = tables_mentioned.remove(0);
-                                    right_table = tables_mentioned.remove(0);
-                                } else if tables_mentioned.len() == 1 {
-                                    // just one table mentioned --> this is a self-join
-                                    left_table = tables_mentioned.remove(0);
-                                    right_table = left_table.clone();
-                                } else {
-                                    unreachable!("more than 2 tables mentioned in join condition!");
-                                };
-
-                                // the condition tree might specify tables in opposite order to
-                                // their join order in the query; if so, flip them
-                                // TODO(malte): this only deals with simple, flat join
-                                // conditions for now.
-                                let l = match *ct.left.as_ref() {
-                                    ConditionExpression::Base(ConditionBase::Field(ref f)) => f,
-                                    _ => unimplemented!(),
-                                };
-                                let r = match *ct.right.as_ref() {
-                                    ConditionExpression::Base(ConditionBase::Field(ref f)) => f,
-                                    _ => unimplemented!(),
-                                };
-                                if *l.table.as_ref().unwrap() == right_table
-                                    && *r.table.as_ref().unwrap() == left_table
-                                {
-                                    ConditionTree {
-                                        operator: ct.operator.clone(),
-                                        left: ct.right.clone(),
-                                        right: ct.left.clone(),
+                        if tables_mentioned.len() == 2 {
+                            // tables can appear in any order in the join predicate, but
+                            // we cannot just rely on that order, since it may lead us to
+                            // flip LEFT JOINs by accident (yes, this happened)
+                            if tables_mentioned[1] != table.name {
+                                // tables are in the wrong order in join predicate, swap
+                                tables_mentioned.swap(0, 1);
+                                assert_eq!(tables_mentioned[1], table.name);
+                            }
+                            left_table = tables_mentioned.remove(0);
+                            right_table = tables_mentioned.remove(0);
+                        } else if tables_mentioned.len() == 1 {
+                            // just one table mentioned --> this is a self-join
+                            left_table = tables_mentioned.remove(0);
+                            right_table = left_table.clone();
+                        } else {
+                            return Err(format!(
+                                "join condition must compare columns of two tables: {}",
+                                cond
+                            ));
+                        };
+
+                        // a conjunction of comparisons joins on several columns at once
+                        split_conjunctions(vec![cond.clone()])
+                            .into_iter()
+                            .map(|ce| match ce {
+                                ConditionExpression::ComparisonOp(ct) => {
+                                    // the condition tree might specify tables in opposite order
+                                    // to their join order in the query; if so, flip them
+                                    let (l, r) = match (ct.left.as_ref(), ct.right.as_ref()) {
+                                        (
+                                            ConditionExpression::Base(ConditionBase::Field(l)),
+                                            ConditionExpression::Base(ConditionBase::Field(r)),
+                                        ) => (l, r),
+                                        _ => {
+                                            return Err(format!(
+                                                "join condition must compare two columns: {}",
+                                                ct
+                                            ))
+                                        }
+                                    };
+                                    if *l.table.as_ref().unwrap() == right_table
+                                        && *r.table.as_ref().unwrap() == left_table
+                                    {
+                                        Ok(flip_comparison(ct))
+                                    } else {
+                                        Ok(ct)
                                     }
-                                } else {
-                                    ct.clone()
                                 }
-                            }
-                            _ => panic!("join condition is not a comparison!"),
-                        }
+                                ce => Err(format!("join condition is not a comparison: {}", ce)),
+                            })
+                            .collect::<Result<_, _>>()?
                     }
                     JoinConstraint::Using(ref cols) => {
-                        assert_eq!(cols.len(), 1);
-                        let col = cols.iter().next().unwrap();
-
                         left_table = prev_table.as_ref().unwrap().clone();
                         right_table = table.name.clone();
 
-                        ConditionTree {
-                            operator: Operator::Equal,
-                            left: wrapcol(&left_table, &col.name),
-                            right: wrapcol(&right_table, &col.name),
-                        }
+                        cols.iter()
+                            .map(|col| ConditionTree {
+                                operator: Operator::Equal,
+                                left: wrapcol(&left_table, &col.name),
+                                right: wrapcol(&right_table, &col.name),
+                            })
+                            .collect()
                     }
                 };
 
-                // add edge for join
-                let mut _e = qg
-                    .edges
-                    .entry((left_table.clone(), right_table.clone()))
-                    .or_insert_with(|| match jc.operator {
-                        JoinOperator::LeftJoin => QueryGraphEdge::LeftJoin(vec![join_pred]),
-                        JoinOperator::Join | JoinOperator::InnerJoin => {
-                            QueryGraphEdge::Join(vec![join_pred])
-                        }
-                        _ => unimplemented!(),
-                    });
+                // add edge for join. nom-sql has no operators for right and full joins, so the
+                // recipe parser reads them as `CROSS JOIN` and `STRAIGHT_JOIN` (see
+                // `recipe::outer_join`).
+                let (edge, left_table, right_table, join_preds) = match jc.operator {
+                    JoinOperator::LeftJoin | JoinOperator::LeftOuterJoin => (
+                        QueryGraphEdge::LeftJoin(vec![]),
+                        left_table,
+                        right_table,
+                        join_preds,
+                    ),
+                    JoinOperator::Join | JoinOperator::InnerJoin => (
+                        QueryGraphEdge::Join(vec![]),
+                        left_table,
+                        right_table,
+                        join_preds,
+                    ),
+                    // `a RIGHT JOIN b` is `b LEFT JOIN a`
+                    JoinOperator::CrossJoin => (
+                        QueryGraphEdge::LeftJoin(vec![]),
+                        right_table,
+                        left_table,
+                        join_preds.into_iter().map(flip_comparison).collect(),
+                    ),
+                    JoinOperator::StraightJoin => (
+                        QueryGraphEdge::FullJoin(vec![]),
+                        left_table,
+                        right_table,
+                        join_preds,
+                    ),
+                };
+                if !matches!(edge, QueryGraphEdge::Join(_)) {
+                    // only inner joins can be planned as theta joins. `IN` only joins with the
+                    // views that compute `NOT IN` subqueries.
+                    if let Some(jp) = join_preds
+                        .iter()
+                        .find(|jp| jp.operator != Operator::Equal && jp.operator != Operator::In)
+                    {
+                        return Err(format!(
+                            "outer join conditions must compare columns for equality: {}",
+                            jp
+                        ));
+                    }
+                }
+                let key = (left_table, right_table);
+                if !explicit_edges.contains(&key) {
+                    explicit_edges.push(key.clone());
+                }
+                match *qg.edges.entry(key).or_insert(edge) {
+                    QueryGraphEdge::Join(ref mut preds)
+                    | QueryGraphEdge::LeftJoin(ref mut preds)
+                    | QueryGraphEdge::FullJoin(ref mut preds) => preds.extend(join_preds),
+                    QueryGraphEdge::SemiJoin(_)
+                    | QueryGraphEdge::AntiJoin(_)
+                    | QueryGraphEdge::GroupBy(_) => unreachable!(),
+                }
             }
-            _ => unimplemented!(),
+            // non-table joins were rejected when adding relations above
+            _ => unreachable!(),
         }
     }
 
@@ -642,21 +992,66 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
             &mut join_predicates,
             &mut global_predicates,
             &mut query_parameters,
-        );
+        )?;
 
         for (_, ces) in local_predicates.iter_mut() {
             *ces = split_conjunctions(ces.clone());
         }
 
+        // `IN (SELECT ...)` subqueries have been flattened into comparisons with a column of the
+        // view that computes them, which the query doesn't otherwise read from; each of these
+        // keeps the rows that have a match in the view, so it's a semi-join with the view.
+        let read_tables: Vec<_> = qg.relations.keys().cloned().collect();
+        let mut remaining_global_predicates = Vec::new();
+        for ce in global_predicates {
+            let jp = match ce {
+                ConditionExpression::ComparisonOp(ref ct) if ct.operator == Operator::In => {
+                    match (&*ct.left, &*ct.right) {
+                        (
+                            ConditionExpression::Base(ConditionBase::Field(Column {
+                                table: Some(ref l),
+                                ..
+                            })),
+                            ConditionExpression::Base(ConditionBase::Field(Column {
+                                table: Some(ref r),
+                                ..
+                            })),
+                        ) if read_tables.contains(l) && !read_tables.contains(r) => {
+                            Some((l.clone(), r.clone(), ct.clone()))
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+            match jp {
+                Some((l, r, jp)) => {
+                    qg.relations
+                        .entry(r.clone())
+                        .or_insert_with(|| new_node(r.clone(), Vec::new(), st));
+                    match *qg
+                        .edges
+                        .entry((l, r))
+                        .or_insert_with(|| QueryGraphEdge::SemiJoin(vec![]))
+                    {
+                        QueryGraphEdge::SemiJoin(ref mut preds) => preds.push(jp),
+                        _ => unreachable!(),
+                    }
+                }
+                None => remaining_global_predicates.push(ce),
+            }
+        }
+        let global_predicates = remaining_global_predicates;
+
         // 1. Add local predicates for each node that has them
         for (rel, preds) in local_predicates {
             if !qg.relations.contains_key(&rel) {
                 // can't have predicates on tables that do not appear in the FROM part of the
                 // statement
-                panic!(
-                    "predicate(s) {:?} on relation {} that is not in query graph",
-                    preds, rel
-                );
+                return Err(format!(
+                    "predicate on table \"{}\", which the query does not select from",
+                    rel
+                ));
             } else {
                 qg.relations.get_mut(&rel).unwrap().predicates.extend(preds);
             }
@@ -676,13 +1071,29 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
                         .entry(r.table.clone().unwrap())
                         .or_insert_with(|| new_node(r.table.clone().unwrap(), Vec::new(), st));
 
+                    // join on all predicates between two tables at once, even if some of them
+                    // name the tables in the opposite order
+                    let (l_table, r_table) = (l.table.clone().unwrap(), r.table.clone().unwrap());
+                    let reversed = (r_table.clone(), l_table.clone());
+                    let (edge, jp) = if l_table != r_table && qg.edges.contains_key(&reversed) {
+                        (reversed, flip_comparison(jp.clone()))
+                    } else {
+                        ((l_table, r_table), jp.clone())
+                    };
+
                     let e = qg
                         .edges
-                        .entry((l.table.clone().unwrap(), r.table.clone().unwrap()))
+                        .entry(edge)
                         .or_insert_with(|| QueryGraphEdge::Join(vec![]));
                     match *e {
-                        QueryGraphEdge::Join(ref mut preds) => preds.push(jp.clone()),
-                        _ => panic!("Expected join edge for join condition {:#?}", jp),
+                        QueryGraphEdge::Join(ref mut preds) => preds.push(jp),
+                        _ => {
+                            return Err(format!(
+                                "join condition {} conflicts with an outer join between the same \
+                                 tables",
+                                jp
+                            ));
+                        }
                     };
                 }
             }
@@ -692,24 +1103,57 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
         //    node for this query. Such columns will be carried all the way through the operators
         //    implementing the query (unlike in a traditional query plan, where the predicates on
         //    parameters might be evaluated sooner).
-        for column in query_parameters.into_iter() {
+        //    Parameters that aren't compared for equality can't be part of the leaf's key, and are
+        //    instead evaluated by the reader on the rows it finds for a key.
+        for param in query_parameters.into_iter() {
+            let column = &param.col;
             match column.table {
-                None => panic!("each parameter's column must have an associated table!"),
+                None => {
+                    return Err(format!("unknown parameter column \"{}\"", column.name));
+                }
                 Some(ref table) => {
                     let rel = qg.relations.get_mut(table).unwrap();
-                    if !rel.columns.contains(&column) {
+                    if !rel.columns.contains(column) {
                         rel.columns.push(column.clone());
                     }
                     // the parameter column is included in the projected columns of the output, but
                     // we also separately register it as a parameter so that we can set keys
                     // correctly on the leaf view
-                    rel.parameters.push(column.clone());
+                    if param.is_equality() {
+                        rel.parameters.push(column.clone());
+                    } else {
+                        qg.parameter_filters.push(param.clone());
+                    }
+                    qg.parameter_order.push(param);
                 }
             }
         }
 
         // 4. Add global predicates
         qg.global_predicates = global_predicates;
+
+        // 5. A left join whose right side is only used to check that it had no match for a row,
+        //    as in `a LEFT JOIN b ON a.x = b.y WHERE b.y IS NULL`, is an anti-join
+        let left_joins: Vec<_> = qg
+            .edges
+            .iter()
+            .filter_map(|(e, qge)| match *qge {
+                QueryGraphEdge::LeftJoin(ref jps) => Some((e.clone(), jps.clone())),
+                _ => None,
+            })
+            .collect();
+        for ((src, dst), jps) in left_joins {
+            if is_anti_join(&dst, &jps, &qg, st) {
+                qg.relations.get_mut(&dst).unwrap().predicates.clear();
+                qg.edges.insert((src, dst), QueryGraphEdge::AntiJoin(jps));
+            } else if jps.iter().any(|jp| jp.operator == Operator::In) {
+                // the left join with a `NOT IN` subquery's view only stands for the anti-join
+                return Err(format!(
+                    "unsupported use of NOT IN subquery {} in query: {}",
+                    dst, st
+                ));
+            }
+        }
     }
 
     // Adds a computed column to the query graph if the given column has a function:
@@ -734,7 +1178,7 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
     for field in st.fields.iter() {
         match *field {
             FieldDefinitionExpression::All | FieldDefinitionExpression::AllInTable(_) => {
-                panic!("Stars should have been expanded by now!")
+                return Err(String::from("stars should have been expanded by now"));
             }
             FieldDefinitionExpression::Value(FieldValueExpression::Literal(ref l)) => {
                 qg.columns.push(OutputColumn::Literal(LiteralColumn {
@@ -800,6 +1244,16 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
                 src_ord
             }
         });
+        // queries with outer joins join in the order that they give
+        if qg.edges.values().any(QueryGraphEdge::is_outer_join) {
+            let implicit = explicit_edges.len();
+            sorted_edges.sort_by_key(|&(e, _)| {
+                explicit_edges
+                    .iter()
+                    .position(|x| x == e)
+                    .unwrap_or(implicit)
+            });
+        }
 
         for (&(ref src, ref dst), edge) in sorted_edges {
             match *edge {
@@ -813,7 +1267,10 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
                         })
                         .collect::<Vec<_>>(),
                 ),
-                QueryGraphEdge::LeftJoin(ref jps) => qg.join_order.extend(
+                QueryGraphEdge::LeftJoin(ref jps)
+                | QueryGraphEdge::FullJoin(ref jps)
+                | QueryGraphEdge::SemiJoin(ref jps)
+                | QueryGraphEdge::AntiJoin(ref jps) => qg.join_order.extend(
                     jps.iter()
                         .enumerate()
                         .map(|(idx, _)| JoinRef {
@@ -828,5 +1285,56 @@ pub fn to_query_graph(st: &SelectStatement) -> Result<QueryGraph, String> {
         }
     }
 
+    // Parameter filters are only applied once the reader has found the rows for a key, so
+    // anything computed over those rows inside the graph would see rows the filters reject.
+    if !qg.parameter_filters.is_empty()
+        && (qg.relations.contains_key("computed_columns") || st.group_by.is_some())
+    {
+        return Err(String::from(
+            "aggregations are not supported in queries with non-equality parameters",
+        ));
+    }
+
+    // HAVING predicates apply to the output of the grouped operators. Comparisons against
+    // literals become global predicates, which are placed after all aggregations, while
+    // comparisons against query parameters are evaluated by the reader, which sees the
+    // aggregated rows.
+    if let Some(GroupByClause {
+        having: Some(ref having),
+        ..
+    }) = st.group_by
+    {
+        let mut having = having.clone();
+        let mut hidden = Vec::new();
+        resolve_having_columns(&mut having, &st.fields, &mut hidden)?;
+        for c in &hidden {
+            add_computed_column(&mut qg, c);
+        }
+
+        for ce in split_conjunctions(vec![having]) {
+            match having_parameter(&ce) {
+                Some(param) => {
+                    qg.parameter_filters.push(param.clone());
+                    qg.parameter_order.push(param);
+                }
+                None if contains_placeholder(&ce) => {
+                    return Err(format!(
+                        "query parameters in HAVING must be compared directly with a column: {}",
+                        ce
+                    ));
+                }
+                None => qg.global_predicates.push(ce),
+            }
+        }
+    }
+
+    if !qg.parameter_filters.is_empty() && st.limit.is_some() {
+        // the limit would apply to the rows before the reader filters them by the parameters
+        return Err(String::from(
+            "LIMIT is not supported in queries with non-equality parameters; \
+             use View::lookup_page to page through the results instead",
+        ));
+    }
+
     Ok(qg)
 }
@@ -117,7 +117,10 @@ impl Signature for QueryGraph {
         for e in self.edges.values() {
             match *e {
                 QueryGraphEdge::Join(ref join_predicates)
-                | QueryGraphEdge::LeftJoin(ref join_predicates) => {
+                | QueryGraphEdge::LeftJoin(ref join_predicates)
+                | QueryGraphEdge::FullJoin(ref join_predicates)
+                | QueryGraphEdge::SemiJoin(ref join_predicates)
+                | QueryGraphEdge::AntiJoin(ref join_predicates) => {
                     for p in join_predicates {
                         for c in &p.contained_columns() {
                             attrs_vec.push(c);
@@ -47,7 +47,15 @@ impl ReferredTables for ConditionExpression {
                     }
                 }
             }
-            _ => unimplemented!(),
+            ConditionExpression::NegationOp(ref inner)
+            | ConditionExpression::Bracketed(ref inner) => {
+                for t in inner.referred_tables() {
+                    if !tables.contains(&t) {
+                        tables.push(t);
+                    }
+                }
+            }
+            ConditionExpression::Base(_) | ConditionExpression::Arithmetic(_) => (),
         }
         tables
     }
@@ -154,6 +154,19 @@ impl Finkelstein {
                         _ => return None,
                     }
                 }
+                QueryGraphEdge::FullJoin(_) => {
+                    match *new_qge {
+                        QueryGraphEdge::FullJoin(_) => {}
+                        // If there is no matching FullJoin edge, we cannot reuse
+                        _ => return None,
+                    }
+                }
+                QueryGraphEdge::SemiJoin(_) | QueryGraphEdge::AntiJoin(_) => {
+                    // semi-joins filter the rows of their left side, so they must match exactly
+                    if new_qge != ex_qge {
+                        return None;
+                    }
+                }
             }
         }
 
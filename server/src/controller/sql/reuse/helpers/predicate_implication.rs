@@ -1,3 +1,4 @@
+use dataflow::ops::filter::like;
 use nom_sql::ConditionExpression::*;
 use nom_sql::{ConditionBase, ConditionExpression, ConditionTree, Literal, Operator};
 
@@ -135,7 +136,53 @@ pub fn complex_predicate_implies(np: &ConditionExpression, ep: &ConditionExpress
     }
 }
 
+/// The literal text that all strings matching a `LIKE` pattern start with.
+fn like_prefix(pattern: &str) -> &str {
+    let end = pattern
+        .find(|c| c == '%' || c == '_' || c == '\\')
+        .unwrap_or_else(|| pattern.len());
+    &pattern[..end]
+}
+
+/// Whether every string that matches the `LIKE` pattern `p1` also matches `p2`.
+fn like_pattern_implies(p1: &str, p2: &str) -> bool {
+    if p1 == p2 {
+        return true;
+    }
+    if !p2.ends_with('%') {
+        return false;
+    }
+
+    // p2 is of the form 'prefix%', and p1 only matches strings that start with that prefix
+    let prefix = p2.trim_end_matches('%');
+    like_prefix(prefix) == prefix && like_prefix(p1).starts_with(prefix)
+}
+
+fn pattern_predicate_implies(np: &ConditionTree, ep: &ConditionTree) -> bool {
+    let (nv, ev) = match (&*np.right, &*ep.right) {
+        (
+            ConditionExpression::Base(ConditionBase::Literal(Literal::String(ref nv))),
+            ConditionExpression::Base(ConditionBase::Literal(Literal::String(ref ev))),
+        ) => (nv, ev),
+        _ => return false,
+    };
+
+    match (&np.operator, &ep.operator) {
+        (Operator::Equal, Operator::Like) => like(nv, ev),
+        (Operator::Equal, Operator::NotLike) => !like(nv, ev),
+        (Operator::Like, Operator::Like) => like_pattern_implies(nv, ev),
+        // everything that p1 excludes is also excluded by p2 if p2 matches a subset of p1
+        (Operator::NotLike, Operator::NotLike) => like_pattern_implies(ev, nv),
+        _ => false,
+    }
+}
+
 fn predicate_implies(np: &ConditionTree, ep: &ConditionTree) -> bool {
+    let is_pattern = |op: &Operator| *op == Operator::Like || *op == Operator::NotLike;
+    if is_pattern(&np.operator) || is_pattern(&ep.operator) {
+        return pattern_predicate_implies(np, ep);
+    }
+
     // use Finkelstein-style direct elimination to check if this NQG predicate
     // implies the corresponding predicates in the EQG
     match *np.right {
@@ -199,6 +246,78 @@ mod tests {
         assert!(predicate_implies(&pc, &pa));
     }
 
+    #[test]
+    fn pattern_predicate_implication() {
+        use nom_sql::ConditionBase::*;
+        use nom_sql::ConditionExpression::*;
+        use nom_sql::Literal;
+
+        let p = |operator, s: &str| ConditionTree {
+            operator,
+            left: Box::new(Base(Field(Column::from("a")))),
+            right: Box::new(Base(Literal(Literal::String(s.into())))),
+        };
+
+        assert!(predicate_implies(
+            &p(Operator::Like, "ab%"),
+            &p(Operator::Like, "ab%")
+        ));
+        assert!(predicate_implies(
+            &p(Operator::Like, "abc%"),
+            &p(Operator::Like, "ab%")
+        ));
+        assert!(predicate_implies(
+            &p(Operator::Like, "ab_d"),
+            &p(Operator::Like, "ab%")
+        ));
+        assert!(!predicate_implies(
+            &p(Operator::Like, "ab%"),
+            &p(Operator::Like, "abc%")
+        ));
+        assert!(!predicate_implies(
+            &p(Operator::Like, "a%c"),
+            &p(Operator::Like, "ab%")
+        ));
+        assert!(!predicate_implies(
+            &p(Operator::Like, "abc%"),
+            &p(Operator::Like, "a_%")
+        ));
+        assert!(predicate_implies(
+            &p(Operator::Like, "abc"),
+            &p(Operator::Like, "%")
+        ));
+
+        assert!(predicate_implies(
+            &p(Operator::Equal, "abc"),
+            &p(Operator::Like, "a_c")
+        ));
+        assert!(!predicate_implies(
+            &p(Operator::Equal, "abc"),
+            &p(Operator::Like, "b%")
+        ));
+        assert!(predicate_implies(
+            &p(Operator::Equal, "abc"),
+            &p(Operator::NotLike, "b%")
+        ));
+        assert!(!predicate_implies(
+            &p(Operator::Like, "a_c"),
+            &p(Operator::Equal, "abc")
+        ));
+
+        assert!(predicate_implies(
+            &p(Operator::NotLike, "ab%"),
+            &p(Operator::NotLike, "abc%")
+        ));
+        assert!(!predicate_implies(
+            &p(Operator::NotLike, "abc%"),
+            &p(Operator::NotLike, "ab%")
+        ));
+        assert!(!predicate_implies(
+            &p(Operator::Like, "ab%"),
+            &p(Operator::NotLike, "ab%")
+        ));
+    }
+
     #[test]
     fn complex_predicate_implication_or() {
         use nom_sql::ConditionBase::*;
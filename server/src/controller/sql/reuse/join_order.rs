@@ -116,7 +116,11 @@ fn chains_to_order(chains: Vec<JoinChain>, order: &mut Vec<JoinRef>) {
 
 fn from_join_ref<'a>(jref: &JoinRef, qg: &'a QueryGraph) -> &'a ConditionTree {
     match qg.edges[&(jref.src.clone(), jref.dst.clone())] {
-        QueryGraphEdge::Join(ref jps) | QueryGraphEdge::LeftJoin(ref jps) => &jps[jref.index],
+        QueryGraphEdge::Join(ref jps)
+        | QueryGraphEdge::LeftJoin(ref jps)
+        | QueryGraphEdge::FullJoin(ref jps)
+        | QueryGraphEdge::SemiJoin(ref jps)
+        | QueryGraphEdge::AntiJoin(ref jps) => &jps[jref.index],
         QueryGraphEdge::GroupBy(_) => unreachable!(),
     }
 }
@@ -125,6 +129,11 @@ pub(super) fn reorder_joins(
     qg: &mut QueryGraph,
     reuse_candidates: &[(ReuseType, (u64, &QueryGraph))],
 ) {
+    // the join order of queries with outer joins stays as the query gives it
+    if qg.edges.values().any(QueryGraphEdge::is_outer_join) {
+        return;
+    }
+
     let mut join_chains = Vec::new();
     // For each reuse candidate, let's find the common join
     // chains it has with the new query graph.
@@ -93,6 +93,23 @@ impl Relaxed {
                         _ => return None,
                     }
                 }
+                QueryGraphEdge::FullJoin(_) => {
+                    if !new_qg.edges.contains_key(srcdst) {
+                        return None;
+                    }
+                    let new_qge = &new_qg.edges[srcdst];
+                    match *new_qge {
+                        QueryGraphEdge::FullJoin(_) => {}
+                        // If there is no matching FullJoin edge, we cannot reuse
+                        _ => return None,
+                    }
+                }
+                QueryGraphEdge::SemiJoin(_) | QueryGraphEdge::AntiJoin(_) => {
+                    // semi-joins filter the rows of their left side, so they must match exactly
+                    if new_qg.edges.get(srcdst) != Some(ex_qge) {
+                        return None;
+                    }
+                }
                 _ => continue,
             }
         }
@@ -153,6 +153,86 @@ async fn it_works_basic() {
     //assert_eq!(cq.lookup(&[id.clone()], true).await, Ok(vec![vec![1.into(), 6.into()]]));
 }
 
+#[tokio::test(threaded_scheduler)]
+async fn range_lookups() {
+    use std::ops::Bound::*;
+
+    let mut g = start_simple("range_lookups").await;
+    g.migrate(|mig| {
+        let a = mig.add_base("a", &["a", "b"], Base::new(vec![]).with_key(vec![0]));
+        let mut emits = HashMap::new();
+        emits.insert(a, vec![0, 1]);
+        let u = Union::new(emits.clone());
+        let ordered = mig.add_ingredient("ordered", &["a", "b"], u);
+        mig.maintain_ordered("ordered".to_string(), ordered, &[0]);
+        let u = Union::new(emits);
+        let plain = mig.add_ingredient("plain", &["a", "b"], u);
+        mig.maintain("plain".to_string(), plain, &[0]);
+    })
+    .await;
+
+    let mut muta = g.table("a").await.unwrap();
+    for i in (0..10).rev() {
+        muta.insert(vec![i.into(), (i * 10).into()]).await.unwrap();
+    }
+    sleep().await;
+
+    let expected: Vec<Vec<DataType>> = (3..=5).map(|i| vec![i.into(), (i * 10).into()]).collect();
+    let key = |i: i32| vec![DataType::from(i)];
+    let mut v = g.view("ordered").await.unwrap();
+    let rs = v.lookup_range(key(3)..=key(5)).await;
+    assert_eq!(rs.unwrap(), expected);
+    let rs = v.lookup_range((Excluded(key(2)), Included(key(5)))).await;
+    assert_eq!(rs.unwrap(), expected);
+    let rs = v.lookup_range(key(8)..).await.unwrap();
+    assert_eq!(rs.len(), 2);
+
+    // partially materialized views that aren't ordered cannot answer range lookups
+    let mut v = g.view("plain").await.unwrap();
+    match v.lookup_range(key(3)..=key(5)).await {
+        Err(noria::error::ViewError::UnsupportedRange) => {}
+        r => panic!("expected an unsupported range, got {:?}", r),
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn partial_range_lookups() {
+    let mut g = start_simple_unsharded("partial_range_lookups").await;
+    g.migrate(|mig| {
+        let a = mig.add_base("a", &["a", "b"], Base::new(vec![]).with_key(vec![0]));
+        let mut emits = HashMap::new();
+        emits.insert(a, vec![0, 1]);
+        let u = Union::new(emits);
+        let ordered = mig.add_ingredient("ordered", &["a", "b"], u);
+        mig.maintain_ordered("ordered".to_string(), ordered, &[0]);
+    })
+    .await;
+
+    let mut muta = g.table("a").await.unwrap();
+    for i in (0..10).step_by(2) {
+        muta.insert(vec![i.into(), (i * 10).into()]).await.unwrap();
+    }
+    sleep().await;
+
+    let row = |i: i32| vec![DataType::from(i), DataType::from(i * 10)];
+    let key = |i: i32| vec![DataType::from(i)];
+    let mut v = g.view("ordered").await.unwrap();
+    let rs = v.lookup_range(key(3)..=key(7)).await.unwrap();
+    assert_eq!(rs, vec![row(4), row(6)]);
+
+    // the range is filled now, so writes to keys in it show up
+    muta.insert(vec![5.into(), 50.into()]).await.unwrap();
+    sleep().await;
+    let rs = v.lookup_range(key(3)..=key(7)).await.unwrap();
+    assert_eq!(rs, vec![row(4), row(5), row(6)]);
+
+    // ranges that overlap the filled one only replay what is missing
+    let rs = v.lookup_range(key(5)..).await.unwrap();
+    assert_eq!(rs, vec![row(5), row(6), row(8)]);
+    let rs = v.lookup(&key(2), true).await.unwrap();
+    assert_eq!(rs, vec![row(2)]);
+}
+
 #[tokio::test(threaded_scheduler)]
 async fn it_completes() {
     let mut builder = Builder::default();
@@ -1259,6 +1339,135 @@ async fn it_works_with_multiple_arithmetic_expressions() {
     assert_eq!(result[0][3], 1230.into());
 }
 
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_non_equality_parameters() {
+    let mut g = start_simple("it_works_with_non_equality_parameters").await;
+    let sql = "CREATE TABLE Car (id int, make varchar(40), price int, PRIMARY KEY(id));
+               QUERY Cheaper: SELECT id FROM Car WHERE make = ? AND price < ?;
+               QUERY Pricier: SELECT id FROM Car WHERE price > ? AND make = ?;
+               QUERY Makes: SELECT id FROM Car WHERE make LIKE ?;
+               QUERY Prices: SELECT id FROM Car WHERE price IN (?, ?);
+               QUERY PriceRange: SELECT id FROM Car WHERE price >= ? AND price < ?;
+               ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Car").await.unwrap();
+    mutator
+        .insert(vec![1.into(), "volvo".into(), 100.into()])
+        .await
+        .unwrap();
+    mutator
+        .insert(vec![2.into(), "volvo".into(), 200.into()])
+        .await
+        .unwrap();
+    mutator
+        .insert(vec![3.into(), "vw".into(), 50.into()])
+        .await
+        .unwrap();
+
+    // Let writes propagate:
+    sleep().await;
+
+    // the filter parameters follow the equality parameters in the lookup key
+    let mut getter = g.view("Cheaper").await.unwrap();
+    let result = getter
+        .lookup(&["volvo".into(), 150.into()], true)
+        .await
+        .unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][0], 1.into());
+
+    // parameter values are given in the order their placeholders appear in the query
+    let mut getter = g.view("Pricier").await.unwrap();
+    let result = getter
+        .lookup(&[150.into(), "volvo".into()], true)
+        .await
+        .unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][0], 2.into());
+    match getter.lookup(&["volvo".into()], true).await {
+        Err(noria::error::ViewError::WrongArity {
+            expected: 2,
+            got: 1,
+        }) => {}
+        r => panic!("{:?}", r),
+    }
+
+    // without equality parameters, the lookup starts with the bogokey
+    let mut getter = g.view("Makes").await.unwrap();
+    let mut result = getter
+        .lookup(&[0.into(), "v%".into()], true)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| r[0].clone())
+        .collect::<Vec<DataType>>();
+    result.sort();
+    assert_eq!(result, vec![1.into(), 2.into(), 3.into()]);
+    let result = getter.lookup(&[0.into(), "v_".into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][0], 3.into());
+
+    let mut getter = g.view("Prices").await.unwrap();
+    let mut result = getter
+        .lookup(&[0.into(), 50.into(), 200.into()], true)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| r[0].clone())
+        .collect::<Vec<DataType>>();
+    result.sort();
+    assert_eq!(result, vec![2.into(), 3.into()]);
+
+    // range parameters are answered from a reader ordered by the compared column
+    let mut getter = g.view("PriceRange").await.unwrap();
+    let result = getter
+        .lookup(&[0.into(), 50.into(), 200.into()], true)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| r[0].clone())
+        .collect::<Vec<DataType>>();
+    assert_eq!(result, vec![3.into(), 1.into()]);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_orders_and_pages_results() {
+    let mut g = start_simple("it_orders_and_pages_results").await;
+    let sql = "CREATE TABLE Car (id int, make varchar(40), price int, PRIMARY KEY(id));
+               QUERY ByPrice: SELECT id, price FROM Car WHERE make = ? ORDER BY price DESC;
+               ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Car").await.unwrap();
+    for (id, price) in &[(1, 200), (2, 100), (3, 300), (4, 150)] {
+        mutator
+            .insert(vec![(*id).into(), "volvo".into(), (*price).into()])
+            .await
+            .unwrap();
+    }
+
+    // Let writes propagate:
+    sleep().await;
+
+    let mut getter = g.view("ByPrice").await.unwrap();
+    let ids = |rs: noria::results::Results| -> Vec<DataType> {
+        rs.into_iter().map(|r| r[0].clone()).collect()
+    };
+    let result = getter.lookup(&["volvo".into()], true).await.unwrap();
+    assert_eq!(ids(result), vec![3.into(), 1.into(), 4.into(), 2.into()]);
+    let result = getter
+        .lookup_page(&["volvo".into()], 1, 2, true)
+        .await
+        .unwrap();
+    assert_eq!(ids(result), vec![1.into(), 4.into()]);
+    let result = getter
+        .lookup_page(&["volvo".into()], 3, 2, true)
+        .await
+        .unwrap();
+    assert_eq!(ids(result), vec![2.into()]);
+}
+
 #[tokio::test(threaded_scheduler)]
 async fn it_works_with_join_arithmetic() {
     let mut g = start_simple("it_works_with_join_arithmetic").await;
@@ -1303,142 +1512,1087 @@ async fn it_works_with_join_arithmetic() {
 }
 
 #[tokio::test(threaded_scheduler)]
-async fn it_works_with_function_arithmetic() {
-    let mut g = start_simple("it_works_with_function_arithmetic").await;
+async fn it_works_with_multi_column_joins() {
+    let mut g = start_simple("it_works_with_multi_column_joins").await;
     let sql = "
-        CREATE TABLE Bread (id int, price int, PRIMARY KEY(id));
-        QUERY Price: SELECT 2 * MAX(price) FROM Bread;
+        CREATE TABLE Stock (store int, item int, qty int);
+        CREATE TABLE Price (store int, item int, price int);
+        QUERY ItemStock: SELECT Stock.store, qty, price FROM Stock \
+                  JOIN Price ON Stock.store = Price.store AND Stock.item = Price.item \
+                  WHERE Stock.item = ?;
     ";
     g.install_recipe(sql).await.unwrap();
 
-    let mut mutator = g.table("Bread").await.unwrap();
-    let mut getter = g.view("Price").await.unwrap();
-    let max_price = 20;
-    for (i, price) in (10..=max_price).enumerate() {
-        let id = i + 1;
-        mutator.insert(vec![id.into(), price.into()]).await.unwrap();
-    }
+    let mut stock_mutator = g.table("Stock").await.unwrap();
+    let mut price_mutator = g.table("Price").await.unwrap();
+    let mut getter = g.view("ItemStock").await.unwrap();
+    stock_mutator
+        .insert(vec![1.into(), 10.into(), 5.into()])
+        .await
+        .unwrap();
+    stock_mutator
+        .insert(vec![2.into(), 10.into(), 7.into()])
+        .await
+        .unwrap();
+    price_mutator
+        .insert(vec![1.into(), 10.into(), 100.into()])
+        .await
+        .unwrap();
+    price_mutator
+        .insert(vec![2.into(), 20.into(), 200.into()])
+        .await
+        .unwrap();
 
     // Let writes propagate:
     sleep().await;
 
-    let result = getter.lookup(&[0.into()], true).await.unwrap();
+    // only store 1 has a price for item 10
+    let result = getter.lookup(&[10.into()], true).await.unwrap();
     assert_eq!(result.len(), 1);
-    assert_eq!(result[0][0], DataType::from(max_price * 2));
+    assert_eq!(result[0][0], 1.into());
+    assert_eq!(result[0][1], 5.into());
+    assert_eq!(result[0][2], 100.into());
+
+    price_mutator
+        .insert(vec![2.into(), 10.into(), 150.into()])
+        .await
+        .unwrap();
+    sleep().await;
+
+    let mut result: Vec<Vec<DataType>> = getter.lookup(&[10.into()], true).await.unwrap().into();
+    result.sort();
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[1][0], 2.into());
+    assert_eq!(result[1][1], 7.into());
+    assert_eq!(result[1][2], 150.into());
 }
 
 #[tokio::test(threaded_scheduler)]
-async fn votes() {
-    // set up graph
-    let mut g = start_simple("votes").await;
-    let _ = g
-        .migrate(|mig| {
-            // add article base nodes (we use two so we can exercise unions too)
-            let article1 = mig.add_base("article1", &["id", "title"], Base::default());
-            let article2 = mig.add_base("article2", &["id", "title"], Base::default());
+async fn it_works_with_theta_joins() {
+    let mut g = start_simple("it_works_with_theta_joins").await;
+    let sql = "
+        CREATE TABLE Events (id int, ts int);
+        CREATE TABLE Windows (name varchar(255), lo int, hi int);
+        QUERY EventWindows: SELECT Events.id, Windows.name FROM Events, Windows \
+                  WHERE Events.ts >= Windows.lo AND Windows.hi >= Events.ts \
+                  AND Windows.name = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
 
-            // add a (stupid) union of article1 + article2
-            let mut emits = HashMap::new();
-            emits.insert(article1, vec![0, 1]);
-            emits.insert(article2, vec![0, 1]);
-            let u = Union::new(emits);
-            let article = mig.add_ingredient("article", &["id", "title"], u);
-            mig.maintain_anonymous(article, &[0]);
+    let mut events_mutator = g.table("Events").await.unwrap();
+    let mut windows_mutator = g.table("Windows").await.unwrap();
+    let mut getter = g.view("EventWindows").await.unwrap();
+    windows_mutator
+        .insert(vec!["a".into(), 0.into(), 10.into()])
+        .await
+        .unwrap();
+    windows_mutator
+        .insert(vec!["b".into(), 5.into(), 15.into()])
+        .await
+        .unwrap();
+    for (id, ts) in vec![(1, 3), (2, 7), (3, 15), (4, 20)] {
+        events_mutator
+            .insert(vec![id.into(), ts.into()])
+            .await
+            .unwrap();
+    }
 
-            // add vote base table
-            let vote = mig.add_base("vote", &["user", "id"], Base::default());
+    // Let writes propagate:
+    sleep().await;
 
-            // add vote count
-            let vc = mig.add_ingredient(
-                "vc",
-                &["id", "votes"],
-                Aggregation::COUNT.over(vote, 0, &[1]),
-            );
-            mig.maintain_anonymous(vc, &[0]);
+    let mut result: Vec<Vec<DataType>> = getter.lookup(&["a".into()], true).await.unwrap().into();
+    result.sort();
+    assert_eq!(
+        result,
+        vec![vec![1.into(), "a".into()], vec![2.into(), "a".into()]]
+    );
 
-            // add final join using first field from article and first from vc
-            let j = Join::new(article, vc, JoinType::Inner, vec![B(0, 0), L(1), R(1)]);
-            let end = mig.add_ingredient("end", &["id", "title", "votes"], j);
-            mig.maintain_anonymous(end, &[0]);
+    let mut result: Vec<Vec<DataType>> = getter.lookup(&["b".into()], true).await.unwrap().into();
+    result.sort();
+    assert_eq!(
+        result,
+        vec![vec![2.into(), "b".into()], vec![3.into(), "b".into()]]
+    );
 
-            (article1, article2, vote, article, vc, end)
-        })
-        .await;
+    // a new window picks up the events that fall into it
+    windows_mutator
+        .insert(vec!["c".into(), 12.into(), 30.into()])
+        .await
+        .unwrap();
+    sleep().await;
 
-    let mut articleq = g.view("article").await.unwrap();
-    let mut vcq = g.view("vc").await.unwrap();
-    let mut endq = g.view("end").await.unwrap();
+    let mut result: Vec<Vec<DataType>> = getter.lookup(&["c".into()], true).await.unwrap().into();
+    result.sort();
+    assert_eq!(
+        result,
+        vec![vec![3.into(), "c".into()], vec![4.into(), "c".into()]]
+    );
+}
 
-    let mut mut1 = g.table("article1").await.unwrap();
-    let mut mut2 = g.table("article2").await.unwrap();
-    let mut mutv = g.table("vote").await.unwrap();
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_correlated_subqueries() {
+    let mut g = start_simple("it_works_with_correlated_subqueries").await;
+    let sql = "
+        CREATE TABLE Article (id int, score int, PRIMARY KEY(id));
+        CREATE TABLE Vote (aid int, stars int);
+        QUERY AboveVotes: SELECT Article.id FROM Article WHERE Article.score > \
+                  (SELECT MAX(Vote.stars) FROM Vote WHERE Vote.aid = Article.id);
+    ";
+    g.install_recipe(sql).await.unwrap();
 
-    let a1: DataType = 1.into();
-    let a2: DataType = 2.into();
+    let mut article_mutator = g.table("Article").await.unwrap();
+    let mut vote_mutator = g.table("Vote").await.unwrap();
+    let mut getter = g.view("AboveVotes").await.unwrap();
+    for &(id, score) in &[(1, 5), (2, 3), (3, 10)] {
+        article_mutator
+            .insert(vec![id.into(), score.into()])
+            .await
+            .unwrap();
+    }
+    for &(aid, stars) in &[(1, 4), (1, 2), (2, 3)] {
+        vote_mutator
+            .insert(vec![aid.into(), stars.into()])
+            .await
+            .unwrap();
+    }
 
-    // make one article
-    mut1.insert(vec![a1.clone(), 2.into()]).await.unwrap();
+    // Let writes propagate:
+    sleep().await;
 
-    // give it some time to propagate
+    // article 3 has no votes, so the subquery has no value to compare with
+    let result = getter.lookup(&[0.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][0], 1.into());
+
+    vote_mutator.insert(vec![3.into(), 9.into()]).await.unwrap();
+    vote_mutator.insert(vec![1.into(), 6.into()]).await.unwrap();
     sleep().await;
 
-    // query articles to see that it was updated
-    assert_eq!(
-        articleq.lookup(&[a1.clone()], true).await.unwrap(),
-        vec![vec![a1.clone(), 2.into()]]
-    );
+    let result = getter.lookup(&[0.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][0], 3.into());
+}
 
-    // make another article
-    mut2.insert(vec![a2.clone(), 4.into()]).await.unwrap();
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_anti_joins() {
+    let mut g = start_simple("it_works_with_anti_joins").await;
+    let sql = "
+        CREATE TABLE Article (id int, title varchar(255), PRIMARY KEY(id));
+        CREATE TABLE Vote (id int, aid int, PRIMARY KEY(id));
+        QUERY NotIn: SELECT Article.id FROM Article \
+                  WHERE Article.id NOT IN (SELECT Vote.aid FROM Vote);
+        QUERY IsNull: SELECT Article.id FROM Article \
+                  LEFT JOIN Vote ON (Article.id = Vote.aid) WHERE Vote.aid IS NULL;
+        QUERY RightIsNull: SELECT Article.id FROM Vote \
+                  RIGHT JOIN Article ON (Article.id = Vote.aid) WHERE Vote.aid IS NULL;
+    ";
+    g.install_recipe(sql).await.unwrap();
 
-    // give it some time to propagate
+    let mut article_mutator = g.table("Article").await.unwrap();
+    let mut vote_mutator = g.table("Vote").await.unwrap();
+    let mut not_in = g.view("NotIn").await.unwrap();
+    let mut is_null = g.view("IsNull").await.unwrap();
+    let mut right_is_null = g.view("RightIsNull").await.unwrap();
+    for id in 1..=3 {
+        article_mutator
+            .insert(vec![id.into(), format!("Article #{}", id).into()])
+            .await
+            .unwrap();
+    }
+    vote_mutator.insert(vec![1.into(), 1.into()]).await.unwrap();
+    vote_mutator.insert(vec![2.into(), 1.into()]).await.unwrap();
+
+    // Let writes propagate:
     sleep().await;
 
-    // query articles again to see that the new article was absorbed
-    // and that the old one is still present
-    assert_eq!(
-        articleq.lookup(&[a1.clone()], true).await.unwrap(),
-        vec![vec![a1.clone(), 2.into()]]
-    );
-    assert_eq!(
-        articleq.lookup(&[a2.clone()], true).await.unwrap(),
-        vec![vec![a2.clone(), 4.into()]]
-    );
+    for getter in &mut [&mut not_in, &mut is_null, &mut right_is_null] {
+        let mut result: Vec<Vec<DataType>> = getter.lookup(&[0.into()], true).await.unwrap().into();
+        result.sort();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0][0], 2.into());
+        assert_eq!(result[1][0], 3.into());
+    }
 
-    // create a vote (user 1 votes for article 1)
-    mutv.insert(vec![1.into(), a1.clone()]).await.unwrap();
+    // a vote for an article retracts it, and removing all of its votes brings it back
+    vote_mutator.insert(vec![3.into(), 2.into()]).await.unwrap();
+    vote_mutator.delete(vec![1.into()]).await.unwrap();
+    vote_mutator.delete(vec![2.into()]).await.unwrap();
+    sleep().await;
 
-    // give it some time to propagate
+    for getter in &mut [&mut not_in, &mut is_null, &mut right_is_null] {
+        let mut result: Vec<Vec<DataType>> = getter.lookup(&[0.into()], true).await.unwrap().into();
+        result.sort();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0][0], 1.into());
+        assert_eq!(result[1][0], 3.into());
+    }
+
+    // with a NULL among the subquery's results, no article is known not to be among them
+    vote_mutator
+        .insert(vec![4.into(), DataType::None])
+        .await
+        .unwrap();
     sleep().await;
 
-    // query vote count to see that the count was updated
-    let res = vcq.lookup(&[a1.clone()], true).await.unwrap();
-    assert!(res.iter().all(|r| r[0] == a1.clone() && r[1] == 1.into()));
-    assert_eq!(res.len(), 1);
+    let result: Vec<Vec<DataType>> = not_in.lookup(&[0.into()], true).await.unwrap().into();
+    assert!(result.is_empty());
+    for getter in &mut [&mut is_null, &mut right_is_null] {
+        let result = getter.lookup(&[0.into()], true).await.unwrap();
+        assert_eq!(result.len(), 2);
+    }
 
-    // check that article 1 appears in the join view with a vote count of one
-    let res = endq.lookup(&[a1.clone()], true).await.unwrap();
-    assert!(
-        res.iter()
-            .any(|r| r[0] == a1.clone() && r[1] == 2.into() && r[2] == 1.into()),
-        "no entry for [1,2,1|2] in {:?}",
-        res
-    );
-    assert_eq!(res.len(), 1);
+    vote_mutator.delete(vec![4.into()]).await.unwrap();
+    sleep().await;
 
-    // check that article 2 doesn't have any votes
-    let res = endq.lookup(&[a2.clone()], true).await.unwrap();
-    assert!(res.len() <= 1) // could be 1 if we had zero-rows
+    let result = not_in.lookup(&[0.into()], true).await.unwrap();
+    assert_eq!(result.len(), 2);
 }
 
 #[tokio::test(threaded_scheduler)]
-async fn empty_migration() {
-    // set up graph
-    let mut g = start_simple("empty_migration").await;
-    g.migrate(|_| {}).await;
+async fn it_works_with_right_and_full_joins() {
+    let mut g = start_simple("it_works_with_right_and_full_joins").await;
+    let sql = "
+        CREATE TABLE Article (id int, title varchar(255), PRIMARY KEY(id));
+        CREATE TABLE Vote (id int, aid int, PRIMARY KEY(id));
+        QUERY RightJoined: SELECT Article.id, Vote.id AS vote FROM Vote \
+                  RIGHT JOIN Article ON (Article.id = Vote.aid);
+        QUERY FullJoined: SELECT Article.id, Vote.id AS vote FROM Article \
+                  FULL OUTER JOIN Vote ON (Article.id = Vote.aid);
+    ";
+    g.install_recipe(sql).await.unwrap();
 
-    let _ = g
-        .migrate(|mig| {
+    let mut article_mutator = g.table("Article").await.unwrap();
+    let mut vote_mutator = g.table("Vote").await.unwrap();
+    let mut right = g.view("RightJoined").await.unwrap();
+    let mut full = g.view("FullJoined").await.unwrap();
+    for id in 1..=2 {
+        article_mutator
+            .insert(vec![id.into(), format!("Article #{}", id).into()])
+            .await
+            .unwrap();
+    }
+    vote_mutator.insert(vec![1.into(), 1.into()]).await.unwrap();
+    vote_mutator.insert(vec![2.into(), 3.into()]).await.unwrap();
+    sleep().await;
+
+    let result: Vec<Vec<DataType>> = right.lookup(&[0.into()], true).await.unwrap().into();
+    assert_eq!(result.len(), 2);
+    assert!(result.contains(&vec![1.into(), 1.into()]));
+    assert!(result.contains(&vec![2.into(), DataType::None]));
+
+    let result: Vec<Vec<DataType>> = full.lookup(&[0.into()], true).await.unwrap().into();
+    assert_eq!(result.len(), 3);
+    assert!(result.contains(&vec![1.into(), 1.into()]));
+    assert!(result.contains(&vec![2.into(), DataType::None]));
+    assert!(result.contains(&vec![DataType::None, 2.into()]));
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_like_filters() {
+    let mut g = start_simple("it_works_with_like_filters").await;
+    let sql = "
+        CREATE TABLE Article (id int, title varchar(255), PRIMARY KEY(id));
+        QUERY Like: SELECT Article.id FROM Article WHERE Article.title LIKE 'Noria%';
+        QUERY NotLike: SELECT Article.id FROM Article WHERE NOT (Article.title LIKE '%_#_');
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Article").await.unwrap();
+    let mut like = g.view("Like").await.unwrap();
+    let mut not_like = g.view("NotLike").await.unwrap();
+    for (id, title) in &[
+        (1, "Noria #1"),
+        (2, "Noria update"),
+        (3, "noria #3"),
+        (4, "Soup #4"),
+    ] {
+        mutator
+            .insert(vec![(*id).into(), (*title).into()])
+            .await
+            .unwrap();
+    }
+    mutator
+        .insert(vec![5.into(), DataType::None])
+        .await
+        .unwrap();
+
+    // Let writes propagate:
+    sleep().await;
+
+    let mut result: Vec<Vec<DataType>> = like.lookup(&[0.into()], true).await.unwrap().into();
+    result.sort();
+    assert_eq!(result, vec![vec![1.into()], vec![2.into()]]);
+
+    // NULL titles match neither the pattern nor its negation
+    let result: Vec<Vec<DataType>> = not_like.lookup(&[0.into()], true).await.unwrap().into();
+    assert_eq!(result, vec![vec![2.into()]]);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_disjunctive_filters() {
+    let mut g = start_simple("it_works_with_disjunctive_filters").await;
+    let sql = "
+        CREATE TABLE Article (id int, votes int, PRIMARY KEY(id));
+        QUERY Either: SELECT Article.id FROM Article \
+                  WHERE Article.id = 1 OR (Article.votes > 10 AND NOT (Article.id = 4));
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Article").await.unwrap();
+    let mut getter = g.view("Either").await.unwrap();
+    for (id, votes) in &[(1, 20), (2, 5), (3, 15), (4, 25)] {
+        mutator
+            .insert(vec![(*id).into(), (*votes).into()])
+            .await
+            .unwrap();
+    }
+    mutator
+        .insert(vec![5.into(), DataType::None])
+        .await
+        .unwrap();
+
+    // Let writes propagate:
+    sleep().await;
+
+    // article 1 matches both disjuncts, but must only show up once
+    let mut result: Vec<Vec<DataType>> = getter.lookup(&[0.into()], true).await.unwrap().into();
+    result.sort();
+    assert_eq!(result, vec![vec![1.into()], vec![3.into()]]);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_in_subqueries() {
+    let mut g = start_simple("it_works_with_in_subqueries").await;
+    let sql = "
+        CREATE TABLE Article (id int, title varchar(255), PRIMARY KEY(id));
+        CREATE TABLE Vote (id int, aid int, PRIMARY KEY(id));
+        QUERY Voted: SELECT Article.id FROM Article \
+                  WHERE Article.id IN (SELECT Vote.aid FROM Vote);
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut article_mutator = g.table("Article").await.unwrap();
+    let mut vote_mutator = g.table("Vote").await.unwrap();
+    let mut getter = g.view("Voted").await.unwrap();
+    for id in 1..=2 {
+        article_mutator
+            .insert(vec![id.into(), format!("Article #{}", id).into()])
+            .await
+            .unwrap();
+    }
+    vote_mutator.insert(vec![1.into(), 1.into()]).await.unwrap();
+    vote_mutator.insert(vec![2.into(), 1.into()]).await.unwrap();
+
+    // Let writes propagate:
+    sleep().await;
+
+    // each article is returned once, however many votes it has
+    let result = getter.lookup(&[0.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][0], 1.into());
+
+    vote_mutator.delete(vec![1.into()]).await.unwrap();
+    vote_mutator.delete(vec![2.into()]).await.unwrap();
+    vote_mutator.insert(vec![3.into(), 2.into()]).await.unwrap();
+    sleep().await;
+
+    let result = getter.lookup(&[0.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][0], 2.into());
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_avg() {
+    let mut g = start_simple("it_works_with_avg").await;
+    let sql = "
+        CREATE TABLE Rating (item int, stars int);
+        QUERY AvgRating: SELECT item, AVG(stars) AS avg_stars FROM Rating \
+                  WHERE item = ? GROUP BY item;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Rating").await.unwrap();
+    let mut getter = g.view("AvgRating").await.unwrap();
+    for &(item, stars) in &[(1, 4), (1, 5), (2, 3)] {
+        mutator
+            .insert(vec![item.into(), stars.into()])
+            .await
+            .unwrap();
+    }
+
+    // Let writes propagate:
+    sleep().await;
+
+    let result = getter.lookup(&[1.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][1], 4.5.into());
+
+    mutator.insert(vec![1.into(), 3.into()]).await.unwrap();
+    sleep().await;
+
+    let result = getter.lookup(&[1.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][1], 4.0.into());
+
+    let result = getter.lookup(&[2.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][1], 3.0.into());
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_having() {
+    let mut g = start_simple("it_works_with_having").await;
+    let sql = "
+        CREATE TABLE Vote (article int, user int);
+        QUERY Popular: SELECT article, COUNT(user) AS votes FROM Vote \
+                  GROUP BY article HAVING votes > 1;
+        QUERY AtLeast: SELECT article FROM Vote GROUP BY article HAVING COUNT(*) >= ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Vote").await.unwrap();
+    for &(article, user) in &[(1, 1), (1, 2), (1, 3), (2, 1), (2, 2), (3, 1)] {
+        mutator
+            .insert(vec![article.into(), user.into()])
+            .await
+            .unwrap();
+    }
+
+    // Let writes propagate:
+    sleep().await;
+
+    let mut getter = g.view("Popular").await.unwrap();
+    let mut result: Vec<Vec<DataType>> = getter.lookup(&[0.into()], true).await.unwrap().into();
+    result.sort();
+    assert_eq!(result.len(), 2);
+    assert_eq!(result[0][0], 1.into());
+    assert_eq!(result[0][1], 3.into());
+    assert_eq!(result[1][0], 2.into());
+    assert_eq!(result[1][1], 2.into());
+
+    // the HAVING parameter follows the bogokey, and filters on the aggregated rows
+    let mut getter = g.view("AtLeast").await.unwrap();
+    let result = getter.lookup(&[0.into(), 3.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][0], 1.into());
+
+    // another vote makes article 3 popular too
+    mutator.insert(vec![3.into(), 2.into()]).await.unwrap();
+    sleep().await;
+
+    let mut getter = g.view("Popular").await.unwrap();
+    let result = getter.lookup(&[0.into()], true).await.unwrap();
+    assert_eq!(result.len(), 3);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_decimals() {
+    let mut g = start_simple("it_works_with_decimals").await;
+    let sql = "
+        CREATE TABLE Item (id int, cart int, price decimal(10,2), PRIMARY KEY(id));
+        QUERY Price: SELECT price FROM Item WHERE id = ?;
+        QUERY CartTotal: SELECT cart, SUM(price) AS total FROM Item WHERE cart = ? GROUP BY cart;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    // prices are stored at the column's scale, whatever type they are given as
+    let mut mutator = g.table("Item").await.unwrap();
+    let prices: Vec<(i32, i32, DataType)> = vec![
+        (1, 1, "19.99".into()),
+        (2, 1, 0.1.into()),
+        (3, 1, DataType::Decimal(12345, 3)),
+        (4, 2, 5.into()),
+    ];
+    for (id, cart, price) in prices {
+        mutator
+            .insert(vec![id.into(), cart.into(), price])
+            .await
+            .unwrap();
+    }
+
+    // Let writes propagate:
+    sleep().await;
+
+    let mut getter = g.view("Price").await.unwrap();
+    let result = getter.lookup(&[3.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][0], DataType::Decimal(1235, 2));
+
+    // and add up exactly
+    let mut getter = g.view("CartTotal").await.unwrap();
+    let result = getter.lookup(&[1.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][1], DataType::Decimal(3244, 2));
+    assert_eq!(result[0][1].to_string(), "32.44");
+    let result = getter.lookup(&[2.into()], true).await.unwrap();
+    assert_eq!(result[0][1].to_string(), "5.00");
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_checks_writes_against_schema() {
+    use noria::error::TableError;
+
+    let mut g = start_simple("it_checks_writes_against_schema").await;
+    let sql = "
+        CREATE TABLE Person (id int, name varchar(8) NOT NULL, born date, PRIMARY KEY(id));
+        QUERY PersonById: SELECT id, name, born FROM Person WHERE id = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Person").await.unwrap();
+    mutator
+        .insert(vec![1.into(), "alice".into(), "1990-06-01".into()])
+        .await
+        .unwrap();
+
+    // values that don't fit their column are rejected
+    let bad_rows: Vec<(Vec<DataType>, &str)> = vec![
+        (vec!["one".into(), "bob".into(), DataType::None], "id"),
+        (vec![2.into(), "bartholomew".into(), DataType::None], "name"),
+        (vec![2.into(), DataType::None, DataType::None], "name"),
+        (vec![2.into(), "bob".into(), "yesterday".into()], "born"),
+    ];
+    for (row, bad_column) in bad_rows {
+        match mutator.insert(row).await {
+            Err(TableError::InvalidValue { column, .. }) => assert_eq!(column, bad_column),
+            r => panic!("expected an invalid {}, got {:?}", bad_column, r),
+        }
+    }
+    match mutator
+        .update(vec![1.into()], vec![(1, DataType::None.into())])
+        .await
+    {
+        Err(TableError::InvalidValue { column, .. }) => assert_eq!(column, "name"),
+        r => panic!("expected an invalid name, got {:?}", r),
+    }
+
+    // Let writes propagate:
+    sleep().await;
+
+    let mut getter = g.view("PersonById").await.unwrap();
+    let result = getter.lookup(&[1.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][1], "alice".into());
+    assert_eq!(result[0][2].to_string(), "1990-06-01");
+    assert!(getter.lookup(&[2.into()], true).await.unwrap().is_empty());
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_enforces_unique_keys() {
+    use noria::error::TableError;
+
+    let mut g = start_simple("it_enforces_unique_keys").await;
+    let sql = "
+        CREATE TABLE Account (id int, email varchar(32) UNIQUE, name varchar(32), PRIMARY KEY(id));
+        QUERY AccountById: SELECT id, email, name FROM Account WHERE id = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Account").await.unwrap();
+    mutator
+        .insert(vec![1.into(), "alice@example.com".into(), "alice".into()])
+        .await
+        .unwrap();
+
+    match mutator
+        .insert(vec![1.into(), "bob@example.com".into(), "bob".into()])
+        .await
+    {
+        Err(TableError::DuplicateKey { columns, key }) => {
+            assert_eq!(columns, vec!["id"]);
+            assert_eq!(key, vec![1.into()]);
+        }
+        r => panic!("expected a duplicate id, got {:?}", r),
+    }
+    match mutator
+        .insert(vec![2.into(), "alice@example.com".into(), "bob".into()])
+        .await
+    {
+        Err(TableError::DuplicateKey { columns, key }) => {
+            assert_eq!(columns, vec!["email"]);
+            assert_eq!(key, vec!["alice@example.com".into()]);
+        }
+        r => panic!("expected a duplicate email, got {:?}", r),
+    }
+
+    // ignored duplicates are silently dropped
+    mutator
+        .insert_ignore(vec![1.into(), "carol@example.com".into(), "carol".into()])
+        .await
+        .unwrap();
+    // NULLs never collide
+    mutator
+        .insert(vec![3.into(), DataType::None, "dave".into()])
+        .await
+        .unwrap();
+    mutator
+        .insert(vec![4.into(), DataType::None, "erin".into()])
+        .await
+        .unwrap();
+
+    // Let writes propagate:
+    sleep().await;
+
+    let mut getter = g.view("AccountById").await.unwrap();
+    let result = getter.lookup(&[1.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][2], "alice".into());
+    assert!(getter.lookup(&[2.into()], true).await.unwrap().is_empty());
+    assert_eq!(getter.lookup(&[4.into()], true).await.unwrap().len(), 1);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_generates_column_values() {
+    let mut g = start_simple("it_generates_column_values").await;
+    let sql = "
+        CREATE TABLE Post (id int NOT NULL AUTO_INCREMENT, title varchar(32),
+                           created_at datetime DEFAULT CURRENT_TIMESTAMP, PRIMARY KEY(id));
+        QUERY PostById: SELECT id, title, created_at FROM Post WHERE id = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Post").await.unwrap();
+    let first = mutator
+        .insert(vec![DataType::None, "first".into(), DataType::None])
+        .await
+        .unwrap()
+        .expect("no id generated");
+    let second = mutator
+        .insert(vec![DataType::None, "second".into(), DataType::None])
+        .await
+        .unwrap()
+        .expect("no id generated");
+    assert_ne!(first, second);
+
+    // nothing is generated for explicitly given ids, and they are never handed out again
+    let explicit = mutator
+        .insert(vec![100.into(), "third".into(), DataType::None])
+        .await
+        .unwrap();
+    assert_eq!(explicit, None);
+    let fourth = mutator
+        .insert(vec![DataType::None, "fourth".into(), DataType::None])
+        .await
+        .unwrap()
+        .expect("no id generated");
+    assert!(fourth != first && fourth != second && fourth != 100.into());
+
+    // Let writes propagate:
+    sleep().await;
+
+    let mut getter = g.view("PostById").await.unwrap();
+    for (id, title) in vec![(first, "first"), (second, "second"), (fourth, "fourth")] {
+        let result = getter.lookup(&[id], true).await.unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0][1], title.into());
+        assert!(!result[0][2].is_none(), "created_at was not filled in");
+    }
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_alters_tables() {
+    let mut g = start_simple("it_alters_tables").await;
+    let sql = "
+        CREATE TABLE Profile (id int, name varchar(32), age int, PRIMARY KEY(id));
+        QUERY ProfileById: SELECT * FROM Profile WHERE id = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Profile").await.unwrap();
+    mutator
+        .insert(vec![1.into(), "alice".into(), 30.into()])
+        .await
+        .unwrap();
+
+    g.extend_recipe(
+        "ALTER TABLE Profile ADD COLUMN bio text, DROP COLUMN age, \
+         RENAME COLUMN name TO nickname;",
+    )
+    .await
+    .unwrap();
+
+    // tables handed out after the change use the new schema
+    let mut mutator = g.table("Profile").await.unwrap();
+    assert_eq!(mutator.columns(), &["id", "nickname", "bio"]);
+    let fields: Vec<_> = mutator
+        .schema()
+        .unwrap()
+        .fields
+        .iter()
+        .map(|cs| cs.column.name.clone())
+        .collect();
+    assert_eq!(fields, vec!["id", "nickname", "bio"]);
+    mutator
+        .insert(vec![2.into(), "bob".into(), "hello".into()])
+        .await
+        .unwrap();
+
+    // Let writes propagate:
+    sleep().await;
+
+    // the query selecting all columns picks up the new schema, and renamed columns keep their data
+    let mut getter = g.view("ProfileById").await.unwrap();
+    assert_eq!(getter.columns(), &["id", "nickname", "bio"]);
+    let result = getter.lookup(&[1.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][1], "alice".into());
+    assert_eq!(result[0][2], DataType::None);
+    let result = getter.lookup(&[2.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][1], "bob".into());
+    assert_eq!(result[0][2], "hello".into());
+
+    // changes that the table cannot support are rejected
+    assert!(g
+        .extend_recipe("ALTER TABLE Profile DROP COLUMN id;")
+        .await
+        .is_err());
+    assert!(g
+        .extend_recipe("ALTER TABLE Nope ADD COLUMN x int;")
+        .await
+        .is_err());
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_drops_views_and_tables() {
+    let mut g = start_simple("it_drops_views_and_tables").await;
+    let sql = "
+        CREATE TABLE Article (id int, title varchar(255), PRIMARY KEY(id));
+        CREATE TABLE Vote (aid int, uid int);
+        QUERY ArticleById: SELECT id, title FROM Article WHERE id = ?;
+        CREATE VIEW VoteCount AS SELECT aid, COUNT(uid) AS votes FROM Vote GROUP BY aid;
+        QUERY VotesByArticle: SELECT aid, votes FROM VoteCount WHERE aid = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Article").await.unwrap();
+    mutator.insert(vec![1.into(), "a".into()]).await.unwrap();
+    let mut mutator = g.table("Vote").await.unwrap();
+    mutator.insert(vec![1.into(), 1.into()]).await.unwrap();
+
+    // views and tables cannot be dropped while other queries use them
+    assert!(g.drop_view("VoteCount").await.is_err());
+    assert!(g.extend_recipe("DROP TABLE Vote;").await.is_err());
+    assert!(g.drop_view("Nope").await.is_err());
+
+    g.drop_view("VotesByArticle").await.unwrap();
+    assert!(g.view("VotesByArticle").await.is_err());
+    g.extend_recipe("DROP VIEW VoteCount;\nDROP TABLE Vote;")
+        .await
+        .unwrap();
+    assert!(g.view("VoteCount").await.is_err());
+    assert!(g.table("Vote").await.is_err());
+
+    // Let writes propagate:
+    sleep().await;
+
+    // queries that did not use the dropped views and tables are unaffected
+    let mut getter = g.view("ArticleById").await.unwrap();
+    let result = getter.lookup(&[1.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][1], "a".into());
+
+    // and dropped views can be defined again
+    g.extend_recipe(
+        "CREATE TABLE Vote (aid int, uid int);\n\
+         QUERY VotesByArticle: SELECT aid, COUNT(uid) AS votes FROM Vote \
+         WHERE aid = ? GROUP BY aid;",
+    )
+    .await
+    .unwrap();
+    let mut mutator = g.table("Vote").await.unwrap();
+    mutator.insert(vec![1.into(), 2.into()]).await.unwrap();
+
+    // Let writes propagate:
+    sleep().await;
+
+    let mut getter = g.view("VotesByArticle").await.unwrap();
+    let result = getter.lookup(&[1.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][1], 1.into());
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_rejects_unsupported_queries() {
+    let mut g = start_simple("it_rejects_unsupported_queries").await;
+    let sql = "
+        CREATE TABLE Article (id int, title varchar(255), PRIMARY KEY(id));
+        QUERY ArticleById: SELECT id, title FROM Article WHERE id = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Article").await.unwrap();
+    mutator.insert(vec![1.into(), "a".into()]).await.unwrap();
+
+    // a failing extension adds none of its queries
+    let e = g
+        .extend_recipe(
+            "QUERY Titles: SELECT title FROM Article;\n\
+             QUERY Bad: SELECT nope FROM Article WHERE id = ?;",
+        )
+        .await
+        .unwrap_err();
+    let e = e.downcast::<noria::error::RecipeError>().unwrap();
+    assert_eq!(e.query, Some(String::from("Bad")));
+    assert_eq!(e.position, Some(2));
+    assert!(g.view("Titles").await.is_err());
+    assert!(g.view("Bad").await.is_err());
+
+    let e = g.extend_recipe("QUERY Bad: SELEC id;").await.unwrap_err();
+    let e = e.downcast::<noria::error::RecipeError>().unwrap();
+    assert_eq!(e.query, Some(String::from("Bad")));
+
+    // the graph keeps working, and can still be extended
+    g.extend_recipe("QUERY Titles: SELECT title FROM Article;")
+        .await
+        .unwrap();
+    mutator.insert(vec![2.into(), "b".into()]).await.unwrap();
+
+    // Let writes propagate:
+    sleep().await;
+
+    let mut getter = g.view("ArticleById").await.unwrap();
+    let result = getter.lookup(&[2.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][1], "b".into());
+    let mut getter = g.view("Titles").await.unwrap();
+    let result = getter.lookup(&[0.into()], true).await.unwrap();
+    assert_eq!(result.len(), 2);
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_plans_recipe_extensions() {
+    let mut g = start_simple("it_plans_recipe_extensions").await;
+    let sql = "
+        CREATE TABLE Article (id int, title varchar(255), PRIMARY KEY(id));
+        QUERY ArticleById: SELECT id, title FROM Article WHERE id = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+    let mut mutator = g.table("Article").await.unwrap();
+    mutator.insert(vec![1.into(), "a".into()]).await.unwrap();
+    let base = g.inputs().await.unwrap()["Article"];
+
+    let extension = "QUERY ArticleByTitle: SELECT id, title FROM Article WHERE title = ?;";
+    let plan = g.plan_recipe(extension).await.unwrap();
+    let query = &plan.queries["ArticleByTitle"];
+    assert!(query.reused_nodes.contains(&base));
+    assert!(query.new_nodes > 0);
+    assert!(plan.new_domains > 0);
+    assert!(plan.removed_leaves.is_empty());
+    assert!(plan
+        .materializations
+        .iter()
+        .any(|s| s.existing.is_none() && s.indices.contains(&vec![1])));
+
+    // planning leaves the recipe and graph as they were
+    assert!(g.view("ArticleByTitle").await.is_err());
+    let bad = "QUERY Bad: SELECT nope FROM Article;";
+    let e = g.plan_recipe(bad).await.unwrap_err();
+    assert!(e.downcast::<noria::error::RecipeError>().is_ok());
+
+    g.extend_recipe(extension).await.unwrap();
+
+    // Let writes propagate:
+    sleep().await;
+
+    let mut getter = g.view("ArticleByTitle").await.unwrap();
+    let result = getter.lookup(&["a".into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][0], 1.into());
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_rolls_back_recipes() {
+    let mut g = start_simple("it_rolls_back_recipes").await;
+    let sql = "
+        CREATE TABLE Article (id int, title varchar(255), PRIMARY KEY(id));
+        QUERY ArticleById: SELECT id, title FROM Article WHERE id = ?;
+    ";
+    g.install_recipe(sql).await.unwrap();
+    let extension = "QUERY ArticleByTitle: SELECT id, title FROM Article WHERE title = ?;";
+    g.extend_recipe(extension).await.unwrap();
+
+    let history = g.recipe_history().await.unwrap();
+    assert_eq!(
+        history.iter().map(|r| r.version).collect::<Vec<_>>(),
+        vec![1, 2]
+    );
+    assert_eq!(
+        history[1].recipes,
+        vec![sql.to_owned(), extension.to_owned()]
+    );
+
+    let result = g.rollback_to(1).await.unwrap();
+    assert_eq!(result.expressions_removed, 1);
+    assert!(g.view("ArticleByTitle").await.is_err());
+
+    // the rollback is recorded as a new version with the earlier recipe
+    let history = g.recipe_history().await.unwrap();
+    assert_eq!(history.last().unwrap().version, 3);
+    assert_eq!(history.last().unwrap().recipes, vec![sql.to_owned()]);
+
+    let e = g.rollback_to(42).await.unwrap_err();
+    assert!(e.downcast::<noria::error::RecipeError>().is_ok());
+
+    let mut mutator = g.table("Article").await.unwrap();
+    mutator.insert(vec![1.into(), "a".into()]).await.unwrap();
+
+    // Let writes propagate:
+    sleep().await;
+
+    let mut getter = g.view("ArticleById").await.unwrap();
+    let result = getter.lookup(&[1.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][1], "a".into());
+
+    // rolling back past a dropped table would recreate it empty, so it is refused
+    g.extend_recipe("CREATE TABLE Comment (id int, PRIMARY KEY(id));")
+        .await
+        .unwrap();
+    g.extend_recipe("DROP TABLE Comment;").await.unwrap();
+    let e = g.rollback_to(4).await.unwrap_err();
+    let e = e.downcast::<noria::error::RecipeError>().unwrap();
+    assert_eq!(e.query, Some("Comment".to_owned()));
+    assert!(g.table("Comment").await.is_err());
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn it_works_with_function_arithmetic() {
+    let mut g = start_simple("it_works_with_function_arithmetic").await;
+    let sql = "
+        CREATE TABLE Bread (id int, price int, PRIMARY KEY(id));
+        QUERY Price: SELECT 2 * MAX(price) FROM Bread;
+    ";
+    g.install_recipe(sql).await.unwrap();
+
+    let mut mutator = g.table("Bread").await.unwrap();
+    let mut getter = g.view("Price").await.unwrap();
+    let max_price = 20;
+    for (i, price) in (10..=max_price).enumerate() {
+        let id = i + 1;
+        mutator.insert(vec![id.into(), price.into()]).await.unwrap();
+    }
+
+    // Let writes propagate:
+    sleep().await;
+
+    let result = getter.lookup(&[0.into()], true).await.unwrap();
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0][0], DataType::from(max_price * 2));
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn votes() {
+    // set up graph
+    let mut g = start_simple("votes").await;
+    let _ = g
+        .migrate(|mig| {
+            // add article base nodes (we use two so we can exercise unions too)
+            let article1 = mig.add_base("article1", &["id", "title"], Base::default());
+            let article2 = mig.add_base("article2", &["id", "title"], Base::default());
+
+            // add a (stupid) union of article1 + article2
+            let mut emits = HashMap::new();
+            emits.insert(article1, vec![0, 1]);
+            emits.insert(article2, vec![0, 1]);
+            let u = Union::new(emits);
+            let article = mig.add_ingredient("article", &["id", "title"], u);
+            mig.maintain_anonymous(article, &[0]);
+
+            // add vote base table
+            let vote = mig.add_base("vote", &["user", "id"], Base::default());
+
+            // add vote count
+            let vc = mig.add_ingredient(
+                "vc",
+                &["id", "votes"],
+                Aggregation::COUNT.over(vote, 0, &[1]),
+            );
+            mig.maintain_anonymous(vc, &[0]);
+
+            // add final join using first field from article and first from vc
+            let j = Join::new(article, vc, JoinType::Inner, vec![B(0, 0), L(1), R(1)]);
+            let end = mig.add_ingredient("end", &["id", "title", "votes"], j);
+            mig.maintain_anonymous(end, &[0]);
+
+            (article1, article2, vote, article, vc, end)
+        })
+        .await;
+
+    let mut articleq = g.view("article").await.unwrap();
+    let mut vcq = g.view("vc").await.unwrap();
+    let mut endq = g.view("end").await.unwrap();
+
+    let mut mut1 = g.table("article1").await.unwrap();
+    let mut mut2 = g.table("article2").await.unwrap();
+    let mut mutv = g.table("vote").await.unwrap();
+
+    let a1: DataType = 1.into();
+    let a2: DataType = 2.into();
+
+    // make one article
+    mut1.insert(vec![a1.clone(), 2.into()]).await.unwrap();
+
+    // give it some time to propagate
+    sleep().await;
+
+    // query articles to see that it was updated
+    assert_eq!(
+        articleq.lookup(&[a1.clone()], true).await.unwrap(),
+        vec![vec![a1.clone(), 2.into()]]
+    );
+
+    // make another article
+    mut2.insert(vec![a2.clone(), 4.into()]).await.unwrap();
+
+    // give it some time to propagate
+    sleep().await;
+
+    // query articles again to see that the new article was absorbed
+    // and that the old one is still present
+    assert_eq!(
+        articleq.lookup(&[a1.clone()], true).await.unwrap(),
+        vec![vec![a1.clone(), 2.into()]]
+    );
+    assert_eq!(
+        articleq.lookup(&[a2.clone()], true).await.unwrap(),
+        vec![vec![a2.clone(), 4.into()]]
+    );
+
+    // create a vote (user 1 votes for article 1)
+    mutv.insert(vec![1.into(), a1.clone()]).await.unwrap();
+
+    // give it some time to propagate
+    sleep().await;
+
+    // query vote count to see that the count was updated
+    let res = vcq.lookup(&[a1.clone()], true).await.unwrap();
+    assert!(res.iter().all(|r| r[0] == a1.clone() && r[1] == 1.into()));
+    assert_eq!(res.len(), 1);
+
+    // check that article 1 appears in the join view with a vote count of one
+    let res = endq.lookup(&[a1.clone()], true).await.unwrap();
+    assert!(
+        res.iter()
+            .any(|r| r[0] == a1.clone() && r[1] == 2.into() && r[2] == 1.into()),
+        "no entry for [1,2,1|2] in {:?}",
+        res
+    );
+    assert_eq!(res.len(), 1);
+
+    // check that article 2 doesn't have any votes
+    let res = endq.lookup(&[a2.clone()], true).await.unwrap();
+    assert!(res.len() <= 1) // could be 1 if we had zero-rows
+}
+
+#[tokio::test(threaded_scheduler)]
+async fn empty_migration() {
+    // set up graph
+    let mut g = start_simple("empty_migration").await;
+    g.migrate(|_| {}).await;
+
+    let _ = g
+        .migrate(|mig| {
             let a = mig.add_base("a", &["a", "b"], Base::default());
             let b = mig.add_base("b", &["a", "b"], Base::default());
 
@@ -2052,6 +3206,65 @@ async fn materialization_frontier() {
     }
 }
 
+#[tokio::test(threaded_scheduler)]
+async fn full_outer_join() {
+    let mut g = start_simple("full_outer_join").await;
+    let (a, b) = g
+        .migrate(|mig| {
+            let a = mig.add_base("a", &["id", "x"], Base::new(vec![]).with_key(vec![0]));
+            let b = mig.add_base("b", &["id", "y"], Base::new(vec![]).with_key(vec![0]));
+            (a, b)
+        })
+        .await;
+    let mut muta = g.table("a").await.unwrap();
+    let mut mutb = g.table("b").await.unwrap();
+
+    // rows that exist before the join does must be replayed into it from both sides
+    muta.insert(vec![1.into(), "a1".into()]).await.unwrap();
+    mutb.insert(vec![2.into(), "b2".into()]).await.unwrap();
+    sleep().await;
+
+    g.migrate(move |mig| {
+        let j = Join::new(a, b, JoinType::Full, vec![B(0, 0), L(1), R(1)]);
+        let j = mig.add_ingredient("j", &["id", "x", "y"], j);
+        mig.maintain_anonymous(j, &[0]);
+    })
+    .await;
+    let mut j = g.view("j").await.unwrap();
+
+    assert_eq!(
+        j.lookup(&[1.into()], true).await.unwrap(),
+        vec![vec![1.into(), "a1".into(), DataType::None]]
+    );
+    assert_eq!(
+        j.lookup(&[2.into()], true).await.unwrap(),
+        vec![vec![2.into(), DataType::None, "b2".into()]]
+    );
+
+    // matches from either side replace the NULL-padded rows
+    mutb.insert(vec![1.into(), "b1".into()]).await.unwrap();
+    muta.insert(vec![2.into(), "a2".into()]).await.unwrap();
+    sleep().await;
+
+    assert_eq!(
+        j.lookup(&[1.into()], true).await.unwrap(),
+        vec![vec![1.into(), "a1".into(), "b1".into()]]
+    );
+    assert_eq!(
+        j.lookup(&[2.into()], true).await.unwrap(),
+        vec![vec![2.into(), "a2".into(), "b2".into()]]
+    );
+
+    // and come back once the match is gone
+    muta.delete(vec![2.into()]).await.unwrap();
+    sleep().await;
+
+    assert_eq!(
+        j.lookup(&[2.into()], true).await.unwrap(),
+        vec![vec![2.into(), DataType::None, "b2".into()]]
+    );
+}
+
 #[tokio::test(threaded_scheduler)]
 async fn crossing_migration() {
     // set up graph
@@ -1,6 +1,7 @@
 use async_bincode::AsyncBincodeStream;
 use dataflow::prelude::DataType;
 use dataflow::prelude::*;
+use dataflow::KeyRange;
 use dataflow::Readers;
 use dataflow::SingleReadHandle;
 use futures_util::{
@@ -9,7 +10,7 @@ use futures_util::{
     future::{FutureExt, TryFutureExt},
     stream::{StreamExt, TryStreamExt},
 };
-use noria::{ReadQuery, ReadReply, Tagged};
+use noria::{ReadError, ReadQuery, ReadReply, Tagged};
 use pin_project::pin_project;
 use std::cell::RefCell;
 use std::collections::HashMap;
@@ -53,7 +54,16 @@ impl From<Vec<u8>> for SerializedReadReplyBatch {
     }
 }
 
-type Ack = tokio::sync::oneshot::Sender<Result<Tagged<ReadReply<SerializedReadReplyBatch>>, ()>>;
+type Reply = Result<Tagged<ReadReply<SerializedReadReplyBatch>>, ()>;
+type Ack = tokio::sync::oneshot::Sender<Reply>;
+
+/// The reply to a blocking read, once it completes.
+fn blocked_reply(r: Result<Reply, tokio::sync::oneshot::error::RecvError>) -> Reply {
+    match r {
+        Err(_) => Err(()),
+        Ok(r) => r,
+    }
+}
 
 pub(super) async fn listen(
     alive: tokio::sync::mpsc::Sender<()>,
@@ -176,6 +186,53 @@ where
     SerializedReadReplyBatch(v)
 }
 
+/// Look up the rows for a single lookup arranged by `SingleReadHandle::arrange_lookups`, which may
+/// carry parameters for the reader's post-lookup filters after the key itself, and return the page
+/// of them given by `offset` and `limit`.
+fn find(
+    reader: &SingleReadHandle,
+    lookup: &[DataType],
+    offset: usize,
+    limit: Option<usize>,
+) -> Result<Option<SerializedReadReplyBatch>, ()> {
+    let (key, args) = reader.split_lookup(lookup);
+    if reader.is_ranged() {
+        // the rows for all the keys in the range must be filtered, sorted and paged together
+        return reader
+            .try_find_ranged_and(lookup, |rs| rs.iter().cloned().collect::<Vec<_>>())
+            .map(|(rows, _)| {
+                rows.map(|rows| {
+                    let rows: Vec<_> = rows.into_iter().flatten().collect();
+                    serialize(reader.post_lookup(&rows, args, offset, limit))
+                })
+            });
+    }
+    reader
+        .try_find_and(key, |rs| {
+            if reader.has_post_lookup() || offset != 0 || limit.is_some() {
+                serialize(reader.post_lookup(rs, args, offset, limit))
+            } else {
+                serialize(rs)
+            }
+        })
+        .map(|r| r.0)
+}
+
+/// Look up the rows for all keys in `range`, in key order.
+fn find_range(
+    reader: &SingleReadHandle,
+    range: &KeyRange,
+) -> Result<Option<SerializedReadReplyBatch>, ()> {
+    reader
+        .try_find_range_and(range, |rs| rs.iter().cloned().collect::<Vec<_>>())
+        .map(|(rows, _)| {
+            rows.map(|rows| {
+                let rows: Vec<_> = rows.into_iter().flatten().collect();
+                serialize(&rows)
+            })
+        })
+}
+
 fn handle_message(
     m: Tagged<ReadQuery>,
     s: &Readers,
@@ -187,6 +244,8 @@ fn handle_message(
             target,
             mut keys,
             block,
+            offset,
+            limit,
         } => {
             let immediate = READERS.with(|readers_cache| {
                 let mut readers_cache = readers_cache.borrow_mut();
@@ -195,6 +254,14 @@ fn handle_message(
                     readers.get(&target).unwrap().clone()
                 });
 
+                if let Err(got) = reader.arrange_lookups(&mut keys[..]) {
+                    let expected = reader.lookup_arity();
+                    return Ok(Tagged {
+                        tag,
+                        v: ReadReply::Normal(Err(ReadError::WrongArity { expected, got })),
+                    });
+                }
+
                 let mut ret = Vec::with_capacity(keys.len());
 
                 // first do non-blocking reads for all keys to see if we can return immediately
@@ -207,8 +274,7 @@ fn handle_message(
                         ret.push(SerializedReadReplyBatch::empty());
                         return false;
                     }
-                    let rs = reader.try_find_and(key, |rs| serialize(rs)).map(|r| r.0);
-                    match rs {
+                    match find(reader, key, offset, limit) {
                         Ok(Some(rs)) => {
                             // immediate hit!
                             ret.push(rs);
@@ -232,7 +298,7 @@ fn handle_message(
                 if !ready {
                     return Ok(Tagged {
                         tag,
-                        v: ReadReply::Normal(Err(())),
+                        v: ReadReply::Normal(Err(ReadError::NotReady)),
                     });
                 }
 
@@ -246,7 +312,7 @@ fn handle_message(
                 }
 
                 // trigger backfills for all the keys we missed on
-                reader.trigger(keys.iter().map(Vec::as_slice));
+                reader.trigger(keys.iter().map(|k| reader.split_lookup(k).0));
 
                 Err((keys, ret, pending))
             });
@@ -268,7 +334,10 @@ fn handle_message(
                                 tag,
                                 target,
                                 keys,
+                                offset,
+                                limit,
                                 pending,
+                                range: None,
                                 read: ret,
                                 truth: s.clone(),
                                 trigger_timeout: trigger,
@@ -281,14 +350,69 @@ fn handle_message(
                             // we're shutting down
                             return Either::Left(Either::Left(future::ready(Err(()))));
                         }
-                        Either::Left(Either::Right(rx.map(|r| match r {
-                            Err(_) => Err(()),
-                            Ok(r) => r,
-                        })))
+                        Either::Left(Either::Right(rx.map(blocked_reply)))
                     }
                 }
             }
         }
+        ReadQuery::Range { target, range } => {
+            let immediate = READERS.with(|readers_cache| {
+                let mut readers_cache = readers_cache.borrow_mut();
+                let reader = readers_cache.entry(target).or_insert_with(|| {
+                    let readers = s.lock().unwrap();
+                    readers.get(&target).unwrap().clone()
+                });
+
+                match find_range(reader, &range) {
+                    // a partial ordered reader fills the range with range replays
+                    Ok(None) if reader.can_trigger_range() => {
+                        if reader.trigger_range(&range) {
+                            None
+                        } else {
+                            // server is shutting down and won't do the backfill
+                            Some(Err(()))
+                        }
+                    }
+                    // other partial readers can't answer this, which the client reports as an
+                    // unsupported range
+                    rows => Some(rows),
+                }
+            });
+
+            match immediate {
+                Some(rows) => Either::Right(future::ready(Ok(Tagged {
+                    tag,
+                    v: ReadReply::Range(rows),
+                }))),
+                None => {
+                    let (tx, rx) = tokio::sync::oneshot::channel();
+                    let trigger = time::Duration::from_millis(TRIGGER_TIMEOUT_MS);
+                    let now = time::Instant::now();
+                    let r = wait.send((
+                        BlockingRead {
+                            tag,
+                            target,
+                            keys: Vec::new(),
+                            offset: 0,
+                            limit: None,
+                            pending: Vec::new(),
+                            range: Some(range),
+                            read: Vec::new(),
+                            truth: s.clone(),
+                            trigger_timeout: trigger,
+                            next_trigger: now,
+                            first: now,
+                        },
+                        tx,
+                    ));
+                    if r.is_err() {
+                        // we're shutting down
+                        return Either::Left(Either::Left(future::ready(Err(()))));
+                    }
+                    Either::Left(Either::Right(rx.map(blocked_reply)))
+                }
+            }
+        }
         ReadQuery::Size { target } => {
             let size = READERS.with(|readers_cache| {
                 let mut readers_cache = readers_cache.borrow_mut();
@@ -316,8 +440,13 @@ struct BlockingRead {
     read: Vec<SerializedReadReplyBatch>,
     // keys we have yet to read
     keys: Vec<Vec<DataType>>,
+    // page of the rows for each key to return
+    offset: usize,
+    limit: Option<usize>,
     // index in self.read that each entyr in keys corresponds to
     pending: Vec<usize>,
+    // range to read, for range reads (which have no keys)
+    range: Option<KeyRange>,
     truth: Readers,
 
     trigger_timeout: time::Duration,
@@ -332,7 +461,10 @@ impl std::fmt::Debug for BlockingRead {
             .field("target", &self.target)
             .field("read", &self.read)
             .field("keys", &self.keys)
+            .field("offset", &self.offset)
+            .field("limit", &self.limit)
             .field("pending", &self.pending)
+            .field("range", &self.range)
             .field("trigger_timeout", &self.trigger_timeout)
             .field("next_trigger", &self.next_trigger)
             .field("first", &self.first)
@@ -342,6 +474,10 @@ impl std::fmt::Debug for BlockingRead {
 
 impl BlockingRead {
     fn check(&mut self) -> Poll<Result<Tagged<ReadReply<SerializedReadReplyBatch>>, ()>> {
+        if self.range.is_some() {
+            return self.check_range();
+        }
+
         READERS.with(|readers_cache| {
             let mut readers_cache = readers_cache.borrow_mut();
             let s = &self.truth;
@@ -364,7 +500,7 @@ impl BlockingRead {
 
             while let Some(read_i) = self.pending.pop() {
                 let key = self.keys.pop().expect("pending.len() == keys.len()");
-                match reader.try_find_and(&key, |rs| serialize(rs)).map(|r| r.0) {
+                match find(reader, &key, self.offset, self.limit) {
                     Ok(Some(rs)) => {
                         read[read_i] = rs;
                     }
@@ -386,7 +522,7 @@ impl BlockingRead {
 
             if !self.keys.is_empty() && now > next_trigger {
                 // maybe the key got filled, then evicted, and we missed it?
-                if !reader.trigger(self.keys.iter().map(Vec::as_slice)) {
+                if !reader.trigger(self.keys.iter().map(|k| reader.split_lookup(k).0)) {
                     // server is shutting down and won't do the backfill
                     return Err(());
                 }
@@ -418,12 +554,62 @@ impl BlockingRead {
             Poll::Pending
         }
     }
+
+    fn check_range(&mut self) -> Poll<Result<Tagged<ReadReply<SerializedReadReplyBatch>>, ()>> {
+        READERS.with(|readers_cache| {
+            let mut readers_cache = readers_cache.borrow_mut();
+            let s = &self.truth;
+            let target = &self.target;
+            let reader = readers_cache.entry(self.target).or_insert_with(|| {
+                let readers = s.lock().unwrap();
+                readers.get(target).unwrap().clone()
+            });
+
+            let now = time::Instant::now();
+            let range = self.range.as_ref().expect("range read without a range");
+            match find_range(reader, range) {
+                Ok(Some(rows)) => {
+                    return Poll::Ready(Ok(Tagged {
+                        tag: self.tag,
+                        v: ReadReply::Range(Ok(Some(rows))),
+                    }));
+                }
+                Err(()) => {
+                    // map has been deleted, so server is shutting down
+                    return Poll::Ready(Err(()));
+                }
+                Ok(None) => {}
+            }
+
+            if now > self.next_trigger {
+                // maybe part of the range got filled, then evicted, and we missed it?
+                if !reader.trigger_range(range) {
+                    // server is shutting down and won't do the backfill
+                    return Poll::Ready(Err(()));
+                }
+
+                self.trigger_timeout *= 2;
+                self.next_trigger = now + self.trigger_timeout;
+            }
+
+            let waited = now - self.first;
+            self.first = now;
+            if waited > time::Duration::from_secs(7) {
+                eprintln!(
+                    "warning: read has been stuck waiting on {:?} for {:?}",
+                    range, waited
+                );
+            }
+
+            Poll::Pending
+        })
+    }
 }
 
 #[cfg(test)]
 mod readreply {
     use super::SerializedReadReplyBatch;
-    use noria::{DataType, ReadReply, Tagged};
+    use noria::{DataType, ReadError, ReadReply, Tagged};
 
     fn rtt_ok(data: Vec<Vec<Vec<DataType>>>) {
         let got: Tagged<ReadReply> = bincode::deserialize(
@@ -514,24 +700,58 @@ mod readreply {
         ]);
     }
 
+    fn rtt_err(e: ReadError) {
+        let got: Tagged<ReadReply> = bincode::deserialize(
+            &bincode::serialize(&Tagged {
+                tag: 32,
+                v: ReadReply::Normal::<SerializedReadReplyBatch>(Err(e)),
+            })
+            .unwrap(),
+        )
+        .unwrap();
+
+        match got {
+            Tagged {
+                tag: 32,
+                v: ReadReply::Normal(Err(got)),
+            } => assert_eq!(got, e),
+            r => panic!("{:?}", r),
+        }
+    }
+
     #[test]
     fn rtt_normal_err() {
+        rtt_err(ReadError::NotReady);
+        rtt_err(ReadError::WrongArity {
+            expected: 2,
+            got: 1,
+        });
+    }
+
+    #[test]
+    fn rtt_range() {
+        let data = vec![
+            vec![DataType::from(1), DataType::from(42)],
+            vec![DataType::from(2), DataType::from(43)],
+        ];
         let got: Tagged<ReadReply> = bincode::deserialize(
             &bincode::serialize(&Tagged {
                 tag: 32,
-                v: ReadReply::Normal::<SerializedReadReplyBatch>(Err(())),
+                v: ReadReply::Range::<SerializedReadReplyBatch>(Ok(Some(super::serialize(&data)))),
             })
             .unwrap(),
         )
         .unwrap();
 
-        assert!(matches!(
-            got,
+        match got {
             Tagged {
+                v: ReadReply::Range(Ok(Some(got))),
                 tag: 32,
-                v: ReadReply::Normal(Err(()))
+            } => {
+                assert_eq!(&*got, &data);
             }
-        ));
+            r => panic!("{:?}", r),
+        }
     }
 
     #[test]
@@ -20,7 +20,7 @@ use futures_util::{
 use noria::channel::{DualTcpStream, CONNECTION_FROM_BASE};
 use noria::internal::DomainIndex;
 use noria::internal::LocalOrNot;
-use noria::{Input, Tagged};
+use noria::{Input, Tagged, WriteReply};
 use pin_project::pin_project;
 use slog;
 use std::collections::{HashMap, VecDeque};
@@ -147,7 +147,7 @@ impl Replica {
             let mut stream = Pin::new(&mut inputs[streami]);
             let mut sent = 0;
 
-            for &tag in &conn.tag_acks {
+            for (tag, reply) in &conn.tag_acks {
                 match stream.as_mut().poll_ready(cx) {
                     Poll::Ready(Ok(())) => {}
                     Poll::Pending => break,
@@ -157,7 +157,10 @@ impl Replica {
                     }
                 }
 
-                if let Err(e) = stream.as_mut().start_send(Tagged { tag, v: () }) {
+                if let Err(e) = stream.as_mut().start_send(Tagged {
+                    tag: *tag,
+                    v: reply.clone(),
+                }) {
                     // start_send shouldn't generally error
                     err.push(e.into());
                     break;
@@ -436,8 +439,8 @@ struct ConnState {
     // number of unacked inputs
     unacked: usize,
 
-    // unsent acks (value is the tag)
-    tag_acks: Vec<u32>,
+    // unsent acks (the tag, and how the write went)
+    tag_acks: Vec<(u32, WriteReply)>,
 
     // epoch counter for each stream index (since they're re-used)
     epoch: usize,
@@ -510,13 +513,13 @@ impl Outboxes {
 }
 
 impl Executor for Outboxes {
-    fn ack(&mut self, id: SourceChannelIdentifier) {
+    fn ack(&mut self, id: SourceChannelIdentifier, reply: WriteReply) {
         self.dirty = true;
         let mut c = &mut self.connections[id.token];
         if id.epoch == c.epoch {
             // if the epoch doesn't match, the stream was closed and a new one has been established
             // note that this only matters for connections that do not wait for all acks!
-            c.tag_acks.push(id.tag);
+            c.tag_acks.push((id.tag, reply));
 
             // NOTE: it's a little sad we can't crash on underflow here.
             // it is because if a send fails, we set c.unacked = 0, and should the domain _then_
@@ -314,7 +314,17 @@ async fn check_query(
                             let s: &str = (&v).into();
                             s.to_string()
                         }
-                        DataType::Timestamp(_) => unimplemented!(),
+                        DataType::Bool(b) => (b as i32).to_string(),
+                        DataType::Decimal(..) | DataType::Date(..) | DataType::Time(..) => {
+                            v.to_string()
+                        }
+                        DataType::Timestamp(ts) => ts.format("%Y-%m-%d %H:%M:%S").to_string(),
+                        // MySQL has no zoned timestamps, so compare them as UTC DATETIMEs
+                        DataType::TimestampTz(ts) => {
+                            ts.naive_utc().format("%Y-%m-%d %H:%M:%S").to_string()
+                        }
+                        DataType::ByteArray(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+                        DataType::Uuid(u) => u.to_string(),
                     })
                     .collect()
             })